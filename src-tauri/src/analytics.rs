@@ -0,0 +1,729 @@
+// Dead-letter queue analytics: page through a DLQ and count messages by reason,
+// content type, subject, forwarding source and enqueue hour
+//
+// Messages are peeked without their bodies, so even a large DLQ is scanned quickly
+// and left untouched.
+//
+// group_messages_by_property does the same for any entity and one chosen dimension: a
+// broker field or an application property, e.g. to find the tenant flooding a queue.
+//
+// find_duplicates reports messages that share a MessageId, and optionally a body, as
+// left behind by producers that resend on retry. Only that variant peeks bodies.
+//
+// analyze_ordering reads a sequence number the producer embeds (an application property
+// or a body field) and reports gaps, repeated values and values delivered after a higher
+// one, per entity or per session / partition key. Gaps are between the lowest and highest
+// values seen, so messages missing before the first one can't be told apart.
+
+use crate::azure::sandbox;
+use crate::azure::timestamps;
+use crate::azure::types::{MessageSource, ServiceBusConnection, ServiceBusMessage};
+use crate::state::ClientCache;
+use chrono::DurationRound;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const PAGE_SIZE: u32 = 250;
+const DEFAULT_MAX_SCAN: u32 = 100_000;
+// Buckets per dimension; the rest are summed into "(other)"
+const MAX_BUCKETS: usize = 50;
+// Duplicate groups returned, largest first, and sequence numbers listed per group
+const MAX_DUPLICATE_GROUPS: usize = 200;
+const MAX_DUPLICATE_SEQUENCE_NUMBERS: usize = 100;
+// Streams returned, most issues first, and gaps / duplicates / out-of-order deliveries
+// listed per stream
+const MAX_ORDERING_STREAMS: usize = 200;
+const MAX_ORDERING_ISSUES: usize = 100;
+const NONE_KEY: &str = "(none)";
+const OTHER_KEY: &str = "(other)";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountBucket {
+    pub key: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterAnalysis {
+    pub entity_path: String,
+    pub scanned_count: u32,
+    // Largest first
+    pub by_reason: Vec<CountBucket>,
+    pub by_content_type: Vec<CountBucket>,
+    pub by_subject: Vec<CountBucket>,
+    // Entity the message was dead-lettered on, for DLQs that auto-forwarding feeds
+    pub by_source: Vec<CountBucket>,
+    // Keyed by the start of the hour (RFC 3339, UTC), oldest first
+    pub enqueued_by_hour: Vec<CountBucket>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_enqueued_utc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newest_enqueued_utc: Option<String>,
+    // Where to continue when the scan stopped at max_scan
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+}
+
+// Payload of the "analyze-dead-letters-progress" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterAnalysisProgress {
+    pub entity_path: String,
+    pub scanned_count: u32,
+}
+
+// A grouping dimension: "subject", ... or {"property": "<name>"} for an application property
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GroupBy {
+    Subject,
+    ContentType,
+    CorrelationId,
+    SessionId,
+    To,
+    ReplyTo,
+    PartitionKey,
+    DeadLetterReason,
+    DeliveryCount,
+    Property(String),
+}
+
+impl GroupBy {
+    fn key(&self, message: &ServiceBusMessage) -> String {
+        let value = match self {
+            GroupBy::Subject => message.subject.clone(),
+            GroupBy::ContentType => message.content_type.clone(),
+            GroupBy::CorrelationId => message.correlation_id.clone(),
+            GroupBy::SessionId => message.session_id.clone(),
+            GroupBy::To => message.to.clone(),
+            GroupBy::ReplyTo => message.reply_to.clone(),
+            GroupBy::PartitionKey => message.partition_key.clone(),
+            GroupBy::DeadLetterReason => message.dead_letter_reason.clone(),
+            GroupBy::DeliveryCount => message.delivery_count.map(|n| n.to_string()),
+            GroupBy::Property(name) => match message.application_properties.as_ref().and_then(|p| p.get(name)) {
+                Some(serde_json::Value::String(text)) => Some(text.clone()),
+                Some(serde_json::Value::Null) | None => None,
+                Some(other) => Some(other.to_string()),
+            },
+        };
+        value.filter(|v| !v.is_empty()).unwrap_or_else(|| NONE_KEY.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageGroups {
+    pub entity_path: String,
+    pub group_by: GroupBy,
+    pub scanned_count: u32,
+    // Distinct values seen, including those summed into "(other)"
+    pub distinct_count: u32,
+    // Largest first
+    pub groups: Vec<CountBucket>,
+    // Where to continue when the scan stopped at max_scan
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+}
+
+// Payload of the "group-messages-progress" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageGroupsProgress {
+    pub entity_path: String,
+    pub scanned_count: u32,
+}
+
+// Messages sharing one MessageId or body hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub key: String,
+    pub count: u32,
+    // Oldest first, at most MAX_DUPLICATE_SEQUENCE_NUMBERS
+    pub sequence_numbers: Vec<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_enqueued_utc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_enqueued_utc: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateReport {
+    pub entity_path: String,
+    pub scanned_count: u32,
+    // Messages beyond the first of their MessageId
+    pub duplicate_count: u32,
+    // Groups with more than one message, before the list is capped
+    pub duplicate_group_count: u32,
+    // Largest first, at most MAX_DUPLICATE_GROUPS
+    pub by_message_id: Vec<DuplicateGroup>,
+    // Keyed by the SHA-256 of the body (first 16 bytes, hex); only with by_body_hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub by_body_hash: Option<Vec<DuplicateGroup>>,
+    // Where to continue when the scan stopped at max_scan
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+}
+
+// Payload of the "find-duplicates-progress" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateReportProgress {
+    pub entity_path: String,
+    pub scanned_count: u32,
+}
+
+// Where the producer's sequence number is: {"property": "<name>"} or
+// {"bodyField": "<path>"} (a JSON pointer, or a dotted path as in export columns)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SequenceField {
+    Property(String),
+    BodyField(String),
+}
+
+impl SequenceField {
+    // Integers, or strings holding one; anything else counts as having no sequence
+    fn value(&self, message: &ServiceBusMessage) -> Option<i64> {
+        let value = match self {
+            SequenceField::Property(name) => message.application_properties.as_ref().and_then(|p| p.get(name)).cloned(),
+            SequenceField::BodyField(path) => crate::export::body_field(message, path),
+        };
+        match value? {
+            serde_json::Value::Number(number) => number.as_i64(),
+            serde_json::Value::String(text) => text.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+// Values missing between two values that were seen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceGap {
+    pub after: i64,
+    pub before: i64,
+    pub missing: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceDuplicate {
+    pub value: i64,
+    // Broker sequence numbers of every message carrying the value, oldest first
+    pub sequence_numbers: Vec<u64>,
+}
+
+// A value delivered after a higher one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutOfOrderDelivery {
+    pub value: i64,
+    pub sequence_number: u64,
+    // Highest value delivered before it
+    pub after_value: i64,
+}
+
+// One producer sequence: the whole entity, or one session / partition key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderingStream {
+    pub key: String,
+    pub message_count: u32,
+    pub lowest_value: i64,
+    pub highest_value: i64,
+    // Totals, before the lists below are capped at MAX_ORDERING_ISSUES
+    pub missing_count: u64,
+    pub duplicate_count: u32,
+    pub out_of_order_count: u32,
+    // Lowest first
+    pub gaps: Vec<SequenceGap>,
+    pub duplicates: Vec<SequenceDuplicate>,
+    // In delivery order
+    pub out_of_order: Vec<OutOfOrderDelivery>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderingReport {
+    pub entity_path: String,
+    pub sequence_field: SequenceField,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_by: Option<GroupBy>,
+    pub scanned_count: u32,
+    // Messages without the field, or whose value isn't an integer
+    pub without_sequence_count: u32,
+    pub stream_count: u32,
+    // Across all streams
+    pub missing_count: u64,
+    pub duplicate_count: u32,
+    pub out_of_order_count: u32,
+    // Most issues first (ties by key), at most MAX_ORDERING_STREAMS
+    pub streams: Vec<OrderingStream>,
+    // Where to continue when the scan stopped at max_scan
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+}
+
+// Payload of the "analyze-ordering-progress" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderingReportProgress {
+    pub entity_path: String,
+    pub scanned_count: u32,
+}
+
+#[derive(Default)]
+struct Occurrences {
+    sequence_numbers: Vec<u64>,
+    // Enqueue times as peeked; RFC 3339 in UTC, so they order as strings
+    first_enqueued: Option<String>,
+    last_enqueued: Option<String>,
+}
+
+impl Occurrences {
+    fn add(&mut self, message: &ServiceBusMessage) {
+        if let Some(seq) = message.sequence_number {
+            self.sequence_numbers.push(seq);
+        }
+        if let Some(enqueued) = &message.enqueued_time_utc {
+            if self.first_enqueued.as_ref().is_none_or(|first| enqueued < first) {
+                self.first_enqueued = Some(enqueued.clone());
+            }
+            if self.last_enqueued.as_ref().is_none_or(|last| enqueued > last) {
+                self.last_enqueued = Some(enqueued.clone());
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    reasons: HashMap<String, u64>,
+    content_types: HashMap<String, u64>,
+    subjects: HashMap<String, u64>,
+    sources: HashMap<String, u64>,
+    hours: HashMap<chrono::DateTime<chrono::Utc>, u64>,
+    oldest: Option<chrono::DateTime<chrono::Utc>>,
+    newest: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Counters {
+    fn add(&mut self, message: &ServiceBusMessage) {
+        let key = |value: &Option<String>| value.clone().filter(|v| !v.is_empty()).unwrap_or_else(|| NONE_KEY.to_string());
+        *self.reasons.entry(key(&dead_letter_reason(message))).or_default() += 1;
+        *self.content_types.entry(key(&message.content_type)).or_default() += 1;
+        *self.subjects.entry(key(&message.subject)).or_default() += 1;
+        *self.sources.entry(key(&message.dead_letter_source)).or_default() += 1;
+
+        let Some(enqueued) = message.enqueued_time_utc.as_deref().and_then(timestamps::parse) else {
+            return;
+        };
+        let hour = enqueued.duration_trunc(chrono::Duration::hours(1)).unwrap_or(enqueued);
+        *self.hours.entry(hour).or_default() += 1;
+        self.oldest = Some(self.oldest.map_or(enqueued, |oldest| oldest.min(enqueued)));
+        self.newest = Some(self.newest.map_or(enqueued, |newest| newest.max(enqueued)));
+    }
+}
+
+// The service keeps the dead-letter reason with the application properties; a peek
+// that didn't lift it into the message field (HTTP transport) still has it there
+fn dead_letter_reason(message: &ServiceBusMessage) -> Option<String> {
+    message.dead_letter_reason.clone().or_else(|| {
+        let value = message.application_properties.as_ref()?.get("DeadLetterReason")?;
+        value.as_str().map(|v| v.to_string())
+    })
+}
+
+// Scan the dead-letter queue of source (a queue or a subscription) from
+// from_sequence_number (default: the head), up to max_scan messages
+pub async fn analyze_dead_letter_queue(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+    on_progress: &(dyn Fn(&DeadLetterAnalysisProgress) + Send + Sync),
+) -> Result<DeadLetterAnalysis, String> {
+    let source = MessageSource { dead_letter: true, ..source.clone() };
+    let entity_path = match (&source.queue_name, &source.topic_name, &source.subscription_name) {
+        (Some(queue), _, _) => format!("{}/$deadletterqueue", queue),
+        (None, Some(topic), Some(subscription)) => format!("{}/Subscriptions/{}/$deadletterqueue", topic, subscription),
+        _ => return Err("A queue or a topic subscription is required".to_string()),
+    };
+    let max_scan = max_scan.unwrap_or(DEFAULT_MAX_SCAN);
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let mut counters = Counters::default();
+    let mut scanned_count = 0u32;
+    let mut next_sequence_number = from_sequence_number.unwrap_or(0);
+    let mut exhausted = false;
+    while scanned_count < max_scan {
+        let count = (max_scan - scanned_count).min(PAGE_SIZE);
+        // Bodies aren't needed for any of the counts
+        let page = match &client {
+            Some(client) => client.peek_source(&source, count, Some(next_sequence_number), Some(0)).await?,
+            None => sandbox::peek_source(&source, count, Some(next_sequence_number))?,
+        };
+        for message in &page {
+            scanned_count += 1;
+            if let Some(seq) = message.sequence_number {
+                next_sequence_number = seq + 1;
+            }
+            counters.add(message);
+        }
+        on_progress(&DeadLetterAnalysisProgress { entity_path: entity_path.clone(), scanned_count });
+        if (page.len() as u32) < count {
+            exhausted = true;
+            break;
+        }
+    }
+    eprintln!("[analyze_dead_letter_queue] Scanned {} messages in {}", scanned_count, entity_path);
+
+    let mut enqueued_by_hour: Vec<_> = counters.hours.into_iter().collect();
+    enqueued_by_hour.sort_by_key(|(hour, _)| *hour);
+    Ok(DeadLetterAnalysis {
+        entity_path,
+        scanned_count,
+        by_reason: top_buckets(counters.reasons),
+        by_content_type: top_buckets(counters.content_types),
+        by_subject: top_buckets(counters.subjects),
+        by_source: top_buckets(counters.sources),
+        enqueued_by_hour: enqueued_by_hour
+            .into_iter()
+            .map(|(hour, count)| CountBucket { key: timestamps::format(hour), count })
+            .collect(),
+        oldest_enqueued_utc: counters.oldest.map(timestamps::format),
+        newest_enqueued_utc: counters.newest.map(timestamps::format),
+        next_sequence_number: if exhausted { None } else { Some(next_sequence_number) },
+    })
+}
+
+// Scan source from from_sequence_number (default: the head), up to max_scan messages,
+// and count them by group_by
+pub async fn group_messages(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    group_by: &GroupBy,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+    on_progress: &(dyn Fn(&MessageGroupsProgress) + Send + Sync),
+) -> Result<MessageGroups, String> {
+    if source.queue_name.is_none() && (source.topic_name.is_none() || source.subscription_name.is_none()) {
+        return Err("A queue or a topic subscription is required".to_string());
+    }
+    if let GroupBy::Property(name) = group_by {
+        if name.trim().is_empty() {
+            return Err("A property name is required".to_string());
+        }
+    }
+    let entity_path = crate::monitor::source_label(source);
+    let max_scan = max_scan.unwrap_or(DEFAULT_MAX_SCAN);
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    let mut scanned_count = 0u32;
+    let mut next_sequence_number = from_sequence_number.unwrap_or(0);
+    let mut exhausted = false;
+    while scanned_count < max_scan {
+        let count = (max_scan - scanned_count).min(PAGE_SIZE);
+        // Every dimension is a property, so bodies aren't needed
+        let page = match &client {
+            Some(client) => client.peek_source(source, count, Some(next_sequence_number), Some(0)).await?,
+            None => sandbox::peek_source(source, count, Some(next_sequence_number))?,
+        };
+        for message in &page {
+            scanned_count += 1;
+            if let Some(seq) = message.sequence_number {
+                next_sequence_number = seq + 1;
+            }
+            *counts.entry(group_by.key(message)).or_default() += 1;
+        }
+        on_progress(&MessageGroupsProgress { entity_path: entity_path.clone(), scanned_count });
+        if (page.len() as u32) < count {
+            exhausted = true;
+            break;
+        }
+    }
+    eprintln!("[group_messages] Scanned {} messages in {} into {} groups", scanned_count, entity_path, counts.len());
+
+    Ok(MessageGroups {
+        entity_path,
+        group_by: group_by.clone(),
+        scanned_count,
+        distinct_count: counts.len() as u32,
+        groups: top_buckets(counts),
+        next_sequence_number: if exhausted { None } else { Some(next_sequence_number) },
+    })
+}
+
+// Scan source from from_sequence_number (default: the head), up to max_scan messages,
+// and report MessageIds (and with by_body_hash, bodies) seen more than once
+pub async fn find_duplicates(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    by_body_hash: bool,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+    on_progress: &(dyn Fn(&DuplicateReportProgress) + Send + Sync),
+) -> Result<DuplicateReport, String> {
+    if source.queue_name.is_none() && (source.topic_name.is_none() || source.subscription_name.is_none()) {
+        return Err("A queue or a topic subscription is required".to_string());
+    }
+    let entity_path = crate::monitor::source_label(source);
+    let max_scan = max_scan.unwrap_or(DEFAULT_MAX_SCAN);
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let mut by_id: HashMap<String, Occurrences> = HashMap::new();
+    let mut by_body: HashMap<String, Occurrences> = HashMap::new();
+    let mut scanned_count = 0u32;
+    let mut next_sequence_number = from_sequence_number.unwrap_or(0);
+    let mut exhausted = false;
+    while scanned_count < max_scan {
+        let count = (max_scan - scanned_count).min(PAGE_SIZE);
+        let max_body_bytes = if by_body_hash { None } else { Some(0) };
+        let page = match &client {
+            Some(client) => client.peek_source(source, count, Some(next_sequence_number), max_body_bytes).await?,
+            None => sandbox::peek_source(source, count, Some(next_sequence_number))?,
+        };
+        for message in &page {
+            scanned_count += 1;
+            if let Some(seq) = message.sequence_number {
+                next_sequence_number = seq + 1;
+            }
+            if let Some(id) = message.message_id.as_ref().filter(|id| !id.is_empty()) {
+                by_id.entry(id.clone()).or_default().add(message);
+            }
+            if by_body_hash {
+                by_body.entry(body_hash(&message.body)).or_default().add(message);
+            }
+        }
+        on_progress(&DuplicateReportProgress { entity_path: entity_path.clone(), scanned_count });
+        if (page.len() as u32) < count {
+            exhausted = true;
+            break;
+        }
+    }
+
+    let duplicate_count = by_id.values().map(|o| o.sequence_numbers.len().saturating_sub(1) as u32).sum();
+    let duplicate_group_count = by_id.values().filter(|o| o.sequence_numbers.len() > 1).count() as u32;
+    eprintln!(
+        "[find_duplicates] Scanned {} messages in {}: {} duplicates in {} MessageIds",
+        scanned_count, entity_path, duplicate_count, duplicate_group_count
+    );
+    Ok(DuplicateReport {
+        entity_path,
+        scanned_count,
+        duplicate_count,
+        duplicate_group_count,
+        by_message_id: duplicate_groups(by_id),
+        by_body_hash: by_body_hash.then(|| duplicate_groups(by_body)),
+        next_sequence_number: if exhausted { None } else { Some(next_sequence_number) },
+    })
+}
+
+// Scan source from from_sequence_number (default: the head), up to max_scan messages,
+// and check the producer sequence in sequence_field, per stream_by value when given
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_ordering(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    sequence_field: &SequenceField,
+    stream_by: Option<&GroupBy>,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+    on_progress: &(dyn Fn(&OrderingReportProgress) + Send + Sync),
+) -> Result<OrderingReport, String> {
+    if source.queue_name.is_none() && (source.topic_name.is_none() || source.subscription_name.is_none()) {
+        return Err("A queue or a topic subscription is required".to_string());
+    }
+    let (SequenceField::Property(name) | SequenceField::BodyField(name)) = sequence_field;
+    if name.trim().is_empty() {
+        return Err("A sequence property or body field is required".to_string());
+    }
+    let entity_path = crate::monitor::source_label(source);
+    let max_scan = max_scan.unwrap_or(DEFAULT_MAX_SCAN);
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    // (value, broker sequence number) per stream, in delivery order
+    let mut streams: HashMap<String, Vec<(i64, u64)>> = HashMap::new();
+    let mut without_sequence_count = 0u32;
+    let mut scanned_count = 0u32;
+    let mut next_sequence_number = from_sequence_number.unwrap_or(0);
+    let mut exhausted = false;
+    while scanned_count < max_scan {
+        let count = (max_scan - scanned_count).min(PAGE_SIZE);
+        let max_body_bytes = match sequence_field {
+            SequenceField::Property(_) => Some(0),
+            SequenceField::BodyField(_) => None,
+        };
+        let page = match &client {
+            Some(client) => client.peek_source(source, count, Some(next_sequence_number), max_body_bytes).await?,
+            None => sandbox::peek_source(source, count, Some(next_sequence_number))?,
+        };
+        for message in &page {
+            scanned_count += 1;
+            if let Some(seq) = message.sequence_number {
+                next_sequence_number = seq + 1;
+            }
+            let Some(value) = sequence_field.value(message) else {
+                without_sequence_count += 1;
+                continue;
+            };
+            let key = stream_by.map_or_else(|| entity_path.clone(), |stream_by| stream_by.key(message));
+            streams.entry(key).or_default().push((value, message.sequence_number.unwrap_or_default()));
+        }
+        on_progress(&OrderingReportProgress { entity_path: entity_path.clone(), scanned_count });
+        if (page.len() as u32) < count {
+            exhausted = true;
+            break;
+        }
+    }
+
+    let stream_count = streams.len() as u32;
+    let mut streams: Vec<OrderingStream> =
+        streams.into_iter().map(|(key, deliveries)| ordering_stream(key, &deliveries)).collect();
+    let missing_count = streams.iter().map(|s| s.missing_count).sum();
+    let duplicate_count = streams.iter().map(|s| s.duplicate_count).sum();
+    let out_of_order_count = streams.iter().map(|s| s.out_of_order_count).sum();
+    eprintln!(
+        "[analyze_ordering] Scanned {} messages in {}: {} missing, {} duplicates, {} out of order in {} streams",
+        scanned_count, entity_path, missing_count, duplicate_count, out_of_order_count, stream_count
+    );
+
+    let issues = |s: &OrderingStream| s.missing_count + s.duplicate_count as u64 + s.out_of_order_count as u64;
+    streams.sort_by(|a, b| issues(b).cmp(&issues(a)).then_with(|| a.key.cmp(&b.key)));
+    streams.truncate(MAX_ORDERING_STREAMS);
+    Ok(OrderingReport {
+        entity_path,
+        sequence_field: sequence_field.clone(),
+        stream_by: stream_by.cloned(),
+        scanned_count,
+        without_sequence_count,
+        stream_count,
+        missing_count,
+        duplicate_count,
+        out_of_order_count,
+        streams,
+        next_sequence_number: if exhausted { None } else { Some(next_sequence_number) },
+    })
+}
+
+// Check one stream's (value, broker sequence number) pairs, given in delivery order
+fn ordering_stream(key: String, deliveries: &[(i64, u64)]) -> OrderingStream {
+    let mut seen: HashMap<i64, Vec<u64>> = HashMap::new();
+    let mut highest: Option<i64> = None;
+    let mut out_of_order = Vec::new();
+    for &(value, sequence_number) in deliveries {
+        let repeats = seen.entry(value).or_default();
+        repeats.push(sequence_number);
+        // A repeated value is reported as a duplicate, not also as out of order
+        if repeats.len() == 1 {
+            if let Some(after_value) = highest.filter(|highest| *highest > value) {
+                out_of_order.push(OutOfOrderDelivery { value, sequence_number, after_value });
+            }
+        }
+        highest = Some(highest.map_or(value, |highest| highest.max(value)));
+    }
+
+    let mut values: Vec<i64> = seen.keys().copied().collect();
+    values.sort_unstable();
+    let gaps: Vec<SequenceGap> = values
+        .windows(2)
+        .filter(|pair| pair[1].abs_diff(pair[0]) > 1)
+        .map(|pair| SequenceGap { after: pair[0], before: pair[1], missing: pair[1].abs_diff(pair[0]) - 1 })
+        .collect();
+    let mut duplicates: Vec<SequenceDuplicate> = seen
+        .into_iter()
+        .filter(|(_, sequence_numbers)| sequence_numbers.len() > 1)
+        .map(|(value, sequence_numbers)| SequenceDuplicate { value, sequence_numbers })
+        .collect();
+    duplicates.sort_by_key(|d| d.value);
+
+    let mut stream = OrderingStream {
+        key,
+        message_count: deliveries.len() as u32,
+        lowest_value: values.first().copied().unwrap_or_default(),
+        highest_value: values.last().copied().unwrap_or_default(),
+        missing_count: gaps.iter().map(|g| g.missing).sum(),
+        duplicate_count: duplicates.iter().map(|d| d.sequence_numbers.len() as u32 - 1).sum(),
+        out_of_order_count: out_of_order.len() as u32,
+        gaps,
+        duplicates,
+        out_of_order,
+    };
+    stream.gaps.truncate(MAX_ORDERING_ISSUES);
+    stream.duplicates.truncate(MAX_ORDERING_ISSUES);
+    for duplicate in &mut stream.duplicates {
+        duplicate.sequence_numbers.truncate(MAX_DUPLICATE_SEQUENCE_NUMBERS);
+    }
+    stream.out_of_order.truncate(MAX_ORDERING_ISSUES);
+    stream
+}
+
+// Text bodies hash as their text; base64 bodies as their encoding, which is as good for
+// telling them apart
+fn body_hash(body: &serde_json::Value) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = match body {
+        serde_json::Value::String(text) => Sha256::digest(text.as_bytes()),
+        other => Sha256::digest(other.to_string().as_bytes()),
+    };
+    digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Keys seen more than once, largest first (ties by key), capped at MAX_DUPLICATE_GROUPS
+fn duplicate_groups(occurrences: HashMap<String, Occurrences>) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = occurrences
+        .into_iter()
+        .filter(|(_, o)| o.sequence_numbers.len() > 1)
+        .map(|(key, mut o)| {
+            let count = o.sequence_numbers.len() as u32;
+            o.sequence_numbers.sort_unstable();
+            o.sequence_numbers.truncate(MAX_DUPLICATE_SEQUENCE_NUMBERS);
+            DuplicateGroup {
+                key,
+                count,
+                sequence_numbers: o.sequence_numbers,
+                first_enqueued_utc: o.first_enqueued,
+                last_enqueued_utc: o.last_enqueued,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    groups.truncate(MAX_DUPLICATE_GROUPS);
+    groups
+}
+
+// Largest first (ties by key), with everything past MAX_BUCKETS summed into "(other)"
+fn top_buckets(counts: HashMap<String, u64>) -> Vec<CountBucket> {
+    let mut buckets: Vec<CountBucket> = counts.into_iter().map(|(key, count)| CountBucket { key, count }).collect();
+    buckets.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    if buckets.len() > MAX_BUCKETS {
+        let other: u64 = buckets.drain(MAX_BUCKETS - 1..).map(|b| b.count).sum();
+        buckets.push(CountBucket { key: OTHER_KEY.to_string(), count: other });
+    }
+    buckets
+}
@@ -0,0 +1,72 @@
+// Append-only audit log of sensitive actions (copying secrets, clearing the clipboard)
+// Stored as JSON lines next to settings.json. Entries describe what happened and to
+// which connection - never the secret itself.
+
+use crate::azure::timestamps;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+
+const AUDIT_FILE_NAME: &str = "audit.log";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    // RFC 3339, UTC
+    pub timestamp: String,
+    // e.g. "secret_copied", "clipboard_cleared"
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
+    pub detail: String,
+}
+
+pub fn audit_log_path() -> Result<PathBuf, String> {
+    Ok(crate::config::config_dir()?.join(AUDIT_FILE_NAME))
+}
+
+pub fn record(action: &str, connection_id: Option<&str>, detail: &str) -> Result<(), String> {
+    let event = AuditEvent {
+        timestamp: timestamps::format(chrono::Utc::now()),
+        action: action.to_string(),
+        connection_id: connection_id.map(|id| id.to_string()),
+        detail: detail.to_string(),
+    };
+    let line = serde_json::to_string(&event).map_err(|e| format!("Failed to serialize audit event: {}", e))?;
+
+    let path = audit_log_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory {}: {}", dir.display(), e))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open audit log {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write audit log {}: {}", path.display(), e))
+}
+
+// Auditing must never block the action itself; failures are only logged
+pub fn record_or_log(action: &str, connection_id: Option<&str>, detail: &str) {
+    if let Err(e) = record(action, connection_id, detail) {
+        eprintln!("[audit] {}", e);
+    }
+}
+
+// Most recent events first. Lines that fail to parse are skipped.
+pub fn read_recent(limit: usize) -> Result<Vec<AuditEvent>, String> {
+    let path = audit_log_path()?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read audit log {}: {}", path.display(), e)),
+    };
+
+    Ok(contents
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<AuditEvent>(line).ok())
+        .take(limit)
+        .collect())
+}
@@ -1,4 +1,4 @@
-use crate::azure::types::ServiceBusConnection;
+use crate::azure::types::{AadTokenInspection, ConnectionStringExplanation, SasTokenInspection, ServiceBusConnection};
 use azure_identity::DefaultAzureCredential;
 use url::Url;
 
@@ -50,6 +50,161 @@ pub fn parse_connection_string(connection_string: &str) -> Result<ParsedConnecti
     })
 }
 
+pub const ROOT_MANAGE_KEY_NAME: &str = "RootManageSharedAccessKey";
+
+// The namespace owner policy grants Manage, Send and Listen on everything in the namespace
+pub fn is_root_manage_key_name(key_name: &str) -> bool {
+    key_name.eq_ignore_ascii_case(ROOT_MANAGE_KEY_NAME)
+}
+
+#[allow(dead_code)]
+pub fn uses_root_manage_key(connection: &ServiceBusConnection) -> bool {
+    connection
+        .connection_string
+        .as_deref()
+        .and_then(|cs| parse_connection_string(cs).ok())
+        .map(|parsed| is_root_manage_key_name(&parsed.shared_access_key_name))
+        .unwrap_or(false)
+}
+
+// Assemble a connection string from its parts and validate it by parsing it back.
+// `namespace` may be a bare namespace name ("contoso"), a host name or an sb:// endpoint.
+#[allow(dead_code)]
+pub fn build_connection_string(
+    namespace: &str,
+    policy_name: &str,
+    key: &str,
+    entity_path: Option<&str>,
+) -> Result<String, String> {
+    use base64::Engine;
+
+    let namespace = namespace.trim();
+    let policy_name = policy_name.trim();
+    let key = key.trim();
+
+    if namespace.is_empty() {
+        return Err("Namespace cannot be empty".to_string());
+    }
+    if policy_name.is_empty() {
+        return Err("Policy name cannot be empty".to_string());
+    }
+    if key.is_empty() {
+        return Err("Shared access key cannot be empty".to_string());
+    }
+    for (label, value) in [("Namespace", namespace), ("Policy name", policy_name), ("Shared access key", key)] {
+        if value.contains(';') {
+            return Err(format!("{} cannot contain ';'", label));
+        }
+    }
+    base64::engine::general_purpose::STANDARD
+        .decode(key)
+        .map_err(|_| "Shared access key is not valid base64 - copy the Primary or Secondary key exactly as shown in the portal".to_string())?;
+
+    // A bare namespace name gets the public cloud suffix; anything with a dot is treated as a host
+    let host = if namespace.contains("://") || namespace.contains('.') {
+        namespace.to_string()
+    } else {
+        format!("{}.servicebus.windows.net", namespace)
+    };
+    let domain = get_endpoint_domain(&host)?;
+    let ns = get_namespace_from_endpoint(&host)?;
+
+    let mut connection_string = format!(
+        "Endpoint=sb://{}{}/;SharedAccessKeyName={};SharedAccessKey={}",
+        ns, domain, policy_name, key
+    );
+    if let Some(path) = entity_path.map(|p| p.trim().trim_matches('/')).filter(|p| !p.is_empty()) {
+        if path.contains(';') {
+            return Err("EntityPath cannot contain ';'".to_string());
+        }
+        connection_string.push_str(&format!(";EntityPath={}", path));
+    }
+
+    // Round-trip through the parser so we never hand out a string the app itself can't use
+    parse_connection_string(&connection_string)?;
+
+    Ok(connection_string)
+}
+
+// Point a connection string at another namespace, keeping its policy, key and
+// EntityPath. new_namespace is a bare name (which stays in the old endpoint's cloud), a
+// host name or an sb:// endpoint.
+#[allow(dead_code)]
+pub fn rebind_connection_string(connection_string: &str, new_namespace: &str) -> Result<String, String> {
+    let parsed = parse_connection_string(connection_string)?;
+    let new_namespace = new_namespace.trim();
+    let host = if new_namespace.contains("://") || new_namespace.contains('.') {
+        new_namespace.to_string()
+    } else {
+        format!("{}{}", new_namespace, get_endpoint_domain(&parsed.endpoint)?)
+    };
+    build_connection_string(
+        &host,
+        &parsed.shared_access_key_name,
+        &parsed.shared_access_key,
+        parsed.entity_path.as_deref(),
+    )
+}
+
+// Describe what a pasted connection string points at and what it likely grants.
+// The secret itself is never included in the explanation.
+#[allow(dead_code)]
+pub fn explain_connection_string(connection_string: &str) -> Result<ConnectionStringExplanation, String> {
+    let parsed = parse_connection_string(connection_string)?;
+    let namespace = get_namespace_from_endpoint(&parsed.endpoint)?;
+    let endpoint_domain = get_endpoint_domain(&parsed.endpoint)?;
+
+    let cloud = match endpoint_domain.as_str() {
+        ".servicebus.windows.net" => "Azure public cloud",
+        ".servicebus.usgovcloudapi.net" => "Azure US Government",
+        ".servicebus.chinacloudapi.cn" => "Azure China (21Vianet)",
+        ".servicebus.cloudapi.de" => "Azure Germany",
+        _ => "Unknown cloud",
+    }
+    .to_string();
+
+    let is_root_manage_key = is_root_manage_key_name(&parsed.shared_access_key_name);
+    let scope = if parsed.entity_path.is_some() { "entity" } else { "namespace" }.to_string();
+
+    let mut notes = Vec::new();
+    match &parsed.entity_path {
+        Some(path) => notes.push(format!(
+            "Scoped to the entity '{}' - it can only be used against that queue or topic, and namespace-wide listing will fail.",
+            path
+        )),
+        None => notes.push(format!(
+            "Scoped to the whole '{}' namespace - it applies to every queue, topic and subscription in it.",
+            namespace
+        )),
+    }
+    if is_root_manage_key {
+        notes.push(
+            "RootManageSharedAccessKey is the namespace owner key: it grants Manage, Send and Listen on everything. Prefer a dedicated policy with only the rights you need.".to_string(),
+        );
+    } else {
+        notes.push(format!(
+            "The rights of policy '{}' (Manage, Send and/or Listen) are configured in Azure and are not encoded in the string itself.",
+            parsed.shared_access_key_name
+        ));
+    }
+    if connection_string.to_lowercase().contains("sharedaccesssignature=") {
+        notes.push("Contains a pre-generated SharedAccessSignature, which expires independently of the key.".to_string());
+    }
+    notes.push("Anyone holding this string can use it until the key is regenerated - treat it like a password.".to_string());
+
+    Ok(ConnectionStringExplanation {
+        endpoint: parsed.endpoint,
+        namespace,
+        endpoint_domain,
+        cloud,
+        policy_name: parsed.shared_access_key_name,
+        entity_path: parsed.entity_path,
+        scope,
+        is_root_manage_key,
+        notes,
+    })
+}
+
 pub fn generate_sas_token(
     resource_uri: &str,
     key_name: &str,
@@ -83,6 +238,162 @@ pub fn generate_sas_token(
     Ok(token)
 }
 
+// Decode a SharedAccessSignature token ("SharedAccessSignature sr=..&sig=..&se=..&skn=..").
+// Accepts the bare token, the token with its prefix, or a connection string carrying
+// SharedAccessSignature=. When a key is given the signature is recomputed to tell a
+// wrong key apart from an expired or mis-scoped token.
+#[allow(dead_code)]
+pub fn inspect_sas_token(token: &str, key: Option<&str>) -> Result<SasTokenInspection, String> {
+    use chrono::{DateTime, Utc};
+
+    let token = token.trim();
+    // ASCII lowercasing keeps byte offsets, so the match can slice the original
+    let token = match token.to_ascii_lowercase().find("sharedaccesssignature=") {
+        Some(start) => token[start + "sharedaccesssignature=".len()..]
+            .split(';')
+            .next()
+            .unwrap_or(""),
+        None => token,
+    };
+    let token = token
+        .strip_prefix("SharedAccessSignature")
+        .unwrap_or(token)
+        .trim();
+    if token.is_empty() {
+        return Err("SAS token cannot be empty".to_string());
+    }
+
+    let mut sr = None;
+    let mut sig = None;
+    let mut se = None;
+    let mut skn = None;
+    for part in token.split('&') {
+        if let Some((name, value)) = part.split_once('=') {
+            match name.trim() {
+                "sr" => sr = Some(value.trim().to_string()),
+                "sig" => sig = Some(value.trim().to_string()),
+                "se" => se = Some(value.trim().to_string()),
+                "skn" => skn = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+    let sr = sr.ok_or("Missing sr (resource URI) in SAS token")?;
+    let sig = sig.ok_or("Missing sig (signature) in SAS token")?;
+    let se = se.ok_or("Missing se (expiry) in SAS token")?;
+    let skn = skn.ok_or("Missing skn (policy name) in SAS token")?;
+
+    let decode = |value: &str| {
+        urlencoding::decode(value)
+            .map(|v| v.into_owned())
+            .map_err(|e| format!("Invalid URL encoding in SAS token: {}", e))
+    };
+    let resource_uri = decode(&sr)?;
+    let policy_name = decode(&skn)?;
+    let expiry: i64 = se
+        .parse()
+        .map_err(|_| format!("Invalid se (expiry) '{}': expected seconds since the Unix epoch", se))?;
+    let expires_at = DateTime::<Utc>::from_timestamp(expiry, 0)
+        .ok_or_else(|| format!("Expiry {} is out of range", expiry))?;
+    let expires_in_seconds = expiry - Utc::now().timestamp();
+    let expired = expires_in_seconds <= 0;
+
+    // sr may be a bare host, an sb:// or an https:// URI
+    let host_and_path = resource_uri
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(&resource_uri);
+    let (host, path) = host_and_path.split_once('/').unwrap_or((host_and_path, ""));
+    let namespace = get_namespace_from_endpoint(host).ok();
+    let entity_path = Some(path.trim_matches('/').to_string()).filter(|p| !p.is_empty());
+
+    let signature_valid = match key {
+        Some(key) => Some(sas_signature_matches(&sr, &resource_uri, &se, &decode(&sig)?, key.trim())?),
+        None => None,
+    };
+
+    let mut notes = Vec::new();
+    if expired {
+        notes.push(format!(
+            "Expired {} ago - the service rejects it with 401 regardless of the key.",
+            describe_seconds(-expires_in_seconds)
+        ));
+    } else if expires_in_seconds < 300 {
+        notes.push(format!(
+            "Expires in {} - small clock differences between this machine and Azure can already cause rejections.",
+            describe_seconds(expires_in_seconds)
+        ));
+    } else {
+        notes.push(format!("Valid for another {}.", describe_seconds(expires_in_seconds)));
+    }
+    match &entity_path {
+        Some(path) => notes.push(format!(
+            "Scoped to '{}' - requests to other entities or namespace-wide listings return 401.",
+            path
+        )),
+        None => notes.push("Scoped to the whole namespace.".to_string()),
+    }
+    if namespace.is_none() {
+        notes.push(format!("'{}' is not a recognized Service Bus host.", host));
+    }
+    match signature_valid {
+        Some(true) => notes.push(format!("Signature matches the provided key for policy '{}'.", policy_name)),
+        Some(false) => notes.push(format!(
+            "Signature does NOT match the provided key - the key is wrong, was regenerated, or belongs to a policy other than '{}'.",
+            policy_name
+        )),
+        None => notes.push("Provide the policy key to check the signature.".to_string()),
+    }
+    if is_root_manage_key_name(&policy_name) {
+        notes.push("Signed with the namespace owner key (RootManageSharedAccessKey).".to_string());
+    }
+
+    Ok(SasTokenInspection {
+        resource_uri,
+        namespace,
+        entity_path,
+        policy_name,
+        expiry,
+        expires_at: crate::azure::timestamps::format(expires_at),
+        expires_in_seconds,
+        expired,
+        signature_valid,
+        notes,
+    })
+}
+
+// Recompute the HMAC over "<url-encoded sr>\n<se>". Token producers differ in how they
+// encode sr, so both the value exactly as it appears in the token and our own encoding
+// of it are tried.
+fn sas_signature_matches(raw_sr: &str, resource_uri: &str, se: &str, signature: &str, key: &str) -> Result<bool, String> {
+    use base64::Engine;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let Ok(expected) = base64::engine::general_purpose::STANDARD.decode(signature) else {
+        return Ok(false);
+    };
+    let encoded_sr = urlencoding::encode(resource_uri).into_owned();
+    for candidate in [raw_sr, encoded_sr.as_str()] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+            .map_err(|e| format!("Failed to create HMAC: {}", e))?;
+        mac.update(format!("{}\n{}", candidate, se).as_bytes());
+        if mac.verify_slice(&expected).is_ok() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn describe_seconds(seconds: i64) -> String {
+    match seconds {
+        s if s < 120 => format!("{} seconds", s),
+        s if s < 7200 => format!("{} minutes", s / 60),
+        s if s < 172800 => format!("{} hours", s / 3600),
+        s => format!("{} days", s / 86400),
+    }
+}
+
 pub fn get_namespace_from_endpoint(endpoint: &str) -> Result<String, String> {
     // Normalize endpoint: handle sb:// scheme, strip trailing slashes, ensure https:// scheme
     let endpoint_normalized = endpoint.trim();
@@ -176,17 +487,161 @@ pub async fn create_credential(
     }
 }
 
+// Scope for Azure Service Bus data and management access with Azure AD
+pub const SERVICE_BUS_SCOPE: &str = "https://servicebus.azure.net/.default";
+
+// Get an access token for an Azure AD connection, the same way requests would
+#[allow(dead_code)]
+pub async fn acquire_aad_token(connection: &ServiceBusConnection) -> Result<String, String> {
+    acquire_aad_token_for_scope(connection, SERVICE_BUS_SCOPE).await
+}
+
+// Scope for Azure Schema Registry, which lives on Event Hubs namespaces
+#[allow(dead_code)]
+pub const SCHEMA_REGISTRY_SCOPE: &str = "https://eventhubs.azure.net/.default";
+
+#[allow(dead_code)]
+pub async fn acquire_aad_token_for_scope(connection: &ServiceBusConnection, scope: &str) -> Result<String, String> {
+    let credential = create_credential(connection).await?;
+    let token = credential
+        .get_token(&[scope])
+        .await
+        .map_err(|e| format!("Failed to get Azure AD token: {}", e))?;
+    Ok(token.token.secret().to_string())
+}
+
+// Scope for Azure Storage, used by blob export destinations
+#[allow(dead_code)]
+pub const STORAGE_SCOPE: &str = "https://storage.azure.com/.default";
+
+// Scope for Azure Key Vault, which can hold message encryption keys
+#[allow(dead_code)]
+pub const KEY_VAULT_SCOPE: &str = "https://vault.azure.net/.default";
+
+// A token from DefaultAzureCredential (Azure CLI, managed identity, ...) for resources
+// that don't belong to a connection
+#[allow(dead_code)]
+pub async fn acquire_default_token(scope: &str) -> Result<String, String> {
+    let credential: Box<dyn azure_core::auth::TokenCredential + Send + Sync> =
+        Box::new(DefaultAzureCredential::default());
+    let token = credential
+        .get_token(&[scope])
+        .await
+        .map_err(|e| format!("Failed to get Azure AD token: {}", e))?;
+    Ok(token.token.secret().to_string())
+}
+
+// Decode the claims of an Azure AD access token (header.payload.signature).
+// Azure RBAC role assignments are evaluated by the service and never appear in the
+// token, so the notes point at the principal to check instead.
+#[allow(dead_code)]
+pub fn inspect_aad_token(token: &str) -> Result<AadTokenInspection, String> {
+    use base64::Engine;
+    use chrono::{DateTime, Utc};
+
+    let token = token.trim();
+    let token = token.strip_prefix("Bearer ").unwrap_or(token).trim();
+    let payload = token
+        .split('.')
+        .nth(1)
+        .ok_or("Not a JWT: expected header.payload.signature")?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|e| format!("Invalid JWT payload encoding: {}", e))?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes)
+        .map_err(|e| format!("Invalid JWT payload: {}", e))?;
+
+    let claim = |name: &str| claims.get(name).and_then(|v| v.as_str()).map(|s| s.to_string());
+    let time_claim = |name: &str| {
+        claims
+            .get(name)
+            .and_then(|v| v.as_i64())
+            .and_then(|t| DateTime::<Utc>::from_timestamp(t, 0))
+    };
+
+    let audience = claim("aud");
+    let object_id = claim("oid");
+    let app_id = claim("appid").or_else(|| claim("azp"));
+    let user_name = claim("upn")
+        .or_else(|| claim("preferred_username"))
+        .or_else(|| claim("unique_name"));
+    let roles: Vec<String> = claims
+        .get("roles")
+        .and_then(|v| v.as_array())
+        .map(|roles| roles.iter().filter_map(|r| r.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default();
+    let scopes: Vec<String> = claim("scp")
+        .map(|scp| scp.split_whitespace().map(|s| s.to_string()).collect())
+        .unwrap_or_default();
+    let expires_at = time_claim("exp");
+    let not_before = time_claim("nbf");
+    let now = Utc::now();
+    let expired = expires_at.map(|exp| exp <= now).unwrap_or(false);
+
+    let mut notes = Vec::new();
+    match &audience {
+        Some(aud) if aud.trim_end_matches('/') == "https://servicebus.azure.net" => {}
+        Some(aud) if aud.contains(".servicebus.") => {}
+        Some(aud) => notes.push(format!(
+            "Audience is '{}', not https://servicebus.azure.net - Service Bus rejects tokens issued for other resources.",
+            aud
+        )),
+        None => notes.push("Token has no audience (aud) claim.".to_string()),
+    }
+    if let Some(exp) = expires_at {
+        if expired {
+            notes.push(format!("Expired at {} - request a new token.", exp.to_rfc3339()));
+        }
+    }
+    if let Some(nbf) = not_before {
+        if nbf > now {
+            notes.push(format!("Not valid before {} - check this machine's clock.", nbf.to_rfc3339()));
+        }
+    }
+    let principal = user_name.clone().or_else(|| app_id.clone()).or_else(|| object_id.clone());
+    notes.push(format!(
+        "Azure RBAC roles are not carried in the token. Receiving needs 'Azure Service Bus Data Receiver', sending 'Azure Service Bus Data Sender', and entity management 'Azure Service Bus Data Owner' assigned to {} on the namespace, queue or topic.",
+        match (&principal, &object_id) {
+            (Some(name), Some(oid)) if name != oid => format!("{} (object id {})", name, oid),
+            (Some(name), _) => name.clone(),
+            (None, _) => "this principal".to_string(),
+        }
+    ));
+    if claim("tid").is_none() {
+        notes.push("Token has no tenant (tid) claim.".to_string());
+    }
+
+    Ok(AadTokenInspection {
+        audience,
+        issuer: claim("iss"),
+        tenant_id: claim("tid"),
+        object_id,
+        app_id,
+        user_name,
+        roles,
+        scopes,
+        issued_at: time_claim("iat").map(crate::azure::timestamps::format),
+        not_before: not_before.map(crate::azure::timestamps::format),
+        expires_at: expires_at.map(crate::azure::timestamps::format),
+        expired,
+        notes,
+    })
+}
+
 #[allow(dead_code)]
 pub fn parse_duration_to_seconds(duration: &str) -> Option<u64> {
-    // Parse ISO 8601 duration (e.g., "PT30S" = 30 seconds, "PT1H" = 3600 seconds)
-    let re = regex::Regex::new(r"PT(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)S)?").ok()?;
-    let caps = re.captures(duration)?;
+    // Parse ISO 8601 duration (e.g., "PT30S" = 30 seconds, "PT1H" = 3600 seconds).
+    // Long TTLs come with days and fractional seconds ("P10675199DT2H48M5.4775807S");
+    // the fraction is dropped.
+    let re = regex::Regex::new(r"^P(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)(?:\.\d+)?S)?)?$").ok()?;
+    let caps = re.captures(duration.trim())?;
     
-    let hours: u64 = caps.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
-    let minutes: u64 = caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
-    let seconds: u64 = caps.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
+    let days: u64 = caps.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
+    let hours: u64 = caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
+    let minutes: u64 = caps.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
+    let seconds: u64 = caps.get(4).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
     
-    Some(hours * 3600 + minutes * 60 + seconds)
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,84 @@
+// Compressed message bodies
+//
+// Some producers gzip or deflate large payloads. A body is decompressed for display when
+// its content type or a Content-Encoding style application property says so, or when it
+// starts with the gzip magic bytes. Deflate has no magic, so it needs one of the hints.
+
+use crate::azure::types::{BodyCompression, ServiceBusMessage};
+use std::io::{Read, Write};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Refuse to inflate beyond this, so a hostile body cannot exhaust memory
+const MAX_DECOMPRESSED_BYTES: u64 = 64 * 1024 * 1024;
+
+// Application properties producers commonly use to flag compression (matched ignoring case)
+const ENCODING_PROPERTIES: &[&str] = &["Content-Encoding", "ContentEncoding", "Compression"];
+
+pub fn detect(message: &ServiceBusMessage, body: &[u8]) -> Option<BodyCompression> {
+    let hint = content_type_hint(message.content_type.as_deref()).or_else(|| property_hint(message));
+    match hint {
+        Some(compression) => Some(compression),
+        None if body.starts_with(&GZIP_MAGIC) => Some(BodyCompression::Gzip),
+        None => None,
+    }
+}
+
+pub fn decompress(body: &[u8], compression: BodyCompression) -> Result<Vec<u8>, String> {
+    match compression {
+        BodyCompression::Gzip => read_limited(flate2::read::GzDecoder::new(body)),
+        // "deflate" is zlib-wrapped per HTTP, but raw deflate streams are common too
+        BodyCompression::Deflate => read_limited(flate2::read::ZlibDecoder::new(body))
+            .or_else(|_| read_limited(flate2::read::DeflateDecoder::new(body))),
+    }
+    .map_err(|e| format!("Failed to decompress {} body: {}", compression.name(), e))
+}
+
+pub fn compress(body: &[u8], compression: BodyCompression) -> Result<Vec<u8>, String> {
+    let level = flate2::Compression::default();
+    let result = match compression {
+        BodyCompression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+            encoder.write_all(body).and_then(|_| encoder.finish())
+        }
+        BodyCompression::Deflate => {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), level);
+            encoder.write_all(body).and_then(|_| encoder.finish())
+        }
+    };
+    result.map_err(|e| format!("Failed to {} message body: {}", compression.name(), e))
+}
+
+fn read_limited(reader: impl Read) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(MAX_DECOMPRESSED_BYTES + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_DECOMPRESSED_BYTES {
+        return Err(std::io::Error::other(format!(
+            "decompressed size exceeds {} MB",
+            MAX_DECOMPRESSED_BYTES / (1024 * 1024)
+        )));
+    }
+    Ok(bytes)
+}
+
+fn content_type_hint(content_type: Option<&str>) -> Option<BodyCompression> {
+    let media_type = content_type?.split(';').next()?.trim().to_ascii_lowercase();
+    match media_type.as_str() {
+        "application/gzip" | "application/x-gzip" => Some(BodyCompression::Gzip),
+        "application/zlib" | "application/deflate" => Some(BodyCompression::Deflate),
+        _ if media_type.ends_with("+gzip") => Some(BodyCompression::Gzip),
+        _ => None,
+    }
+}
+
+fn property_hint(message: &ServiceBusMessage) -> Option<BodyCompression> {
+    let properties = message.application_properties.as_ref()?.as_object()?;
+    properties
+        .iter()
+        .filter(|(name, _)| ENCODING_PROPERTIES.iter().any(|p| p.eq_ignore_ascii_case(name)))
+        .find_map(|(_, value)| match value.as_str()?.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(BodyCompression::Gzip),
+            "deflate" | "zlib" => Some(BodyCompression::Deflate),
+            _ => None,
+        })
+}
@@ -0,0 +1,181 @@
+// Legacy .NET DataContract message bodies
+//
+// The old WindowsAzure.ServiceBus SDK serialized `new BrokeredMessage("text")` with
+// DataContractSerializer: the string arrives wrapped in a <string> element, either as
+// text XML or in the .NET Binary XML format (MC-NBFX). Only the string case is unwrapped;
+// other object graphs are left as they are. The envelope is remembered so the payload
+// can be wrapped again on resend, keeping legacy receivers (GetBody<string>()) working.
+
+use crate::azure::types::BodyEnvelope;
+use xml::reader::{EventReader, XmlEvent};
+
+const SERIALIZATION_NS: &str = "http://schemas.microsoft.com/2003/10/Serialization/";
+
+// MC-NBFX record types
+const SHORT_ELEMENT: u8 = 0x40;
+const SHORT_XMLNS_ATTRIBUTE: u8 = 0x08;
+const END_ELEMENT: u8 = 0x01;
+const CHARS8_TEXT: u8 = 0x98;
+const CHARS16_TEXT: u8 = 0x9A;
+const CHARS32_TEXT: u8 = 0x9C;
+const EMPTY_TEXT: u8 = 0xA8;
+
+// The wrapped string and the envelope it came in, or None for any other body
+pub fn unwrap(body: &[u8]) -> Option<(String, BodyEnvelope)> {
+    if body.first() == Some(&SHORT_ELEMENT) {
+        return unwrap_binary(body).map(|text| (text, BodyEnvelope::DataContractBinary));
+    }
+    unwrap_xml(std::str::from_utf8(body).ok()?).map(|text| (text, BodyEnvelope::DataContractXml))
+}
+
+pub fn wrap(text: &str, envelope: BodyEnvelope) -> Vec<u8> {
+    match envelope {
+        BodyEnvelope::DataContractXml => format!(
+            "<string xmlns=\"{}\">{}</string>",
+            SERIALIZATION_NS,
+            crate::azure::authorization::escape_xml(text)
+        )
+        .into_bytes(),
+        BodyEnvelope::DataContractBinary => {
+            let mut bytes = vec![SHORT_ELEMENT];
+            write_name(&mut bytes, "string");
+            bytes.push(SHORT_XMLNS_ATTRIBUTE);
+            write_name(&mut bytes, SERIALIZATION_NS);
+            // The "with end element" variant (record type + 1) closes <string>
+            let text = text.as_bytes();
+            if text.is_empty() {
+                bytes.push(EMPTY_TEXT + 1);
+            } else if let Ok(len) = u8::try_from(text.len()) {
+                bytes.push(CHARS8_TEXT + 1);
+                bytes.push(len);
+            } else if let Ok(len) = u16::try_from(text.len()) {
+                bytes.push(CHARS16_TEXT + 1);
+                bytes.extend_from_slice(&len.to_le_bytes());
+            } else {
+                bytes.push(CHARS32_TEXT + 1);
+                bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            }
+            bytes.extend_from_slice(text);
+            bytes
+        }
+    }
+}
+
+fn unwrap_xml(xml: &str) -> Option<String> {
+    let xml = xml.trim_start_matches('\u{feff}').trim();
+    if !xml.starts_with('<') {
+        return None;
+    }
+
+    let mut depth = 0usize;
+    let mut text = String::new();
+    for event in EventReader::from_str(xml) {
+        match event.ok()? {
+            XmlEvent::StartElement { name, .. } => {
+                depth += 1;
+                // <string> must be the root and have no children
+                if depth > 1 || name.local_name != "string" || name.namespace.as_deref() != Some(SERIALIZATION_NS) {
+                    return None;
+                }
+            }
+            XmlEvent::EndElement { .. } => depth -= 1,
+            XmlEvent::Characters(chars) | XmlEvent::CData(chars) => text.push_str(&chars),
+            XmlEvent::Whitespace(chars) if depth == 1 => text.push_str(&chars),
+            _ => {}
+        }
+    }
+    Some(text)
+}
+
+fn unwrap_binary(body: &[u8]) -> Option<String> {
+    let mut reader = Reader { bytes: body, position: 0 };
+    if reader.byte()? != SHORT_ELEMENT || reader.name()? != "string" {
+        return None;
+    }
+    if reader.byte()? != SHORT_XMLNS_ATTRIBUTE || reader.name()? != SERIALIZATION_NS {
+        return None;
+    }
+
+    let mut text = Vec::new();
+    loop {
+        let record = reader.byte()?;
+        if record == END_ELEMENT {
+            break;
+        }
+        // Text records come in pairs; the odd one also ends the element
+        let ends_element = record & 1 == 1;
+        match record & !1 {
+            CHARS8_TEXT => {
+                let len = reader.byte()? as usize;
+                text.extend_from_slice(reader.take(len)?);
+            }
+            CHARS16_TEXT => {
+                let len = u16::from_le_bytes(reader.take(2)?.try_into().ok()?) as usize;
+                text.extend_from_slice(reader.take(len)?);
+            }
+            CHARS32_TEXT => {
+                let len = u32::from_le_bytes(reader.take(4)?.try_into().ok()?) as usize;
+                text.extend_from_slice(reader.take(len)?);
+            }
+            EMPTY_TEXT => {}
+            // Dictionary strings, UTF-16 text and other records are not handled
+            _ => return None,
+        }
+        if ends_element {
+            break;
+        }
+    }
+
+    // Anything after </string> means this was not a plain serialized string
+    if reader.position != body.len() {
+        return None;
+    }
+    String::from_utf8(text).ok()
+}
+
+// Names are prefixed with their length as a MultiByteInt31
+fn write_name(bytes: &mut Vec<u8>, name: &str) {
+    let mut len = name.len() as u32;
+    while len >= 0x80 {
+        bytes.push((len as u8 & 0x7F) | 0x80);
+        len >>= 7;
+    }
+    bytes.push(len as u8);
+    bytes.extend_from_slice(name.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.position)?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.position..self.position.checked_add(len)?)?;
+        self.position += len;
+        Some(slice)
+    }
+
+    fn multi_byte_int31(&mut self) -> Option<usize> {
+        let mut value = 0usize;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7F) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn name(&mut self) -> Option<&'a str> {
+        let len = self.multi_byte_int31()?;
+        std::str::from_utf8(self.take(len)?).ok()
+    }
+}
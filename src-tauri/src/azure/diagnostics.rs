@@ -0,0 +1,103 @@
+// Diagnostics helpers
+// Pure functions that explain what the service reports, kept separate from the
+// HTTP/AMQP code in servicebus.rs so they can be reused by the CLI binaries.
+
+use crate::azure::types::MessageCountVerification;
+
+// Runtime counts as reported by the entity description (MessageCountDetails)
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeCounts {
+    pub active: Option<u64>,
+    pub dead_letter: Option<u64>,
+    pub scheduled: Option<u64>,
+    pub transfer: Option<u64>,
+    pub requires_session: bool,
+}
+
+// Cross-check the number of messages a peek returned (or a purge removed) against the
+// runtime counts, and list the likely reasons when they differ.
+#[allow(dead_code)]
+pub fn compare_message_counts(
+    entity_path: &str,
+    operation: &str,
+    dead_letter: bool,
+    observed_count: u64,
+    requested_count: Option<u64>,
+    counts: &RuntimeCounts,
+) -> Result<MessageCountVerification, String> {
+    let runtime_count = if dead_letter { counts.dead_letter } else { counts.active };
+    let scheduled = if dead_letter { 0 } else { counts.scheduled.unwrap_or(0) };
+    let mut reasons = Vec::new();
+
+    let matches = match operation {
+        "peek" => {
+            let runtime = runtime_count.unwrap_or(0);
+            let hit_limit = requested_count.map(|r| observed_count >= r).unwrap_or(false);
+
+            if observed_count < runtime {
+                if hit_limit {
+                    reasons.push(format!(
+                        "Peek stopped at the requested maximum of {} messages; page further to see the remaining {}.",
+                        observed_count,
+                        runtime - observed_count
+                    ));
+                } else {
+                    reasons.push("A single peek call can return fewer messages than requested; keep paging from the last sequence number to see the rest.".to_string());
+                    reasons.push("Consumers may have received and completed messages after the count was read.".to_string());
+                }
+                if counts.requires_session {
+                    reasons.push("The entity requires sessions; messages locked in sessions held by other receivers can be skipped.".to_string());
+                }
+            } else if observed_count > runtime {
+                if scheduled > 0 {
+                    reasons.push(format!(
+                        "{} scheduled messages are returned by peek but are not part of the active count until their enqueue time.",
+                        scheduled
+                    ));
+                }
+                reasons.push("New messages may have arrived after the count was read.".to_string());
+            }
+            // Hitting the requested limit is expected, not a mismatch
+            observed_count == runtime || (hit_limit && observed_count < runtime)
+        }
+        "purge" => {
+            let remaining = runtime_count.unwrap_or(0);
+            if remaining > 0 {
+                reasons.push(format!(
+                    "{} messages remain after removing {}. They may have arrived during the purge, or were locked by active consumers while the purge ran.",
+                    remaining, observed_count
+                ));
+                reasons.push("The purge stops after several consecutive empty receives, so slow namespaces can end it early; run it again to continue.".to_string());
+            }
+            if scheduled > 0 {
+                reasons.push(format!(
+                    "{} scheduled messages cannot be purged until they become active.",
+                    scheduled
+                ));
+            }
+            remaining == 0
+        }
+        other => return Err(format!("Unknown operation '{}'. Expected 'peek' or 'purge'", other)),
+    };
+
+    if runtime_count.is_none() {
+        reasons.push("The service did not report runtime counts for this entity.".to_string());
+    }
+    if !dead_letter {
+        if let Some(transfer) = counts.transfer.filter(|t| *t > 0) {
+            reasons.push(format!("{} messages are still in the transfer queue waiting to be forwarded.", transfer));
+        }
+    }
+    reasons.push("Runtime counts are refreshed asynchronously by the service and can lag a few seconds behind.".to_string());
+
+    Ok(MessageCountVerification {
+        entity_path: entity_path.to_string(),
+        operation: operation.to_string(),
+        dead_letter,
+        observed_count,
+        runtime_count,
+        scheduled_count: counts.scheduled,
+        matches,
+        reasons: if matches { Vec::new() } else { reasons },
+    })
+}
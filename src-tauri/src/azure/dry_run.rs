@@ -0,0 +1,83 @@
+// Dry run: mutating requests are logged and recorded instead of sent
+//
+// While enabled, create/update/delete of entities, sends, purges and every operation
+// that settles, moves or removes messages stop right before the request goes out and
+// report success. Operations that only exist over AMQP are recorded with method "AMQP".
+// The planned requests (method, URL and body, never the Authorization header) can be
+// read back with planned_requests().
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+// Oldest requests are dropped beyond this
+const MAX_PLANNED_REQUESTS: usize = 1000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PLANNED: OnceLock<Mutex<Vec<PlannedRequest>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedRequest {
+    // RFC 3339, UTC
+    pub timestamp: String,
+    // e.g. "create queue", "send message"
+    pub operation: String,
+    pub method: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+fn with_planned<T>(f: impl FnOnce(&mut Vec<PlannedRequest>) -> T) -> T {
+    let mutex = PLANNED.get_or_init(|| Mutex::new(Vec::new()));
+    let mut planned = mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut planned)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn set_enabled(enabled: bool) {
+    if ENABLED.swap(enabled, Ordering::SeqCst) != enabled {
+        eprintln!("[dry_run] {}", if enabled { "Enabled" } else { "Disabled" });
+    }
+}
+
+// Returns true when dry run is on: the request was recorded and must not be sent
+pub fn intercept(operation: &str, method: &str, url: &str, body: Option<&str>) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    match body {
+        Some(body) => eprintln!("[dry_run] {}: {} {}\n{}", operation, method, url, body),
+        None => eprintln!("[dry_run] {}: {} {}", operation, method, url),
+    }
+    let request = PlannedRequest {
+        timestamp: crate::azure::timestamps::format(chrono::Utc::now()),
+        operation: operation.to_string(),
+        method: method.to_string(),
+        url: url.to_string(),
+        body: body.map(|b| b.to_string()),
+    };
+    with_planned(|planned| {
+        if planned.len() >= MAX_PLANNED_REQUESTS {
+            planned.remove(0);
+        }
+        planned.push(request);
+    });
+    true
+}
+
+// Oldest first
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn planned_requests() -> Vec<PlannedRequest> {
+    with_planned(|planned| planned.clone())
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn clear() {
+    with_planned(|planned| planned.clear());
+}
@@ -0,0 +1,281 @@
+// Error catalog for user-facing errors
+//
+// Every entry has a stable code (SBX-xxxx), a key, an English template with {param}
+// placeholders and an optional hint. Errors still travel to the frontend as strings
+// (prefixed with the code), so existing callers keep working; describe_error turns such
+// a string back into code + params so the UI can localize it or link to documentation.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorCatalogEntry {
+    pub code: &'static str,
+    pub key: &'static str,
+    pub template: &'static str,
+    pub hint: Option<&'static str>,
+}
+
+pub const CATALOG: &[ErrorCatalogEntry] = &[
+    ErrorCatalogEntry {
+        code: "SBX-1001",
+        key: "connection_string_invalid",
+        template: "Invalid connection string: {detail}",
+        hint: Some("Copy the connection string from \"Shared access policies\" in the Azure portal"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1010",
+        key: "unauthorized",
+        template: "Failed to {operation}: unauthorized ({status}) - {detail}",
+        hint: Some("Check the shared access key name and key in the connection string"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1011",
+        key: "forbidden",
+        template: "Failed to {operation}: access denied ({status}) - {detail}",
+        hint: Some("The policy is missing a right this operation needs (Manage, Send or Listen)"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1012",
+        key: "sas_signature_invalid",
+        template: "Failed to {operation}: SAS signature invalid ({status}) - {detail}",
+        hint: Some("Check the system clock and that the key has not been rotated"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1020",
+        key: "entity_not_found",
+        template: "Failed to {operation}: entity not found ({status}) - {detail}",
+        hint: Some("Check the entity name; it may have been deleted or renamed"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1021",
+        key: "entity_conflict",
+        template: "Failed to {operation}: conflict ({status}) - {detail}",
+        hint: Some("The entity already exists or another operation is in progress"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1022",
+        key: "precondition_failed",
+        template: "Failed to {operation}: entity changed ({status}) - {detail}",
+        hint: Some("Reload the entity and try again"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1030",
+        key: "throttled",
+        template: "Failed to {operation}: service busy ({status}) - {detail}",
+        hint: Some("The namespace is throttling requests; wait a moment and retry"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1031",
+        key: "quota_exceeded",
+        template: "Failed to {operation}: quota exceeded ({status}) - {detail}",
+        hint: Some("The entity or namespace has reached its size or count limit"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1040",
+        key: "bad_request",
+        template: "Failed to {operation}: request rejected ({status}) - {detail}",
+        hint: Some("One of the supplied properties is invalid for this entity or tier"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1050",
+        key: "server_error",
+        template: "Failed to {operation}: server error ({status}) - {detail}",
+        hint: Some("Azure Service Bus returned an internal error; retry later"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1060",
+        key: "network_error",
+        template: "Failed to {operation}: network error - {detail}",
+        hint: Some("Check network connectivity, proxy settings and the namespace name"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1061",
+        key: "timeout",
+        template: "Failed to {operation}: timed out - {detail}",
+        hint: Some("The service did not respond in time; retry or check connectivity"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1070",
+        key: "forward_references",
+        template: "Cannot delete {entity}: {count} entities forward to it - {entities}",
+        hint: Some("Change their ForwardTo settings first, or delete anyway to break the forwarding chain"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1080",
+        key: "system_entity",
+        template: "Cannot {operation} {entity}: names starting with $ are Service Bus system entities",
+        hint: Some("Use the dead-letter and transfer dead-letter actions to work with sub-queues"),
+    },
+    ErrorCatalogEntry {
+        code: "SBX-1999",
+        key: "unknown",
+        template: "{detail}",
+        hint: None,
+    },
+];
+
+// A catalog error with its parameters, as returned to the frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogError {
+    pub code: String,
+    pub key: String,
+    pub message: String,
+    pub params: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl CatalogError {
+    pub fn new(key: &str, params: &[(&str, &str)]) -> Self {
+        let entry = lookup_key(key).unwrap_or_else(|| lookup_key("unknown").expect("unknown entry"));
+        let params: BTreeMap<String, String> = params
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        let mut message = entry.template.to_string();
+        for (name, value) in &params {
+            message = message.replace(&format!("{{{}}}", name), value);
+        }
+
+        CatalogError {
+            code: entry.code.to_string(),
+            key: entry.key.to_string(),
+            message,
+            params,
+            hint: entry.hint.map(|h| h.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+fn lookup_key(key: &str) -> Option<&'static ErrorCatalogEntry> {
+    CATALOG.iter().find(|entry| entry.key == key)
+}
+
+fn lookup_code(code: &str) -> Option<&'static ErrorCatalogEntry> {
+    CATALOG.iter().find(|entry| entry.code == code)
+}
+
+// Error string for a failed management/data plane HTTP call.
+// operation is the lower-case verb phrase ("get queue", "peek messages").
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn http_error(operation: &str, status: reqwest::StatusCode, body: &str) -> String {
+    let detail = error_detail(body);
+    let status_text = status.to_string();
+    let key = key_for_status(status.as_u16(), &detail);
+    CatalogError::new(key, &[("operation", operation), ("status", &status_text), ("detail", &detail)]).to_string()
+}
+
+fn key_for_status(status: u16, detail: &str) -> &'static str {
+    let lower = detail.to_lowercase();
+    match status {
+        400 => "bad_request",
+        401 if lower.contains("signature") || lower.contains("expired") => "sas_signature_invalid",
+        401 => "unauthorized",
+        403 if lower.contains("quota") => "quota_exceeded",
+        403 => "forbidden",
+        404 => "entity_not_found",
+        409 => "entity_conflict",
+        412 => "precondition_failed",
+        429 | 503 => "throttled",
+        500..=599 => "server_error",
+        _ => "unknown",
+    }
+}
+
+// Service Bus error bodies look like <Error><Code>..</Code><Detail>..</Detail></Error>;
+// the Detail text is what's worth showing
+fn error_detail(body: &str) -> String {
+    regex::Regex::new(r"(?s)<Detail>(.*?)</Detail>")
+        .ok()
+        .and_then(|re| re.captures(body).map(|cap| cap[1].trim().to_string()))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+// Whether an error is the service refusing the credential (401/403), e.g. a management
+// call made with a Listen-only policy
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn is_access_denied(raw: &str) -> bool {
+    matches!(describe_error(raw).key.as_str(), "unauthorized" | "forbidden")
+}
+
+// Turn any error string back into a catalog error.
+// Strings that already carry an SBX code keep it; older free-form errors are classified
+// from their HTTP status and wording.
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn describe_error(raw: &str) -> CatalogError {
+    let coded = regex::Regex::new(r"(?s)^(SBX-\d{4}): (.*)$").ok().and_then(|re| {
+        re.captures(raw).map(|cap| (cap[1].to_string(), cap[2].to_string()))
+    });
+    let (code, text) = match coded {
+        Some((code, text)) => (Some(code), text),
+        None => (None, raw.to_string()),
+    };
+
+    let params = extract_params(&text);
+    let entry = code
+        .as_deref()
+        .and_then(lookup_code)
+        .unwrap_or_else(|| lookup_key(classify_text(&text, &params)).expect("catalog key"));
+
+    CatalogError {
+        code: entry.code.to_string(),
+        key: entry.key.to_string(),
+        message: text,
+        params,
+        hint: entry.hint.map(|h| h.to_string()),
+    }
+}
+
+// Pull operation/status/detail out of "Failed to <operation>: ... (<status>) - <detail>"
+fn extract_params(text: &str) -> BTreeMap<String, String> {
+    let mut params = BTreeMap::new();
+    if let Some(cap) = regex::Regex::new(r"^Failed to ([^:]+):\s*(.*)$")
+        .ok()
+        .and_then(|re| re.captures(text.lines().next().unwrap_or("")))
+    {
+        params.insert("operation".to_string(), cap[1].trim().to_string());
+    }
+    if let Some(cap) = regex::Regex::new(r"\b([1-5]\d\d)(?: [A-Za-z ]+)?\b")
+        .ok()
+        .and_then(|re| re.captures(text))
+    {
+        params.insert("status".to_string(), cap[0].trim().to_string());
+    }
+    let detail = text.split_once(" - ").map(|(_, d)| d).unwrap_or(text);
+    params.insert("detail".to_string(), detail.trim().to_string());
+    params
+}
+
+fn classify_text(text: &str, params: &BTreeMap<String, String>) -> &'static str {
+    let lower = text.to_lowercase();
+    if lower.contains("connection string") {
+        return "connection_string_invalid";
+    }
+    if let Some(status) = params
+        .get("status")
+        .and_then(|s| s.split_whitespace().next())
+        .and_then(|s| s.parse::<u16>().ok())
+    {
+        let key = key_for_status(status, text);
+        if key != "unknown" {
+            return key;
+        }
+    }
+    if lower.contains("timed out") || lower.contains("timeout") {
+        "timeout"
+    } else if lower.contains("error sending request") || lower.contains("dns") || lower.contains("connect") {
+        "network_error"
+    } else {
+        "unknown"
+    }
+}
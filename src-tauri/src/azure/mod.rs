@@ -1,4 +1,13 @@
 pub mod auth;
+pub mod authorization;
+pub mod compression;
+pub mod data_contract;
+pub mod diagnostics;
+pub mod dry_run;
+pub mod errors;
+pub mod raw_xml;
+pub mod rules;
+pub mod sandbox;
 pub mod servicebus;
+pub mod timestamps;
 pub mod types;
-
@@ -0,0 +1,90 @@
+// Raw entity description XML (expert mode)
+//
+// Lets users edit properties the typed structs don't model yet. Edited XML is
+// checked locally before anything is sent: it must be well-formed and carry
+// exactly one description of the same kind as the entity being updated.
+
+use xml::reader::{EventReader, XmlEvent};
+
+const SERVICE_BUS_NS: &str = "http://schemas.microsoft.com/netservices/2010/10/servicebus/connect";
+const ATOM_NS: &str = "http://www.w3.org/2005/Atom";
+
+// A validated description: its element name (e.g. "QueueDescription") and its XML
+#[allow(dead_code)]
+pub struct EntityDescription {
+    pub kind: String,
+    pub xml: String,
+}
+
+// Accepts either a full Atom <entry> or the bare description element
+#[allow(dead_code)]
+pub fn parse_entity_description(xml: &str) -> Result<EntityDescription, String> {
+    let xml = xml.trim();
+    if xml.is_empty() {
+        return Err("XML is empty".to_string());
+    }
+
+    let mut depth = 0usize;
+    let mut root: Option<String> = None;
+    let mut in_content = false;
+    let mut content_depth = 0usize;
+    let mut descriptions = Vec::new();
+
+    for event in EventReader::from_str(xml) {
+        match event.map_err(|e| format!("XML is not well-formed: {}", e))? {
+            XmlEvent::StartElement { name, .. } => {
+                depth += 1;
+                let namespace = name.namespace.as_deref().unwrap_or_default();
+                if depth == 1 {
+                    root = Some(name.local_name.clone());
+                    if name.local_name == "entry" {
+                        if namespace != ATOM_NS {
+                            return Err(format!("<entry> must be in the Atom namespace ({})", ATOM_NS));
+                        }
+                    } else {
+                        descriptions.push((name.local_name.clone(), namespace.to_string()));
+                    }
+                } else if depth == 2 && name.local_name == "content" && root.as_deref() == Some("entry") {
+                    in_content = true;
+                    content_depth = depth;
+                } else if in_content && depth == content_depth + 1 {
+                    descriptions.push((name.local_name.clone(), namespace.to_string()));
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                if in_content && depth == content_depth {
+                    in_content = false;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    let (kind, namespace) = match descriptions.as_slice() {
+        [single] => single.clone(),
+        [] => return Err("No entity description found (expected <content> with a *Description element)".to_string()),
+        _ => return Err("Only one entity description can be submitted at a time".to_string()),
+    };
+    if !kind.ends_with("Description") {
+        return Err(format!("<{}> is not an entity description", kind));
+    }
+    if namespace != SERVICE_BUS_NS {
+        return Err(format!("<{}> must be in the Service Bus namespace ({})", kind, SERVICE_BUS_NS));
+    }
+
+    let description_xml = if root.as_deref() == Some("entry") {
+        regex::Regex::new(r#"(?s)<content[^>]*>(.*?)</content>"#)
+            .ok()
+            .and_then(|re| re.captures(xml))
+            .map(|cap| cap[1].trim().to_string())
+            .ok_or("Could not extract <content> from the entry")?
+    } else {
+        // Drop an XML declaration; the description is embedded in an entry
+        regex::Regex::new(r#"^<\?xml[^>]*\?>"#)
+            .map(|re| re.replace(xml, "").trim().to_string())
+            .map_err(|e| format!("Failed to build regex: {}", e))?
+    };
+
+    Ok(EntityDescription { kind, xml: description_xml })
+}
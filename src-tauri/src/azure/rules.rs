@@ -0,0 +1,113 @@
+// Subscription rules (filters and actions)
+//
+// Rules are written as <RuleDescription> XML. A new subscription can carry its
+// first rule in <DefaultRuleDescription>, which replaces the match-all $Default
+// rule as part of the create call itself.
+
+use crate::azure::authorization::escape_xml;
+use crate::azure::types::{RuleFilter, SubscriptionRule};
+
+const XML_SCHEMA_INSTANCE_NS: &str = "http://www.w3.org/2001/XMLSchema-instance";
+const XML_SCHEMA_NS: &str = "http://www.w3.org/2001/XMLSchema";
+const DEFAULT_RULE_NAME: &str = "$Default";
+
+// <DefaultRuleDescription> element for a subscription description
+#[allow(dead_code)]
+pub fn default_rule_description_xml(rule: &SubscriptionRule) -> Result<String, String> {
+    Ok(format!(
+        "<DefaultRuleDescription xmlns:i=\"{}\">{}</DefaultRuleDescription>",
+        XML_SCHEMA_INSTANCE_NS,
+        rule_body_xml(rule)?
+    ))
+}
+
+// Filter, Action and Name, in the order the service expects them
+fn rule_body_xml(rule: &SubscriptionRule) -> Result<String, String> {
+    let mut xml = filter_xml(&rule.filter)?;
+    match rule.sql_action.as_deref().map(str::trim) {
+        Some(action) if !action.is_empty() => xml.push_str(&format!(
+            r#"<Action i:type="SqlRuleAction"><SqlExpression>{}</SqlExpression><CompatibilityLevel>20</CompatibilityLevel></Action>"#,
+            escape_xml(action)
+        )),
+        _ => xml.push_str(r#"<Action i:type="EmptyRuleAction"/>"#),
+    }
+    let name = rule.name.as_deref().unwrap_or(DEFAULT_RULE_NAME);
+    if name.trim().is_empty() {
+        return Err("Rule name cannot be empty".to_string());
+    }
+    xml.push_str(&format!("<Name>{}</Name>", escape_xml(name)));
+    Ok(xml)
+}
+
+fn filter_xml(filter: &RuleFilter) -> Result<String, String> {
+    match filter.filter_type.to_ascii_lowercase().as_str() {
+        "sql" => {
+            let expression = filter
+                .sql_expression
+                .as_deref()
+                .map(str::trim)
+                .filter(|e| !e.is_empty())
+                .ok_or("A SQL filter needs a sqlExpression (e.g. \"priority = 'high'\")")?;
+            Ok(format!(
+                r#"<Filter i:type="SqlFilter"><SqlExpression>{}</SqlExpression><CompatibilityLevel>20</CompatibilityLevel></Filter>"#,
+                escape_xml(expression)
+            ))
+        }
+        "correlation" => correlation_filter_xml(filter),
+        other => Err(format!("Unknown filter type '{}' (expected \"sql\" or \"correlation\")", other)),
+    }
+}
+
+fn correlation_filter_xml(filter: &RuleFilter) -> Result<String, String> {
+    // Element order is fixed by the service's data contract
+    let fields = [
+        ("CorrelationId", &filter.correlation_id),
+        ("MessageId", &filter.message_id),
+        ("To", &filter.to),
+        ("ReplyTo", &filter.reply_to),
+        ("Label", &filter.subject),
+        ("SessionId", &filter.session_id),
+        ("ReplyToSessionId", &filter.reply_to_session_id),
+        ("ContentType", &filter.content_type),
+    ];
+
+    let mut inner = String::new();
+    for (element, value) in fields {
+        if let Some(value) = value {
+            inner.push_str(&format!("<{0}>{1}</{0}>", element, escape_xml(value)));
+        }
+    }
+
+    if let Some(properties) = filter.properties.as_ref().filter(|p| !p.is_empty()) {
+        let mut keys: Vec<&String> = properties.keys().collect();
+        keys.sort();
+        inner.push_str("<Properties>");
+        for key in keys {
+            let (xsd_type, value) = match &properties[key] {
+                serde_json::Value::String(s) => ("string", escape_xml(s)),
+                serde_json::Value::Bool(b) => ("boolean", b.to_string()),
+                serde_json::Value::Number(n) if n.is_i64() => ("long", n.to_string()),
+                serde_json::Value::Number(n) => ("double", n.to_string()),
+                _ => {
+                    return Err(format!(
+                        "Correlation filter property '{}' must be a string, number or boolean",
+                        key
+                    ))
+                }
+            };
+            inner.push_str(&format!(
+                r#"<KeyValueOfstringanyType><Key>{}</Key><Value i:type="d6p1:{}" xmlns:d6p1="{}">{}</Value></KeyValueOfstringanyType>"#,
+                escape_xml(key),
+                xsd_type,
+                XML_SCHEMA_NS,
+                value
+            ));
+        }
+        inner.push_str("</Properties>");
+    }
+
+    if inner.is_empty() {
+        return Err("A correlation filter needs at least one system or application property to match".to_string());
+    }
+    Ok(format!(r#"<Filter i:type="CorrelationFilter">{}</Filter>"#, inner))
+}
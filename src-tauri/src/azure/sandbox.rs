@@ -0,0 +1,716 @@
+// Training sandbox: an in-memory namespace for practicing message dispositions
+//
+// Connections flagged with training_mode never reach Azure. Commands route them here
+// instead, so new team members can purge, dead-letter and resubmit messages without
+// touching a real namespace. Everything lives in process memory and resets on restart
+// (or via reset_training_sandbox). All names and errors are labeled "training".
+//
+// The sandbox only has queues, without sessions, scheduling or auto-forwarding.
+// Commands for anything else fail with a training error, and ClientCache refuses to
+// build a client for a training connection, so a command without a sandbox branch
+// can't reach a real namespace either.
+
+use crate::azure::types::*;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+pub const TRAINING_CONNECTION_ID: &str = "training-sandbox";
+pub const TRAINING_NAMESPACE: &str = "training-sandbox";
+
+const DEFAULT_LOCK_DURATION_SECONDS: u64 = 30;
+const DEFAULT_MAX_DELIVERY_COUNT: u32 = 10;
+
+struct StoredMessage {
+    message: ServiceBusMessage,
+    sequence_number: u64,
+    delivery_count: u32,
+}
+
+struct LockedEntry {
+    stored: StoredMessage,
+    locked_until: chrono::DateTime<chrono::Utc>,
+}
+
+struct SandboxQueue {
+    properties: QueueProperties,
+    active: VecDeque<StoredMessage>,
+    dead_letter: VecDeque<StoredMessage>,
+    // Lock token -> message received in peek-lock mode
+    locked: HashMap<String, LockedEntry>,
+}
+
+struct TrainingNamespace {
+    queues: BTreeMap<String, SandboxQueue>,
+    next_sequence_number: u64,
+    next_lock_id: u64,
+}
+
+static TRAINING: OnceLock<Mutex<TrainingNamespace>> = OnceLock::new();
+
+fn with_namespace<T>(f: impl FnOnce(&mut TrainingNamespace) -> Result<T, String>) -> Result<T, String> {
+    let mutex = TRAINING.get_or_init(|| Mutex::new(TrainingNamespace::seeded()));
+    let mut namespace = mutex.lock().map_err(|_| "Training sandbox state is unavailable".to_string())?;
+    namespace.expire_locks();
+    f(&mut namespace)
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn is_training(connection: &ServiceBusConnection) -> bool {
+    connection.training_mode.unwrap_or(false)
+}
+
+// Connection object the frontend can add to jump into the sandbox
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn training_connection() -> ServiceBusConnection {
+    let now = chrono::Utc::now().timestamp_millis();
+    ServiceBusConnection {
+        id: TRAINING_CONNECTION_ID.to_string(),
+        name: "Training sandbox (not a real namespace)".to_string(),
+        connection_string: None,
+        namespace: Some(TRAINING_NAMESPACE.to_string()),
+        use_azure_ad: None,
+        tenant_id: None,
+        client_id: None,
+        created_at: now,
+        updated_at: now,
+        uses_root_manage_key: None,
+        training_mode: Some(true),
+        transport: None,
+        color: None,
+    }
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn reset() -> Result<(), String> {
+    with_namespace(|ns| {
+        *ns = TrainingNamespace::seeded();
+        Ok(())
+    })
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn list_queues() -> Result<Vec<QueueProperties>, String> {
+    with_namespace(|ns| Ok(ns.queues.values().map(|q| q.current_properties()).collect()))
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn get_queue(queue_name: &str) -> Result<QueueProperties, String> {
+    with_namespace(|ns| Ok(ns.queue(queue_name)?.current_properties()))
+}
+
+// The sandbox only has queues; anything else is reported as an error for that entity
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn estimate_backlog(entity: &BacklogEntity) -> BacklogEstimate {
+    let entity_path = match (&entity.queue_name, &entity.topic_name, &entity.subscription_name) {
+        (Some(q), _, _) => q.clone(),
+        (None, Some(t), Some(s)) => format!("{}/Subscriptions/{}", t, s),
+        (None, Some(t), None) => t.clone(),
+        _ => String::new(),
+    };
+    let queue = entity
+        .queue_name
+        .as_deref()
+        .ok_or_else(|| "The training sandbox only has queues".to_string())
+        .and_then(get_queue);
+    match queue {
+        Ok(queue) => BacklogEstimate {
+            entity_path,
+            modified: true,
+            etag: None,
+            message_count: queue.message_count,
+            active_message_count: queue.active_message_count,
+            dead_letter_message_count: queue.dead_letter_message_count,
+            scheduled_message_count: queue.scheduled_message_count,
+            transfer_message_count: queue.transfer_message_count,
+            transfer_dead_letter_message_count: queue.transfer_dead_letter_message_count,
+            error: None,
+        },
+        Err(e) => BacklogEstimate {
+            entity_path,
+            modified: false,
+            etag: None,
+            message_count: None,
+            active_message_count: None,
+            dead_letter_message_count: None,
+            scheduled_message_count: None,
+            transfer_message_count: None,
+            transfer_dead_letter_message_count: None,
+            error: Some(e),
+        },
+    }
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn peek_messages(
+    queue_name: &str,
+    dead_letter: bool,
+    max_count: u32,
+    from_sequence_number: Option<u64>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    with_namespace(|ns| {
+        let queue = ns.queue(queue_name)?;
+        let source = if dead_letter { &queue.dead_letter } else { &queue.active };
+        Ok(source
+            .iter()
+            .filter(|stored| stored.sequence_number >= from_sequence_number.unwrap_or(0))
+            .take(max_count as usize)
+            .map(|stored| stored.to_message(None))
+            .collect())
+    })
+}
+
+// Peek a message source; only queues exist in the sandbox
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn peek_source(
+    source: &MessageSource,
+    max_count: u32,
+    from_sequence_number: Option<u64>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    peek_messages(training_queue(source)?, source.dead_letter, max_count, from_sequence_number)
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn send_message(queue_name: &str, message: &ServiceBusMessage) -> Result<(), String> {
+    with_namespace(|ns| {
+        let stored = ns.new_message(message.clone());
+        ns.queue_mut(queue_name)?.active.push_back(stored);
+        Ok(())
+    })
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn purge_queue(queue_name: &str, purge_dead_letter: bool) -> Result<u32, String> {
+    with_namespace(|ns| {
+        let queue = ns.queue_mut(queue_name)?;
+        let target = if purge_dead_letter { &mut queue.dead_letter } else { &mut queue.active };
+        let purged = target.len() as u32;
+        target.clear();
+        Ok(purged)
+    })
+}
+
+// Receive-and-delete from the head of a queue or its dead-letter queue
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn drain_messages(queue_name: &str, dead_letter: bool, max_count: u32) -> Result<Vec<ServiceBusMessage>, String> {
+    with_namespace(|ns| {
+        let queue = ns.queue_mut(queue_name)?;
+        let source = if dead_letter { &mut queue.dead_letter } else { &mut queue.active };
+        let count = (max_count as usize).min(source.len());
+        Ok(source.drain(..count).map(|stored| stored.to_message(None)).collect())
+    })
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn receive_messages(queue_name: &str, max_count: u32) -> Result<Vec<LockedMessage>, String> {
+    with_namespace(|ns| {
+        let mut received = Vec::new();
+        while (received.len() as u32) < max_count {
+            let Some(mut stored) = ns.queue_mut(queue_name)?.active.pop_front() else {
+                break;
+            };
+            stored.delivery_count += 1;
+
+            ns.next_lock_id += 1;
+            let lock_token = format!("training-lock-{}", ns.next_lock_id);
+            let queue = ns.queue_mut(queue_name)?;
+            let lock_seconds = queue.properties.lock_duration_in_seconds.unwrap_or(DEFAULT_LOCK_DURATION_SECONDS);
+            let locked_until = chrono::Utc::now() + chrono::Duration::seconds(lock_seconds as i64);
+
+            received.push(LockedMessage {
+                message: stored.to_message(Some(locked_until)),
+                lock_token: lock_token.clone(),
+            });
+            queue.locked.insert(lock_token, LockedEntry { stored, locked_until });
+        }
+        Ok(received)
+    })
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn complete_message(queue_name: &str, lock_token: &str) -> Result<(), String> {
+    with_namespace(|ns| {
+        ns.queue_mut(queue_name)?.take_lock(lock_token)?;
+        Ok(())
+    })
+}
+
+// Abandon returns the message to the queue; like the real service, exceeding the
+// max delivery count moves it to the dead-letter queue instead
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn abandon_message(queue_name: &str, lock_token: &str) -> Result<(), String> {
+    with_namespace(|ns| {
+        let queue = ns.queue_mut(queue_name)?;
+        let stored = queue.take_lock(lock_token)?;
+        queue.release(stored);
+        Ok(())
+    })
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn dead_letter_message(
+    queue_name: &str,
+    sequence_number: u64,
+    reason: &str,
+    description: Option<&str>,
+) -> Result<(), String> {
+    with_namespace(|ns| {
+        let queue = ns.queue_mut(queue_name)?;
+        let position = queue
+            .active
+            .iter()
+            .position(|m| m.sequence_number == sequence_number)
+            .ok_or_else(|| format!("[training] Message with sequence number {} was not found", sequence_number))?;
+        let mut stored = queue.active.remove(position).expect("position is in range");
+        stored.message.dead_letter_reason = Some(reason.to_string());
+        stored.message.dead_letter_error_description = description.map(|d| d.to_string());
+        queue.dead_letter.push_back(stored);
+        Ok(())
+    })
+}
+
+// Dead-letter up to max_count messages from the head of the queue
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn dead_letter_all(queue_name: &str, reason: &str, description: Option<&str>, max_count: u32) -> Result<u32, String> {
+    with_namespace(|ns| {
+        let queue = ns.queue_mut(queue_name)?;
+        let count = (max_count as usize).min(queue.active.len());
+        for mut stored in queue.active.drain(..count).collect::<Vec<_>>() {
+            stored.message.dead_letter_reason = Some(reason.to_string());
+            stored.message.dead_letter_error_description = description.map(|d| d.to_string());
+            queue.dead_letter.push_back(stored);
+        }
+        Ok(count as u32)
+    })
+}
+
+// Move dead-lettered messages back to the active queue (clears the dead-letter fields)
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn resubmit_dead_letters(queue_name: &str, max_count: u32) -> Result<u32, String> {
+    with_namespace(|ns| ns.move_head(queue_name, true, queue_name, max_count)).map(|(moved, _)| moved)
+}
+
+// Move up to max_count messages from the head of a queue (or its dead-letter queue) to
+// another queue, as new messages
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn move_messages(source: &MessageSource, destination: &str, count: u32) -> Result<MoveMessagesProgress, String> {
+    let queue_name = training_queue(source)?;
+    if !source.dead_letter && queue_name == destination {
+        return Err("Source and destination must be different entities".to_string());
+    }
+    let (moved, last_sequence_number) =
+        with_namespace(|ns| ns.move_head(queue_name, source.dead_letter, destination, count))?;
+    Ok(MoveMessagesProgress {
+        source: source_path(queue_name, source.dead_letter),
+        destination: destination.to_string(),
+        requested: count,
+        moved,
+        last_sequence_number,
+        done: true,
+    })
+}
+
+// Resubmit a queue's dead letters to destination (default: the queue itself). There is
+// a single receiver and nothing else touches the sandbox meanwhile, so the counts
+// always verify.
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn drain_dead_letters(
+    source: &MessageSource,
+    destination: Option<&str>,
+    count: Option<u32>,
+) -> Result<DeadLetterDrainReport, String> {
+    let queue_name = training_queue(source)?;
+    let destination = destination.unwrap_or(queue_name).to_string();
+    let before = get_queue(queue_name)?.dead_letter_message_count.unwrap_or(0);
+    let requested = count.unwrap_or(before.min(u32::MAX as u64) as u32);
+    let (moved, last_sequence_number) =
+        with_namespace(|ns| ns.move_head(queue_name, true, &destination, requested))?;
+    let after = get_queue(queue_name)?.dead_letter_message_count.unwrap_or(0);
+    Ok(DeadLetterDrainReport {
+        source: source_path(queue_name, true),
+        destination,
+        receivers: 1,
+        requested,
+        moved,
+        dead_letter_count_before: before,
+        dead_letter_count_after: after,
+        verified: true,
+        checkpoints: vec![ReceiverCheckpoint {
+            receiver_id: 0,
+            moved,
+            last_sequence_number,
+            finished: true,
+            error: None,
+        }],
+        notes: Vec::new(),
+    })
+}
+
+// Remove the given messages wherever they are in the queue; unlike the real service
+// nothing has to be locked to find them, so scanned stays 0
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn delete_messages_by_sequence(source: &MessageSource, sequence_numbers: &[u64]) -> Result<DeleteMessagesProgress, String> {
+    let queue_name = training_queue(source)?;
+    let mut deleted = Vec::new();
+    let mut not_found = Vec::new();
+    with_namespace(|ns| {
+        let queue = ns.queue_mut(queue_name)?;
+        let messages = if source.dead_letter { &mut queue.dead_letter } else { &mut queue.active };
+        for &sequence_number in sequence_numbers {
+            match messages.iter().position(|m| m.sequence_number == sequence_number) {
+                Some(position) => {
+                    messages.remove(position);
+                    deleted.push(sequence_number);
+                }
+                None => not_found.push(sequence_number),
+            }
+        }
+        Ok(())
+    })?;
+    Ok(DeleteMessagesProgress {
+        source: source_path(queue_name, source.dead_letter),
+        requested: sequence_numbers.len() as u32,
+        deleted,
+        scanned: 0,
+        not_found,
+        done: true,
+    })
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn create_queue(queue_name: &str, properties: Option<&QueueProperties>) -> Result<(), String> {
+    if let Some(properties) = properties {
+        check_queue_settings(properties)?;
+    }
+    with_namespace(|ns| {
+        if ns.queues.contains_key(queue_name) {
+            return Err(format!("[training] Queue '{}' already exists in the training sandbox", queue_name));
+        }
+        let mut queue = SandboxQueue::new(queue_name);
+        if let Some(properties) = properties {
+            queue.apply_settings(properties);
+        }
+        ns.queues.insert(queue_name.to_string(), queue);
+        Ok(())
+    })
+}
+
+// Settings only; the messages stay where they are
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn update_queue(queue_name: &str, properties: &QueueProperties) -> Result<(), String> {
+    check_queue_settings(properties)?;
+    with_namespace(|ns| {
+        ns.queue_mut(queue_name)?.apply_settings(properties);
+        Ok(())
+    })
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn delete_queue(queue_name: &str) -> Result<(), String> {
+    with_namespace(|ns| {
+        ns.queues
+            .remove(queue_name)
+            .map(|_| ())
+            .ok_or_else(|| format!("[training] Queue '{}' does not exist in the training sandbox", queue_name))
+    })
+}
+
+// Runtime counts for verify_message_counts
+#[allow(dead_code)] // Used by main app, not test binary
+pub fn runtime_counts(queue_name: &str) -> Result<crate::azure::diagnostics::RuntimeCounts, String> {
+    let queue = get_queue(queue_name)?;
+    Ok(crate::azure::diagnostics::RuntimeCounts {
+        active: queue.active_message_count,
+        dead_letter: queue.dead_letter_message_count,
+        scheduled: queue.scheduled_message_count,
+        transfer: queue.transfer_message_count,
+        requires_session: false,
+    })
+}
+
+fn training_queue(source: &MessageSource) -> Result<&str, String> {
+    source
+        .queue_name
+        .as_deref()
+        .ok_or_else(|| "[training] Topics and subscriptions are not available in the training sandbox".to_string())
+}
+
+fn source_path(queue_name: &str, dead_letter: bool) -> String {
+    if dead_letter {
+        format!("{}/$deadletterqueue", queue_name)
+    } else {
+        queue_name.to_string()
+    }
+}
+
+fn check_queue_settings(properties: &QueueProperties) -> Result<(), String> {
+    if properties.requires_session.unwrap_or(false) {
+        return Err("Sessions are not supported in the training sandbox".to_string());
+    }
+    if properties.forward_to.is_some() || properties.forward_dead_lettered_messages_to.is_some() {
+        return Err("Auto-forwarding is not supported in the training sandbox".to_string());
+    }
+    Ok(())
+}
+
+impl TrainingNamespace {
+    // A few queues with realistic content, including poison messages in a DLQ
+    fn seeded() -> Self {
+        let mut ns = TrainingNamespace {
+            queues: BTreeMap::new(),
+            next_sequence_number: 0,
+            next_lock_id: 0,
+        };
+
+        for name in ["training-orders", "training-payments", "training-notifications"] {
+            ns.queues.insert(name.to_string(), SandboxQueue::new(name));
+        }
+
+        for i in 1..=25 {
+            let stored = ns.new_message(sample_message(
+                serde_json::json!({ "orderId": format!("ORD-{:04}", i), "amount": i * 10, "currency": "USD" }),
+                "OrderCreated",
+            ));
+            ns.queues.get_mut("training-orders").expect("seeded").active.push_back(stored);
+        }
+        for i in 1..=8 {
+            let mut stored = ns.new_message(sample_message(
+                serde_json::json!({ "paymentId": format!("PAY-{:04}", i), "amount": "not-a-number" }),
+                "PaymentReceived",
+            ));
+            stored.delivery_count = DEFAULT_MAX_DELIVERY_COUNT;
+            stored.message.dead_letter_reason = Some("MaxDeliveryCountExceeded".to_string());
+            stored.message.dead_letter_error_description =
+                Some("Message could not be consumed after 10 delivery attempts.".to_string());
+            ns.queues.get_mut("training-payments").expect("seeded").dead_letter.push_back(stored);
+        }
+
+        ns
+    }
+
+    fn new_message(&mut self, message: ServiceBusMessage) -> StoredMessage {
+        self.next_sequence_number += 1;
+        let mut message = message;
+        message.enqueued_time_utc = Some(crate::azure::timestamps::format(chrono::Utc::now()));
+        if message.message_id.is_none() {
+            message.message_id = Some(format!("training-{}", self.next_sequence_number));
+        }
+        StoredMessage {
+            message,
+            sequence_number: self.next_sequence_number,
+            delivery_count: 0,
+        }
+    }
+
+    // Take up to max_count messages off the head of a queue or its DLQ and send them to
+    // destination as new messages. Returns the count and the last sequence number taken.
+    fn move_head(
+        &mut self,
+        queue_name: &str,
+        dead_letter: bool,
+        destination: &str,
+        max_count: u32,
+    ) -> Result<(u32, Option<u64>), String> {
+        // Fail before anything is taken off the source
+        self.queue(destination)?;
+        let taken: Vec<StoredMessage> = {
+            let queue = self.queue_mut(queue_name)?;
+            let source = if dead_letter { &mut queue.dead_letter } else { &mut queue.active };
+            let count = (max_count as usize).min(source.len());
+            source.drain(..count).collect()
+        };
+
+        let last_sequence_number = taken.last().map(|stored| stored.sequence_number);
+        let moved = taken.len() as u32;
+        for stored in taken {
+            let mut message = stored.message;
+            message.dead_letter_reason = None;
+            message.dead_letter_error_description = None;
+            let stored = self.new_message(message);
+            self.queue_mut(destination)?.active.push_back(stored);
+        }
+        Ok((moved, last_sequence_number))
+    }
+
+    fn queue(&self, queue_name: &str) -> Result<&SandboxQueue, String> {
+        self.queues
+            .get(queue_name)
+            .ok_or_else(|| format!("[training] Queue '{}' does not exist in the training sandbox", queue_name))
+    }
+
+    fn queue_mut(&mut self, queue_name: &str) -> Result<&mut SandboxQueue, String> {
+        self.queues
+            .get_mut(queue_name)
+            .ok_or_else(|| format!("[training] Queue '{}' does not exist in the training sandbox", queue_name))
+    }
+
+    // Locks that ran out go back to the queue, as they would on the real service
+    fn expire_locks(&mut self) {
+        let now = chrono::Utc::now();
+        for queue in self.queues.values_mut() {
+            let expired: Vec<String> = queue
+                .locked
+                .iter()
+                .filter(|(_, entry)| entry.locked_until <= now)
+                .map(|(token, _)| token.clone())
+                .collect();
+            for token in expired {
+                if let Some(entry) = queue.locked.remove(&token) {
+                    queue.release(entry.stored);
+                }
+            }
+        }
+    }
+}
+
+impl SandboxQueue {
+    fn new(name: &str) -> Self {
+        SandboxQueue {
+            properties: QueueProperties {
+                name: name.to_string(),
+                max_size_in_megabytes: Some(1024),
+                lock_duration_in_seconds: Some(DEFAULT_LOCK_DURATION_SECONDS),
+                max_delivery_count: Some(DEFAULT_MAX_DELIVERY_COUNT),
+                default_message_time_to_live_in_seconds: None,
+                dead_lettering_on_message_expiration: Some(false),
+                duplicate_detection_history_time_window_in_seconds: None,
+                enable_batched_operations: Some(true),
+                auto_delete_on_idle_in_seconds: None,
+                status: None,
+                enable_partitioning: Some(false),
+                requires_session: Some(false),
+                requires_duplicate_detection: Some(false),
+                max_message_size_in_kilobytes: None,
+                forward_to: None,
+                forward_dead_lettered_messages_to: None,
+                user_metadata: None,
+                created_at: None,
+                updated_at: None,
+                accessed_at: None,
+                message_count: None,
+                active_message_count: None,
+                dead_letter_message_count: None,
+                scheduled_message_count: None,
+                transfer_message_count: None,
+                transfer_dead_letter_message_count: None,
+                size_in_bytes: None,
+                connection: None,
+            },
+            active: VecDeque::new(),
+            dead_letter: VecDeque::new(),
+            locked: HashMap::new(),
+        }
+    }
+
+    // What a create or update can change; the name and counts are the sandbox's own
+    fn apply_settings(&mut self, properties: &QueueProperties) {
+        let settings = &mut self.properties;
+        if properties.lock_duration_in_seconds.is_some() {
+            settings.lock_duration_in_seconds = properties.lock_duration_in_seconds;
+        }
+        if properties.max_delivery_count.is_some() {
+            settings.max_delivery_count = properties.max_delivery_count;
+        }
+        if properties.max_size_in_megabytes.is_some() {
+            settings.max_size_in_megabytes = properties.max_size_in_megabytes;
+        }
+        if properties.default_message_time_to_live_in_seconds.is_some() {
+            settings.default_message_time_to_live_in_seconds = properties.default_message_time_to_live_in_seconds;
+        }
+        if properties.dead_lettering_on_message_expiration.is_some() {
+            settings.dead_lettering_on_message_expiration = properties.dead_lettering_on_message_expiration;
+        }
+        if properties.user_metadata.is_some() {
+            settings.user_metadata = properties.user_metadata.clone();
+        }
+    }
+
+    fn current_properties(&self) -> QueueProperties {
+        let active = (self.active.len() + self.locked.len()) as u64;
+        let dead_letter = self.dead_letter.len() as u64;
+        QueueProperties {
+            message_count: Some(active + dead_letter),
+            active_message_count: Some(active),
+            dead_letter_message_count: Some(dead_letter),
+            scheduled_message_count: Some(0),
+            transfer_message_count: Some(0),
+            transfer_dead_letter_message_count: Some(0),
+            ..self.properties.clone()
+        }
+    }
+
+    fn take_lock(&mut self, lock_token: &str) -> Result<StoredMessage, String> {
+        self.locked
+            .remove(lock_token)
+            .map(|entry| entry.stored)
+            .ok_or_else(|| "[training] The lock supplied is invalid: it expired or the message was already settled".to_string())
+    }
+
+    fn release(&mut self, mut stored: StoredMessage) {
+        let max_delivery_count = self.properties.max_delivery_count.unwrap_or(DEFAULT_MAX_DELIVERY_COUNT);
+        if stored.delivery_count >= max_delivery_count {
+            stored.message.dead_letter_reason = Some("MaxDeliveryCountExceeded".to_string());
+            stored.message.dead_letter_error_description = Some(format!(
+                "Message could not be consumed after {} delivery attempts.",
+                max_delivery_count
+            ));
+            self.dead_letter.push_back(stored);
+            return;
+        }
+        // Keep the queue in sequence order
+        let position = self
+            .active
+            .iter()
+            .position(|m| m.sequence_number > stored.sequence_number)
+            .unwrap_or(self.active.len());
+        self.active.insert(position, stored);
+    }
+}
+
+impl StoredMessage {
+    fn to_message(&self, locked_until: Option<chrono::DateTime<chrono::Utc>>) -> ServiceBusMessage {
+        ServiceBusMessage {
+            sequence_number: Some(self.sequence_number),
+            delivery_count: Some(self.delivery_count),
+            locked_until_utc: locked_until.map(crate::azure::timestamps::format),
+            ..self.message.clone()
+        }
+    }
+}
+
+fn sample_message(body: serde_json::Value, subject: &str) -> ServiceBusMessage {
+    ServiceBusMessage {
+        body,
+        message_id: None,
+        content_type: Some("application/json".to_string()),
+        correlation_id: None,
+        session_id: None,
+        reply_to: None,
+        reply_to_session_id: None,
+        subject: Some(subject.to_string()),
+        time_to_live: None,
+        to: None,
+        application_properties: Some(serde_json::json!({ "environment": "training" })),
+        delivery_count: None,
+        enqueued_time_utc: None,
+        locked_until_utc: None,
+        sequence_number: None,
+        dead_letter_reason: None,
+        dead_letter_error_description: None,
+        dead_letter_source: None,
+        scheduled_enqueue_time_utc: None,
+        partition_key: None,
+        via_partition_key: None,
+        state: None,
+        body_size: None,
+        body_truncated: None,
+        body_encoding: None,
+        body_hex_preview: None,
+        body_envelope: None,
+        body_compression: None,
+        raw_body: None,
+        decoded_body: None,
+        decode_error: None,
+        parse_warning: None,
+        expires_at_utc: None,
+    }
+}
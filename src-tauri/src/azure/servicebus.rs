@@ -2,13 +2,28 @@ use crate::azure::auth::{
     generate_sas_token, get_namespace_from_endpoint, get_endpoint_domain, parse_connection_string,
     ParsedConnectionString,
 };
+use crate::azure::compression;
+use crate::azure::data_contract;
+use crate::azure::dry_run;
+use crate::azure::errors::{http_error, is_access_denied, CatalogError};
+use crate::azure::timestamps;
 use crate::azure::types::*;
 use reqwest::Client;
 use serde::Deserialize;
 use serde_xml_rs::from_str;
-use std::time::Duration;
 
 const API_VERSION: &str = "2021-05";
+// Peek tuning limits (see PeekOptions)
+const DEFAULT_PEEK_TIMEOUT_SECONDS: u32 = 60;
+const MAX_PEEK_TIMEOUT_SECONDS: u32 = 300;
+const MAX_HTTP_PEEK_PAGE_SIZE: u32 = 32;
+const MAX_AMQP_PEEK_PAGE_SIZE: u32 = 5000;
+// Safety caps for delete_messages_by_sequence
+const MAX_DELETE_BY_SEQUENCE: usize = 500;
+const MAX_DELETE_SCAN: u32 = 5000;
+// drain_messages holds every drained message in memory
+const MAX_DRAIN_COUNT: u32 = 10_000;
+const DRAIN_BATCH_SIZE: u32 = 16;
 
 pub struct ServiceBusClient {
     client: Client,
@@ -16,6 +31,7 @@ pub struct ServiceBusClient {
     endpoint_domain: String,
     parsed_connection: Option<ParsedConnectionString>,
     use_azure_ad: bool,
+    transport: Option<Transport>,
 }
 
 #[allow(dead_code)] // Methods are used by main app, not all by test binary
@@ -61,6 +77,7 @@ impl ServiceBusClient {
             endpoint_domain,
             parsed_connection,
             use_azure_ad: connection.use_azure_ad.unwrap_or(false),
+            transport: connection.transport,
         })
     }
 
@@ -93,112 +110,30 @@ impl ServiceBusClient {
 
     // Queue operations
     pub async fn list_queues(&self) -> Result<Vec<QueueProperties>, String> {
-        let mut all_queues = Vec::new();
-        let mut url = format!("{}/$Resources/Queues?api-version={}", self.get_base_url(), API_VERSION);
-        let mut page_count = 0;
-        
-        loop {
-            page_count += 1;
-            eprintln!("[list_queues] Fetching page {} from: {}", page_count, url);
-            
-            let auth_header = self.get_auth_header(&url).await?;
-
-            let response = self
-                .client
-                .get(&url)
-                .header("Authorization", &auth_header)
-                .send()
-                .await
-                .map_err(|e| format!("Failed to list queues: {}", e))?;
-
-            let status = response.status();
-            if !status.is_success() {
-                let error_text = response.text().await.unwrap_or_default();
-                return Err(format!("Failed to list queues: {} - {}", status, error_text));
-            }
-
-            let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
-            
-            // Extract next link manually from XML since serde_xml_rs doesn't parse attributes correctly
-            let next_link_href = if let Some(cap) = regex::Regex::new(r#"<link\s+rel="next"\s+href="([^"]+)""#)
-                .ok()
-                .and_then(|re| re.captures(&xml))
-            {
-                Some(cap[1].replace("&amp;", "&"))
-            } else {
-                None
-            };
-            
-            if page_count == 1 {
-                eprintln!("[list_queues] Extracted next link: {:?}", next_link_href);
-            }
-            
-            let feed: QueueFeed = from_str(&xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
-
-            eprintln!("[list_queues] Page {}: Found {} entries", page_count, feed.entries.len());
-
-            // If we got 0 entries, we're done
-            if feed.entries.is_empty() {
-                eprintln!("[list_queues] No entries returned, pagination complete");
-                break;
-            }
-
-            // Extract content for each entry using regex (since serde_xml_rs can't handle nested XML in content)
-            // Match each entry's content separately
-            let content_regex = regex::Regex::new(r#"(?s)<entry[^>]*>.*?<title[^>]*>([^<]+)</title>.*?<content[^>]*type="application/xml"[^>]*>(.*?)</content>"#).ok();
-            
-            for entry in feed.entries {
-                let mut entry_with_content = entry.clone();
-                
-                // Try to find content for this entry by matching title
-                if let Some(ref re) = content_regex {
-                    if let Some(cap) = re.captures_iter(&xml).find(|cap| {
-                        cap.get(1).map(|m| m.as_str().trim()) == Some(entry.title.trim())
-                    }) {
-                        if let Some(content_match) = cap.get(2) {
-                            entry_with_content.content = Some(content_match.as_str().to_string());
-                        }
-                    }
-                }
-                
-                let props = self.queue_entry_to_properties(&entry_with_content)?;
-                all_queues.push(props);
-            }
-
-            eprintln!("[list_queues] Total queues so far: {}", all_queues.len());
-
-            // Use the manually extracted next link
-            if let Some(href) = next_link_href {
-                // Normalize the href URL
-                let next_url = if let Some(href) = href.strip_prefix("http") {
-                    format!("http{}", href)
-                } else {
-                    href.clone()
-                };
-                
-                // Decode URLs for comparison (normalize %24 to $, etc.)
-                let normalized_current = url.replace("%24", "$");
-                let normalized_next = next_url.replace("%24", "$");
-                
-                // Check if the next URL is the same as current URL to prevent infinite loop
-                if normalized_next == normalized_current {
-                    eprintln!("[list_queues] Next link is same as current URL, pagination complete");
-                    break;
-                }
-                
-                eprintln!("[list_queues] Following next link: {}", next_url);
-                url = next_url;
-                continue;
-            } else {
-                eprintln!("[list_queues] No next link found, pagination complete");
-            }
-            
-            // No more pages
-            break;
-        }
+        self.list_queues_with_progress(None).await
+    }
 
-        eprintln!("[list_queues] Final total: {} queues", all_queues.len());
-        Ok(all_queues)
+    // Same as list_queues, reporting each page as it arrives
+    pub async fn list_queues_with_progress(
+        &self,
+        on_progress: Option<&(dyn Fn(&ListingProgress<QueueProperties>) + Send + Sync)>,
+    ) -> Result<Vec<QueueProperties>, String> {
+        let url = format!("{}/$Resources/Queues?api-version={}", self.get_base_url(), API_VERSION);
+        let queues = self
+            .list_feed_pages(url, "list queues", "queues", None, on_progress, |xml| {
+                let feed: QueueFeed = parse_feed(xml)?;
+                feed.entries
+                    .into_iter()
+                    .map(|entry| {
+                        let content = entry_content(xml, &entry.title);
+                        self.queue_entry_to_properties(&QueueEntry { content, ..entry })
+                    })
+                    .collect()
+            })
+            .await?;
+
+        eprintln!("[list_queues] Final total: {} queues", queues.len());
+        Ok(without_system_entities(queues, |q| &q.name))
     }
 
     pub async fn list_queues_page(&self, skip: Option<u32>, top: Option<u32>) -> Result<Vec<QueueProperties>, String> {
@@ -226,74 +161,152 @@ impl ServiceBusClient {
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to list queues: {} - {}", status, error_text));
+            return Err(http_error("list queues", status, &error_text));
         }
 
         let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
         
-        let feed: QueueFeed = from_str(&xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
+        let feed: QueueFeed = parse_feed(&xml)?;
 
         eprintln!("[list_queues_page] Found {} entries", feed.entries.len());
 
-        let mut queues = Vec::new();
-        
-        // Extract content for each entry using regex
-        let content_regex = regex::Regex::new(r#"(?s)<entry[^>]*>.*?<title[^>]*>([^<]+)</title>.*?<content[^>]*type="application/xml"[^>]*>(.*?)</content>"#).ok();
-        
-        for entry in feed.entries {
-            let mut entry_with_content = entry.clone();
-            
-            // Try to find content for this entry by matching title
-            if let Some(ref re) = content_regex {
-                if let Some(cap) = re.captures_iter(&xml).find(|cap| {
-                    cap.get(1).map(|m| m.as_str().trim()) == Some(entry.title.trim())
-                }) {
-                    if let Some(content_match) = cap.get(2) {
-                        entry_with_content.content = Some(content_match.as_str().to_string());
-                    }
-                }
-            }
-            
-            let props = self.queue_entry_to_properties(&entry_with_content)?;
-            queues.push(props);
-        }
+        let queues = feed
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let content = entry_content(&xml, &entry.title);
+                self.queue_entry_to_properties(&QueueEntry { content, ..entry })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
 
         eprintln!("[list_queues_page] Returning {} queues", queues.len());
-        Ok(queues)
+        Ok(without_system_entities(queues, |q| &q.name))
     }
 
     pub async fn get_queue(&self, queue_name: &str) -> Result<QueueProperties, String> {
         let url = format!("{}/{}?api-version={}", self.get_base_url(), queue_name, API_VERSION);
-        let auth_header = self.get_auth_header(&url).await?;
+        let (xml, _) = self
+            .get_entity_xml_if_changed(&url, "get queue", None)
+            .await?
+            .ok_or("Queue was not returned")?;
+        self.parse_queue_xml(&xml)
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .header("Authorization", &auth_header)
-            .send()
+    // Re-fetch a queue, skipping the parse when it has not changed since `etag`
+    pub async fn refresh_queue(&self, queue_name: &str, etag: Option<&str>) -> Result<EntityRefresh<QueueProperties>, String> {
+        let url = format!("{}/{}?api-version={}", self.get_base_url(), queue_name, API_VERSION);
+        match self.get_entity_xml_if_changed(&url, "get queue", etag).await? {
+            Some((xml, etag)) => Ok(EntityRefresh {
+                modified: true,
+                etag: Some(etag),
+                entity: Some(self.parse_queue_xml(&xml)?),
+            }),
+            None => Ok(EntityRefresh {
+                modified: false,
+                etag: etag.map(|e| e.to_string()),
+                entity: None,
+            }),
+        }
+    }
+
+    fn parse_queue_xml(&self, xml: &str) -> Result<QueueProperties, String> {
+        let mut entry: QueueEntry = from_str(xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
+        entry.content = entry_content(xml, &entry.title);
+        self.queue_entry_to_properties(&entry)
+    }
+
+    // GET an entity description with If-None-Match. Returns None when the entity is
+    // unchanged (304, or the same body hash when the service sends no ETag), otherwise
+    // the XML and the ETag to send next time.
+    async fn get_entity_xml_if_changed(
+        &self,
+        url: &str,
+        operation: &str,
+        etag: Option<&str>,
+    ) -> Result<Option<(String, String)>, String> {
+        let auth_header = self.get_auth_header(url).await?;
+        get_xml_if_changed(self.client.clone(), url.to_string(), auth_header, operation.to_string(), etag.map(|e| e.to_string()))
             .await
-            .map_err(|e| format!("Failed to get queue: {}", e))?;
+    }
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to get queue: {} - {}", status, error_text));
-        }
+    // Runtime message counts for many entities at once. Only the entity descriptions
+    // are fetched (never messages), a bounded number at a time, and entities whose
+    // description is unchanged since `etag` come back with modified: false and no counts.
+    pub async fn estimate_backlog(&self, entities: &[BacklogEntity]) -> Vec<BacklogEstimate> {
+        const CONCURRENCY: usize = 16;
+
+        let mut estimates = Vec::with_capacity(entities.len());
+        for chunk in entities.chunks(CONCURRENCY) {
+            let mut tasks = tokio::task::JoinSet::new();
+            let mut chunk_estimates: Vec<BacklogEstimate> = Vec::with_capacity(chunk.len());
+            for (index, entity) in chunk.iter().enumerate() {
+                let mut estimate = BacklogEstimate {
+                    entity_path: String::new(),
+                    modified: false,
+                    etag: entity.etag.clone(),
+                    message_count: None,
+                    active_message_count: None,
+                    dead_letter_message_count: None,
+                    scheduled_message_count: None,
+                    transfer_message_count: None,
+                    transfer_dead_letter_message_count: None,
+                    error: None,
+                };
+                let entity_path = match (&entity.queue_name, &entity.topic_name, &entity.subscription_name) {
+                    (Some(q), _, _) => q.clone(),
+                    (None, Some(t), Some(s)) => format!("{}/Subscriptions/{}", t, s),
+                    (None, Some(t), None) => t.clone(),
+                    _ => {
+                        estimate.error = Some("Either queue_name or topic_name must be provided".to_string());
+                        chunk_estimates.push(estimate);
+                        continue;
+                    }
+                };
+                estimate.entity_path = entity_path.clone();
+                let url = format!("{}/{}?api-version={}", self.get_base_url(), entity_path, API_VERSION);
+                match self.get_auth_header(&url).await {
+                    Ok(auth_header) => {
+                        let operation = format!("get entity '{}'", entity_path);
+                        let request = get_xml_if_changed(self.client.clone(), url, auth_header, operation, entity.etag.clone());
+                        tasks.spawn(async move { (index, request.await) });
+                    }
+                    Err(e) => estimate.error = Some(e),
+                }
+                chunk_estimates.push(estimate);
+            }
 
-        let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
-        let mut entry: QueueEntry = from_str(&xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
-        
-        // Extract content XML using regex (same approach as list_queues)
-        let content_regex = regex::Regex::new(r#"(?s)<entry[^>]*>.*?<title[^>]*>([^<]+)</title>.*?<content[^>]*type="application/xml"[^>]*>(.*?)</content>"#).ok();
-        if let Some(ref re) = content_regex {
-            if let Some(cap) = re.captures(&xml) {
-                if let Some(content_match) = cap.get(2) {
-                    entry.content = Some(content_match.as_str().to_string());
+            while let Some(joined) = tasks.join_next().await {
+                let (index, result) = match joined {
+                    Ok(joined) => joined,
+                    Err(e) => {
+                        eprintln!("[estimate_backlog] Request task failed: {}", e);
+                        continue;
+                    }
+                };
+                let estimate = &mut chunk_estimates[index];
+                match result {
+                    // Azure answers 200 with an empty feed for entities that don't exist
+                    Ok(Some((xml, _))) if !xml.contains("<entry") => {
+                        estimate.error = Some(format!("Entity '{}' not found", estimate.entity_path));
+                    }
+                    Ok(Some((xml, etag))) => {
+                        estimate.modified = true;
+                        estimate.etag = Some(etag);
+                        let count = |name: &str| message_count_detail(&xml, name);
+                        estimate.message_count = count("MessageCount");
+                        estimate.active_message_count = count("ActiveMessageCount");
+                        estimate.dead_letter_message_count = count("DeadLetterMessageCount");
+                        estimate.scheduled_message_count = count("ScheduledMessageCount");
+                        estimate.transfer_message_count = count("TransferMessageCount");
+                        estimate.transfer_dead_letter_message_count = count("TransferDeadLetterMessageCount");
+                    }
+                    Ok(None) => {}
+                    Err(e) => estimate.error = Some(e),
                 }
             }
+            estimates.extend(chunk_estimates);
         }
-
-        self.queue_entry_to_properties(&entry)
+        estimates
     }
 
     pub async fn create_queue(&self, queue_name: &str, properties: Option<&QueueProperties>) -> Result<(), String> {
@@ -301,6 +314,9 @@ impl ServiceBusClient {
         let auth_header = self.get_auth_header(&url).await?;
 
         let xml = self.queue_properties_to_xml(queue_name, properties, false)?;
+        if dry_run::intercept("create queue", "PUT", &url, Some(&xml)) {
+            return Ok(());
+        }
 
         let response = self
             .client
@@ -315,7 +331,7 @@ impl ServiceBusClient {
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to create queue: {} - {}", status, error_text));
+            return Err(http_error("create queue", status, &error_text));
         }
 
         Ok(())
@@ -324,6 +340,7 @@ impl ServiceBusClient {
     pub async fn update_queue(&self, queue_name: &str, properties: &QueueProperties) -> Result<(), String> {
         // Get existing queue first
         let existing = self.get_queue(queue_name).await?;
+        check_immutable_properties("queue", queue_name, IMMUTABLE_QUEUE_PROPERTIES, properties, &existing)?;
         
         // Merge properties: use new value if provided, otherwise use existing value
         // For updates, we must include ALL updatable properties, using existing values for ones not being changed
@@ -337,10 +354,22 @@ impl ServiceBusClient {
             dead_lettering_on_message_expiration: properties.dead_lettering_on_message_expiration.or(existing.dead_lettering_on_message_expiration),
             duplicate_detection_history_time_window_in_seconds: properties.duplicate_detection_history_time_window_in_seconds.or(existing.duplicate_detection_history_time_window_in_seconds),
             enable_batched_operations: properties.enable_batched_operations.or(existing.enable_batched_operations),
+            auto_delete_on_idle_in_seconds: properties.auto_delete_on_idle_in_seconds.or(existing.auto_delete_on_idle_in_seconds),
+            status: properties.status.clone().or(existing.status),
+            max_message_size_in_kilobytes: properties.max_message_size_in_kilobytes.or(existing.max_message_size_in_kilobytes),
+            forward_to: properties.forward_to.clone().or(existing.forward_to),
+            forward_dead_lettered_messages_to: properties
+                .forward_dead_lettered_messages_to
+                .clone()
+                .or(existing.forward_dead_lettered_messages_to),
+            user_metadata: properties.user_metadata.clone().or(existing.user_metadata),
             // Immutable properties - always use existing values (cannot be changed)
             enable_partitioning: existing.enable_partitioning, // Always use existing - immutable
             requires_session: existing.requires_session, // Always use existing - immutable
             requires_duplicate_detection: existing.requires_duplicate_detection, // Always use existing - immutable
+            created_at: existing.created_at,
+            updated_at: existing.updated_at,
+            accessed_at: existing.accessed_at,
             message_count: existing.message_count,
             active_message_count: existing.active_message_count,
             dead_letter_message_count: existing.dead_letter_message_count,
@@ -348,6 +377,7 @@ impl ServiceBusClient {
             transfer_message_count: existing.transfer_message_count,
             transfer_dead_letter_message_count: existing.transfer_dead_letter_message_count,
             size_in_bytes: existing.size_in_bytes,
+            connection: None,
         };
 
         // For updates, we need to use create_queue but mark it as an update to exclude immutable properties
@@ -356,6 +386,9 @@ impl ServiceBusClient {
 
         // Generate XML for update (excluding immutable properties)
         let xml = self.queue_properties_to_xml(queue_name, Some(&merged), true)?;
+        if dry_run::intercept("update queue", "PUT", &url, Some(&xml)) {
+            return Ok(());
+        }
         
         // Log the XML for debugging (remove in production)
         eprintln!("[update_queue] XML being sent:\n{}", xml);
@@ -377,118 +410,261 @@ impl ServiceBusClient {
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to update queue: {} - {}", status, error_text));
+            return Err(http_error("update queue", status, &error_text));
         }
 
         Ok(())
     }
 
-    pub async fn delete_queue(&self, queue_name: &str) -> Result<(), String> {
-        let url = format!("{}/{}?api-version={}", self.get_base_url(), queue_name, API_VERSION);
-        let auth_header = self.get_auth_header(&url).await?;
-
-        let response = self
-            .client
-            .delete(&url)
-            .header("Authorization", &auth_header)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to delete queue: {}", e))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to delete queue: {} - {}", status, error_text));
+    // Create a uniquely named queue that deletes itself after being idle, for reply
+    // queues and other throwaway tests. The name prefix marks it as created by the app.
+    pub async fn create_temporary_queue(&self, auto_delete_on_idle_seconds: Option<u64>) -> Result<QueueProperties, String> {
+        let idle = auto_delete_on_idle_seconds.unwrap_or(TEMPORARY_QUEUE_IDLE_SECONDS);
+        if idle < 300 {
+            return Err("Auto-delete on idle must be at least 300 seconds (5 minutes)".to_string());
         }
 
-        Ok(())
-    }
+        let queue_name = format!(
+            "{}{}-{:08x}",
+            TEMPORARY_QUEUE_PREFIX,
+            chrono::Utc::now().format("%Y%m%d%H%M%S"),
+            rand::random::<u32>()
+        );
+        let properties = QueueProperties {
+            name: queue_name.clone(),
+            max_size_in_megabytes: None,
+            lock_duration_in_seconds: None,
+            max_delivery_count: None,
+            default_message_time_to_live_in_seconds: None,
+            dead_lettering_on_message_expiration: None,
+            duplicate_detection_history_time_window_in_seconds: None,
+            enable_batched_operations: None,
+            auto_delete_on_idle_in_seconds: Some(idle),
+            status: None,
+            enable_partitioning: None,
+            requires_session: None,
+            requires_duplicate_detection: None,
+            max_message_size_in_kilobytes: None,
+            forward_to: None,
+            forward_dead_lettered_messages_to: None,
+            user_metadata: None,
+            created_at: None,
+            updated_at: None,
+            accessed_at: None,
+            message_count: None,
+            active_message_count: None,
+            dead_letter_message_count: None,
+            scheduled_message_count: None,
+            transfer_message_count: None,
+            transfer_dead_letter_message_count: None,
+            size_in_bytes: None,
+            connection: None,
+        };
+        self.create_queue(&queue_name, Some(&properties)).await?;
 
-    // Topic operations
-    pub async fn list_topics(&self) -> Result<Vec<TopicProperties>, String> {
-        let mut all_topics = Vec::new();
-        let mut url = format!("{}/$Resources/Topics?api-version={}", self.get_base_url(), API_VERSION);
-        
-        loop {
-            let auth_header = self.get_auth_header(&url).await?;
+        eprintln!("[create_temporary_queue] Created '{}' (auto-delete after {}s idle)", queue_name, idle);
+        self.get_queue(&queue_name).await
+    }
 
-            let response = self
-                .client
-                .get(&url)
-                .header("Authorization", &auth_header)
-                .send()
-                .await
-                .map_err(|e| format!("Failed to list topics: {}", e))?;
+    // Request/reply round trip: send the request with ReplyTo pointing at a reply queue
+    // (a new temporary queue unless one is given) and wait for a message whose
+    // CorrelationId matches the request's MessageId. A temporary queue is deleted
+    // afterwards; it would also delete itself once idle.
+    pub async fn request_reply(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        request: &ServiceBusMessage,
+        reply_queue: Option<&str>,
+        timeout_seconds: u32,
+    ) -> Result<RequestReplyResult, String> {
+        let started = std::time::Instant::now();
+        let timeout = std::time::Duration::from_secs(timeout_seconds.clamp(1, 600) as u64);
+
+        let (reply_queue, temporary) = match reply_queue {
+            Some(name) => (name.to_string(), false),
+            None => (self.create_temporary_queue(None).await?.name, true),
+        };
 
-            let status = response.status();
-            if !status.is_success() {
-                let error_text = response.text().await.unwrap_or_default();
-                return Err(format!("Failed to list topics: {} - {}", status, error_text));
-            }
+        let mut request = request.clone();
+        let message_id = request
+            .message_id
+            .get_or_insert_with(|| format!("{:016x}{:016x}", rand::random::<u64>(), rand::random::<u64>()))
+            .clone();
+        request.reply_to = Some(reply_queue.clone());
 
-            let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
-            let feed: TopicFeed = from_str(&xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
+        let result = self
+            .await_reply(queue_name, topic_name, &request, &message_id, &reply_queue, started + timeout)
+            .await;
 
-            for entry in feed.entries {
-                let props = self.topic_entry_to_properties(&entry)?;
-                all_topics.push(props);
+        if temporary {
+            if let Err(e) = self.delete_queue(&reply_queue).await {
+                eprintln!("[request_reply] Failed to delete temporary queue '{}': {}", reply_queue, e);
             }
+        }
 
-            // Check for next page link
-            let next_link = feed.links.iter().find(|link| {
-                link.rel.as_ref().map(|r| r == "next").unwrap_or(false)
-            });
+        let (reply, unmatched_replies) = result?;
+        eprintln!(
+            "[request_reply] {} after {}ms",
+            if reply.is_some() { "Reply received" } else { "Timed out" },
+            started.elapsed().as_millis()
+        );
+        Ok(RequestReplyResult {
+            request,
+            timed_out: reply.is_none(),
+            reply,
+            reply_queue,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            unmatched_replies,
+        })
+    }
 
-            if let Some(link) = next_link {
-                if let Some(href) = &link.href {
-                    if href.starts_with("http") {
-                        if let Ok(parsed_url) = url::Url::parse(href) {
-                            url = format!("{}{}?{}", self.get_base_url(), parsed_url.path(), parsed_url.query().unwrap_or(""));
-                        } else {
-                            break;
-                        }
-                    } else {
-                        url = format!("{}{}", self.get_base_url(), href);
-                    }
-                    continue;
+    async fn await_reply(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        request: &ServiceBusMessage,
+        message_id: &str,
+        reply_queue: &str,
+        deadline: std::time::Instant,
+    ) -> Result<(Option<ServiceBusMessage>, u32), String> {
+        self.send_message(queue_name, topic_name, request).await?;
+
+        let mut unmatched = Vec::new();
+        let mut reply = None;
+        while reply.is_none() {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            let wait = remaining.as_secs().clamp(1, 60) as u32;
+            for locked in self.receive_messages(Some(reply_queue), None, None, 10, Some(wait)).await? {
+                let sequence_number = locked.message.sequence_number.unwrap_or_default();
+                if reply.is_none() && locked.message.correlation_id.as_deref() == Some(message_id) {
+                    self.complete_message(Some(reply_queue), None, None, sequence_number, &locked.lock_token)
+                        .await?;
+                    reply = Some(locked.message);
+                } else {
+                    unmatched.push((sequence_number, locked.lock_token));
                 }
             }
-            
-            break;
         }
 
-        Ok(all_topics)
+        // Leave unrelated messages for whoever else uses the reply queue
+        for (sequence_number, lock_token) in &unmatched {
+            if let Err(e) = self.abandon_message(Some(reply_queue), None, None, *sequence_number, lock_token).await {
+                eprintln!("[request_reply] Failed to release message {}: {}", sequence_number, e);
+            }
+        }
+        Ok((reply, unmatched.len() as u32))
     }
 
-    pub async fn get_topic(&self, topic_name: &str) -> Result<TopicProperties, String> {
-        let url = format!("{}/{}?api-version={}", self.get_base_url(), topic_name, API_VERSION);
+    // Temporary queues created by this app that the service has not deleted yet
+    pub async fn list_temporary_queues(&self) -> Result<Vec<QueueProperties>, String> {
+        Ok(self
+            .list_queues()
+            .await?
+            .into_iter()
+            .filter(|q| is_temporary_queue(&q.name))
+            .collect())
+    }
+
+    pub async fn delete_queue(&self, queue_name: &str) -> Result<(), String> {
+        check_not_system_entity("delete", queue_name)?;
+        let url = format!("{}/{}?api-version={}", self.get_base_url(), queue_name, API_VERSION);
+        if dry_run::intercept("delete queue", "DELETE", &url, None) {
+            return Ok(());
+        }
         let auth_header = self.get_auth_header(&url).await?;
 
         let response = self
             .client
-            .get(&url)
+            .delete(&url)
             .header("Authorization", &auth_header)
             .send()
             .await
-            .map_err(|e| format!("Failed to get topic: {}", e))?;
+            .map_err(|e| format!("Failed to delete queue: {}", e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to get topic: {} - {}", status, error_text));
+            return Err(http_error("delete queue", status, &error_text));
         }
 
-        let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
-        let entry: TopicEntry = from_str(&xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
+        Ok(())
+    }
+
+    // Topic operations
+    pub async fn list_topics(&self) -> Result<Vec<TopicProperties>, String> {
+        self.list_topics_with_progress(None).await
+    }
+
+    pub async fn list_topics_with_progress(
+        &self,
+        on_progress: Option<&(dyn Fn(&ListingProgress<TopicProperties>) + Send + Sync)>,
+    ) -> Result<Vec<TopicProperties>, String> {
+        let url = format!("{}/$Resources/Topics?api-version={}", self.get_base_url(), API_VERSION);
+        let topics = self
+            .list_feed_pages(url, "list topics", "topics", None, on_progress, |xml| {
+                let feed: TopicFeed = parse_feed(xml)?;
+                feed.entries
+                    .into_iter()
+                    .map(|entry| {
+                        let content = entry_content(xml, &entry.title);
+                        self.topic_entry_to_properties(&TopicEntry { content, ..entry })
+                    })
+                    .collect()
+            })
+            .await?;
+        Ok(without_system_entities(topics, |t| &t.name))
+    }
+
+    pub async fn get_topic(&self, topic_name: &str) -> Result<TopicProperties, String> {
+        let url = format!("{}/{}?api-version={}", self.get_base_url(), topic_name, API_VERSION);
+        let (xml, _) = self
+            .get_entity_xml_if_changed(&url, "get topic", None)
+            .await?
+            .ok_or("Topic was not returned")?;
+        self.parse_topic_xml(&xml)
+    }
+
+    // Re-fetch a topic, skipping the parse when it has not changed since `etag`
+    pub async fn refresh_topic(&self, topic_name: &str, etag: Option<&str>) -> Result<EntityRefresh<TopicProperties>, String> {
+        let url = format!("{}/{}?api-version={}", self.get_base_url(), topic_name, API_VERSION);
+        match self.get_entity_xml_if_changed(&url, "get topic", etag).await? {
+            Some((xml, etag)) => Ok(EntityRefresh {
+                modified: true,
+                etag: Some(etag),
+                entity: Some(self.parse_topic_xml(&xml)?),
+            }),
+            None => Ok(EntityRefresh {
+                modified: false,
+                etag: etag.map(|e| e.to_string()),
+                entity: None,
+            }),
+        }
+    }
 
+    fn parse_topic_xml(&self, xml: &str) -> Result<TopicProperties, String> {
+        let mut entry: TopicEntry = from_str(xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
+        entry.content = entry_content(xml, &entry.title);
         self.topic_entry_to_properties(&entry)
     }
 
     pub async fn create_topic(&self, topic_name: &str, properties: Option<&TopicProperties>) -> Result<(), String> {
+        self.put_topic(topic_name, properties, false).await
+    }
+
+    // Creates, or with is_update replaces the description of an existing topic
+    async fn put_topic(&self, topic_name: &str, properties: Option<&TopicProperties>, is_update: bool) -> Result<(), String> {
+        let action = if is_update { "update topic" } else { "create topic" };
         let url = format!("{}/{}?api-version={}", self.get_base_url(), topic_name, API_VERSION);
         let auth_header = self.get_auth_header(&url).await?;
 
-        let xml = self.topic_properties_to_xml(topic_name, properties)?;
+        let xml = self.topic_properties_to_xml(topic_name, properties, is_update)?;
+        if dry_run::intercept(action, "PUT", &url, Some(&xml)) {
+            return Ok(());
+        }
 
         let response = self
             .client
@@ -498,12 +674,12 @@ impl ServiceBusClient {
             .body(xml)
             .send()
             .await
-            .map_err(|e| format!("Failed to create topic: {}", e))?;
+            .map_err(|e| format!("Failed to {}: {}", action, e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to create topic: {} - {}", status, error_text));
+            return Err(http_error(action, status, &error_text));
         }
 
         Ok(())
@@ -511,6 +687,7 @@ impl ServiceBusClient {
 
     pub async fn update_topic(&self, topic_name: &str, properties: &TopicProperties) -> Result<(), String> {
         let existing = self.get_topic(topic_name).await?;
+        check_immutable_properties("topic", topic_name, IMMUTABLE_TOPIC_PROPERTIES, properties, &existing)?;
         
         let merged = TopicProperties {
             name: properties.name.clone(),
@@ -518,17 +695,31 @@ impl ServiceBusClient {
             default_message_time_to_live_in_seconds: properties.default_message_time_to_live_in_seconds.or(existing.default_message_time_to_live_in_seconds),
             duplicate_detection_history_time_window_in_seconds: properties.duplicate_detection_history_time_window_in_seconds.or(existing.duplicate_detection_history_time_window_in_seconds),
             enable_batched_operations: properties.enable_batched_operations.or(existing.enable_batched_operations),
-            enable_partitioning: properties.enable_partitioning.or(existing.enable_partitioning),
-            requires_duplicate_detection: properties.requires_duplicate_detection.or(existing.requires_duplicate_detection),
+            // Immutable - checked above
+            enable_partitioning: existing.enable_partitioning,
+            requires_duplicate_detection: existing.requires_duplicate_detection,
+            auto_delete_on_idle_in_seconds: properties.auto_delete_on_idle_in_seconds.or(existing.auto_delete_on_idle_in_seconds),
+            status: properties.status.clone().or(existing.status),
+            support_ordering: properties.support_ordering.or(existing.support_ordering),
+            max_message_size_in_kilobytes: properties.max_message_size_in_kilobytes.or(existing.max_message_size_in_kilobytes),
+            user_metadata: properties.user_metadata.clone().or(existing.user_metadata),
+            created_at: existing.created_at,
+            updated_at: existing.updated_at,
+            accessed_at: existing.accessed_at,
             size_in_bytes: existing.size_in_bytes,
             subscription_count: existing.subscription_count,
+            connection: None,
         };
 
-        self.create_topic(topic_name, Some(&merged)).await
+        self.put_topic(topic_name, Some(&merged), true).await
     }
 
     pub async fn delete_topic(&self, topic_name: &str) -> Result<(), String> {
+        check_not_system_entity("delete", topic_name)?;
         let url = format!("{}/{}?api-version={}", self.get_base_url(), topic_name, API_VERSION);
+        if dry_run::intercept("delete topic", "DELETE", &url, None) {
+            return Ok(());
+        }
         let auth_header = self.get_auth_header(&url).await?;
 
         let response = self
@@ -542,7 +733,7 @@ impl ServiceBusClient {
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to delete topic: {} - {}", status, error_text));
+            return Err(http_error("delete topic", status, &error_text));
         }
 
         Ok(())
@@ -550,112 +741,802 @@ impl ServiceBusClient {
 
     // Subscription operations
     pub async fn list_subscriptions(&self, topic_name: &str) -> Result<Vec<SubscriptionProperties>, String> {
-        let mut all_subscriptions = Vec::new();
-        let mut url = format!("{}/{}/Subscriptions?api-version={}", self.get_base_url(), topic_name, API_VERSION);
-        
-        loop {
-            let auth_header = self.get_auth_header(&url).await?;
-
-            let response = self
-                .client
-                .get(&url)
-                .header("Authorization", &auth_header)
-                .send()
-                .await
-                .map_err(|e| format!("Failed to list subscriptions: {}", e))?;
-
-            let status = response.status();
-            if !status.is_success() {
-                let error_text = response.text().await.unwrap_or_default();
-                return Err(format!("Failed to list subscriptions: {} - {}", status, error_text));
-            }
-
-            let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
-            let feed: SubscriptionFeed = from_str(&xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
-
-            // Extract content for each entry using regex (since serde_xml_rs can't handle nested XML in content)
-            // Match each entry's content separately
-            let content_regex = regex::Regex::new(r#"(?s)<entry[^>]*>.*?<title[^>]*>([^<]+)</title>.*?<content[^>]*type="application/xml"[^>]*>(.*?)</content>"#).ok();
-            
-            for entry in feed.entries {
-                let mut entry_with_content = entry.clone();
-                
-                // Try to find content for this entry by matching title
-                if let Some(ref re) = content_regex {
-                    if let Some(cap) = re.captures_iter(&xml).find(|cap| {
-                        cap.get(1).map(|m| m.as_str().trim()) == Some(entry.title.trim())
-                    }) {
-                        if let Some(content_match) = cap.get(2) {
-                            entry_with_content.content = Some(content_match.as_str().to_string());
-                        }
-                    }
-                }
-                
-                let props = self.subscription_entry_to_properties(topic_name, &entry_with_content)?;
-                all_subscriptions.push(props);
-            }
-
-            // Check for next page link
-            let next_link = feed.links.iter().find(|link| {
-                link.rel.as_ref().map(|r| r == "next").unwrap_or(false)
-            });
-
-            if let Some(link) = next_link {
-                if let Some(href) = &link.href {
-                    if href.starts_with("http") {
-                        if let Ok(parsed_url) = url::Url::parse(href) {
-                            url = format!("{}{}?{}", self.get_base_url(), parsed_url.path(), parsed_url.query().unwrap_or(""));
-                        } else {
-                            break;
-                        }
-                    } else {
-                        url = format!("{}{}", self.get_base_url(), href);
-                    }
-                    continue;
-                }
-            }
-            
-            break;
-        }
+        self.list_subscriptions_with_progress(topic_name, None).await
+    }
 
-        Ok(all_subscriptions)
+    pub async fn list_subscriptions_with_progress(
+        &self,
+        topic_name: &str,
+        on_progress: Option<&(dyn Fn(&ListingProgress<SubscriptionProperties>) + Send + Sync)>,
+    ) -> Result<Vec<SubscriptionProperties>, String> {
+        let url = format!("{}/{}/Subscriptions?api-version={}", self.get_base_url(), topic_name, API_VERSION);
+        let subscriptions = self
+            .list_feed_pages(url, "list subscriptions", "subscriptions", Some(topic_name), on_progress, |xml| {
+                let feed: SubscriptionFeed = parse_feed(xml)?;
+                feed.entries
+                    .into_iter()
+                    .map(|entry| {
+                        let content = entry_content(xml, &entry.title);
+                        self.subscription_entry_to_properties(topic_name, &SubscriptionEntry { content, ..entry })
+                    })
+                    .collect()
+            })
+            .await?;
+        Ok(without_system_entities(subscriptions, |s| &s.subscription_name))
     }
 
-    pub async fn create_subscription(
+    // One page of a topic's subscriptions with their settings and runtime counts. With
+    // include_rule_counts each subscription's rules feed is fetched as well, a bounded
+    // number at a time.
+    pub async fn list_subscriptions_page(
         &self,
         topic_name: &str,
-        subscription_name: &str,
-        properties: Option<&SubscriptionProperties>,
-    ) -> Result<(), String> {
-        let url = format!("{}/{}/Subscriptions/{}?api-version={}", self.get_base_url(), topic_name, subscription_name, API_VERSION);
+        skip: u32,
+        top: u32,
+        include_rule_counts: bool,
+    ) -> Result<SubscriptionPage, String> {
+        const CONCURRENCY: usize = 16;
+
+        let url = format!(
+            "{}/{}/Subscriptions?api-version={}&$skip={}&$top={}",
+            self.get_base_url(),
+            topic_name,
+            API_VERSION,
+            skip,
+            top
+        );
         let auth_header = self.get_auth_header(&url).await?;
-
-        let xml = self.subscription_properties_to_xml(topic_name, subscription_name, properties)?;
-
         let response = self
             .client
-            .put(&url)
+            .get(&url)
             .header("Authorization", &auth_header)
-            .header("Content-Type", "application/atom+xml;type=entry;charset=utf-8")
-            .body(xml)
             .send()
             .await
-            .map_err(|e| format!("Failed to create subscription: {}", e))?;
+            .map_err(|e| format!("Failed to list subscriptions: {}", e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to create subscription: {} - {}", status, error_text));
+            return Err(http_error("list subscriptions", status, &error_text));
         }
 
-        Ok(())
+        let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+        let feed: SubscriptionFeed = parse_feed(&xml)?;
+        let mut items = feed
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let content = entry_content(&xml, &entry.title);
+                self.subscription_entry_to_properties(topic_name, &SubscriptionEntry { content, ..entry })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if include_rule_counts {
+            for (chunk_index, chunk) in items.chunks_mut(CONCURRENCY).enumerate() {
+                let mut tasks = tokio::task::JoinSet::new();
+                for (index, subscription) in chunk.iter().enumerate() {
+                    // A subscription has at most 2000 rules, so one page holds them all
+                    let rules_url = format!(
+                        "{}/{}/Subscriptions/{}/Rules?api-version={}&$top=2000",
+                        self.get_base_url(),
+                        topic_name,
+                        subscription.subscription_name,
+                        API_VERSION
+                    );
+                    let auth_header = self.get_auth_header(&rules_url).await?;
+                    let client = self.client.clone();
+                    tasks.spawn(async move { (index, count_feed_entries(client, rules_url, auth_header).await) });
+                }
+                while let Some(joined) = tasks.join_next().await {
+                    match joined {
+                        Ok((index, Ok(count))) => chunk[index].rule_count = Some(count),
+                        Ok((index, Err(e))) => eprintln!(
+                            "[list_subscriptions_page] Failed to count rules of '{}': {}",
+                            chunk[index].subscription_name, e
+                        ),
+                        Err(e) => eprintln!("[list_subscriptions_page] Rule count task {} failed: {}", chunk_index, e),
+                    }
+                }
+            }
+        }
+
+        // Counted before filtering so a page of system entities doesn't end the paging
+        let has_more = items.len() as u32 >= top && top > 0;
+        let items = without_system_entities(items, |s| &s.subscription_name);
+        eprintln!(
+            "[list_subscriptions_page] {} subscriptions of '{}' (skip={}, has_more={})",
+            items.len(),
+            topic_name,
+            skip,
+            has_more
+        );
+        Ok(SubscriptionPage { topic_name: topic_name.to_string(), skip, items, has_more })
     }
 
-    // ============================================================================
-    // Message Operations (azservicebus SDK)
-    // ============================================================================
-    // These operations use the azservicebus SDK for better performance,
-    // reliability, and proper AMQP-based message handling.
+    // Queues and subscriptions whose ForwardTo or ForwardDeadLetteredMessagesTo points
+    // at entity_name. Subscriptions of entity_name itself are skipped, since deleting a
+    // topic deletes them too.
+    pub async fn find_forward_references(&self, entity_name: &str) -> Result<Vec<ForwardReference>, String> {
+        let target = entity_name.trim_matches('/').to_lowercase();
+        let mut references = Vec::new();
+
+        let url = format!("{}/$Resources/Queues?api-version={}", self.get_base_url(), API_VERSION);
+        for (name, content) in self.list_feed_contents(url, "list queues", "queues", None).await? {
+            references.extend(forward_references(&name, &content, &target));
+        }
+        for topic in self.list_topics().await? {
+            if topic.name.eq_ignore_ascii_case(&target) {
+                continue;
+            }
+            let url = format!("{}/{}/Subscriptions?api-version={}", self.get_base_url(), topic.name, API_VERSION);
+            for (name, content) in self.list_feed_contents(url, "list subscriptions", "subscriptions", Some(&topic.name)).await? {
+                let path = format!("{}/Subscriptions/{}", topic.name, name);
+                references.extend(forward_references(&path, &content, &target));
+            }
+        }
+
+        eprintln!("[find_forward_references] {} entities forward to '{}'", references.len(), entity_name);
+        Ok(references)
+    }
+
+    // Refuses with forward_references when anything auto-forwards to entity_name
+    pub async fn check_not_forwarded_to(&self, entity_name: &str) -> Result<(), String> {
+        let references = self.find_forward_references(entity_name).await?;
+        if references.is_empty() {
+            return Ok(());
+        }
+        let entities: Vec<String> =
+            references.iter().map(|r| format!("{} ({})", r.entity_path, r.property)).collect();
+        Err(CatalogError::new(
+            "forward_references",
+            &[
+                ("entity", entity_name),
+                ("count", &references.len().to_string()),
+                ("entities", &entities.join(", ")),
+            ],
+        )
+        .to_string())
+    }
+
+    // (title, description XML) of every entry in a management feed
+    async fn list_feed_contents(
+        &self,
+        url: String,
+        operation: &str,
+        entity_type: &str,
+        scope: Option<&str>,
+    ) -> Result<Vec<(String, String)>, String> {
+        self.list_feed_pages(url, operation, entity_type, scope, None, |xml| {
+            let feed: SubscriptionFeed = parse_feed(xml)?;
+            Ok(feed
+                .entries
+                .into_iter()
+                .map(|entry| {
+                    let content = entry_content(xml, &entry.title).unwrap_or_default();
+                    (entry.title, content)
+                })
+                .collect())
+        })
+        .await
+    }
+
+    // Shared pagination for management feeds.
+    // Fetches pages by following rel="next" links until a page is empty, hands each
+    // page's XML to parse_page, and reports every parsed page through on_progress so
+    // large namespaces can be rendered incrementally.
+    async fn list_feed_pages<T: Clone>(
+        &self,
+        first_url: String,
+        operation: &str,
+        entity_type: &str,
+        scope: Option<&str>,
+        on_progress: Option<&(dyn Fn(&ListingProgress<T>) + Send + Sync)>,
+        parse_page: impl Fn(&str) -> Result<Vec<T>, String>,
+    ) -> Result<Vec<T>, String> {
+        let mut items = Vec::new();
+        let mut url = first_url;
+        let mut page = 0u32;
+
+        loop {
+            page += 1;
+            eprintln!("[{}] Fetching page {} from: {}", operation, page, url);
+
+            let auth_header = self.get_auth_header(&url).await?;
+            let response = self
+                .client
+                .get(&url)
+                .header("Authorization", &auth_header)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to {}: {}", operation, e))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                return Err(http_error(operation, status, &error_text));
+            }
+
+            let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+            let page_items = parse_page(&xml)?;
+            if page_items.is_empty() {
+                break;
+            }
+            items.extend(page_items.iter().cloned());
+
+            let next_url = next_link(&xml).map(|href| self.normalize_next_link(&href));
+            if let Some(callback) = on_progress {
+                callback(&ListingProgress {
+                    entity_type: entity_type.to_string(),
+                    scope: scope.map(|s| s.to_string()),
+                    page,
+                    loaded: items.len() as u64,
+                    has_more: next_url.is_some(),
+                    items: page_items,
+                });
+            }
+
+            match next_url {
+                // A next link pointing back at the current page would loop forever
+                Some(next) if next.replace("%24", "$") != url.replace("%24", "$") => url = next,
+                _ => break,
+            }
+        }
+
+        Ok(items)
+    }
+
+    // Next links may be absolute or relative; always request them from our own base URL
+    fn normalize_next_link(&self, href: &str) -> String {
+        if href.starts_with("http") {
+            match url::Url::parse(href) {
+                Ok(parsed) => format!("{}{}?{}", self.get_base_url(), parsed.path(), parsed.query().unwrap_or("")),
+                Err(_) => href.to_string(),
+            }
+        } else {
+            format!("{}{}", self.get_base_url(), href)
+        }
+    }
+
+    pub async fn get_subscription(&self, topic_name: &str, subscription_name: &str) -> Result<SubscriptionProperties, String> {
+        let url = format!("{}/{}/Subscriptions/{}?api-version={}", self.get_base_url(), topic_name, subscription_name, API_VERSION);
+        let (xml, _) = self
+            .get_entity_xml_if_changed(&url, "get subscription", None)
+            .await?
+            .ok_or("Subscription was not returned")?;
+        self.parse_subscription_xml(topic_name, &xml)
+    }
+
+    // Re-fetch a subscription, skipping the parse when it has not changed since `etag`
+    pub async fn refresh_subscription(
+        &self,
+        topic_name: &str,
+        subscription_name: &str,
+        etag: Option<&str>,
+    ) -> Result<EntityRefresh<SubscriptionProperties>, String> {
+        let url = format!("{}/{}/Subscriptions/{}?api-version={}", self.get_base_url(), topic_name, subscription_name, API_VERSION);
+        match self.get_entity_xml_if_changed(&url, "get subscription", etag).await? {
+            Some((xml, etag)) => Ok(EntityRefresh {
+                modified: true,
+                etag: Some(etag),
+                entity: Some(self.parse_subscription_xml(topic_name, &xml)?),
+            }),
+            None => Ok(EntityRefresh {
+                modified: false,
+                etag: etag.map(|e| e.to_string()),
+                entity: None,
+            }),
+        }
+    }
+
+    fn parse_subscription_xml(&self, topic_name: &str, xml: &str) -> Result<SubscriptionProperties, String> {
+        let mut entry: SubscriptionEntry = from_str(xml).map_err(|e| format!("Failed to parse XML: {}", e))?;
+        entry.content = entry_content(xml, &entry.title);
+        self.subscription_entry_to_properties(topic_name, &entry)
+    }
+
+    // Compare what a peek or purge actually saw with the entity's runtime counts. A
+    // dead-letter queue peeked by path ("orders/$deadletterqueue") is checked against
+    // its entity's dead-letter count.
+    pub async fn verify_message_counts(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        dead_letter: bool,
+        operation: &str,
+        observed_count: u64,
+        requested_count: Option<u64>,
+    ) -> Result<MessageCountVerification, String> {
+        use crate::azure::diagnostics::compare_message_counts;
+
+        let parent = queue_name.and_then(dead_letter_parent);
+        let (queue_name, topic_name, subscription_name, dead_letter) = match parent {
+            Some(path) => match split_subscription_path(path) {
+                Some((topic, subscription)) => (None, Some(topic), Some(subscription), true),
+                None => (Some(path), None, None, true),
+            },
+            None => (queue_name, topic_name, subscription_name, dead_letter),
+        };
+        let (entity_path, counts) = self.runtime_counts(queue_name, topic_name, subscription_name).await?;
+        compare_message_counts(&entity_path, operation, dead_letter, observed_count, requested_count, &counts)
+    }
+
+    // Entity path and runtime counts for a queue or subscription
+    pub async fn runtime_counts(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+    ) -> Result<(String, crate::azure::diagnostics::RuntimeCounts), String> {
+        use crate::azure::diagnostics::RuntimeCounts;
+
+        let result = if let Some(q) = queue_name {
+            let queue = self.get_queue(q).await?;
+            (
+                q.to_string(),
+                RuntimeCounts {
+                    active: queue.active_message_count,
+                    dead_letter: queue.dead_letter_message_count,
+                    scheduled: queue.scheduled_message_count,
+                    transfer: queue.transfer_message_count,
+                    requires_session: queue.requires_session.unwrap_or(false),
+                },
+            )
+        } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+            let subscription = self.get_subscription(t, s).await?;
+            (
+                format!("{}/Subscriptions/{}", t, s),
+                RuntimeCounts {
+                    active: subscription.active_message_count,
+                    dead_letter: subscription.dead_letter_message_count,
+                    scheduled: subscription.scheduled_message_count,
+                    transfer: subscription.transfer_message_count,
+                    requires_session: subscription.requires_session.unwrap_or(false),
+                },
+            )
+        } else {
+            return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+        };
+
+        Ok(result)
+    }
+
+    // Apply the same settings to all (or the named) subscriptions of a topic. Each
+    // subscription is updated on its own, so one failure doesn't stop the rest.
+    pub async fn apply_settings_to_subscriptions(
+        &self,
+        topic_name: &str,
+        subscription_names: Option<&[String]>,
+        settings: &SubscriptionSettings,
+    ) -> Result<Vec<SubscriptionSettingsResult>, String> {
+        validate_subscription_settings(settings)?;
+
+        let subscription_names = match subscription_names {
+            Some(names) => names.to_vec(),
+            None => self
+                .list_subscriptions(topic_name)
+                .await?
+                .into_iter()
+                .map(|s| s.subscription_name)
+                .collect(),
+        };
+
+        let mut results = Vec::with_capacity(subscription_names.len());
+        for subscription_name in subscription_names {
+            let outcome = self.update_subscription_settings(topic_name, &subscription_name, settings).await;
+            if let Err(e) = &outcome {
+                eprintln!("[apply_settings_to_subscriptions] {}/{}: {}", topic_name, subscription_name, e);
+            }
+            results.push(SubscriptionSettingsResult {
+                subscription_name,
+                success: outcome.is_ok(),
+                error: outcome.err(),
+            });
+        }
+
+        let failed = results.iter().filter(|r| !r.success).count();
+        eprintln!(
+            "[apply_settings_to_subscriptions] Updated {} of {} subscriptions of '{}'",
+            results.len() - failed,
+            results.len(),
+            topic_name
+        );
+        Ok(results)
+    }
+
+    // Edit the settings in place in the current description, so everything else
+    // (forwarding, sessions, rules) is sent back unchanged
+    async fn update_subscription_settings(
+        &self,
+        topic_name: &str,
+        subscription_name: &str,
+        settings: &SubscriptionSettings,
+    ) -> Result<(), String> {
+        use crate::azure::auth::seconds_to_duration;
+
+        let entity_path = format!("{}/Subscriptions/{}", topic_name, subscription_name);
+        let entry = self.get_entity_entry_xml(&entity_path).await?;
+        let mut description = Self::extract_entity_description(&entry)?;
+
+        if let Some(seconds) = settings.lock_duration_in_seconds {
+            description = set_description_element(&description, "LockDuration", &seconds_to_duration(seconds))?;
+        }
+        if let Some(count) = settings.max_delivery_count {
+            description = set_description_element(&description, "MaxDeliveryCount", &count.to_string())?;
+        }
+        if let Some(seconds) = settings.default_message_time_to_live_in_seconds {
+            description =
+                set_description_element(&description, "DefaultMessageTimeToLive", &seconds_to_duration(seconds))?;
+        }
+        if let Some(enabled) = settings.dead_lettering_on_message_expiration {
+            description =
+                set_description_element(&description, "DeadLetteringOnMessageExpiration", &enabled.to_string())?;
+        }
+
+        self.put_entity_description(&entity_path, &description).await
+    }
+
+    // Namespace-wide find and replace: every queue, topic and subscription matching
+    // filter gets changes, edited in place like update_subscription_settings. Without
+    // apply nothing is written and the report is the preview. Entities are updated one
+    // at a time, so one failure doesn't stop the rest.
+    pub async fn bulk_edit_entities(
+        &self,
+        filter: &EntityFilter,
+        changes: &[PropertyAssignment],
+        apply: bool,
+        on_progress: &(dyn Fn(&BulkEditProgress) + Send + Sync),
+    ) -> Result<BulkEditReport, String> {
+        if changes.is_empty() {
+            return Err("At least one property change is required".to_string());
+        }
+        for change in changes {
+            property_xml_value(change.property, &change.value)?;
+        }
+        for condition in &filter.conditions {
+            validate_condition(condition)?;
+        }
+        let name_pattern = match &filter.name_pattern {
+            Some(pattern) => Some(regex::Regex::new(pattern).map_err(|e| format!("Invalid name pattern: {}", e))?),
+            None => None,
+        };
+
+        // Only kinds that have every changed property can take the change
+        let wanted = |kind: EntityKind| {
+            (filter.kinds.is_empty() || filter.kinds.contains(&kind))
+                && changes.iter().all(|change| property_applies_to(change.property, kind))
+        };
+        let mut entities: Vec<(EntityKind, String)> = Vec::new();
+        if wanted(EntityKind::Queue) {
+            entities.extend(self.list_queues().await?.into_iter().map(|q| (EntityKind::Queue, q.name)));
+        }
+        if wanted(EntityKind::Topic) || wanted(EntityKind::Subscription) {
+            for topic in self.list_topics().await? {
+                if wanted(EntityKind::Subscription) {
+                    for subscription in self.list_subscriptions(&topic.name).await? {
+                        let path = format!("{}/Subscriptions/{}", topic.name, subscription.subscription_name);
+                        entities.push((EntityKind::Subscription, path));
+                    }
+                }
+                if wanted(EntityKind::Topic) {
+                    entities.push((EntityKind::Topic, topic.name));
+                }
+            }
+        }
+        entities.retain(|(_, path)| name_pattern.as_ref().is_none_or(|re| re.is_match(path)));
+
+        let mut report = BulkEditReport {
+            applied: apply,
+            scanned_count: entities.len() as u32,
+            matched_count: 0,
+            unchanged_count: 0,
+            updated_count: 0,
+            failed_count: 0,
+            entities: Vec::new(),
+        };
+        let total = entities.len() as u32;
+        for (index, (kind, entity_path)) in entities.into_iter().enumerate() {
+            let mut result =
+                EntityEditResult { kind, entity_path: entity_path.clone(), changes: Vec::new(), applied: false, error: None };
+            let description = match self.get_entity_entry_xml(&entity_path).await {
+                Ok(entry) => Self::extract_entity_description(&entry),
+                Err(e) => Err(e),
+            };
+            let outcome = match description {
+                Ok(description) if filter.conditions.iter().all(|c| condition_matches(c, &description)) => {
+                    report.matched_count += 1;
+                    result.changes = changes
+                        .iter()
+                        .map(|change| PropertyDiff {
+                            property: change.property,
+                            before: description_value(change.property, &description),
+                            after: change.value.clone(),
+                        })
+                        .filter(|diff| !same_value(diff.property, diff.before.as_ref(), &diff.after))
+                        .collect();
+                    if result.changes.is_empty() {
+                        report.unchanged_count += 1;
+                        Ok(false)
+                    } else if apply {
+                        self.put_entity_description(&entity_path, &apply_diffs(&description, &result.changes)?)
+                            .await
+                            .map(|_| true)
+                    } else {
+                        Ok(true)
+                    }
+                }
+                Ok(_) => Ok(false),
+                Err(e) => Err(e),
+            };
+            match outcome {
+                Ok(true) => {
+                    result.applied = apply;
+                    if apply {
+                        report.updated_count += 1;
+                    }
+                    report.entities.push(result);
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("[bulk_edit_entities] {}: {}", entity_path, e);
+                    result.error = Some(e);
+                    report.failed_count += 1;
+                    report.entities.push(result);
+                }
+            }
+            on_progress(&BulkEditProgress { entity_path, index: index as u32 + 1, total });
+        }
+
+        eprintln!(
+            "[bulk_edit_entities] {} of {} entities matched, {} to change, {} updated, {} failed",
+            report.matched_count,
+            report.scanned_count,
+            report.matched_count - report.unchanged_count,
+            report.updated_count,
+            report.failed_count
+        );
+        Ok(report)
+    }
+
+    // An initial rule is sent as the subscription's default rule, so it replaces the
+    // match-all $Default rule in the same call and no unfiltered messages arrive.
+    pub async fn create_subscription(
+        &self,
+        topic_name: &str,
+        subscription_name: &str,
+        properties: Option<&SubscriptionProperties>,
+        initial_rule: Option<&SubscriptionRule>,
+    ) -> Result<(), String> {
+        // Build the body first so an invalid rule fails before anything is created
+        let xml = self.subscription_properties_to_xml(topic_name, subscription_name, properties, initial_rule)?;
+
+        let url = format!("{}/{}/Subscriptions/{}?api-version={}", self.get_base_url(), topic_name, subscription_name, API_VERSION);
+        if dry_run::intercept("create subscription", "PUT", &url, Some(&xml)) {
+            return Ok(());
+        }
+        let auth_header = self.get_auth_header(&url).await?;
+
+        let response = self
+            .client
+            .put(&url)
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/atom+xml;type=entry;charset=utf-8")
+            .body(xml)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create subscription: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(http_error("create subscription", status, &error_text));
+        }
+
+        Ok(())
+    }
+
+    // ============================================================================
+    // Authorization Rules (REST API)
+    // ============================================================================
+    // Entity-level SAS policies are part of the queue/topic description, so they
+    // are read and written through the same Atom entry as the entity itself.
+    // ============================================================================
+
+    // Fetch the raw Atom entry for an entity (queue, topic or topic/Subscriptions/sub)
+    async fn get_entity_entry_xml(&self, entity_path: &str) -> Result<String, String> {
+        let url = format!("{}/{}?api-version={}", self.get_base_url(), entity_path, API_VERSION);
+        let auth_header = self.get_auth_header(&url).await?;
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get entity '{}': {}", entity_path, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(http_error(&format!("get entity '{}'", entity_path), status, &error_text));
+        }
+
+        let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+        // Azure answers 200 with an empty feed for entities that don't exist
+        if !xml.contains("<entry") {
+            return Err(format!("Failed to get entity '{}': 404 Not Found", entity_path));
+        }
+        Ok(xml)
+    }
+
+    // Extract the <QueueDescription>/<TopicDescription> element from an Atom entry
+    fn extract_entity_description(entry_xml: &str) -> Result<String, String> {
+        regex::Regex::new(r#"(?s)<content[^>]*>(.*?)</content>"#)
+            .ok()
+            .and_then(|re| re.captures(entry_xml))
+            .map(|cap| cap[1].trim().to_string())
+            .ok_or_else(|| "Entity entry has no description content".to_string())
+    }
+
+    // PUT an existing entity description back (If-Match: * marks it as an update)
+    async fn put_entity_description(&self, entity_path: &str, description_xml: &str) -> Result<(), String> {
+        let url = format!("{}/{}?api-version={}", self.get_base_url(), entity_path, API_VERSION);
+        let auth_header = self.get_auth_header(&url).await?;
+
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><entry xmlns="http://www.w3.org/2005/Atom"><content type="application/xml">{}</content></entry>"#,
+            description_xml
+        );
+        if dry_run::intercept("update entity", "PUT", &url, Some(&xml)) {
+            return Ok(());
+        }
+
+        let response = self
+            .client
+            .put(&url)
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/atom+xml;type=entry;charset=utf-8")
+            .header("If-Match", "*")
+            .body(xml)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to update entity '{}': {}", entity_path, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(http_error(&format!("update entity '{}'", entity_path), status, &error_text));
+        }
+
+        Ok(())
+    }
+
+    // Raw Atom entry of an entity, for expert mode
+    pub async fn get_entity_xml(&self, entity_path: &str) -> Result<String, String> {
+        self.get_entity_entry_xml(entity_path).await
+    }
+
+    // Replace an entity description with user-edited XML (a full entry or the bare
+    // description). The XML is validated locally and must describe the same kind of
+    // entity as the one on the service. Returns the entry as stored after the update.
+    pub async fn update_entity_xml(&self, entity_path: &str, xml: &str) -> Result<String, String> {
+        use crate::azure::raw_xml::parse_entity_description;
+
+        let edited = parse_entity_description(xml)?;
+        let current = parse_entity_description(&self.get_entity_entry_xml(entity_path).await?)?;
+        if edited.kind != current.kind {
+            return Err(format!(
+                "'{}' is described by <{}>, but the submitted XML contains <{}>",
+                entity_path, current.kind, edited.kind
+            ));
+        }
+
+        self.put_entity_description(entity_path, &edited.xml).await?;
+        eprintln!("[update_entity_xml] Updated <{}> of '{}'", edited.kind, entity_path);
+        self.get_entity_entry_xml(entity_path).await
+    }
+
+    pub async fn list_authorization_rules(&self, entity_path: &str) -> Result<Vec<AuthorizationRule>, String> {
+        let entry = self.get_entity_entry_xml(entity_path).await?;
+        let description = Self::extract_entity_description(&entry)?;
+        Ok(crate::azure::authorization::parse_authorization_rules(&description))
+    }
+
+    // Regenerate the primary or secondary key of an entity-level SAS rule.
+    // Returns the updated rule together with the key that was replaced.
+    pub async fn regenerate_authorization_rule_key(
+        &self,
+        entity_path: &str,
+        key_name: &str,
+        key_type: &str,
+    ) -> Result<(AuthorizationRule, String), String> {
+        use crate::azure::authorization::{generate_sas_key, parse_authorization_rules, replace_authorization_rules};
+
+        let entry = self.get_entity_entry_xml(entity_path).await?;
+        let description = Self::extract_entity_description(&entry)?;
+        let mut rules = parse_authorization_rules(&description);
+
+        let rule = rules
+            .iter_mut()
+            .find(|r| r.key_name == key_name)
+            .ok_or_else(|| format!("Authorization rule '{}' not found on '{}'", key_name, entity_path))?;
+
+        let new_key = generate_sas_key();
+        let old_key = match key_type {
+            "primary" => rule.primary_key.replace(new_key),
+            "secondary" => rule.secondary_key.replace(new_key),
+            other => return Err(format!("Invalid key type '{}'. Expected 'primary' or 'secondary'", other)),
+        }
+        .unwrap_or_default();
+        rule.modified_time = Some(timestamps::format(chrono::Utc::now()));
+        let updated_rule = rule.clone();
+
+        let updated_description = replace_authorization_rules(&description, &rules)?;
+        self.put_entity_description(entity_path, &updated_description).await?;
+
+        eprintln!("[regenerate_authorization_rule_key] Regenerated {} key of '{}' on '{}'", key_type, key_name, entity_path);
+        Ok((updated_rule, old_key))
+    }
+
+    // Add a new entity-level SAS rule with freshly generated keys
+    pub async fn create_authorization_rule(
+        &self,
+        entity_path: &str,
+        key_name: &str,
+        rights: &[String],
+    ) -> Result<AuthorizationRule, String> {
+        use crate::azure::authorization::{generate_sas_key, parse_authorization_rules, replace_authorization_rules};
+
+        if rights.is_empty() {
+            return Err("At least one access right is required".to_string());
+        }
+        if let Some(invalid) = rights.iter().find(|r| !matches!(r.as_str(), "Manage" | "Send" | "Listen")) {
+            return Err(format!("Invalid access right '{}'. Expected Manage, Send or Listen", invalid));
+        }
+        // The service rejects Manage without Send and Listen
+        if rights.iter().any(|r| r == "Manage") && !(rights.iter().any(|r| r == "Send") && rights.iter().any(|r| r == "Listen")) {
+            return Err("Manage rights also require Send and Listen".to_string());
+        }
+
+        let entry = self.get_entity_entry_xml(entity_path).await?;
+        let description = Self::extract_entity_description(&entry)?;
+        let mut rules = parse_authorization_rules(&description);
+
+        if rules.iter().any(|r| r.key_name == key_name) {
+            return Err(format!("Authorization rule '{}' already exists on '{}'", key_name, entity_path));
+        }
+
+        let now = timestamps::format(chrono::Utc::now());
+        let rule = AuthorizationRule {
+            key_name: key_name.to_string(),
+            rights: rights.to_vec(),
+            primary_key: Some(generate_sas_key()),
+            secondary_key: Some(generate_sas_key()),
+            created_time: Some(now.clone()),
+            modified_time: Some(now),
+        };
+        rules.push(rule.clone());
+
+        let updated_description = replace_authorization_rules(&description, &rules)?;
+        self.put_entity_description(entity_path, &updated_description).await?;
+
+        eprintln!("[create_authorization_rule] Created rule '{}' ({:?}) on '{}'", key_name, rights, entity_path);
+        Ok(rule)
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn endpoint_domain(&self) -> &str {
+        &self.endpoint_domain
+    }
+
+    // ============================================================================
+    // Message Operations (azservicebus SDK)
+    // ============================================================================
+    // These operations use the azservicebus SDK for better performance,
+    // reliability, and proper AMQP-based message handling.
     // ============================================================================
 
     // Peek messages using azservicebus SDK
@@ -665,6 +1546,8 @@ impl ServiceBusClient {
         topic_name: Option<&str>,
         subscription_name: Option<&str>,
         max_count: u32,
+        from_sequence_number: Option<u64>,
+        options: &PeekOptions,
     ) -> Result<Vec<ServiceBusMessage>, String> {
         use azservicebus::prelude::*;
         
@@ -710,10 +1593,29 @@ impl ServiceBusClient {
 
         // Peek messages using SDK
         // peek_messages takes (max_count: u32, from_sequence_number: Option<i64>)
-        let sdk_messages = receiver
-            .peek_messages(max_count, None)
-            .await
+        // and starts at the first message at or after from_sequence_number
+        let page_size = options.page_size.unwrap_or(max_count).max(1);
+        let mut sdk_messages = Vec::new();
+        let mut next_sequence_number = from_sequence_number.map(|seq| seq as i64);
+        while (sdk_messages.len() as u32) < max_count {
+            let count = (max_count - sdk_messages.len() as u32).min(page_size);
+            let peek = receiver.peek_messages(count, next_sequence_number);
+            let page = match options.timeout_seconds {
+                Some(seconds) => tokio::time::timeout(std::time::Duration::from_secs(seconds as u64), peek)
+                    .await
+                    .map_err(|_| format!("Peek timed out after {}s", seconds))?,
+                None => peek.await,
+            }
             .map_err(|e| format!("Failed to peek messages: {}", e))?;
+            let full_page = page.len() as u32 == count;
+            if let Some(last) = page.last() {
+                next_sequence_number = Some(last.sequence_number() + 1);
+            }
+            sdk_messages.extend(page);
+            if !full_page {
+                break;
+            }
+        }
 
         eprintln!("[peek_messages_sdk] SDK returned {} messages", sdk_messages.len());
 
@@ -722,33 +1624,22 @@ impl ServiceBusClient {
         for (idx, sdk_msg) in sdk_messages.iter().enumerate() {
             eprintln!("[peek_messages_sdk] Processing message {}", idx + 1);
             
-            // Get message body (returns Result)
-            let body_bytes = sdk_msg.body().map_err(|e| format!("Failed to get message body: {}", e))?;
+            // Get message body (returns Result). A body that isn't a data section is shown
+            // empty with a warning rather than failing the whole page.
+            let (body_bytes, body_warning) = match sdk_msg.body() {
+                Ok(bytes) => (bytes, None),
+                Err(e) => (&[][..], Some(format!("Body could not be read: {}", e))),
+            };
             
-            // Try to parse as JSON, otherwise use as string
-            let body = match serde_json::from_slice::<serde_json::Value>(body_bytes) {
-                Ok(json) => json,
-                Err(_) => {
-                    // If not JSON, try as UTF-8 string
-                    match std::str::from_utf8(body_bytes) {
-                        Ok(s) => serde_json::Value::String(s.to_string()),
-                        Err(_) => serde_json::Value::String(format!("<binary data: {} bytes>", body_bytes.len())),
-                    }
-                }
-            };
+            // Parse as JSON, otherwise use as string (or a truncated preview when projecting)
+            let DecodedBody { body, body_encoding, body_hex_preview, body_envelope, body_size, body_truncated } =
+                decode_peeked_body(body_bytes, options.max_body_bytes);
 
             // Access properties from ReceivedMessage and create our ServiceBusMessage
-            // Convert OffsetDateTime to string - use format! with Display trait
-            let enqueued_time_str = format!("{}", sdk_msg.enqueued_time());
+            let enqueued_time_str = timestamps::from_offset_date_time(sdk_msg.enqueued_time());
             
-            // Extract application properties (user_properties) from the SDK message
-            // Try to get user_properties - the exact API may vary, so we'll try a few approaches
-            let application_properties = {
-                // Try to access user_properties - this might be through a method or direct field
-                // For now, we'll set to None and can enhance later if the SDK provides access
-                // The SDK may expose this differently - checking what's available
-                None
-            };
+            // Application (user) properties, so they survive peek -> edit -> resend
+            let application_properties = sdk_msg.application_properties().map(application_properties_to_json);
             
             // Extract delivery_count - try to get it from the message
             // Peeked messages may not have delivery_count, but let's try
@@ -758,7 +1649,7 @@ impl ServiceBusClient {
                 None
             };
             
-            let message = crate::azure::types::ServiceBusMessage {
+            let mut message = crate::azure::types::ServiceBusMessage {
                 body,
                 message_id: sdk_msg.message_id().as_ref().map(|id| id.to_string()),
                 correlation_id: sdk_msg.correlation_id().as_ref().map(|id| id.to_string()),
@@ -774,10 +1665,29 @@ impl ServiceBusClient {
                 delivery_count,
                 enqueued_time_utc: Some(enqueued_time_str),
                 locked_until_utc: None, // Peek doesn't lock
-                dead_letter_reason: None,
-                dead_letter_error_description: None,
+                // Only set on dead-lettered messages, so every DLQ read through here keeps them
+                dead_letter_reason: sdk_msg.dead_letter_reason().map(|r| r.to_string()),
+                dead_letter_error_description: sdk_msg.dead_letter_error_description().map(|d| d.to_string()),
+                dead_letter_source: sdk_msg.dead_letter_source().map(|s| s.to_string()),
+                scheduled_enqueue_time_utc: None,
+                partition_key: sdk_msg.partition_key().map(|k| k.to_string()),
+                via_partition_key: sdk_msg.transaction_partition_key().map(|k| k.to_string()),
+                state: Some(format!("{:?}", sdk_msg.state())),
+                body_size,
+                body_truncated,
+                body_encoding,
+                body_hex_preview,
+                body_envelope,
+                body_compression: None,
+                raw_body: None,
+                decoded_body: None,
+                decode_error: None,
+                parse_warning: None,
+                expires_at_utc: None,
             };
-            
+            decompress_body(&mut message);
+            message.parse_warning = body_warning.or_else(|| json_parse_warning(&message));
+
             messages.push(message);
         }
 
@@ -789,16 +1699,69 @@ impl ServiceBusClient {
         Ok(messages)
     }
 
-    // Main peek_messages method - delegates to SDK implementation
+    // Main peek_messages method - SDK unless the connection asks for HTTP
     pub async fn peek_messages(
         &self,
         queue_name: Option<&str>,
         topic_name: Option<&str>,
         subscription_name: Option<&str>,
         max_count: u32,
+        from_sequence_number: Option<u64>,
+        max_body_bytes: Option<u32>,
+    ) -> Result<Vec<ServiceBusMessage>, String> {
+        let options = PeekOptions { max_body_bytes, ..Default::default() };
+        self.peek_messages_with_options(queue_name, topic_name, subscription_name, max_count, from_sequence_number, &options)
+            .await
+    }
+
+    // peek_messages with the timeout and page size tuned, for slow namespaces or very
+    // large messages
+    pub async fn peek_messages_with_options(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        max_count: u32,
+        from_sequence_number: Option<u64>,
+        options: &PeekOptions,
     ) -> Result<Vec<ServiceBusMessage>, String> {
+        let http = self.transport == Some(Transport::Http);
+        validate_peek_options(options, http)?;
+        if http {
+            // The REST peek decodes bodies itself, so max_body_bytes does not apply
+            return self
+                .peek_messages_rest(queue_name, topic_name, subscription_name, max_count, from_sequence_number, options)
+                .await;
+        }
         // Use SDK implementation for proper batch peeking
-        self.peek_messages_sdk(queue_name, topic_name, subscription_name, max_count).await
+        self.peek_messages_sdk(queue_name, topic_name, subscription_name, max_count, from_sequence_number, options)
+            .await
+    }
+
+    // Peek a message source (queue, subscription or either's DLQ) from a sequence number
+    pub async fn peek_source(
+        &self,
+        source: &MessageSource,
+        max_count: u32,
+        from_sequence_number: Option<u64>,
+        max_body_bytes: Option<u32>,
+    ) -> Result<Vec<ServiceBusMessage>, String> {
+        if source.dead_letter {
+            // The DLQ is addressed by path, like a queue
+            let path = source_entity_path(source)?;
+            return self
+                .peek_messages(Some(&path), None, None, max_count, from_sequence_number, max_body_bytes)
+                .await;
+        }
+        self.peek_messages(
+            source.queue_name.as_deref(),
+            source.topic_name.as_deref(),
+            source.subscription_name.as_deref(),
+            max_count,
+            from_sequence_number,
+            max_body_bytes,
+        )
+        .await
     }
 
     // Peek messages from dead letter queue using azservicebus SDK
@@ -808,6 +1771,41 @@ impl ServiceBusClient {
         topic_name: Option<&str>,
         subscription_name: Option<&str>,
         max_count: u32,
+        max_body_bytes: Option<u32>,
+    ) -> Result<Vec<ServiceBusMessage>, String> {
+        self.peek_dead_letter_sub_queue(queue_name, topic_name, subscription_name, "$deadletterqueue", max_count, max_body_bytes)
+            .await
+    }
+
+    // Peek the transfer dead-letter queue: messages that could not be auto-forwarded
+    // (ForwardTo / ForwardDeadLetteredMessagesTo) from this entity
+    pub async fn peek_transfer_dead_letter_messages(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        max_count: u32,
+        max_body_bytes: Option<u32>,
+    ) -> Result<Vec<ServiceBusMessage>, String> {
+        self.peek_dead_letter_sub_queue(
+            queue_name,
+            topic_name,
+            subscription_name,
+            "$Transfer/$deadletterqueue",
+            max_count,
+            max_body_bytes,
+        )
+        .await
+    }
+
+    async fn peek_dead_letter_sub_queue(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        sub_queue: &str,
+        max_count: u32,
+        max_body_bytes: Option<u32>,
     ) -> Result<Vec<ServiceBusMessage>, String> {
         use azservicebus::prelude::*;
         
@@ -834,9 +1832,9 @@ impl ServiceBusClient {
         .await
         .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
 
-        // Create receiver for dead letter queue using the $deadletterqueue path (lowercase)
+        // Create receiver for the dead letter sub-queue ($deadletterqueue, lowercase)
         let mut receiver = if let Some(q) = queue_name {
-            let dead_letter_path = format!("{}/$deadletterqueue", q);
+            let dead_letter_path = format!("{}/{}", q, sub_queue);
             eprintln!("[peek_dead_letter_messages_sdk] Creating receiver for dead letter queue: {}", dead_letter_path);
             client
                 .create_receiver_for_queue(&dead_letter_path, ServiceBusReceiverOptions::default())
@@ -845,7 +1843,7 @@ impl ServiceBusClient {
         } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
             // For subscriptions, the path is: topic/Subscriptions/subscription/$deadletterqueue
             // We can use create_receiver_for_queue with the full path
-            let dead_letter_path = format!("{}/Subscriptions/{}/$deadletterqueue", t, s);
+            let dead_letter_path = format!("{}/Subscriptions/{}/{}", t, s, sub_queue);
             eprintln!("[peek_dead_letter_messages_sdk] Creating receiver for dead letter subscription: {}", dead_letter_path);
             client
                 .create_receiver_for_queue(&dead_letter_path, ServiceBusReceiverOptions::default())
@@ -868,38 +1866,32 @@ impl ServiceBusClient {
         for (idx, sdk_msg) in sdk_messages.iter().enumerate() {
             eprintln!("[peek_dead_letter_messages_sdk] Processing dead letter message {}", idx + 1);
             
-            // Get message body (returns Result)
-            let body_bytes = sdk_msg.body().map_err(|e| format!("Failed to get message body: {}", e))?;
-            
-            // Try to parse as JSON, otherwise use as string
-            let body = match serde_json::from_slice::<serde_json::Value>(body_bytes) {
-                Ok(json) => json,
-                Err(_) => {
-                    // If not JSON, try as UTF-8 string
-                    match std::str::from_utf8(body_bytes) {
-                        Ok(s) => serde_json::Value::String(s.to_string()),
-                        Err(_) => serde_json::Value::String(format!("<binary data: {} bytes>", body_bytes.len())),
-                    }
-                }
+            // Get message body (returns Result). A body that isn't a data section is shown
+            // empty with a warning rather than failing the whole page.
+            let (body_bytes, body_warning) = match sdk_msg.body() {
+                Ok(bytes) => (bytes, None),
+                Err(e) => (&[][..], Some(format!("Body could not be read: {}", e))),
             };
+            
+            // Parse as JSON, otherwise use as string (or a truncated preview when projecting)
+            let DecodedBody { body, body_encoding, body_hex_preview, body_envelope, body_size, body_truncated } =
+                decode_peeked_body(body_bytes, max_body_bytes);
 
             // Access properties from ReceivedMessage and create our ServiceBusMessage
-            let enqueued_time_str = format!("{}", sdk_msg.enqueued_time());
+            let enqueued_time_str = timestamps::from_offset_date_time(sdk_msg.enqueued_time());
             
-            // Extract application properties - dead letter messages may have DeadLetterReason and DeadLetterErrorDescription
-            let application_properties = None; // TODO: Extract from user_properties if available
-            
-            // Try to extract dead letter reason and error description from application properties
-            // These are typically stored in the message's application properties
-            let (dead_letter_reason, dead_letter_error_description) = {
-                // Try to get from user_properties if available
-                // DeadLetterReason and DeadLetterErrorDescription are typically in application properties
-                (None, None)
-            };
-            
-            let delivery_count = None; // Not available on peeked messages
+            // Application properties, including DeadLetterReason and DeadLetterErrorDescription
+            let application_properties = sdk_msg.application_properties().map(application_properties_to_json);
+
+            // The SDK reads these from the application properties set when dead-lettering
+            let dead_letter_reason = sdk_msg.dead_letter_reason().map(|r| r.to_string());
+            let dead_letter_error_description = sdk_msg.dead_letter_error_description().map(|d| d.to_string());
+            let dead_letter_source = sdk_msg.dead_letter_source().map(|s| s.to_string());
+
+            // How many times delivery was attempted before the message was dead-lettered
+            let delivery_count = sdk_msg.delivery_count();
             
-            let message = crate::azure::types::ServiceBusMessage {
+            let mut message = crate::azure::types::ServiceBusMessage {
                 body,
                 message_id: sdk_msg.message_id().as_ref().map(|id| id.to_string()),
                 correlation_id: sdk_msg.correlation_id().as_ref().map(|id| id.to_string()),
@@ -917,8 +1909,26 @@ impl ServiceBusClient {
                 locked_until_utc: None, // Peek doesn't lock
                 dead_letter_reason,
                 dead_letter_error_description,
+                dead_letter_source,
+                scheduled_enqueue_time_utc: None,
+                partition_key: sdk_msg.partition_key().map(|k| k.to_string()),
+                via_partition_key: sdk_msg.transaction_partition_key().map(|k| k.to_string()),
+                state: Some(format!("{:?}", sdk_msg.state())),
+                body_size,
+                body_truncated,
+                body_encoding,
+                body_hex_preview,
+                body_envelope,
+                body_compression: None,
+                raw_body: None,
+                decoded_body: None,
+                decode_error: None,
+                parse_warning: None,
+                expires_at_utc: None,
             };
-            
+            decompress_body(&mut message);
+            message.parse_warning = body_warning.or_else(|| json_parse_warning(&message));
+
             messages.push(message);
         }
 
@@ -930,968 +1940,4184 @@ impl ServiceBusClient {
         Ok(messages)
     }
 
-    // Original REST API implementation (kept for backward compatibility if needed)
-    #[allow(dead_code)]
-    pub async fn peek_messages_rest(
+    // Dead-letter a specific message (by sequence number) with a reason and description.
+    // The REST API has no dead-letter operation, so this uses an SDK peek-lock receiver.
+    pub async fn dead_letter_message(
         &self,
         queue_name: Option<&str>,
         topic_name: Option<&str>,
         subscription_name: Option<&str>,
-        max_count: u32,
-    ) -> Result<Vec<ServiceBusMessage>, String> {
-        let entity_path = if let Some(q) = queue_name {
-            eprintln!("[peek_messages] Peeking from queue: {}", q);
-            q.to_string()
+        sequence_number: u64,
+        dead_letter_reason: &str,
+        dead_letter_error_description: Option<&str>,
+    ) -> Result<(), String> {
+        if dead_letter_reason.trim().is_empty() {
+            return Err("A dead-letter reason is required".to_string());
+        }
+
+        let action = LockedAction::DeadLetter {
+            reason: dead_letter_reason.to_string(),
+            description: dead_letter_error_description.map(|d| d.to_string()),
+        };
+        self.settle_by_sequence_number(queue_name, topic_name, subscription_name, sequence_number, action)
+            .await
+    }
+
+    // Defer a specific message (by sequence number). Deferred messages stay in the entity
+    // but are only handed out again by receive_deferred_messages.
+    pub async fn defer_message(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        sequence_number: u64,
+    ) -> Result<(), String> {
+        self.settle_by_sequence_number(queue_name, topic_name, subscription_name, sequence_number, LockedAction::Defer)
+            .await
+    }
+
+    // Lock one message by sequence number and settle it, without locking any other.
+    // A deferred message is received by its sequence number. An active message can only
+    // be locked from the head, so it is settled only when a peek (which locks nothing)
+    // shows no active message ahead of it; otherwise the caller is told why.
+    async fn settle_by_sequence_number(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        sequence_number: u64,
+        action: LockedAction,
+    ) -> Result<(), String> {
+        use azservicebus::prelude::*;
+        use azservicebus::ServiceBusMessageState;
+
+        let entity_path = message_entity_path(queue_name, topic_name, subscription_name)?;
+        let arguments = match &action {
+            LockedAction::DeadLetter { reason, description } => serde_json::json!({
+                "sequenceNumber": sequence_number,
+                "deadLetterReason": reason,
+                "deadLetterErrorDescription": description,
+            }),
+            LockedAction::Defer => serde_json::json!({ "sequenceNumber": sequence_number }),
+        };
+        if self.intercept_amqp(action.label(), &entity_path, arguments) {
+            return Ok(());
+        }
+
+        let connection_string = self.sdk_connection_string()?;
+        let max_peeked = 1000usize; // Give up rather than peek through an entire large queue
+        let operation = action.name();
+
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        // Default receive mode is peek-lock
+        let options = ServiceBusReceiverOptions::default();
+        let mut receiver = if let Some(q) = queue_name {
+            client
+                .create_receiver_for_queue(q, options)
+                .await
+                .map_err(|e| format!("Failed to create queue receiver: {}", e))?
         } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
-            eprintln!("[peek_messages] Peeking from topic/subscription: {}/{}", t, s);
-            format!("{}/Subscriptions/{}", t, s)
+            client
+                .create_receiver_for_subscription(t, s, options)
+                .await
+                .map_err(|e| format!("Failed to create subscription receiver: {}", e))?
         } else {
             return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
         };
 
-        // Azure Service Bus peek uses GET request, not POST
-        // Format: /{entity-path}/messages/head?timeout={seconds}&maxcount={count}&api-version={version}
-        let base_url = format!("{}/{}/messages/head?timeout=60&api-version={}", self.get_base_url(), entity_path, API_VERSION);
-        eprintln!("[peek_messages] Base URL: {}", base_url);
-
-        let mut all_messages = Vec::new();
-        let max_per_request = max_count.min(32); // Azure allows max 32 messages per peek
-        let mut sequence_number: Option<i64> = None; // For pagination
-        let mut seen_message_ids = std::collections::HashSet::new(); // Track seen messages to avoid duplicates
+        eprintln!("[{}] Looking for sequence number {}", operation, sequence_number);
 
-        loop {
-            let remaining = max_count as usize - all_messages.len();
-            if remaining == 0 {
-                break;
-            }
-            
-            let count = remaining.min(max_per_request as usize);
-            
-            // When we get JSON responses without sequence numbers, we can't paginate properly
-            // Try different approaches: with maxcount, without maxcount, or with maxcount=1
-            let mut peek_url = if all_messages.is_empty() {
-                // First request: try with the requested maxcount
-                format!("{}&maxcount={}", base_url, count)
-            } else if sequence_number.is_none() && seen_message_ids.len() == all_messages.len() {
-                // We got JSON without sequence numbers - try without maxcount to see if we get XML
-                // Or try with maxcount=1 to get individual messages
-                eprintln!("[peek_messages] Got JSON without sequence numbers, trying without maxcount to get XML Atom feed");
-                base_url.clone() // No maxcount parameter
-            } else {
-                // Use the requested count
-                format!("{}&maxcount={}", base_url, count.min(32))
+        let not_found = || {
+            format!(
+                "Message with sequence number {} was not found (it may be locked by another receiver or already removed)",
+                sequence_number
+            )
+        };
+        let locked = async {
+            // The first active message from the head is the one a receive would lock
+            let mut peeked = 0usize;
+            let mut from_sequence_number = 0i64;
+            let first_active = loop {
+                let page = receiver
+                    .peek_messages(100, Some(from_sequence_number))
+                    .await
+                    .map_err(|e| format!("Failed to peek messages: {}", e))?;
+                let Some(last) = page.last() else {
+                    return Err(not_found());
+                };
+                from_sequence_number = last.sequence_number() + 1;
+                peeked += page.len();
+
+                if let Some(target) = page.iter().find(|m| m.sequence_number() as u64 == sequence_number) {
+                    if matches!(target.state(), ServiceBusMessageState::Deferred) {
+                        return receiver
+                            .receive_deferred_messages([sequence_number as i64])
+                            .await
+                            .map_err(|e| format!("Failed to receive deferred message: {}", e))?
+                            .into_iter()
+                            .next()
+                            .ok_or_else(not_found);
+                    }
+                    if matches!(target.state(), ServiceBusMessageState::Scheduled) {
+                        return Err(format!("Message {} is scheduled; cancel it instead", sequence_number));
+                    }
+                }
+                if let Some(active) = page.iter().find(|m| matches!(m.state(), ServiceBusMessageState::Active)) {
+                    break active.sequence_number() as u64;
+                }
+                if peeked >= max_peeked {
+                    return Err(not_found());
+                }
             };
-            
-            // Add from parameter for pagination if we have a sequence number
-            if let Some(seq) = sequence_number {
-                peek_url = format!("{}&from={}", peek_url, seq);
+            if first_active != sequence_number {
+                return Err(format!(
+                    "Message {} is not at the head of the entity (message {} is ahead of it). Only the next message to be received, or a deferred one, can be {} by sequence number.",
+                    sequence_number,
+                    first_active,
+                    if matches!(action, LockedAction::Defer) { "deferred" } else { "dead-lettered" }
+                ));
             }
-            
-            eprintln!("[peek_messages] Fetching from URL: {}", peek_url);
-            
-            // Update auth header for the new URL
-            let auth_header = self.get_auth_header(&peek_url).await?;
-            
-            let response = self
-                .client
-                .get(&peek_url)
-                .header("Authorization", &auth_header)
-                .header("Accept", "application/atom+xml")
-                .header("Content-Type", "application/atom+xml")
-                .send()
+
+            let received = receiver
+                .receive_messages_with_max_wait_time(1, Some(std::time::Duration::from_secs(5)))
                 .await
-                .map_err(|e| {
-                    eprintln!("[peek_messages] Request failed: {}", e);
-                    format!("Failed to peek messages: {}", e)
-                })?;
+                .map_err(|e| format!("Failed to receive message: {}", e))?
+                .into_iter()
+                .next()
+                .ok_or_else(not_found)?;
+            if received.sequence_number() as u64 != sequence_number {
+                // Another receiver took the target between the peek and the receive
+                if let Err(e) = receiver.abandon_message(&received, None).await {
+                    eprintln!("[{}] Failed to abandon message {}: {}", operation, received.sequence_number(), e);
+                }
+                return Err(not_found());
+            }
+            Ok(received)
+        }
+        .await;
+
+        let result = match locked {
+            Ok(sdk_msg) => match &action {
+                LockedAction::DeadLetter { reason, description } => {
+                    let dead_letter_options = DeadLetterOptions {
+                        dead_letter_reason: Some(reason.clone()),
+                        dead_letter_error_description: description.clone(),
+                        properties_to_modify: None,
+                    };
+                    receiver
+                        .dead_letter_message(&sdk_msg, dead_letter_options)
+                        .await
+                        .map_err(|e| format!("Failed to dead-letter message: {}", e))
+                }
+                LockedAction::Defer => receiver
+                    .defer_message(&sdk_msg, None)
+                    .await
+                    .map_err(|e| format!("Failed to defer message: {}", e)),
+            },
+            Err(e) => Err(e),
+        };
 
-            let status = response.status();
-            eprintln!("[peek_messages] Response status: {}", status);
-            
-            // Extract Content-Type header before consuming the response
-            let content_type = response.headers()
-                .get("content-type")
-                .and_then(|h| h.to_str().ok())
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| "unknown".to_string());
-            eprintln!("[peek_messages] Content-Type: {}", content_type);
-            
-            // Check for 204 No Content (no messages) before checking success
-            if status.as_u16() == 204 {
-                eprintln!("[peek_messages] No messages (204 No Content)");
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        result
+    }
+
+    // Remove specific messages: lock messages from the head, complete the ones whose
+    // sequence number was asked for, and hold the rest under lock until the scan ends.
+    // Held messages are then abandoned, which increments their delivery count. The scan
+    // stops once every target was found or max_scan messages were locked.
+    pub async fn delete_messages_by_sequence(
+        &self,
+        source: &MessageSource,
+        sequence_numbers: &[u64],
+        max_scan: u32,
+        on_progress: &(dyn Fn(&DeleteMessagesProgress) + Send + Sync),
+    ) -> Result<DeleteMessagesProgress, String> {
+        use azservicebus::prelude::*;
+        use std::collections::BTreeSet;
+
+        let mut remaining: BTreeSet<u64> = sequence_numbers.iter().copied().collect();
+        if remaining.is_empty() {
+            return Err("No sequence numbers to delete".to_string());
+        }
+        check_source_not_system_entity("delete messages from", source)?;
+        if remaining.len() > MAX_DELETE_BY_SEQUENCE {
+            return Err(format!(
+                "Too many messages: at most {} can be deleted by sequence number at once",
+                MAX_DELETE_BY_SEQUENCE
+            ));
+        }
+        let max_scan = max_scan.min(MAX_DELETE_SCAN);
+
+        let source_path = source_entity_path(source)?;
+        let arguments = serde_json::json!({ "sequenceNumbers": remaining, "maxScan": max_scan });
+        if self.intercept_amqp("delete messages", &source_path, arguments) {
+            return Ok(DeleteMessagesProgress {
+                source: source_path,
+                requested: remaining.len() as u32,
+                deleted: Vec::new(),
+                scanned: 0,
+                not_found: Vec::new(),
+                done: true,
+            });
+        }
+
+        let connection_string = self.sdk_connection_string()?;
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        // Default receive mode is peek-lock
+        let mut receiver = match (&source.topic_name, &source.subscription_name) {
+            (Some(t), Some(s)) if source.queue_name.is_none() && !source.dead_letter => client
+                .create_receiver_for_subscription(t, s, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create subscription receiver: {}", e))?,
+            _ => client
+                .create_receiver_for_queue(&source_path, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create receiver for {}: {}", source_path, e))?,
+        };
+
+        let mut progress = DeleteMessagesProgress {
+            source: source_path.clone(),
+            requested: remaining.len() as u32,
+            deleted: Vec::new(),
+            scanned: 0,
+            not_found: Vec::new(),
+            done: false,
+        };
+        eprintln!(
+            "[delete_messages_by_sequence] Looking for {} messages in {} (scanning at most {})",
+            remaining.len(),
+            source_path,
+            max_scan
+        );
+
+        let mut held = Vec::new();
+        let mut failure = None;
+        while !remaining.is_empty() && progress.scanned < max_scan {
+            let batch_size = (max_scan - progress.scanned).min(32);
+            let batch = match receiver
+                .receive_messages_with_max_wait_time(batch_size, Some(std::time::Duration::from_secs(5)))
+                .await
+            {
+                Ok(batch) => batch,
+                Err(e) => {
+                    failure = Some(format!("Failed to receive messages: {}", e));
+                    break;
+                }
+            };
+            if batch.is_empty() {
                 break;
             }
-            
-            if !status.is_success() {
-                let error_text = response.text().await.unwrap_or_default();
-                eprintln!("[peek_messages] Error response: {}", error_text);
-                return Err(format!("Failed to peek messages: {} - {}", status, error_text));
-            }
 
-            // Parse messages from response
-            // Azure Service Bus returns messages in Atom feed format (XML)
-            // However, sometimes Azure returns JSON instead, especially when maxcount is used
-            let response_text = response.text().await.map_err(|e| {
-                eprintln!("[peek_messages] Failed to read response text: {}", e);
-                format!("Failed to read response: {}", e)
-            })?;
-            
-            // Debug: Check if response might be XML-wrapped JSON by looking for XML tags
-            // even if it starts with {
-            if response_text.trim_start().starts_with('{') {
-                // Check if there are any XML tags in the response (might be XML-wrapped)
-                if response_text.contains('<') && (response_text.contains("feed") || response_text.contains("entry")) {
-                    eprintln!("[peek_messages] Response starts with {{ but contains XML tags - might be XML-wrapped JSON");
+            progress.scanned += batch.len() as u32;
+            for sdk_msg in batch {
+                let sequence_number = sdk_msg.sequence_number() as u64;
+                if failure.is_none() && remaining.remove(&sequence_number) {
+                    match receiver.complete_message(&sdk_msg).await {
+                        Ok(()) => progress.deleted.push(sequence_number),
+                        Err(e) => {
+                            failure = Some(format!("Failed to complete message {}: {}", sequence_number, e));
+                            held.push(sdk_msg);
+                        }
+                    }
+                } else {
+                    held.push(sdk_msg);
                 }
             }
-            
-            eprintln!("[peek_messages] Response length: {} chars", response_text.len());
-            
-            // Log full response if small, or preview if large
-            if response_text.len() < 2000 {
-                eprintln!("[peek_messages] Full response: {}", response_text);
-            } else {
-                eprintln!("[peek_messages] Response preview (first 1000 chars): {}", &response_text[..1000.min(response_text.len())]);
-                eprintln!("[peek_messages] Response preview (last 500 chars): {}", &response_text[response_text.len().saturating_sub(500)..]);
-            }
-            
-            // Check if response starts with XML declaration or feed tag
-            let trimmed = response_text.trim_start();
-            let is_xml_start = trimmed.starts_with("<?xml") || trimmed.starts_with("<feed") || trimmed.starts_with("<entry");
-            let is_json_start = trimmed.starts_with('{') || trimmed.starts_with('[');
-            
-            eprintln!("[peek_messages] Response starts with XML: {}, JSON: {}", is_xml_start, is_json_start);
-            
-            // Check if response might be XML-wrapped JSON (look for common XML patterns even if it starts with {)
-            let might_be_xml_wrapped = trimmed.contains("<feed") || trimmed.contains("<entry") || trimmed.contains("<?xml");
-            if might_be_xml_wrapped && is_json_start {
-                eprintln!("[peek_messages] Warning: Response starts with JSON but might contain XML elements - could be XML-wrapped JSON");
+            if failure.is_some() {
+                break;
             }
-            
-            // Try to parse as XML Atom feed first (standard Azure Service Bus format)
-            // Even if it starts with JSON, it might be XML-wrapped JSON content
-            // However, if Content-Type says XML but body is JSON, Azure might be misconfigured
-            // Try parsing as XML first
-            let feed_result: Result<MessageFeed, _> = from_str(&response_text);
-            
-            // If XML parsing fails and Content-Type says XML but body is JSON,
-            // this might indicate Azure is returning raw message body instead of Atom feed
-            // This could happen if maxcount parameter causes issues or if Azure is misconfigured
-            
-            match feed_result {
-                Ok(feed) => {
-                    // Successfully parsed as XML Atom feed
-                    let entry_count = feed.entries.len();
-                    eprintln!("[peek_messages] Parsed {} entries from XML feed", entry_count);
-                    
-                    if entry_count == 0 {
-                        // No more messages
-                        break;
-                    }
-                    
-                    for (idx, entry) in feed.entries.iter().enumerate() {
-                        eprintln!("[peek_messages] Processing entry {}", idx);
-                        let message = self.message_entry_to_message(entry).map_err(|e| {
-                            eprintln!("[peek_messages] Failed to convert entry {} to message: {}", idx, e);
-                            e
-                        })?;
-                        all_messages.push(message);
-                    }
-                    
-                    eprintln!("[peek_messages] Successfully processed {} messages, total so far: {}", entry_count, all_messages.len());
-                    
-                    // Track sequence number for pagination (from last message)
-                    if let Some(last_entry) = feed.entries.last() {
-                        if let Some(seq) = last_entry.sequence_number {
-                            sequence_number = Some(seq);
-                        }
-                    }
-                    
-                    // If we got fewer messages than requested, we're done
-                    if entry_count < count {
+            on_progress(&progress);
+        }
+
+        // Release everything we locked while scanning
+        for sdk_msg in &held {
+            if let Err(e) = receiver.abandon_message(sdk_msg, None).await {
+                eprintln!("[delete_messages_by_sequence] Failed to abandon message {}: {}", sdk_msg.sequence_number(), e);
+            }
+        }
+        eprintln!("[delete_messages_by_sequence] Released {} messages that were not targeted", held.len());
+
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        if let Some(e) = failure {
+            return Err(format!("Delete stopped after {} messages: {}", progress.deleted.len(), e));
+        }
+
+        progress.not_found = remaining.into_iter().collect();
+        progress.done = true;
+        on_progress(&progress);
+        eprintln!(
+            "[delete_messages_by_sequence] Deleted {} of {} messages from {}",
+            progress.deleted.len(),
+            progress.requested,
+            source_path
+        );
+        Ok(progress)
+    }
+
+    // Receive deferred messages by sequence number. With complete the messages are removed
+    // from the entity; otherwise they are abandoned, which leaves them deferred.
+    pub async fn receive_deferred_messages(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        sequence_numbers: &[u64],
+        complete: bool,
+    ) -> Result<Vec<crate::azure::types::ServiceBusMessage>, String> {
+        use azservicebus::prelude::*;
+
+        if sequence_numbers.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Completing removes the messages; without complete they are only read and abandoned
+        if complete {
+            let entity_path = message_entity_path(queue_name, topic_name, subscription_name)?;
+            let arguments = serde_json::json!({ "sequenceNumbers": sequence_numbers, "complete": true });
+            if self.intercept_amqp("receive deferred messages", &entity_path, arguments) {
+                return Ok(Vec::new());
+            }
+        }
+
+        let connection_string = self.sdk_connection_string()?;
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        let options = ServiceBusReceiverOptions::default();
+        let mut receiver = if let Some(q) = queue_name {
+            client
+                .create_receiver_for_queue(q, options)
+                .await
+                .map_err(|e| format!("Failed to create queue receiver: {}", e))?
+        } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+            client
+                .create_receiver_for_subscription(t, s, options)
+                .await
+                .map_err(|e| format!("Failed to create subscription receiver: {}", e))?
+        } else {
+            return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+        };
+
+        let result = receiver
+            .receive_deferred_messages(sequence_numbers.iter().map(|seq| *seq as i64))
+            .await
+            .map_err(|e| format!("Failed to receive deferred messages: {}", e));
+
+        let mut messages = Vec::new();
+        let mut failure = None;
+        if let Ok(received) = &result {
+            for sdk_msg in received {
+                // Stop here, but still dispose the receiver and client below
+                match received_message_to_message(sdk_msg) {
+                    Ok(message) => messages.push(message),
+                    Err(e) => {
+                        failure = Some(e);
                         break;
                     }
-                    
-                    // Continue to next iteration of loop for pagination
-                    continue;
                 }
-                Err(xml_err) => {
-                    // XML parsing failed - check if it's JSON
-                    eprintln!("[peek_messages] XML parsing failed: {}", xml_err);
-                    
-                    if is_json_start && !is_xml_start {
-                        eprintln!("[peek_messages] Detected JSON response (raw message body)");
-                        // Azure is returning JSON instead of XML Atom feed - this is non-standard
-                        // Try to parse as JSON - could be a single message object or an array
-                        match serde_json::from_str::<serde_json::Value>(&response_text) {
-                            Ok(json_value) => {
-                                // Check if it's an array of messages
-                                if let Some(array) = json_value.as_array() {
-                                    eprintln!("[peek_messages] JSON is an array with {} messages", array.len());
-                                    for (_idx, item) in array.iter().enumerate() {
-                                        // Extract message properties from JSON object
-                                        let message_id = item.get("MessageId").and_then(|v| v.as_str()).map(|s| s.to_string());
-                                        let body = item.clone(); // Use the whole JSON object as body
-                                        
-                                        let message = ServiceBusMessage {
-                                            body,
-                                            message_id: message_id.clone(),
-                                            correlation_id: item.get("CorrelationId").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            content_type: Some("application/json".to_string()),
-                                            sequence_number: item.get("SequenceNumber").and_then(|v| v.as_i64()).map(|s| s as u64),
-                                            subject: item.get("Subject").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            reply_to: item.get("ReplyTo").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            reply_to_session_id: item.get("ReplyToSessionId").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            session_id: item.get("SessionId").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            time_to_live: None,
-                                            to: item.get("To").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            application_properties: None,
-                                            delivery_count: item.get("DeliveryCount").and_then(|v| v.as_u64()).map(|s| s as u32),
-                                            enqueued_time_utc: item.get("EnqueuedTimeUtc").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            locked_until_utc: item.get("LockedUntilUtc").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            dead_letter_reason: item.get("DeadLetterReason").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                            dead_letter_error_description: item.get("DeadLetterErrorDescription").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        };
-                                        all_messages.push(message);
-                                    }
-                                    eprintln!("[peek_messages] Created {} messages from JSON array", array.len());
-                                } else {
-                                    // Single message object
-                                    eprintln!("[peek_messages] JSON is a single message object");
-                                    // Extract message properties from JSON object
-                                    let message_id = json_value.get("MessageId").and_then(|v| v.as_str()).map(|s| s.to_string());
-                                    let body = json_value.clone(); // Use the whole JSON object as body
-                                    
-                                    // Try to extract sequence number for pagination
-                                    let seq_num = json_value.get("SequenceNumber").and_then(|v| v.as_i64());
-                                    
-                                    let message = ServiceBusMessage {
-                                        body,
-                                        message_id: message_id.clone(),
-                                        correlation_id: json_value.get("CorrelationId").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        content_type: Some("application/json".to_string()),
-                                        sequence_number: seq_num.map(|s| s as u64),
-                                        subject: json_value.get("Subject").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        reply_to: json_value.get("ReplyTo").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        reply_to_session_id: json_value.get("ReplyToSessionId").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        session_id: json_value.get("SessionId").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        time_to_live: None,
-                                        to: json_value.get("To").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        application_properties: None,
-                                        delivery_count: json_value.get("DeliveryCount").and_then(|v| v.as_u64()).map(|s| s as u32),
-                                        enqueued_time_utc: json_value.get("EnqueuedTimeUtc").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        locked_until_utc: json_value.get("LockedUntilUtc").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        dead_letter_reason: json_value.get("DeadLetterReason").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                        dead_letter_error_description: json_value.get("DeadLetterErrorDescription").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                                    };
-                                    // Check if we've already seen this message
-                                    if let Some(ref msg_id) = message_id {
-                                        if seen_message_ids.contains(msg_id) {
-                                            eprintln!("[peek_messages] Already seen message ID {}, stopping pagination", msg_id);
-                                            break; // We're getting duplicates, stop
-                                        }
-                                        seen_message_ids.insert(msg_id.clone());
-                                    }
-                                    
-                                    all_messages.push(message);
-                                    eprintln!("[peek_messages] Created 1 message from JSON object");
-                                    
-                                    // Update sequence number for pagination if available
-                                    if let Some(seq) = seq_num {
-                                        sequence_number = Some(seq);
-                                        eprintln!("[peek_messages] Extracted sequence number {} for pagination", seq);
-                                    }
-                                    
-                                    // When Azure returns JSON, it typically returns one message at a time
-                                    // We need to make multiple requests to get more messages
-                                    // Track this message ID to avoid duplicates
-                                    if let Some(ref msg_id) = message_id {
-                                        if seen_message_ids.contains(msg_id) {
-                                            eprintln!("[peek_messages] Already seen message ID {}, stopping pagination", msg_id);
-                                            break; // We're getting duplicates, stop
-                                        }
-                                        seen_message_ids.insert(msg_id.clone());
-                                    }
-                                    
-                                    // If we got only 1 message but requested more, continue making requests
-                                    if all_messages.len() < max_count as usize {
-                                        if sequence_number.is_some() {
-                                            eprintln!("[peek_messages] Got 1 JSON message, continuing pagination with sequence number (have {} of {})", all_messages.len(), max_count);
-                                            continue; // Continue loop to get more messages
-                                        } else {
-                                            // No sequence number, but Azure might return different messages on subsequent requests
-                                            // Try making another request - Azure might cycle through messages
-                                            eprintln!("[peek_messages] Got 1 JSON message without sequence number. Making another request to get next message (have {} of {})", all_messages.len(), max_count);
-                                            // Reset to try getting the next message
-                                            sequence_number = None;
-                                            continue; // Continue to get more messages
-                                        }
-                                    } else {
-                                        // We've reached max_count
-                                        break;
-                                    }
-                                }
-                            }
-                            Err(json_err) => {
-                                eprintln!("[peek_messages] Failed to parse as JSON: {}", json_err);
-                                return Err(format!("Failed to parse response as XML or JSON. XML error: {}, JSON error: {}", xml_err, json_err));
-                            }
+                let settled = if complete {
+                    receiver.complete_message(sdk_msg).await
+                } else {
+                    receiver.abandon_message(sdk_msg, None).await
+                };
+                if let Err(e) = settled {
+                    failure = Some(format!("Failed to settle deferred message {}: {}", sdk_msg.sequence_number(), e));
+                    break;
+                }
+            }
+        }
+
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        result?;
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        eprintln!("[receive_deferred_messages] Received {} of {} deferred messages", messages.len(), sequence_numbers.len());
+        Ok(messages)
+    }
+
+    // Move messages from a queue/subscription (or its DLQ) to a queue or topic.
+    // Each message is received under peek-lock, sent to the destination, and only then
+    // completed on the source. A failure abandons the in-flight messages and stops, so
+    // nothing is lost; a crash between send and complete can leave a duplicate behind.
+    pub async fn move_messages(
+        &self,
+        source: &MessageSource,
+        destination: &str,
+        count: u32,
+        on_progress: &(dyn Fn(&MoveMessagesProgress) + Send + Sync),
+    ) -> Result<MoveMessagesProgress, String> {
+        use azservicebus::prelude::*;
+
+        check_source_not_system_entity("move messages from", source)?;
+        check_not_system_entity("move messages to", destination)?;
+        let source_path = source_entity_path(source)?;
+        if source_path.eq_ignore_ascii_case(destination) {
+            return Err("Source and destination must be different entities".to_string());
+        }
+        let arguments = serde_json::json!({ "destination": destination, "count": count });
+        if self.intercept_amqp("move messages", &source_path, arguments) {
+            let progress = MoveMessagesProgress {
+                source: source_path,
+                destination: destination.to_string(),
+                requested: count,
+                moved: 0,
+                last_sequence_number: None,
+                done: true,
+            };
+            on_progress(&progress);
+            return Ok(progress);
+        }
+
+        let connection_string = self.sdk_connection_string()?;
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        let mut receiver = match (&source.topic_name, &source.subscription_name) {
+            (Some(t), Some(s)) if source.queue_name.is_none() && !source.dead_letter => client
+                .create_receiver_for_subscription(t, s, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create subscription receiver: {}", e))?,
+            _ => client
+                .create_receiver_for_queue(&source_path, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create receiver for {}: {}", source_path, e))?,
+        };
+        let mut sender = client
+            .create_sender(destination, ServiceBusSenderOptions::default())
+            .await
+            .map_err(|e| format!("Failed to create sender: {}", e))?;
+
+        let mut progress = MoveMessagesProgress {
+            source: source_path.clone(),
+            destination: destination.to_string(),
+            requested: count,
+            moved: 0,
+            last_sequence_number: None,
+            done: false,
+        };
+        eprintln!("[move_messages] Moving up to {} messages from {} to {}", count, source_path, destination);
+
+        let mut failure = None;
+        'batches: while progress.moved < count {
+            let batch_size = (count - progress.moved).min(32);
+            let batch = match receiver
+                .receive_messages_with_max_wait_time(batch_size, Some(std::time::Duration::from_secs(5)))
+                .await
+            {
+                Ok(batch) => batch,
+                Err(e) => {
+                    failure = Some(format!("Failed to receive messages: {}", e));
+                    break;
+                }
+            };
+            if batch.is_empty() {
+                break;
+            }
+
+            for (idx, received) in batch.iter().enumerate() {
+                let step = match forward_message(received, source.dead_letter) {
+                    Ok(outgoing) => sender
+                        .send_message(outgoing)
+                        .await
+                        .map_err(|e| format!("Failed to send message {}: {}", received.sequence_number(), e)),
+                    Err(e) => Err(e),
+                };
+                let step = match step {
+                    Ok(()) => receiver
+                        .complete_message(received)
+                        .await
+                        .map_err(|e| format!("Sent message {} but failed to complete it on the source: {}", received.sequence_number(), e)),
+                    Err(e) => Err(e),
+                };
+
+                match step {
+                    Ok(()) => {
+                        progress.moved += 1;
+                        progress.last_sequence_number = Some(received.sequence_number() as u64);
+                    }
+                    Err(e) => {
+                        // Release this message and the rest of the batch
+                        for pending in &batch[idx..] {
+                            let _ = receiver.abandon_message(pending, None).await;
                         }
-                    } else {
-                        // Not JSON, and XML parsing failed
-                        eprintln!("[peek_messages] Response starts with: {}", &response_text[..response_text.len().min(100)]);
-                        return Err(format!("Failed to parse message feed: {}", xml_err));
+                        failure = Some(e);
+                        break 'batches;
                     }
                 }
             }
+
+            on_progress(&progress);
+        }
+
+        sender.dispose().await.map_err(|e| format!("Failed to dispose sender: {}", e))?;
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        if let Some(e) = failure {
+            // Report what was moved up to the failure before giving up
+            on_progress(&progress);
+            return Err(format!("Move stopped after {} messages: {}", progress.moved, e));
+        }
+
+        progress.done = true;
+        on_progress(&progress);
+        eprintln!("[move_messages] Moved {} messages", progress.moved);
+        Ok(progress)
+    }
+
+    // Quarantine a queue or subscription: dead-letter its active messages with reason,
+    // e.g. when a consumer bug means none of them should be processed but all must be
+    // kept. Only as many messages as were active at the start (or max_count) are taken,
+    // so new arrivals are left alone. A failure abandons the rest of the batch and stops.
+    pub async fn dead_letter_all(
+        &self,
+        source: &MessageSource,
+        reason: &str,
+        description: Option<&str>,
+        max_count: Option<u32>,
+        on_progress: &(dyn Fn(&DeadLetterAllProgress) + Send + Sync),
+    ) -> Result<DeadLetterAllProgress, String> {
+        use azservicebus::prelude::*;
+
+        if reason.trim().is_empty() {
+            return Err("A dead-letter reason is required".to_string());
+        }
+        if source.dead_letter {
+            return Err("Messages in a dead-letter queue are already dead-lettered".to_string());
+        }
+        check_source_not_system_entity("dead-letter messages of", source)?;
+        let source_path = source_entity_path(source)?;
+        let count = match max_count {
+            Some(count) => count,
+            None => {
+                let (_, counts) = self
+                    .runtime_counts(
+                        source.queue_name.as_deref(),
+                        source.topic_name.as_deref(),
+                        source.subscription_name.as_deref(),
+                    )
+                    .await?;
+                let active = counts.active.ok_or("Could not read the active message count; pass max_count")?;
+                active.min(u32::MAX as u64) as u32
+            }
+        };
+
+        let mut progress = DeadLetterAllProgress {
+            source: source_path.clone(),
+            reason: reason.to_string(),
+            requested: count,
+            dead_lettered: 0,
+            done: false,
+        };
+        let arguments = serde_json::json!({
+            "count": count,
+            "deadLetterReason": reason,
+            "deadLetterErrorDescription": description,
+        });
+        if count == 0 || self.intercept_amqp("dead-letter messages", &source_path, arguments) {
+            progress.done = true;
+            on_progress(&progress);
+            return Ok(progress);
+        }
+
+        let connection_string = self.sdk_connection_string()?;
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+        let mut receiver = match (&source.topic_name, &source.subscription_name) {
+            (Some(t), Some(s)) if source.queue_name.is_none() => client
+                .create_receiver_for_subscription(t, s, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create subscription receiver: {}", e))?,
+            _ => client
+                .create_receiver_for_queue(&source_path, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create receiver for {}: {}", source_path, e))?,
+        };
+        eprintln!("[dead_letter_all] Dead-lettering up to {} messages of {}: {}", count, source_path, reason);
+
+        let mut failure = None;
+        'batches: while progress.dead_lettered < count {
+            let batch_size = (count - progress.dead_lettered).min(32);
+            let batch = match receiver
+                .receive_messages_with_max_wait_time(batch_size, Some(std::time::Duration::from_secs(5)))
+                .await
+            {
+                Ok(batch) => batch,
+                Err(e) => {
+                    failure = Some(format!("Failed to receive messages: {}", e));
+                    break;
+                }
+            };
+            if batch.is_empty() {
+                break;
+            }
+
+            for (idx, received) in batch.iter().enumerate() {
+                let options = DeadLetterOptions {
+                    dead_letter_reason: Some(reason.to_string()),
+                    dead_letter_error_description: description.map(|d| d.to_string()),
+                    properties_to_modify: None,
+                };
+                match receiver.dead_letter_message(received, options).await {
+                    Ok(()) => progress.dead_lettered += 1,
+                    Err(e) => {
+                        for pending in &batch[idx..] {
+                            let _ = receiver.abandon_message(pending, None).await;
+                        }
+                        failure = Some(format!("Failed to dead-letter message {}: {}", received.sequence_number(), e));
+                        break 'batches;
+                    }
+                }
+            }
+
+            on_progress(&progress);
+        }
+
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        if let Some(e) = failure {
+            return Err(format!("Stopped after dead-lettering {} messages: {}", progress.dead_lettered, e));
+        }
+
+        progress.done = true;
+        on_progress(&progress);
+        eprintln!("[dead_letter_all] Dead-lettered {} messages of {}", progress.dead_lettered, source_path);
+        Ok(progress)
+    }
+
+    // Resubmit a large DLQ with several receivers in parallel, each a move_messages from
+    // the DLQ. The count (or the DLQ size when the drain starts) is split between them so
+    // new dead letters arriving mid-drain are left alone. Each receiver keeps a checkpoint
+    // (moved, last sequence number, error) from the completions it performed; every
+    // message is sent before it is completed.
+    pub async fn drain_dead_letters(
+        self: &std::sync::Arc<Self>,
+        source: &MessageSource,
+        destination: Option<&str>,
+        receivers: u32,
+        count: Option<u32>,
+        on_progress: std::sync::Arc<dyn Fn(&DeadLetterDrainProgress) + Send + Sync>,
+    ) -> Result<DeadLetterDrainReport, String> {
+        use std::sync::{Arc, Mutex};
+
+        check_source_not_system_entity("drain dead letters of", source)?;
+        let source = MessageSource {
+            dead_letter: true,
+            ..source.clone()
+        };
+        let source_path = source_entity_path(&source)?;
+        let destination = match (destination, &source.queue_name) {
+            (Some(d), _) => d.to_string(),
+            (None, Some(q)) => q.clone(),
+            (None, None) => {
+                return Err("A destination is required when draining a subscription dead-letter queue".to_string())
+            }
+        };
+
+        check_not_system_entity("drain dead letters to", &destination)?;
+
+        let (_, counts_before) = self
+            .runtime_counts(source.queue_name.as_deref(), source.topic_name.as_deref(), source.subscription_name.as_deref())
+            .await?;
+        let before = counts_before.dead_letter.unwrap_or(0);
+        let requested = count.unwrap_or(before.min(u32::MAX as u64) as u32);
+        let receivers = receivers.clamp(1, 16).min(requested.div_ceil(32).max(1));
+
+        let arguments = serde_json::json!({ "destination": destination, "count": requested, "receivers": receivers });
+        if self.intercept_amqp("drain dead letters", &source_path, arguments) {
+            return Ok(DeadLetterDrainReport {
+                source: source_path,
+                destination,
+                receivers,
+                requested,
+                moved: 0,
+                dead_letter_count_before: before,
+                dead_letter_count_after: before,
+                verified: false,
+                checkpoints: Vec::new(),
+                notes: vec!["Dry run: the drain was only planned, nothing was resubmitted.".to_string()],
+            });
+        }
+
+        eprintln!(
+            "[drain_dead_letters] Draining {} of {} messages from {} to {} with {} receivers",
+            requested, before, source_path, destination, receivers
+        );
+
+        let checkpoints = Arc::new(Mutex::new(
+            (0..receivers)
+                .map(|receiver_id| ReceiverCheckpoint {
+                    receiver_id,
+                    moved: 0,
+                    last_sequence_number: None,
+                    finished: false,
+                    error: None,
+                })
+                .collect::<Vec<_>>(),
+        ));
+        let report = {
+            let checkpoints = checkpoints.clone();
+            let source_path = source_path.clone();
+            let destination = destination.clone();
+            move || {
+                let Ok(snapshot) = checkpoints.lock().map(|c| c.clone()) else {
+                    return;
+                };
+                on_progress(&DeadLetterDrainProgress {
+                    source: source_path.clone(),
+                    destination: destination.clone(),
+                    moved: snapshot.iter().map(|c| c.moved).sum(),
+                    checkpoints: snapshot,
+                });
+            }
+        };
+        let report = Arc::new(report);
+
+        let mut workers = tokio::task::JoinSet::new();
+        for receiver_id in 0..receivers {
+            let share = requested / receivers + u32::from(receiver_id < requested % receivers);
+            let client = self.clone();
+            let source = source.clone();
+            let destination = destination.clone();
+            let checkpoints = checkpoints.clone();
+            let report = report.clone();
+            workers.spawn(async move {
+                let update = |f: &dyn Fn(&mut ReceiverCheckpoint)| {
+                    if let Ok(mut checkpoints) = checkpoints.lock() {
+                        if let Some(checkpoint) = checkpoints.get_mut(receiver_id as usize) {
+                            f(checkpoint);
+                        }
+                    }
+                };
+                let on_moved = |progress: &MoveMessagesProgress| {
+                    update(&|c| {
+                        c.moved = progress.moved;
+                        c.last_sequence_number = progress.last_sequence_number;
+                    });
+                    report();
+                };
+                let result = if share == 0 {
+                    Ok(())
+                } else {
+                    client.move_messages(&source, &destination, share, &on_moved).await.map(|_| ())
+                };
+                if let Err(e) = result {
+                    eprintln!("[drain_dead_letters] Receiver {} stopped: {}", receiver_id, e);
+                    update(&|c| c.error = Some(e.clone()));
+                }
+                update(&|c| c.finished = true);
+                report();
+            });
+        }
+        while let Some(result) = workers.join_next().await {
+            if let Err(e) = result {
+                eprintln!("[drain_dead_letters] Receiver task failed: {}", e);
+            }
+        }
+
+        let checkpoints = checkpoints.lock().map_err(|_| "Drain checkpoints are unavailable".to_string())?.clone();
+        let moved: u32 = checkpoints.iter().map(|c| c.moved).sum();
+        let verified = checkpoints.iter().all(|c| c.finished && c.error.is_none());
+
+        // The DLQ count is only reported; it can lag a moment behind the completions
+        let (_, counts_after) = self
+            .runtime_counts(source.queue_name.as_deref(), source.topic_name.as_deref(), source.subscription_name.as_deref())
+            .await?;
+        let after = counts_after.dead_letter.unwrap_or(0);
+        let expected = before.saturating_sub(moved as u64);
+
+        let mut notes = Vec::new();
+        if after > expected {
+            notes.push(format!(
+                "{} more messages are in the dead-letter queue than expected; new messages may have been dead-lettered while the drain was running and were left in place.",
+                after - expected
+            ));
+        } else if after < expected {
+            notes.push(format!(
+                "{} fewer messages are in the dead-letter queue than expected; another receiver may have removed them during the drain.",
+                expected - after
+            ));
+        }
+        if moved < requested {
+            notes.push(format!("Resubmitted {} of the {} messages requested.", moved, requested));
+        }
+        for checkpoint in checkpoints.iter().filter(|c| c.error.is_some()) {
+            notes.push(format!(
+                "Receiver {} stopped after {} messages: {}",
+                checkpoint.receiver_id,
+                checkpoint.moved,
+                checkpoint.error.as_deref().unwrap_or_default()
+            ));
+        }
+        if receivers > 1 {
+            notes.push("Messages were resubmitted by parallel receivers, so their relative order was not preserved.".to_string());
+        }
+
+        Ok(DeadLetterDrainReport {
+            source: source_path,
+            destination,
+            receivers,
+            requested,
+            moved,
+            dead_letter_count_before: before,
+            dead_letter_count_after: after,
+            verified,
+            checkpoints,
+            notes,
+        })
+    }
+
+    // Dry run for operations that only exist over AMQP: recorded against the entity's
+    // URL with method "AMQP" and the operation's arguments as the body
+    fn intercept_amqp(&self, operation: &str, entity_path: &str, arguments: serde_json::Value) -> bool {
+        let url = format!("{}/{}", self.get_base_url(), entity_path);
+        dry_run::intercept(operation, "AMQP", &url, Some(&arguments.to_string()))
+    }
+
+    // Connection string for the SDK, rebuilt from the parsed components
+    fn sdk_connection_string(&self) -> Result<String, String> {
+        let parsed = self
+            .parsed_connection
+            .as_ref()
+            .ok_or("Connection string not available for SDK")?;
+        Ok(format!(
+            "Endpoint=sb://{}{}/;SharedAccessKeyName={};SharedAccessKey={}",
+            self.namespace,
+            self.endpoint_domain,
+            parsed.shared_access_key_name,
+            parsed.shared_access_key
+        ))
+    }
+
+    // REST API implementation, used when the connection selects the HTTP transport
+    #[allow(dead_code)]
+    pub async fn peek_messages_rest(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        max_count: u32,
+        from_sequence_number: Option<u64>,
+        options: &PeekOptions,
+    ) -> Result<Vec<ServiceBusMessage>, String> {
+        let entity_path = if let Some(q) = queue_name {
+            eprintln!("[peek_messages] Peeking from queue: {}", q);
+            q.to_string()
+        } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+            eprintln!("[peek_messages] Peeking from topic/subscription: {}/{}", t, s);
+            format!("{}/Subscriptions/{}", t, s)
+        } else {
+            return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+        };
+
+        // Azure Service Bus peek uses GET request, not POST
+        // Format: /{entity-path}/messages/head?timeout={seconds}&maxcount={count}&api-version={version}
+        let timeout = options.timeout_seconds.unwrap_or(DEFAULT_PEEK_TIMEOUT_SECONDS);
+        let base_url = format!(
+            "{}/{}/messages/head?timeout={}&api-version={}",
+            self.get_base_url(),
+            entity_path,
+            timeout,
+            API_VERSION
+        );
+        eprintln!("[peek_messages] Base URL: {}", base_url);
+
+        let mut all_messages = Vec::new();
+        // Azure allows max 32 messages per peek
+        let max_per_request = max_count.min(options.page_size.unwrap_or(MAX_HTTP_PEEK_PAGE_SIZE));
+        let mut sequence_number: Option<i64> = from_sequence_number.map(|seq| seq as i64); // For pagination
+        let mut seen_message_ids = std::collections::HashSet::new(); // Track seen messages to avoid duplicates
+
+        loop {
+            let remaining = max_count as usize - all_messages.len();
+            if remaining == 0 {
+                break;
+            }
+            
+            let count = remaining.min(max_per_request as usize);
+            
+            // When we get JSON responses without sequence numbers, we can't paginate properly
+            // Try different approaches: with maxcount, without maxcount, or with maxcount=1
+            let mut peek_url = if all_messages.is_empty() {
+                // First request: try with the requested maxcount
+                format!("{}&maxcount={}", base_url, count)
+            } else if sequence_number.is_none() && seen_message_ids.len() == all_messages.len() {
+                // We got JSON without sequence numbers - try without maxcount to see if we get XML
+                // Or try with maxcount=1 to get individual messages
+                eprintln!("[peek_messages] Got JSON without sequence numbers, trying without maxcount to get XML Atom feed");
+                base_url.clone() // No maxcount parameter
+            } else {
+                // Use the requested count
+                format!("{}&maxcount={}", base_url, count)
+            };
+            
+            // Add from parameter for pagination if we have a sequence number
+            if let Some(seq) = sequence_number {
+                peek_url = format!("{}&from={}", peek_url, seq);
+            }
+            
+            eprintln!("[peek_messages] Fetching from URL: {}", peek_url);
+            
+            // Update auth header for the new URL
+            let auth_header = self.get_auth_header(&peek_url).await?;
+            
+            let response = self
+                .client
+                .get(&peek_url)
+                .header("Authorization", &auth_header)
+                .header("Accept", "application/atom+xml")
+                .header("Content-Type", "application/atom+xml")
+                .send()
+                .await
+                .map_err(|e| {
+                    eprintln!("[peek_messages] Request failed: {}", e);
+                    format!("Failed to peek messages: {}", e)
+                })?;
+
+            let status = response.status();
+            eprintln!("[peek_messages] Response status: {}", status);
+            
+            // Extract Content-Type header before consuming the response
+            let content_type = response.headers()
+                .get("content-type")
+                .and_then(|h| h.to_str().ok())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            eprintln!("[peek_messages] Content-Type: {}", content_type);
+            
+            // Check for 204 No Content (no messages) before checking success
+            if status.as_u16() == 204 {
+                eprintln!("[peek_messages] No messages (204 No Content)");
+                break;
+            }
+            
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                eprintln!("[peek_messages] Error response: {}", error_text);
+                return Err(http_error("peek messages", status, &error_text));
+            }
+
+            // Parse messages from response
+            // Azure Service Bus returns messages in Atom feed format (XML)
+            // However, sometimes Azure returns JSON instead, especially when maxcount is used
+            let response_text = response.text().await.map_err(|e| {
+                eprintln!("[peek_messages] Failed to read response text: {}", e);
+                format!("Failed to read response: {}", e)
+            })?;
+            
+            // Debug: Check if response might be XML-wrapped JSON by looking for XML tags
+            // even if it starts with {
+            if response_text.trim_start().starts_with('{') {
+                // Check if there are any XML tags in the response (might be XML-wrapped)
+                if response_text.contains('<') && (response_text.contains("feed") || response_text.contains("entry")) {
+                    eprintln!("[peek_messages] Response starts with {{ but contains XML tags - might be XML-wrapped JSON");
+                }
+            }
+            
+            eprintln!("[peek_messages] Response length: {} chars", response_text.len());
+            
+            // Log full response if small, or preview if large
+            if response_text.len() < 2000 {
+                eprintln!("[peek_messages] Full response: {}", response_text);
+            } else {
+                eprintln!("[peek_messages] Response preview (first 1000 chars): {}", &response_text[..1000.min(response_text.len())]);
+                eprintln!("[peek_messages] Response preview (last 500 chars): {}", &response_text[response_text.len().saturating_sub(500)..]);
+            }
+            
+            // Check if response starts with XML declaration or feed tag
+            let trimmed = response_text.trim_start();
+            let is_xml_start = trimmed.starts_with("<?xml") || trimmed.starts_with("<feed") || trimmed.starts_with("<entry");
+            let is_json_start = trimmed.starts_with('{') || trimmed.starts_with('[');
+            
+            eprintln!("[peek_messages] Response starts with XML: {}, JSON: {}", is_xml_start, is_json_start);
+            
+            // Check if response might be XML-wrapped JSON (look for common XML patterns even if it starts with {)
+            let might_be_xml_wrapped = trimmed.contains("<feed") || trimmed.contains("<entry") || trimmed.contains("<?xml");
+            if might_be_xml_wrapped && is_json_start {
+                eprintln!("[peek_messages] Warning: Response starts with JSON but might contain XML elements - could be XML-wrapped JSON");
+            }
+            
+            // An empty body or a feed without entries means the queue is empty
+            if is_empty_body(&response_text) || (is_xml_start && !response_text.contains("<entry")) {
+                eprintln!("[peek_messages] Empty feed, no more messages");
+                break;
+            }
+            
+            // Try to parse as XML Atom feed first (standard Azure Service Bus format)
+            // Even if it starts with JSON, it might be XML-wrapped JSON content
+            // However, if Content-Type says XML but body is JSON, Azure might be misconfigured
+            // Try parsing as XML first
+            let feed_result = match from_str::<MessageFeed>(&response_text) {
+                Ok(feed) => Ok(feed.entries),
+                // One malformed entry fails the whole feed: take the entries one at a time
+                Err(xml_err) if is_xml_start => {
+                    eprintln!("[peek_messages] Feed did not parse ({}), parsing entries one by one", xml_err);
+                    Ok(lenient_feed_entries(&response_text))
+                }
+                Err(xml_err) => Err(xml_err),
+            };
+            
+            // If XML parsing fails and Content-Type says XML but body is JSON,
+            // this might indicate Azure is returning raw message body instead of Atom feed
+            // This could happen if maxcount parameter causes issues or if Azure is misconfigured
+            
+            match feed_result {
+                Ok(entries) => {
+                    // Successfully parsed as XML Atom feed
+                    let entry_count = entries.len();
+                    eprintln!("[peek_messages] Parsed {} entries from XML feed", entry_count);
+                    
+                    if entry_count == 0 {
+                        // No more messages
+                        break;
+                    }
+                    
+                    for (idx, entry) in entries.iter().enumerate() {
+                        eprintln!("[peek_messages] Processing entry {}", idx);
+                        let message = self.message_entry_to_message(entry).map_err(|e| {
+                            eprintln!("[peek_messages] Failed to convert entry {} to message: {}", idx, e);
+                            e
+                        })?;
+                        all_messages.push(message);
+                    }
+                    
+                    eprintln!("[peek_messages] Successfully processed {} messages, total so far: {}", entry_count, all_messages.len());
+                    
+                    // Track sequence number for pagination (from last message)
+                    if let Some(last_entry) = entries.last() {
+                        if let Some(seq) = last_entry.sequence_number {
+                            sequence_number = Some(seq);
+                        }
+                    }
+                    
+                    // If we got fewer messages than requested, we're done
+                    if entry_count < count {
+                        break;
+                    }
+                    
+                    // Continue to next iteration of loop for pagination
+                    continue;
+                }
+                Err(xml_err) => {
+                    // XML parsing failed - check if it's JSON
+                    eprintln!("[peek_messages] XML parsing failed: {}", xml_err);
+                    
+                    if is_json_start && !is_xml_start {
+                        eprintln!("[peek_messages] Detected JSON response (raw message body)");
+                        // Azure is returning JSON instead of XML Atom feed - this is non-standard
+                        // Try to parse as JSON - could be a single message object or an array
+                        match serde_json::from_str::<serde_json::Value>(&response_text) {
+                            Ok(json_value) => {
+                                // Check if it's an array of messages
+                                if let Some(array) = json_value.as_array() {
+                                    eprintln!("[peek_messages] JSON is an array with {} messages", array.len());
+                                    for (_idx, item) in array.iter().enumerate() {
+                                        // Extract message properties from JSON object
+                                        let message_id = item.get("MessageId").and_then(|v| v.as_str()).map(|s| s.to_string());
+                                        let body = item.clone(); // Use the whole JSON object as body
+                                        
+                                        let message = ServiceBusMessage {
+                                            body,
+                                            message_id: message_id.clone(),
+                                            correlation_id: item.get("CorrelationId").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            content_type: Some("application/json".to_string()),
+                                            sequence_number: item.get("SequenceNumber").and_then(|v| v.as_i64()).map(|s| s as u64),
+                                            subject: item.get("Subject").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            reply_to: item.get("ReplyTo").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            reply_to_session_id: item.get("ReplyToSessionId").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            session_id: item.get("SessionId").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            time_to_live: None,
+                                            to: item.get("To").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            application_properties: item.get("UserProperties").cloned(),
+                                            delivery_count: item.get("DeliveryCount").and_then(|v| v.as_u64()).map(|s| s as u32),
+                                            enqueued_time_utc: item.get("EnqueuedTimeUtc").and_then(|v| v.as_str()).map(timestamps::normalize),
+                                            locked_until_utc: item.get("LockedUntilUtc").and_then(|v| v.as_str()).map(timestamps::normalize),
+                                            dead_letter_reason: item.get("DeadLetterReason").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            dead_letter_error_description: item.get("DeadLetterErrorDescription").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            dead_letter_source: item.get("DeadLetterSource").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            scheduled_enqueue_time_utc: item.get("ScheduledEnqueueTimeUtc").and_then(|v| v.as_str()).map(timestamps::normalize),
+                                            partition_key: item.get("PartitionKey").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            via_partition_key: item.get("ViaPartitionKey").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            state: item.get("State").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                            body_size: None,
+                                            body_truncated: None,
+                                            body_encoding: None,
+                                            body_hex_preview: None,
+                                            body_envelope: None,
+                                            body_compression: None,
+                                            raw_body: None,
+                                            decoded_body: None,
+                                            decode_error: None,
+                                            parse_warning: None,
+                                            expires_at_utc: None,
+                                        };
+                                        all_messages.push(message);
+                                    }
+                                    eprintln!("[peek_messages] Created {} messages from JSON array", array.len());
+                                } else {
+                                    // Single message object
+                                    eprintln!("[peek_messages] JSON is a single message object");
+                                    // Extract message properties from JSON object
+                                    let message_id = json_value.get("MessageId").and_then(|v| v.as_str()).map(|s| s.to_string());
+                                    let body = json_value.clone(); // Use the whole JSON object as body
+                                    
+                                    // Try to extract sequence number for pagination
+                                    let seq_num = json_value.get("SequenceNumber").and_then(|v| v.as_i64());
+                                    
+                                    let message = ServiceBusMessage {
+                                        body,
+                                        message_id: message_id.clone(),
+                                        correlation_id: json_value.get("CorrelationId").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        content_type: Some("application/json".to_string()),
+                                        sequence_number: seq_num.map(|s| s as u64),
+                                        subject: json_value.get("Subject").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        reply_to: json_value.get("ReplyTo").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        reply_to_session_id: json_value.get("ReplyToSessionId").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        session_id: json_value.get("SessionId").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        time_to_live: None,
+                                        to: json_value.get("To").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        application_properties: json_value.get("UserProperties").cloned(),
+                                        delivery_count: json_value.get("DeliveryCount").and_then(|v| v.as_u64()).map(|s| s as u32),
+                                        enqueued_time_utc: json_value.get("EnqueuedTimeUtc").and_then(|v| v.as_str()).map(timestamps::normalize),
+                                        locked_until_utc: json_value.get("LockedUntilUtc").and_then(|v| v.as_str()).map(timestamps::normalize),
+                                        dead_letter_reason: json_value.get("DeadLetterReason").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        dead_letter_error_description: json_value.get("DeadLetterErrorDescription").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        dead_letter_source: json_value.get("DeadLetterSource").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        scheduled_enqueue_time_utc: json_value.get("ScheduledEnqueueTimeUtc").and_then(|v| v.as_str()).map(timestamps::normalize),
+                                        partition_key: json_value.get("PartitionKey").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        via_partition_key: json_value.get("ViaPartitionKey").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        state: json_value.get("State").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                                        body_size: None,
+                                        body_truncated: None,
+                                        body_encoding: None,
+                                        body_hex_preview: None,
+                                        body_envelope: None,
+                                        body_compression: None,
+                                        raw_body: None,
+                                        decoded_body: None,
+                                        decode_error: None,
+                                        parse_warning: None,
+                                        expires_at_utc: None,
+                                    };
+                                    // Check if we've already seen this message
+                                    if let Some(ref msg_id) = message_id {
+                                        if seen_message_ids.contains(msg_id) {
+                                            eprintln!("[peek_messages] Already seen message ID {}, stopping pagination", msg_id);
+                                            break; // We're getting duplicates, stop
+                                        }
+                                        seen_message_ids.insert(msg_id.clone());
+                                    }
+                                    
+                                    all_messages.push(message);
+                                    eprintln!("[peek_messages] Created 1 message from JSON object");
+                                    
+                                    // Update sequence number for pagination if available
+                                    if let Some(seq) = seq_num {
+                                        sequence_number = Some(seq);
+                                        eprintln!("[peek_messages] Extracted sequence number {} for pagination", seq);
+                                    }
+                                    
+                                    // When Azure returns JSON, it typically returns one message at a time
+                                    // We need to make multiple requests to get more messages
+                                    // Track this message ID to avoid duplicates
+                                    if let Some(ref msg_id) = message_id {
+                                        if seen_message_ids.contains(msg_id) {
+                                            eprintln!("[peek_messages] Already seen message ID {}, stopping pagination", msg_id);
+                                            break; // We're getting duplicates, stop
+                                        }
+                                        seen_message_ids.insert(msg_id.clone());
+                                    }
+                                    
+                                    // If we got only 1 message but requested more, continue making requests
+                                    if all_messages.len() < max_count as usize {
+                                        if sequence_number.is_some() {
+                                            eprintln!("[peek_messages] Got 1 JSON message, continuing pagination with sequence number (have {} of {})", all_messages.len(), max_count);
+                                            continue; // Continue loop to get more messages
+                                        } else {
+                                            // No sequence number, but Azure might return different messages on subsequent requests
+                                            // Try making another request - Azure might cycle through messages
+                                            eprintln!("[peek_messages] Got 1 JSON message without sequence number. Making another request to get next message (have {} of {})", all_messages.len(), max_count);
+                                            // Reset to try getting the next message
+                                            sequence_number = None;
+                                            continue; // Continue to get more messages
+                                        }
+                                    } else {
+                                        // We've reached max_count
+                                        break;
+                                    }
+                                }
+                            }
+                            Err(json_err) => {
+                                eprintln!("[peek_messages] Failed to parse as JSON: {}", json_err);
+                                return Err(format!("Failed to parse response as XML or JSON. XML error: {}, JSON error: {}", xml_err, json_err));
+                            }
+                        }
+                    } else {
+                        // Not JSON, and XML parsing failed
+                        eprintln!("[peek_messages] Response starts with: {}", &response_text[..response_text.len().min(100)]);
+                        return Err(format!("Failed to parse message feed: {}", xml_err));
+                    }
+                }
+            }
+        }
+
+        Ok(all_messages)
+    }
+    
+    fn message_entry_to_message(&self, entry: &MessageEntry) -> Result<ServiceBusMessage, String> {
+        // Parse message body from content
+        // Content might be base64 encoded or plain text/JSON
+        let mut body_envelope = None;
+        let mut warnings: Vec<String> = entry.parse_warning.iter().cloned().collect();
+        let body = if let Some(ref content) = entry.content {
+            // Bodies sent by the legacy .NET SDK arrive in a DataContract envelope
+            let content = match data_contract::unwrap(content.as_bytes()) {
+                Some((text, envelope)) => {
+                    body_envelope = Some(envelope);
+                    text
+                }
+                None => content.clone(),
+            };
+            let (content, warning) = clean_entry_content(content);
+            warnings.extend(warning);
+            // Try to parse as JSON first
+            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&content) {
+                json_value
+            } else {
+                // If not JSON, wrap as string value
+                serde_json::Value::String(content)
+            }
+        } else {
+            serde_json::Value::Null
+        };
+        let parse_properties = |name: &str, raw: Option<&str>, warnings: &mut Vec<String>| {
+            let raw = raw.filter(|raw| !raw.trim().is_empty())?;
+            serde_json::from_str::<serde_json::Value>(raw)
+                .map_err(|e| warnings.push(format!("{} could not be parsed: {}", name, e)))
+                .ok()
+        };
+        let application_properties = parse_properties("UserProperties", entry.user_properties.as_deref(), &mut warnings);
+        let broker_properties = parse_properties("BrokerProperties", entry.broker_properties.as_deref(), &mut warnings);
+        
+        // Parse BrokerProperties from the entry
+        // BrokerProperties is a JSON string in the entry's content or as a property
+        let mut message = ServiceBusMessage {
+            body,
+            message_id: entry.message_id.clone(),
+            correlation_id: entry.correlation_id.clone(),
+            content_type: entry.content_type.clone(),
+            sequence_number: entry.sequence_number.map(|s| s as u64),
+            subject: None,
+            reply_to: None,
+            reply_to_session_id: None,
+            session_id: None,
+            time_to_live: None,
+            to: None,
+            application_properties,
+            delivery_count: None,
+            enqueued_time_utc: None,
+            locked_until_utc: None,
+            dead_letter_reason: None,
+            dead_letter_error_description: None,
+            dead_letter_source: None,
+            scheduled_enqueue_time_utc: None,
+            partition_key: None,
+            via_partition_key: None,
+            state: None,
+            body_size: None,
+            body_truncated: None,
+            body_encoding: None,
+            body_hex_preview: None,
+            body_envelope,
+            body_compression: None,
+            raw_body: None,
+            decoded_body: None,
+            decode_error: None,
+            parse_warning: None,
+            expires_at_utc: None,
+        };
+        
+        // Parse BrokerProperties if available (a JSON string)
+        if let Some(props) = broker_properties {
+            apply_broker_properties(&mut message, &props);
+        }
+        warnings.extend(json_parse_warning(&message));
+        if !warnings.is_empty() {
+            message.parse_warning = Some(warnings.join("; "));
+        }
+        
+        Ok(message)
+    }
+
+    // Send a message. Returns the sequence number of a scheduled message; over HTTP
+    // the service does not report it.
+    pub async fn send_message(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        message: &ServiceBusMessage,
+    ) -> Result<Option<u64>, String> {
+        if dry_run::is_enabled() {
+            let entity_path = queue_name
+                .or(topic_name)
+                .ok_or("Either queue_name or topic_name must be provided")?;
+            let url = format!("{}/{}/messages?api-version={}", self.get_base_url(), entity_path, API_VERSION);
+            // The REST batch element carries the body and both property sets; binary
+            // bodies can't go in one, so those are shown as the message itself
+            let body = match batch_item(message) {
+                Ok(Some(item)) => item.to_string(),
+                _ => serde_json::to_string(message).map_err(|e| format!("Failed to serialize message: {}", e))?,
+            };
+            dry_run::intercept("send message", "POST", &url, Some(&body));
+            return Ok(None);
+        }
+        if self.transport == Some(Transport::Http) {
+            return self.send_message_rest(queue_name, topic_name, message).await.map(|_| None);
+        }
+        self.send_message_sdk(queue_name, topic_name, message).await
+    }
+
+    // Send a single message with POST /{entity}/messages. System properties travel in the
+    // BrokerProperties header and application properties as one header each.
+    pub async fn send_message_rest(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        message: &ServiceBusMessage,
+    ) -> Result<(), String> {
+        let entity_path = queue_name
+            .or(topic_name)
+            .ok_or("Either queue_name or topic_name must be provided")?;
+        // Same BrokerProperties as a batch element; the body goes as raw bytes, so
+        // binary, compressed and enveloped bodies can be sent too
+        let broker_properties = serde_json::Value::Object(broker_properties(message)?);
+        let body = message_body_bytes(message)?;
+
+        let url = format!("{}/{}/messages?api-version={}", self.get_base_url(), entity_path, API_VERSION);
+        let auth_header = self.get_auth_header(&url).await?;
+
+        let mut request = self
+            .client
+            .post(&url)
+            .header("Authorization", &auth_header)
+            .header("BrokerProperties", broker_properties.to_string())
+            .header(
+                "Content-Type",
+                message.content_type.as_deref().unwrap_or("application/json"),
+            );
+        if let Some(serde_json::Value::Object(properties)) = &message.application_properties {
+            for (key, value) in properties {
+                // Strings are quoted so the receiver can tell them from numbers and booleans
+                let header_value = match value {
+                    serde_json::Value::String(_) | serde_json::Value::Bool(_) | serde_json::Value::Number(_) => value.to_string(),
+                    _ => return Err(format!("Application property '{}' must be a string, number or boolean", key)),
+                };
+                let name = reqwest::header::HeaderName::from_bytes(key.as_bytes())
+                    .map_err(|_| format!("Application property '{}' can't be sent as an HTTP header", key))?;
+                request = request.header(name, header_value);
+            }
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to send message: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(http_error("send message", status, &error_text));
+        }
+        Ok(())
+    }
+
+    // Send message using azservicebus SDK
+    pub async fn send_message_sdk(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        message: &ServiceBusMessage,
+    ) -> Result<Option<u64>, String> {
+        use azservicebus::prelude::*;
+
+        check_partition_key(message)?;
+        
+        let connection_string = if let Some(ref parsed) = self.parsed_connection {
+            // Reconstruct connection string from parsed components
+            format!(
+                "Endpoint=sb://{}{}/;SharedAccessKeyName={};SharedAccessKey={}",
+                self.namespace,
+                self.endpoint_domain,
+                parsed.shared_access_key_name,
+                parsed.shared_access_key
+            )
+        } else {
+            return Err("Connection string not available for SDK".to_string());
+        };
+
+        let entity_path = if let Some(q) = queue_name {
+            q.to_string()
+        } else if let Some(t) = topic_name {
+            t.to_string()
+        } else {
+            return Err("Either queue_name or topic_name must be provided".to_string());
+        };
+
+        eprintln!("[send_message] Using azservicebus SDK to send message to: {}", entity_path);
+
+        // Create ServiceBus client
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        // Create sender for queue or topic
+        let mut sender = client
+            .create_sender(&entity_path, ServiceBusSenderOptions::default())
+            .await
+            .map_err(|e| format!("Failed to create sender: {}", e))?;
+
+        // Convert message body to bytes
+        let body_bytes = message_body_bytes(message)?;
+
+        // Create SDK message
+        let mut sdk_message = ServiceBusMessage::new(body_bytes);
+
+        // Set message properties
+        // Note: Some setters return Result, others return () - handle accordingly
+        if let Some(msg_id) = &message.message_id {
+            sdk_message.set_message_id(msg_id.clone())
+                .map_err(|e| format!("Failed to set message_id: {}", e))?;
+        }
+        if let Some(content_type) = &message.content_type {
+            sdk_message.set_content_type(content_type.clone());
+        }
+        if let Some(corr_id) = &message.correlation_id {
+            sdk_message.set_correlation_id(corr_id.clone());
+        }
+        if let Some(session_id) = &message.session_id {
+            sdk_message.set_session_id(session_id.clone())
+                .map_err(|e| format!("Failed to set session_id: {}", e))?;
+        }
+        if let Some(reply_to) = &message.reply_to {
+            sdk_message.set_reply_to(reply_to.clone());
+        }
+        if let Some(reply_to_session_id) = &message.reply_to_session_id {
+            sdk_message.set_reply_to_session_id(reply_to_session_id.clone())
+                .map_err(|e| format!("Failed to set reply_to_session_id: {}", e))?;
+        }
+        if let Some(subject) = &message.subject {
+            sdk_message.set_subject(subject.clone());
+        }
+        if let Some(ttl) = message.time_to_live {
+            sdk_message.set_time_to_live(std::time::Duration::from_secs(ttl))
+                .map_err(|e| format!("Failed to set time_to_live: {}", e))?;
+        }
+        if let Some(to) = &message.to {
+            sdk_message.set_to(to.clone());
+        }
+        if let Some(partition_key) = &message.partition_key {
+            sdk_message.set_partition_key(partition_key.clone())
+                .map_err(|e| format!("Failed to set partition_key: {}", e))?;
+        }
+        if let Some(via_partition_key) = &message.via_partition_key {
+            sdk_message.set_transaction_partition_key(via_partition_key.clone())
+                .map_err(|e| format!("Failed to set via_partition_key: {}", e))?;
+        }
+        // Application properties keep their JSON type (string, number, bool) on the wire
+        if let Some(properties) = &message.application_properties {
+            let properties = properties
+                .as_object()
+                .ok_or("Application properties must be a JSON object")?;
+            let target = sdk_message.application_properties_mut();
+            for (key, value) in properties {
+                target.0.insert(key.clone(), json_to_simple_value(key, value)?);
+            }
+        }
+
+        // Send the message, or schedule it when a ScheduledEnqueueTimeUtc was given
+        let scheduled_sequence_number = if let Some(scheduled_time) = &message.scheduled_enqueue_time_utc {
+            let enqueue_time = parse_scheduled_time(scheduled_time)?;
+            let sequence_number = sender
+                .schedule_message(sdk_message, enqueue_time)
+                .await
+                .map_err(|e| format!("Failed to schedule message: {}", e))?;
+            eprintln!("[send_message] Message scheduled for {} (sequence number {})", scheduled_time, sequence_number);
+            Some(sequence_number as u64)
+        } else {
+            sender
+                .send_message(sdk_message)
+                .await
+                .map_err(|e| format!("Failed to send message: {}", e))?;
+            eprintln!("[send_message] Message sent successfully");
+            None
+        };
+
+        // Cleanup
+        sender.dispose().await.map_err(|e| format!("Failed to dispose sender: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        Ok(scheduled_sequence_number)
+    }
+
+    // Send many messages with the REST batch format (application/vnd.microsoft.servicebus.json).
+    // Messages are split into requests that stay under the 256 KB batch limit; returns how
+    // many were sent. On failure the error says how many earlier messages already went out.
+    // A message whose body can't be a batch element (binary, compressed, encrypted) is
+    // sent on its own with send_message, in its place in the order.
+    pub async fn send_messages_batch(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        messages: &[ServiceBusMessage],
+    ) -> Result<u32, String> {
+        const MAX_BATCH_BYTES: usize = 200 * 1024; // Leave headroom under the 256 KB limit
+
+        let entity_path = queue_name
+            .or(topic_name)
+            .ok_or("Either queue_name or topic_name must be provided")?;
+        check_not_system_entity("send to", entity_path)?;
+
+        let mut parts: Vec<SendPart> = Vec::new();
+        let mut current: Vec<serde_json::Value> = Vec::new();
+        let mut current_size = 0usize;
+        for (index, message) in messages.iter().enumerate() {
+            let Some(item) = batch_item(message).map_err(|e| format!("Message {}: {}", index + 1, e))? else {
+                if !current.is_empty() {
+                    parts.push(SendPart::Batch(std::mem::take(&mut current)));
+                    current_size = 0;
+                }
+                parts.push(SendPart::Single(index));
+                continue;
+            };
+            let size = item.to_string().len();
+            if size > MAX_BATCH_BYTES {
+                return Err(format!("Message {} is too large for a batch ({} bytes)", index + 1, size));
+            }
+            if current_size + size > MAX_BATCH_BYTES && !current.is_empty() {
+                parts.push(SendPart::Batch(std::mem::take(&mut current)));
+                current_size = 0;
+            }
+            current_size += size;
+            current.push(item);
+        }
+        if !current.is_empty() {
+            parts.push(SendPart::Batch(current));
+        }
+
+        eprintln!("[send_messages_batch] Sending {} messages to {} in {} requests", messages.len(), entity_path, parts.len());
+
+        let url = format!("{}/{}/messages?api-version={}", self.get_base_url(), entity_path, API_VERSION);
+        let mut sent = 0u32;
+        for part in parts {
+            let batch = match part {
+                SendPart::Batch(batch) => batch,
+                SendPart::Single(index) => {
+                    self.send_message(queue_name, topic_name, &messages[index])
+                        .await
+                        .map_err(|e| format!("Message {}: {} ({} already sent)", index + 1, e, sent))?;
+                    sent += 1;
+                    continue;
+                }
+            };
+            let auth_header = self.get_auth_header(&url).await?;
+            let body = serde_json::to_string(&batch)
+                .map_err(|e| format!("Failed to serialize message batch: {}", e))?;
+            if dry_run::intercept("send message batch", "POST", &url, Some(&body)) {
+                sent += batch.len() as u32;
+                continue;
+            }
+
+            let response = self
+                .client
+                .post(&url)
+                .header("Authorization", &auth_header)
+                .header("Content-Type", "application/vnd.microsoft.servicebus.json")
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to send message batch ({} already sent): {}", sent, e))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                let error = http_error("send message batch", status, &error_text);
+                return Err(format!("{} ({} already sent)", error, sent));
+            }
+            sent += batch.len() as u32;
+        }
+
+        eprintln!("[send_messages_batch] Sent {} messages", sent);
+        Ok(sent)
+    }
+
+    // Cancel a scheduled message on the queue or topic it was scheduled on
+    pub async fn cancel_scheduled_message(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        sequence_number: u64,
+    ) -> Result<(), String> {
+        use azservicebus::prelude::*;
+
+        let entity_path = queue_name
+            .or(topic_name)
+            .ok_or("Either queue_name or topic_name must be provided")?;
+        let arguments = serde_json::json!({ "sequenceNumber": sequence_number });
+        if self.intercept_amqp("cancel scheduled message", entity_path, arguments) {
+            return Ok(());
+        }
+        let connection_string = self.sdk_connection_string()?;
+
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+        let mut sender = client
+            .create_sender(entity_path, ServiceBusSenderOptions::default())
+            .await
+            .map_err(|e| format!("Failed to create sender: {}", e))?;
+
+        let result = sender
+            .cancel_scheduled_message(sequence_number as i64)
+            .await
+            .map_err(|e| format!("Failed to cancel scheduled message {}: {}", sequence_number, e));
+
+        sender.dispose().await.map_err(|e| format!("Failed to dispose sender: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        result
+    }
+
+    // List scheduled messages. Peek returns scheduled messages mixed in with active and
+    // deferred ones, so page through the entity and keep only the scheduled state.
+    pub async fn peek_scheduled_messages(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        max_count: u32,
+    ) -> Result<Vec<crate::azure::types::ServiceBusMessage>, String> {
+        use azservicebus::prelude::*;
+        use azservicebus::ServiceBusMessageState;
+
+        let max_scanned = 5000usize; // Upper bound on messages peeked while searching
+        let connection_string = self.sdk_connection_string()?;
+
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        let mut receiver = if let Some(q) = queue_name {
+            client
+                .create_receiver_for_queue(q, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create queue receiver: {}", e))?
+        } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+            client
+                .create_receiver_for_subscription(t, s, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create subscription receiver: {}", e))?
+        } else {
+            return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+        };
+
+        let mut scheduled = Vec::new();
+        let mut scanned = 0usize;
+        let mut from_sequence_number: Option<i64> = None;
+
+        while (scheduled.len() as u32) < max_count && scanned < max_scanned {
+            let page = receiver
+                .peek_messages(100, from_sequence_number)
+                .await
+                .map_err(|e| format!("Failed to peek messages: {}", e))?;
+            if page.is_empty() {
+                break;
+            }
+            scanned += page.len();
+
+            for sdk_msg in &page {
+                if sdk_msg.state() != ServiceBusMessageState::Scheduled || (scheduled.len() as u32) >= max_count {
+                    continue;
+                }
+
+                let body_bytes = sdk_msg.body().map_err(|e| format!("Failed to get message body: {}", e))?;
+                let decoded = decode_peeked_body(body_bytes, None);
+
+                scheduled.push(crate::azure::types::ServiceBusMessage {
+                    body: decoded.body,
+                    message_id: sdk_msg.message_id().as_ref().map(|id| id.to_string()),
+                    correlation_id: sdk_msg.correlation_id().as_ref().map(|id| id.to_string()),
+                    content_type: sdk_msg.content_type().as_ref().map(|ct| ct.to_string()),
+                    sequence_number: Some(sdk_msg.sequence_number() as u64),
+                    subject: sdk_msg.subject().as_ref().map(|s| s.to_string()),
+                    reply_to: sdk_msg.reply_to().as_ref().map(|r| r.to_string()),
+                    reply_to_session_id: sdk_msg.reply_to_session_id().as_ref().map(|s| s.to_string()),
+                    session_id: sdk_msg.session_id().as_ref().map(|s| s.to_string()),
+                    time_to_live: sdk_msg.time_to_live().map(|ttl| ttl.as_secs()),
+                    to: sdk_msg.to().as_ref().map(|t| t.to_string()),
+                    application_properties: sdk_msg.application_properties().map(application_properties_to_json),
+                    delivery_count: None,
+                    enqueued_time_utc: Some(timestamps::from_offset_date_time(sdk_msg.enqueued_time())),
+                    locked_until_utc: None,
+                    dead_letter_reason: None,
+                    dead_letter_error_description: None,
+                    dead_letter_source: None,
+                    scheduled_enqueue_time_utc: Some(timestamps::from_offset_date_time(sdk_msg.scheduled_enqueue_time())),
+                    partition_key: sdk_msg.partition_key().map(|k| k.to_string()),
+                    via_partition_key: sdk_msg.transaction_partition_key().map(|k| k.to_string()),
+                    state: Some("Scheduled".to_string()),
+                    body_size: None,
+                    body_truncated: None,
+                    body_encoding: decoded.body_encoding,
+                    body_hex_preview: decoded.body_hex_preview,
+                    body_envelope: decoded.body_envelope,
+                    body_compression: None,
+                    raw_body: None,
+                    decoded_body: None,
+                    decode_error: None,
+                    parse_warning: None,
+                    expires_at_utc: None,
+                });
+            }
+            from_sequence_number = page.last().map(|m| m.sequence_number() + 1);
+        }
+
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        eprintln!("[peek_scheduled_messages] Found {} scheduled messages in {} peeked", scheduled.len(), scanned);
+        Ok(scheduled)
+    }
+
+    // Shift the enqueue time of scheduled messages on a queue by shift_seconds.
+    // The service cannot change a schedule in place, so each message is scheduled again
+    // with the new time and only then cancelled at its old sequence number. A failure
+    // between the two leaves a duplicate, never a lost message. Scheduled messages on a
+    // topic cannot be peeked back, so only queues are supported.
+    pub async fn reschedule_messages(
+        &self,
+        queue_name: &str,
+        sequence_numbers: &[u64],
+        shift_seconds: i64,
+    ) -> Result<Vec<RescheduledMessage>, String> {
+        if sequence_numbers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let scheduled = self.peek_scheduled_messages(Some(queue_name), None, None, 5000).await?;
+        let mut targets = Vec::with_capacity(sequence_numbers.len());
+        let mut missing = Vec::new();
+        for seq in sequence_numbers {
+            match scheduled.iter().find(|m| m.sequence_number == Some(*seq)) {
+                Some(message) => targets.push(message.clone()),
+                None => missing.push(seq.to_string()),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(format!(
+                "Scheduled messages not found in '{}' (already enqueued or cancelled?): {}",
+                queue_name,
+                missing.join(", ")
+            ));
+        }
+
+        // Validate every message before touching any of them
+        let now = chrono::Utc::now();
+        let mut planned = Vec::with_capacity(targets.len());
+        for mut message in targets {
+            let seq = message.sequence_number.unwrap_or_default();
+            let current = message
+                .scheduled_enqueue_time_utc
+                .as_deref()
+                .ok_or_else(|| format!("Message {} has no scheduled enqueue time", seq))?;
+            let current = timestamps::parse(current)
+                .ok_or_else(|| format!("Message {} has an unreadable scheduled time '{}'", seq, current))?;
+            let new_time = current + chrono::Duration::seconds(shift_seconds);
+            if new_time <= now {
+                return Err(format!(
+                    "Message {} would be rescheduled to {}, which is in the past",
+                    seq,
+                    timestamps::format(new_time)
+                ));
+            }
+            message.scheduled_enqueue_time_utc = Some(timestamps::format(new_time));
+            // Broker-assigned values must not be sent back
+            message.sequence_number = None;
+            message.enqueued_time_utc = None;
+            message.state = None;
+            planned.push((seq, message));
+        }
+
+        // Under dry run each move is recorded and nothing is rescheduled
+        if dry_run::is_enabled() {
+            for (old_seq, message) in &planned {
+                let arguments = serde_json::json!({
+                    "sequenceNumber": old_seq,
+                    "scheduledEnqueueTimeUtc": message.scheduled_enqueue_time_utc,
+                });
+                self.intercept_amqp("reschedule message", queue_name, arguments);
+            }
+            return Ok(Vec::new());
+        }
+
+        let mut rescheduled = Vec::with_capacity(planned.len());
+        for (old_seq, message) in planned {
+            let progress = || format!("({} of {} rescheduled)", rescheduled.len(), sequence_numbers.len());
+            // The SDK path reports the new sequence number, HTTP does not
+            let new_seq = self
+                .send_message_sdk(Some(queue_name), None, &message)
+                .await
+                .map_err(|e| format!("Failed to reschedule message {}: {} {}", old_seq, e, progress()))?
+                .ok_or_else(|| format!("Service returned no sequence number for rescheduled message {} {}", old_seq, progress()))?;
+            self.cancel_scheduled_message(Some(queue_name), None, old_seq)
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Message {} was scheduled again as {} but the original could not be cancelled: {} {}",
+                        old_seq, new_seq, e, progress()
+                    )
+                })?;
+            rescheduled.push(RescheduledMessage {
+                old_sequence_number: old_seq,
+                new_sequence_number: new_seq,
+                scheduled_enqueue_time_utc: message.scheduled_enqueue_time_utc.unwrap_or_default(),
+            });
+        }
+
+        eprintln!("[reschedule_messages] Rescheduled {} messages in {} by {}s", rescheduled.len(), queue_name, shift_seconds);
+        Ok(rescheduled)
+    }
+
+    // List sessions that currently hold messages. There is no browse operation for
+    // sessions, so each available session is accepted in turn and kept open until the
+    // end (that is what makes the next accept return a different one). Sessions held
+    // by a running consumer can't be accepted and are not listed, and while this runs
+    // the listed sessions are locked for other receivers.
+    pub async fn list_sessions(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        max_sessions: u32,
+    ) -> Result<Vec<SessionInfo>, String> {
+        use azservicebus::prelude::*;
+
+        let max_counted = 1000usize; // Messages peeked per session for the count
+        let accept_timeout = std::time::Duration::from_secs(5); // No answer means no more sessions
+        let connection_string = self.sdk_connection_string()?;
+
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        let mut receivers = Vec::new();
+        let mut sessions = Vec::new();
+        let mut failure = None;
+
+        while (sessions.len() as u32) < max_sessions {
+            let options = ServiceBusSessionReceiverOptions::default();
+            let accepted = if let Some(q) = queue_name {
+                tokio::time::timeout(accept_timeout, client.accept_next_session_for_queue(q, options)).await
+            } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+                tokio::time::timeout(accept_timeout, client.accept_next_session_for_subscription(t, s, options)).await
+            } else {
+                failure = Some("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+                break;
+            };
+            let mut receiver = match accepted {
+                Ok(Ok(receiver)) => receiver,
+                // Timed out or nothing left to accept
+                Ok(Err(e)) => {
+                    eprintln!("[list_sessions] Stopped accepting sessions: {}", e);
+                    break;
+                }
+                Err(_) => break,
+            };
+
+            let mut counted = 0usize;
+            let mut more_messages = false;
+            let mut from_sequence_number: Option<i64> = None;
+            loop {
+                let page = match receiver.peek_messages(100, from_sequence_number).await {
+                    Ok(page) => page,
+                    Err(e) => {
+                        failure = Some(format!("Failed to peek session '{}': {}", receiver.session_id(), e));
+                        break;
+                    }
+                };
+                if page.is_empty() {
+                    break;
+                }
+                counted += page.len();
+                if counted >= max_counted {
+                    more_messages = true;
+                    counted = max_counted;
+                    break;
+                }
+                from_sequence_number = page.last().map(|m| m.sequence_number() + 1);
+            }
+
+            sessions.push(SessionInfo {
+                session_id: receiver.session_id().to_string(),
+                message_count: counted as u32,
+                more_messages,
+            });
+            receivers.push(receiver);
+            if failure.is_some() {
+                break;
+            }
+        }
+
+        for receiver in receivers {
+            if let Err(e) = receiver.dispose().await {
+                eprintln!("[list_sessions] Failed to release session: {}", e);
+            }
+        }
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        eprintln!("[list_sessions] Found {} sessions", sessions.len());
+        Ok(sessions)
+    }
+
+    // Peek the messages of one session. Accepting the session locks it for the
+    // duration of the peek.
+    pub async fn peek_session_messages(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        session_id: &str,
+        max_count: u32,
+        from_sequence_number: Option<u64>,
+    ) -> Result<Vec<crate::azure::types::ServiceBusMessage>, String> {
+        use azservicebus::prelude::*;
+
+        let connection_string = self.sdk_connection_string()?;
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        let options = ServiceBusSessionReceiverOptions::default();
+        let mut receiver = if let Some(q) = queue_name {
+            client
+                .accept_session_for_queue(q, session_id, options)
+                .await
+                .map_err(|e| format!("Failed to accept session '{}': {}", session_id, e))?
+        } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+            client
+                .accept_session_for_subscription(t, s, session_id, options)
+                .await
+                .map_err(|e| format!("Failed to accept session '{}': {}", session_id, e))?
+        } else {
+            return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+        };
+
+        let result = receiver
+            .peek_messages(max_count, from_sequence_number.map(|seq| seq as i64))
+            .await
+            .map_err(|e| format!("Failed to peek session '{}': {}", session_id, e));
+
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        let mut messages = Vec::new();
+        for sdk_msg in &result? {
+            let mut message = received_message_to_message(sdk_msg)?;
+            // Peeked messages are not locked
+            message.locked_until_utc = None;
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+
+    // Receive messages of one session in receive-and-delete mode: they are removed
+    // from the entity as they are returned.
+    pub async fn receive_session_messages(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        session_id: &str,
+        max_count: u32,
+        wait_time_seconds: Option<u32>,
+    ) -> Result<Vec<crate::azure::types::ServiceBusMessage>, String> {
+        use azservicebus::prelude::*;
+
+        let wait_time = wait_time_seconds.unwrap_or(5).clamp(1, 60);
+        let entity_path = message_entity_path(queue_name, topic_name, subscription_name)?;
+        let arguments = serde_json::json!({ "sessionId": session_id, "maxCount": max_count });
+        if self.intercept_amqp("receive session messages", &entity_path, arguments) {
+            return Ok(Vec::new());
+        }
+        let connection_string = self.sdk_connection_string()?;
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        let options = ServiceBusSessionReceiverOptions {
+            receive_mode: azservicebus::ServiceBusReceiveMode::ReceiveAndDelete,
+            ..Default::default()
+        };
+        let mut receiver = if let Some(q) = queue_name {
+            client
+                .accept_session_for_queue(q, session_id, options)
+                .await
+                .map_err(|e| format!("Failed to accept session '{}': {}", session_id, e))?
+        } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+            client
+                .accept_session_for_subscription(t, s, session_id, options)
+                .await
+                .map_err(|e| format!("Failed to accept session '{}': {}", session_id, e))?
+        } else {
+            return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+        };
+
+        let result = receiver
+            .receive_messages_with_max_wait_time(max_count, Some(std::time::Duration::from_secs(wait_time as u64)))
+            .await
+            .map_err(|e| format!("Failed to receive from session '{}': {}", session_id, e));
+
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        let mut messages = Vec::new();
+        for sdk_msg in &result? {
+            let mut message = received_message_to_message(sdk_msg)?;
+            // Already deleted, there is no lock to show
+            message.locked_until_utc = None;
+            messages.push(message);
+        }
+        eprintln!("[receive_session_messages] Received {} messages from session '{}'", messages.len(), session_id);
+        Ok(messages)
+    }
+
+    pub async fn get_session_state(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        session_id: &str,
+    ) -> Result<SessionState, String> {
+        use base64::Engine;
+
+        let state = self
+            .session_state(queue_name, topic_name, subscription_name, session_id, None)
+            .await?;
+        Ok(SessionState {
+            session_id: session_id.to_string(),
+            size: state.len() as u64,
+            text: String::from_utf8(state.clone()).ok(),
+            base64: base64::engine::general_purpose::STANDARD.encode(&state),
+        })
+    }
+
+    // Replace the state of a session. encoding is "text" (default) or "base64";
+    // an empty state clears it.
+    pub async fn set_session_state(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        session_id: &str,
+        state: &str,
+        encoding: Option<&str>,
+    ) -> Result<(), String> {
+        use base64::Engine;
+
+        let bytes = match encoding.unwrap_or("text") {
+            "text" => state.as_bytes().to_vec(),
+            "base64" => base64::engine::general_purpose::STANDARD
+                .decode(state.trim())
+                .map_err(|e| format!("Session state is not valid base64: {}", e))?,
+            other => return Err(format!("Unknown session state encoding '{}' (expected \"text\" or \"base64\")", other)),
+        };
+        let entity_path = message_entity_path(queue_name, topic_name, subscription_name)?;
+        let arguments = serde_json::json!({ "sessionId": session_id, "state": state, "encoding": encoding.unwrap_or("text") });
+        if self.intercept_amqp("set session state", &entity_path, arguments) {
+            return Ok(());
+        }
+        self.session_state(queue_name, topic_name, subscription_name, session_id, Some(bytes))
+            .await
+            .map(|_| ())
+    }
+
+    // Accept a session and read its state, or write new_state and return it
+    async fn session_state(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        session_id: &str,
+        new_state: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, String> {
+        use azservicebus::prelude::*;
+
+        let connection_string = self.sdk_connection_string()?;
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        let options = ServiceBusSessionReceiverOptions::default();
+        let mut receiver = if let Some(q) = queue_name {
+            client
+                .accept_session_for_queue(q, session_id, options)
+                .await
+                .map_err(|e| format!("Failed to accept session '{}': {}", session_id, e))?
+        } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+            client
+                .accept_session_for_subscription(t, s, session_id, options)
+                .await
+                .map_err(|e| format!("Failed to accept session '{}': {}", session_id, e))?
+        } else {
+            return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+        };
+
+        let result = match new_state {
+            Some(state) => receiver
+                .set_session_state(state.clone())
+                .await
+                .map(|_| state)
+                .map_err(|e| format!("Failed to set state of session '{}': {}", session_id, e)),
+            None => receiver
+                .get_session_state()
+                .await
+                .map_err(|e| format!("Failed to get state of session '{}': {}", session_id, e)),
+        };
+
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        result
+    }
+
+    // Purge queue using the REST receive-and-delete endpoint (DELETE /{entity}/messages/head)
+    // Each call removes at most one message, so calls are issued in concurrent batches
+    // until a whole batch comes back empty.
+    // on_progress gets the number of messages removed so far after every batch
+    pub async fn purge_queue(
+        &self,
+        queue_name: &str,
+        purge_dead_letter: bool,
+        on_progress: Option<&(dyn Fn(u32) + Send + Sync)>,
+    ) -> Result<u32, String> {
+        check_not_system_entity("purge", queue_name)?;
+        let entity_path = if purge_dead_letter {
+            format!("{}/$DeadLetterQueue", queue_name)
+        } else {
+            queue_name.to_string()
+        };
+        self.purge_entity(&entity_path, on_progress).await
+    }
+
+    pub(crate) async fn purge_entity(
+        &self,
+        entity_path: &str,
+        on_progress: Option<&(dyn Fn(u32) + Send + Sync)>,
+    ) -> Result<u32, String> {
+        // Short server-side wait: purging only cares about messages that are already there
+        let url = format!(
+            "{}/{}/messages/head?timeout=1&api-version={}",
+            self.get_base_url(),
+            entity_path,
+            API_VERSION
+        );
+        // A purge is this receive-and-delete repeated until the entity is empty
+        if dry_run::intercept("purge", "DELETE", &url, None) {
+            return Ok(0);
+        }
+
+        let batch_size = 32u32; // Concurrent receive-and-delete calls per batch
+        let max_consecutive_empty = 2u32; // Stop after 2 batches that returned nothing
+        let mut purged_count = 0u32;
+        let mut consecutive_empty_batches = 0u32;
+        let mut batch_number = 0u32;
+
+        eprintln!("[purge_queue] Purging {} via receive-and-delete", entity_path);
+
+        loop {
+            batch_number += 1;
+            // Regenerate the token per batch so long purges never outlive it
+            let auth_header = self.get_auth_header(&url).await?;
+
+            let mut tasks = tokio::task::JoinSet::new();
+            for _ in 0..batch_size {
+                tasks.spawn(receive_and_delete_head(self.client.clone(), url.clone(), auth_header.clone()));
+            }
+
+            let mut deleted_in_batch = 0u32;
+            let mut batch_error = None;
+            while let Some(result) = tasks.join_next().await {
+                match result {
+                    Ok(Ok(Some(_))) => deleted_in_batch += 1,
+                    Ok(Ok(None)) => {}
+                    Ok(Err(e)) => batch_error = Some(e),
+                    Err(e) => batch_error = Some(format!("Receive task failed: {}", e)),
+                }
+            }
+            purged_count += deleted_in_batch;
+
+            if let Some(e) = batch_error {
+                return Err(format!("Purge stopped after removing {} messages: {}", purged_count, e));
+            }
+
+            eprintln!("[purge_queue] Batch {}: removed {} (total {})", batch_number, deleted_in_batch, purged_count);
+            if let Some(callback) = on_progress {
+                callback(purged_count);
+            }
+
+            if deleted_in_batch == 0 {
+                consecutive_empty_batches += 1;
+                if consecutive_empty_batches >= max_consecutive_empty {
+                    break;
+                }
+            } else {
+                consecutive_empty_batches = 0;
+            }
+        }
+
+        eprintln!("[purge_queue] Purged {} messages from {}", purged_count, entity_path);
+        Ok(purged_count)
+    }
+
+    // Receive-and-delete up to max_count messages from source and return them, to take
+    // everything off an entity and archive it. Drained messages exist nowhere else, so an
+    // error part way ends the drain with what was taken instead of failing it, and
+    // on_batch sees every batch as soon as it is off the entity. Each batch runs as its
+    // own task, so cancelling the drain lets the batch in flight finish and reach
+    // on_batch before the drain stops.
+    pub async fn drain_messages(
+        &self,
+        source: &MessageSource,
+        max_count: u32,
+        on_batch: OnDrainedBatch,
+    ) -> Result<DrainResult, String> {
+        check_source_not_system_entity("drain", source)?;
+        if max_count == 0 || max_count > MAX_DRAIN_COUNT {
+            return Err(format!("max_count must be between 1 and {}", MAX_DRAIN_COUNT));
+        }
+        let entity_path = source_entity_path(source)?;
+        let url = format!(
+            "{}/{}/messages/head?timeout=1&api-version={}",
+            self.get_base_url(),
+            entity_path,
+            API_VERSION
+        );
+        let mut result = DrainResult { entity_path, messages: Vec::new(), error: None };
+        if dry_run::intercept("drain", "DELETE", &url, None) {
+            return Ok(result);
+        }
+
+        eprintln!("[drain_messages] Draining up to {} messages from {}", max_count, result.entity_path);
+        while (result.messages.len() as u32) < max_count {
+            let batch_size = (max_count - result.messages.len() as u32).min(DRAIN_BATCH_SIZE);
+            let auth_header = match self.get_auth_header(&url).await {
+                Ok(header) => header,
+                Err(e) => {
+                    result.error = Some(e);
+                    break;
+                }
+            };
+
+            // A spawned task isn't aborted with this future
+            let batch_task = tokio::spawn(drain_batch(
+                self.client.clone(),
+                url.clone(),
+                auth_header,
+                batch_size,
+                on_batch.clone(),
+            ));
+            let (batch, error) = match batch_task.await {
+                Ok(drained) => drained,
+                Err(e) => (Vec::new(), Some(format!("Receive task failed: {}", e))),
+            };
+            if error.is_some() {
+                result.error = error;
+            }
+
+            let empty = batch.is_empty();
+            result.messages.extend(batch);
+            if empty || result.error.is_some() {
+                break;
+            }
+        }
+
+        eprintln!(
+            "[drain_messages] Drained {} messages from {}{}",
+            result.messages.len(),
+            result.entity_path,
+            result.error.as_deref().map(|e| format!(", stopped by: {}", e)).unwrap_or_default()
+        );
+        Ok(result)
+    }
+
+    // ============================================================================
+    // Peek-Lock Operations (REST API)
+    // ============================================================================
+    // Receive locks a message (POST /{entity}/messages/head) and returns a lock token.
+    // The message stays on the entity until it is completed (DELETE) or abandoned (PUT)
+    // via /{entity}/messages/{sequenceNumber}/{lockToken}, or until the lock expires.
+    // ============================================================================
+
+    // Receive up to max_count messages in peek-lock mode, over HTTP unless the
+    // connection asks for AMQP
+    pub async fn receive_messages(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        max_count: u32,
+        wait_time_seconds: Option<u32>,
+    ) -> Result<Vec<LockedMessage>, String> {
+        if self.transport == Some(Transport::Amqp) {
+            return self
+                .receive_messages_sdk(queue_name, topic_name, subscription_name, max_count, wait_time_seconds)
+                .await;
+        }
+        self.receive_messages_rest(queue_name, topic_name, subscription_name, max_count, wait_time_seconds)
+            .await
+    }
+
+    // Receive under peek-lock with the SDK. Locks on non-session entities are held by
+    // the broker, not the link, so the returned tokens can still be settled with
+    // complete_message/abandon_message after the receiver is closed.
+    pub async fn receive_messages_sdk(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        max_count: u32,
+        wait_time_seconds: Option<u32>,
+    ) -> Result<Vec<LockedMessage>, String> {
+        use azservicebus::prelude::*;
+
+        let wait_time = wait_time_seconds.unwrap_or(5).clamp(1, 60);
+        let connection_string = self.sdk_connection_string()?;
+        let mut client = ServiceBusClient::new_from_connection_string(
+            &connection_string,
+            ServiceBusClientOptions::default(),
+        )
+        .await
+        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+
+        let mut receiver = if let Some(q) = queue_name {
+            client
+                .create_receiver_for_queue(q, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create queue receiver: {}", e))?
+        } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+            client
+                .create_receiver_for_subscription(t, s, ServiceBusReceiverOptions::default())
+                .await
+                .map_err(|e| format!("Failed to create subscription receiver: {}", e))?
+        } else {
+            return Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string());
+        };
+
+        let result = receiver
+            .receive_messages_with_max_wait_time(max_count, Some(std::time::Duration::from_secs(wait_time as u64)))
+            .await
+            .map_err(|e| format!("Failed to receive messages: {}", e));
+
+        let mut messages = Vec::new();
+        if let Ok(received) = &result {
+            for sdk_msg in received {
+                let lock_token = sdk_msg
+                    .lock_token()
+                    .map(|token| token.to_string())
+                    .ok_or("Received message has no lock token")?;
+                messages.push(LockedMessage {
+                    message: received_message_to_message(sdk_msg)?,
+                    lock_token,
+                });
+            }
+        }
+
+        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
+        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+
+        result?;
+        eprintln!("[receive_messages_sdk] Locked {} messages", messages.len());
+        Ok(messages)
+    }
+
+    // wait_time_seconds is how long the service may wait for the first message;
+    // later calls use a 1 second wait so an emptied entity returns quickly.
+    pub async fn receive_messages_rest(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        max_count: u32,
+        wait_time_seconds: Option<u32>,
+    ) -> Result<Vec<LockedMessage>, String> {
+        let entity_path = message_entity_path(queue_name, topic_name, subscription_name)?;
+        let mut wait_time = wait_time_seconds.unwrap_or(5).clamp(1, 60);
+        let mut messages = Vec::new();
+
+        eprintln!("[receive_messages] Receiving up to {} messages from {}", max_count, entity_path);
+
+        while (messages.len() as u32) < max_count {
+            let url = format!(
+                "{}/{}/messages/head?timeout={}&api-version={}",
+                self.get_base_url(),
+                entity_path,
+                wait_time,
+                API_VERSION
+            );
+            let auth_header = self.get_auth_header(&url).await?;
+
+            let response = self
+                .client
+                .post(&url)
+                .header("Authorization", &auth_header)
+                .header("Content-Length", "0")
+                .send()
+                .await
+                .map_err(|e| format!("Failed to receive message: {}", e))?;
+
+            let status = response.status();
+            if status == reqwest::StatusCode::NO_CONTENT {
+                break;
+            }
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                if messages.is_empty() {
+                    return Err(http_error("receive message", status, &error_text));
+                }
+                // Keep what is already locked - the caller needs those lock tokens
+                eprintln!("[receive_messages] Stopping after {} messages: {} - {}", messages.len(), status, error_text);
+                break;
+            }
+
+            let headers = response.headers().clone();
+            let body = response.bytes().await.map_err(|e| format!("Failed to read response: {}", e))?;
+            let lock_token = parse_broker_properties(&headers)
+                .and_then(|props| props.get("LockToken").and_then(|v| v.as_str()).map(|s| s.to_string()))
+                .ok_or("Received message has no lock token")?;
+
+            messages.push(LockedMessage {
+                message: rest_message_from_response(&body, &headers),
+                lock_token,
+            });
+            wait_time = 1;
+        }
+
+        eprintln!("[receive_messages] Locked {} messages", messages.len());
+        Ok(messages)
+    }
+
+    // Complete a locked message, removing it from the entity
+    pub async fn complete_message(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        sequence_number: u64,
+        lock_token: &str,
+    ) -> Result<(), String> {
+        let entity_path = message_entity_path(queue_name, topic_name, subscription_name)?;
+        let url = self.locked_message_url(&entity_path, sequence_number, lock_token);
+        if dry_run::intercept("complete message", "DELETE", &url, None) {
+            return Ok(());
         }
+        let auth_header = self.get_auth_header(&url).await?;
 
-        Ok(all_messages)
+        let response = self
+            .client
+            .delete(&url)
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to complete message: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(http_error("complete message", status, &error_text));
+        }
+
+        Ok(())
     }
-    
-    fn message_entry_to_message(&self, entry: &MessageEntry) -> Result<ServiceBusMessage, String> {
-        // Parse message body from content
-        // Content might be base64 encoded or plain text/JSON
-        let body = if let Some(ref content) = entry.content {
-            // Try to parse as JSON first
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(content) {
-                json_value
-            } else {
-                // If not JSON, wrap as string value
-                serde_json::Value::String(content.clone())
+
+    // Abandon a locked message, releasing the lock so it can be received again.
+    // The service increments the delivery count.
+    pub async fn abandon_message(
+        &self,
+        queue_name: Option<&str>,
+        topic_name: Option<&str>,
+        subscription_name: Option<&str>,
+        sequence_number: u64,
+        lock_token: &str,
+    ) -> Result<(), String> {
+        let entity_path = message_entity_path(queue_name, topic_name, subscription_name)?;
+        let url = self.locked_message_url(&entity_path, sequence_number, lock_token);
+        if dry_run::intercept("abandon message", "PUT", &url, None) {
+            return Ok(());
+        }
+        let auth_header = self.get_auth_header(&url).await?;
+
+        let response = self
+            .client
+            .put(&url)
+            .header("Authorization", &auth_header)
+            .header("Content-Length", "0")
+            .send()
+            .await
+            .map_err(|e| format!("Failed to abandon message: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(http_error("abandon message", status, &error_text));
+        }
+
+        Ok(())
+    }
+
+    fn locked_message_url(&self, entity_path: &str, sequence_number: u64, lock_token: &str) -> String {
+        format!(
+            "{}/{}/messages/{}/{}?api-version={}",
+            self.get_base_url(),
+            entity_path,
+            sequence_number,
+            lock_token,
+            API_VERSION
+        )
+    }
+
+    // Test connection by attempting to list queues (uses REST API)
+    pub async fn test_connection(&self) -> Result<bool, String> {
+        // Test by trying to list queues (limited to 1)
+        match self.list_queues().await {
+            Ok(_) => Ok(true),
+            // A Listen-only credential can't list, but still works for its entity
+            Err(e) if self.entity_path().is_some() && is_access_denied(&e) => {
+                let access = self.probe_access().await?;
+                Ok(access.can_listen == Some(true) || access.entity_kind.as_deref() == Some("topic"))
             }
-        } else {
-            serde_json::Value::Null
+            Err(e) => {
+                eprintln!("Connection test failed: {}", e);
+                Ok(false)
+            }
+        }
+    }
+
+    // The EntityPath of an entity-scoped connection string
+    pub fn entity_path(&self) -> Option<&str> {
+        self.parsed_connection
+            .as_ref()
+            .and_then(|parsed| parsed.entity_path.as_deref())
+            .map(|path| path.trim_matches('/'))
+            .filter(|path| !path.is_empty())
+    }
+
+    // What this credential can do. Management calls need Manage; a Listen-only policy,
+    // usually on one entity (EntityPath), can still peek and receive there.
+    pub async fn probe_access(&self) -> Result<ConnectionAccess, String> {
+        let management = self.list_queues_page(Some(0), Some(1)).await;
+        let entity_path = self.entity_path().map(|path| path.to_string());
+        let (entity_kind, can_listen) = match &entity_path {
+            Some(path) => self.probe_entity(path).await?,
+            None => (None, None),
         };
-        
-        // Parse BrokerProperties from the entry
-        // BrokerProperties is a JSON string in the entry's content or as a property
-        let mut message = ServiceBusMessage {
-            body,
-            message_id: entry.message_id.clone(),
-            correlation_id: entry.correlation_id.clone(),
-            content_type: entry.content_type.clone(),
-            sequence_number: entry.sequence_number.map(|s| s as u64),
-            subject: None,
-            reply_to: None,
-            reply_to_session_id: None,
-            session_id: None,
-            time_to_live: None,
-            to: None,
-            application_properties: None,
-            delivery_count: None,
-            enqueued_time_utc: None,
-            locked_until_utc: None,
-            dead_letter_reason: None,
-            dead_letter_error_description: None,
+        Ok(ConnectionAccess {
+            can_manage: management.is_ok(),
+            management_error: management.err(),
+            entity_path,
+            entity_kind: entity_kind.map(|kind| kind.to_string()),
+            can_listen,
+        })
+    }
+
+    // Identify an entity without management rights by peeking it: (kind, can listen)
+    // Errors other than a refused credential or a topic are returned as they are
+    async fn probe_entity(&self, path: &str) -> Result<(Option<&'static str>, Option<bool>), String> {
+        if let Some((topic, subscription)) = split_subscription_path(path) {
+            return match self.peek_messages(None, Some(topic), Some(subscription), 1, None, Some(0)).await {
+                Ok(_) => Ok((Some("subscription"), Some(true))),
+                Err(e) if is_access_denied(&e) => Ok((Some("subscription"), Some(false))),
+                Err(e) => Err(e),
+            };
+        }
+        match self.peek_messages(Some(path), None, None, 1, None, Some(0)).await {
+            Ok(_) => Ok((Some("queue"), Some(true))),
+            Err(e) if is_access_denied(&e) => Ok((None, Some(false))),
+            // Topics can't be peeked; their subscriptions can, with the same policy
+            Err(e) if is_topic_receive_error(&e) => Ok((Some("topic"), None)),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Stand-ins for the listings when the credential can't list: the entity the
+    // EntityPath names, by name only (settings and counts need Manage as well)
+    pub async fn scoped_queues(&self) -> Result<Vec<QueueProperties>, String> {
+        let Some(path) = self.entity_path() else {
+            return Ok(Vec::new());
         };
-        
-        // Parse BrokerProperties if available
-        if let Some(ref broker_props) = entry.broker_properties {
-            // BrokerProperties is a JSON string, parse it
-            if let Ok(props) = serde_json::from_str::<serde_json::Value>(broker_props) {
-                if let Some(msg_id) = props.get("MessageId").and_then(|v| v.as_str()) {
-                    message.message_id = Some(msg_id.to_string());
-                }
-                if let Some(corr_id) = props.get("CorrelationId").and_then(|v| v.as_str()) {
-                    message.correlation_id = Some(corr_id.to_string());
+        Ok(match self.probe_entity(path).await? {
+            (Some("queue"), _) => vec![QueueProperties { name: path.to_string(), ..Default::default() }],
+            _ => Vec::new(),
+        })
+    }
+
+    pub async fn scoped_topics(&self) -> Result<Vec<TopicProperties>, String> {
+        let Some(path) = self.entity_path() else {
+            return Ok(Vec::new());
+        };
+        let topic = match split_subscription_path(path) {
+            Some((topic, _)) => topic,
+            None if self.probe_entity(path).await?.0 == Some("topic") => path,
+            None => return Ok(Vec::new()),
+        };
+        Ok(vec![TopicProperties { name: topic.to_string(), ..Default::default() }])
+    }
+
+    pub fn scoped_subscriptions(&self, topic_name: &str) -> Vec<SubscriptionProperties> {
+        match self.entity_path().and_then(split_subscription_path) {
+            Some((topic, subscription)) if topic.eq_ignore_ascii_case(topic_name) => vec![SubscriptionProperties {
+                topic_name: topic.to_string(),
+                subscription_name: subscription.to_string(),
+                ..Default::default()
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    // Helper methods for XML parsing and generation
+    fn queue_entry_to_properties(&self, entry: &QueueEntry) -> Result<QueueProperties, String> {
+        let element = |name: &str| description_element(entry.content.as_deref(), name);
+        let count = |name: &str| element(name).and_then(|v| v.parse::<u64>().ok());
+        let duration = |name: &str| description_duration(entry.content.as_deref(), name);
+        let flag = |name: &str| element(name).and_then(|v| v.parse::<bool>().ok());
+        let timestamp = |name: &str| description_timestamp(entry.content.as_deref(), name);
+
+        Ok(QueueProperties {
+            name: entry.title.clone(),
+            max_size_in_megabytes: count("MaxSizeInMegabytes"),
+            lock_duration_in_seconds: duration("LockDuration"),
+            max_delivery_count: element("MaxDeliveryCount").and_then(|v| v.parse().ok()),
+            default_message_time_to_live_in_seconds: duration("DefaultMessageTimeToLive"),
+            dead_lettering_on_message_expiration: flag("DeadLetteringOnMessageExpiration"),
+            duplicate_detection_history_time_window_in_seconds: duration("DuplicateDetectionHistoryTimeWindow"),
+            enable_batched_operations: flag("EnableBatchedOperations"),
+            auto_delete_on_idle_in_seconds: duration("AutoDeleteOnIdle"),
+            status: element("Status"),
+            enable_partitioning: flag("EnablePartitioning"),
+            requires_session: flag("RequiresSession"),
+            requires_duplicate_detection: flag("RequiresDuplicateDetection"),
+            max_message_size_in_kilobytes: count("MaxMessageSizeInKilobytes"),
+            forward_to: element("ForwardTo"),
+            forward_dead_lettered_messages_to: element("ForwardDeadLetteredMessagesTo"),
+            user_metadata: element("UserMetadata"),
+            created_at: timestamp("CreatedAt"),
+            updated_at: timestamp("UpdatedAt"),
+            accessed_at: timestamp("AccessedAt"),
+            message_count: count("MessageCount"),
+            active_message_count: count("ActiveMessageCount"),
+            dead_letter_message_count: count("DeadLetterMessageCount"),
+            scheduled_message_count: count("ScheduledMessageCount"),
+            transfer_message_count: count("TransferMessageCount"),
+            transfer_dead_letter_message_count: count("TransferDeadLetterMessageCount"),
+            size_in_bytes: count("SizeInBytes"),
+            connection: None,
+        })
+    }
+
+    fn queue_properties_to_xml(&self, queue_name: &str, properties: Option<&QueueProperties>, is_update: bool) -> Result<String, String> {
+        use crate::azure::auth::seconds_to_duration;
+        use crate::azure::authorization::escape_xml;
+
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?><entry xmlns="http://www.w3.org/2005/Atom"><title>"#);
+        xml.push_str(&escape_xml(queue_name));
+        xml.push_str(r#"</title><content type="application/xml"><QueueDescription xmlns="http://schemas.microsoft.com/netservices/2010/10/servicebus/connect">"#);
+
+        // The service rejects a QueueDescription whose elements are out of order, so they are
+        // written in its data contract order. Immutable properties are left out of updates.
+        if let Some(props) = properties {
+            let mut element = |name: &str, value: Option<String>| {
+                if let Some(value) = value {
+                    xml.push_str(&format!("<{0}>{1}</{0}>", name, value));
                 }
-                if let Some(seq) = props.get("SequenceNumber").and_then(|v| v.as_i64()) {
-                    message.sequence_number = Some(seq as u64);
+            };
+            let immutable = |value: Option<bool>| value.filter(|_| !is_update).map(|v| v.to_string());
+            // An empty string clears a forward or the metadata, since the update replaces them
+            let text = |value: &Option<String>| value.as_deref().filter(|v| !v.is_empty()).map(escape_xml);
+
+            element("LockDuration", props.lock_duration_in_seconds.map(seconds_to_duration));
+            element("MaxSizeInMegabytes", props.max_size_in_megabytes.map(|v| v.to_string()));
+            element("RequiresDuplicateDetection", immutable(props.requires_duplicate_detection));
+            element("RequiresSession", immutable(props.requires_session));
+            element("DefaultMessageTimeToLive", props.default_message_time_to_live_in_seconds.map(seconds_to_duration));
+            element("DeadLetteringOnMessageExpiration", props.dead_lettering_on_message_expiration.map(|v| v.to_string()));
+            element(
+                "DuplicateDetectionHistoryTimeWindow",
+                props.duplicate_detection_history_time_window_in_seconds.map(seconds_to_duration),
+            );
+            element("MaxDeliveryCount", props.max_delivery_count.map(|v| v.to_string()));
+            element("EnableBatchedOperations", props.enable_batched_operations.map(|v| v.to_string()));
+            element("Status", props.status.as_deref().map(escape_xml));
+            element("ForwardTo", text(&props.forward_to));
+            element("UserMetadata", text(&props.user_metadata));
+            element("AutoDeleteOnIdle", props.auto_delete_on_idle_in_seconds.map(seconds_to_duration));
+            element("EnablePartitioning", immutable(props.enable_partitioning));
+            element("ForwardDeadLetteredMessagesTo", text(&props.forward_dead_lettered_messages_to));
+            // Premium namespaces only
+            element("MaxMessageSizeInKilobytes", props.max_message_size_in_kilobytes.map(|v| v.to_string()));
+        }
+
+        xml.push_str(r#"</QueueDescription></content></entry>"#);
+
+        Ok(xml)
+    }
+
+    fn topic_entry_to_properties(&self, entry: &TopicEntry) -> Result<TopicProperties, String> {
+        let element = |name: &str| description_element(entry.content.as_deref(), name);
+        let count = |name: &str| element(name).and_then(|v| v.parse::<u64>().ok());
+        let duration = |name: &str| description_duration(entry.content.as_deref(), name);
+        let flag = |name: &str| element(name).and_then(|v| v.parse::<bool>().ok());
+        let timestamp = |name: &str| description_timestamp(entry.content.as_deref(), name);
+
+        Ok(TopicProperties {
+            name: entry.title.clone(),
+            max_size_in_megabytes: count("MaxSizeInMegabytes"),
+            default_message_time_to_live_in_seconds: duration("DefaultMessageTimeToLive"),
+            duplicate_detection_history_time_window_in_seconds: duration("DuplicateDetectionHistoryTimeWindow"),
+            enable_batched_operations: flag("EnableBatchedOperations"),
+            enable_partitioning: flag("EnablePartitioning"),
+            requires_duplicate_detection: flag("RequiresDuplicateDetection"),
+            auto_delete_on_idle_in_seconds: duration("AutoDeleteOnIdle"),
+            status: element("Status"),
+            support_ordering: flag("SupportOrdering"),
+            max_message_size_in_kilobytes: count("MaxMessageSizeInKilobytes"),
+            user_metadata: element("UserMetadata"),
+            created_at: timestamp("CreatedAt"),
+            updated_at: timestamp("UpdatedAt"),
+            accessed_at: timestamp("AccessedAt"),
+            size_in_bytes: count("SizeInBytes"),
+            subscription_count: count("SubscriptionCount"),
+            connection: None,
+        })
+    }
+
+    // Elements in TopicDescription data contract order, like queue_properties_to_xml
+    fn topic_properties_to_xml(&self, topic_name: &str, properties: Option<&TopicProperties>, is_update: bool) -> Result<String, String> {
+        use crate::azure::auth::seconds_to_duration;
+        use crate::azure::authorization::escape_xml;
+
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?><entry xmlns="http://www.w3.org/2005/Atom"><title>"#);
+        xml.push_str(&escape_xml(topic_name));
+        xml.push_str(r#"</title><content type="application/xml"><TopicDescription xmlns="http://schemas.microsoft.com/netservices/2010/10/servicebus/connect">"#);
+
+        if let Some(props) = properties {
+            let mut element = |name: &str, value: Option<String>| {
+                if let Some(value) = value {
+                    xml.push_str(&format!("<{0}>{1}</{0}>", name, value));
                 }
+            };
+            let immutable = |value: Option<bool>| value.filter(|_| !is_update).map(|v| v.to_string());
+
+            element("DefaultMessageTimeToLive", props.default_message_time_to_live_in_seconds.map(seconds_to_duration));
+            element("MaxSizeInMegabytes", props.max_size_in_megabytes.map(|v| v.to_string()));
+            element("RequiresDuplicateDetection", immutable(props.requires_duplicate_detection));
+            element(
+                "DuplicateDetectionHistoryTimeWindow",
+                props.duplicate_detection_history_time_window_in_seconds.map(seconds_to_duration),
+            );
+            element("EnableBatchedOperations", props.enable_batched_operations.map(|v| v.to_string()));
+            element("Status", props.status.as_deref().map(escape_xml));
+            element("UserMetadata", props.user_metadata.as_deref().filter(|v| !v.is_empty()).map(escape_xml));
+            element("SupportOrdering", props.support_ordering.map(|v| v.to_string()));
+            element("AutoDeleteOnIdle", props.auto_delete_on_idle_in_seconds.map(seconds_to_duration));
+            element("EnablePartitioning", immutable(props.enable_partitioning));
+            element("MaxMessageSizeInKilobytes", props.max_message_size_in_kilobytes.map(|v| v.to_string()));
+        }
+
+        xml.push_str(r#"</TopicDescription></content></entry>"#);
+
+        Ok(xml)
+    }
+
+    fn subscription_entry_to_properties(&self, topic_name: &str, entry: &SubscriptionEntry) -> Result<SubscriptionProperties, String> {
+        // Parse message counts from content XML
+        let mut message_count: Option<u64> = None;
+        let mut active_message_count: Option<u64> = None;
+        let mut dead_letter_message_count: Option<u64> = None;
+        let mut transfer_message_count: Option<u64> = None;
+        let mut transfer_dead_letter_message_count: Option<u64> = None;
+        
+        if let Some(ref content) = entry.content {
+            // Extract counts from CountDetails using regex
+            // Format: <d2p1:ActiveMessageCount>0</d2p1:ActiveMessageCount>
+            if let Some(cap) = regex::Regex::new(r#"<d2p1:ActiveMessageCount>(\d+)</d2p1:ActiveMessageCount>"#)
+                .ok()
+                .and_then(|re| re.captures(content))
+            {
+                active_message_count = cap[1].parse().ok();
+            }
+            if let Some(cap) = regex::Regex::new(r#"<d2p1:DeadLetterMessageCount>(\d+)</d2p1:DeadLetterMessageCount>"#)
+                .ok()
+                .and_then(|re| re.captures(content))
+            {
+                dead_letter_message_count = cap[1].parse().ok();
+            }
+            if let Some(cap) = regex::Regex::new(r#"<d2p1:TransferMessageCount>(\d+)</d2p1:TransferMessageCount>"#)
+                .ok()
+                .and_then(|re| re.captures(content))
+            {
+                transfer_message_count = cap[1].parse().ok();
+            }
+            if let Some(cap) = regex::Regex::new(r#"<d2p1:TransferDeadLetterMessageCount>(\d+)</d2p1:TransferDeadLetterMessageCount>"#)
+                .ok()
+                .and_then(|re| re.captures(content))
+            {
+                transfer_dead_letter_message_count = cap[1].parse().ok();
+            }
+            // Also check for MessageCount (total)
+            if let Some(cap) = regex::Regex::new(r#"<MessageCount>(\d+)</MessageCount>"#)
+                .ok()
+                .and_then(|re| re.captures(content))
+            {
+                message_count = cap[1].parse().ok();
             }
         }
+        // Under whatever prefix the namespace uses, like the queue and topic counts
+        let scheduled_message_count =
+            description_element(entry.content.as_deref(), "ScheduledMessageCount").and_then(|v| v.parse().ok());
         
-        Ok(message)
+        // Settings are plain elements of the SubscriptionDescription
+        let setting = |name: &str| {
+            let content = entry.content.as_deref()?;
+            let re = regex::Regex::new(&format!(r#"<{0}>([^<]*)</{0}>"#, name)).ok()?;
+            re.captures(content).map(|cap| cap[1].trim().to_string())
+        };
+        let duration = |name: &str| setting(name).and_then(|v| crate::azure::auth::parse_duration_to_seconds(&v));
+        let flag = |name: &str| setting(name).and_then(|v| v.parse::<bool>().ok());
+
+        Ok(SubscriptionProperties {
+            topic_name: topic_name.to_string(),
+            subscription_name: entry.title.clone(),
+            max_delivery_count: setting("MaxDeliveryCount").and_then(|v| v.parse().ok()),
+            lock_duration_in_seconds: duration("LockDuration"),
+            default_message_time_to_live_in_seconds: duration("DefaultMessageTimeToLive"),
+            dead_lettering_on_message_expiration: flag("DeadLetteringOnMessageExpiration"),
+            enable_batched_operations: flag("EnableBatchedOperations"),
+            requires_session: flag("RequiresSession"),
+            message_count,
+            active_message_count,
+            dead_letter_message_count,
+            scheduled_message_count,
+            transfer_message_count,
+            transfer_dead_letter_message_count,
+            rule_count: None,
+            connection: None,
+        })
+    }
+
+    fn subscription_properties_to_xml(
+        &self,
+        _topic_name: &str,
+        subscription_name: &str,
+        _properties: Option<&SubscriptionProperties>,
+        initial_rule: Option<&SubscriptionRule>,
+    ) -> Result<String, String> {
+        use crate::azure::authorization::escape_xml;
+
+        let Some(rule) = initial_rule else {
+            return Ok(format!(r#"<?xml version="1.0" encoding="utf-8"?><entry xmlns="http://www.w3.org/2005/Atom"><title>{}</title></entry>"#, escape_xml(subscription_name)));
+        };
+
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?><entry xmlns="http://www.w3.org/2005/Atom"><title>"#);
+        xml.push_str(&escape_xml(subscription_name));
+        xml.push_str(r#"</title><content type="application/xml"><SubscriptionDescription xmlns="http://schemas.microsoft.com/netservices/2010/10/servicebus/connect">"#);
+        xml.push_str(&crate::azure::rules::default_rule_description_xml(rule)?);
+        xml.push_str(r#"</SubscriptionDescription></content></entry>"#);
+        Ok(xml)
+    }
+}
+
+// Limits the service enforces, checked up front so a bad value fails the whole job
+// instead of every subscription
+fn validate_subscription_settings(settings: &SubscriptionSettings) -> Result<(), String> {
+    if settings.lock_duration_in_seconds.is_none()
+        && settings.max_delivery_count.is_none()
+        && settings.default_message_time_to_live_in_seconds.is_none()
+        && settings.dead_lettering_on_message_expiration.is_none()
+    {
+        return Err("No settings to apply".to_string());
+    }
+    if let Some(seconds) = settings.lock_duration_in_seconds {
+        if !(5..=300).contains(&seconds) {
+            return Err(format!("Lock duration must be between 5 seconds and 5 minutes, got {} seconds", seconds));
+        }
+    }
+    if settings.max_delivery_count == Some(0) {
+        return Err("Max delivery count must be at least 1".to_string());
+    }
+    if settings.default_message_time_to_live_in_seconds == Some(0) {
+        return Err("Message time to live must be at least 1 second".to_string());
+    }
+    Ok(())
+}
+
+// TimeSpan.MaxValue, which the service returns for "never" (TTL, AutoDeleteOnIdle)
+const NEVER_SECONDS: u64 = 10_675_199 * 86_400;
+
+// Text of an element of an entity description. Counts are in CountDetails under whatever
+// prefix the service gave that namespace (<d2p1:ActiveMessageCount>), so any prefix is accepted.
+fn description_element(content: Option<&str>, name: &str) -> Option<String> {
+    let re = regex::Regex::new(&format!(r#"<(?:\w+:)?{0}>([^<]*)</(?:\w+:)?{0}>"#, name)).ok()?;
+    re.captures(content?).map(|cap| cap[1].trim().to_string()).filter(|v| !v.is_empty())
+}
+
+// A duration in seconds; "never" stays None
+fn description_duration(content: Option<&str>, name: &str) -> Option<u64> {
+    description_element(content, name)
+        .and_then(|v| crate::azure::auth::parse_duration_to_seconds(&v))
+        .filter(|seconds| *seconds < NEVER_SECONDS)
+}
+
+// AccessedAt is DateTime.MinValue until the entity is first used
+fn description_timestamp(content: Option<&str>, name: &str) -> Option<String> {
+    description_element(content, name).filter(|v| !v.starts_with("0001-01-01")).map(|v| timestamps::normalize(&v))
+}
+
+// Description element of a bulk-editable property
+fn property_element(property: EntityProperty) -> &'static str {
+    match property {
+        EntityProperty::LockDuration => "LockDuration",
+        EntityProperty::MaxDeliveryCount => "MaxDeliveryCount",
+        EntityProperty::DefaultMessageTimeToLive => "DefaultMessageTimeToLive",
+        EntityProperty::DeadLetteringOnMessageExpiration => "DeadLetteringOnMessageExpiration",
+        EntityProperty::DuplicateDetectionHistoryTimeWindow => "DuplicateDetectionHistoryTimeWindow",
+        EntityProperty::MaxSizeInMegabytes => "MaxSizeInMegabytes",
+        EntityProperty::AutoDeleteOnIdle => "AutoDeleteOnIdle",
+        EntityProperty::EnableBatchedOperations => "EnableBatchedOperations",
+        EntityProperty::Status => "Status",
+    }
+}
+
+fn property_applies_to(property: EntityProperty, kind: EntityKind) -> bool {
+    match property {
+        EntityProperty::LockDuration
+        | EntityProperty::MaxDeliveryCount
+        | EntityProperty::DeadLetteringOnMessageExpiration => kind != EntityKind::Topic,
+        EntityProperty::DuplicateDetectionHistoryTimeWindow | EntityProperty::MaxSizeInMegabytes => {
+            kind != EntityKind::Subscription
+        }
+        EntityProperty::DefaultMessageTimeToLive
+        | EntityProperty::AutoDeleteOnIdle
+        | EntityProperty::EnableBatchedOperations
+        | EntityProperty::Status => true,
+    }
+}
+
+fn is_duration_property(property: EntityProperty) -> bool {
+    matches!(
+        property,
+        EntityProperty::LockDuration
+            | EntityProperty::DefaultMessageTimeToLive
+            | EntityProperty::DuplicateDetectionHistoryTimeWindow
+            | EntityProperty::AutoDeleteOnIdle
+    )
+}
+
+// The current value as bulk edits express it: seconds for durations, numbers, booleans
+// and the Status string
+fn description_value(property: EntityProperty, description: &str) -> Option<serde_json::Value> {
+    let re = regex::Regex::new(&format!(r#"<{0}>([^<]*)</{0}>"#, property_element(property))).ok()?;
+    let text = re.captures(description)?[1].trim().to_string();
+    match property {
+        _ if is_duration_property(property) => crate::azure::auth::parse_duration_to_seconds(&text).map(Into::into),
+        EntityProperty::MaxDeliveryCount | EntityProperty::MaxSizeInMegabytes => text.parse::<u64>().ok().map(Into::into),
+        EntityProperty::DeadLetteringOnMessageExpiration | EntityProperty::EnableBatchedOperations => {
+            text.parse::<bool>().ok().map(Into::into)
+        }
+        _ => Some(text.into()),
+    }
+}
+
+// The element text for a new value, checking it has the property's type
+fn property_xml_value(property: EntityProperty, value: &serde_json::Value) -> Result<String, String> {
+    let element = property_element(property);
+    match property {
+        _ if is_duration_property(property) => value
+            .as_u64()
+            .filter(|seconds| *seconds > 0)
+            .map(crate::azure::auth::seconds_to_duration)
+            .ok_or_else(|| format!("{} must be a number of seconds greater than 0", element)),
+        EntityProperty::MaxDeliveryCount | EntityProperty::MaxSizeInMegabytes => value
+            .as_u64()
+            .filter(|number| *number > 0)
+            .map(|number| number.to_string())
+            .ok_or_else(|| format!("{} must be a number greater than 0", element)),
+        EntityProperty::DeadLetteringOnMessageExpiration | EntityProperty::EnableBatchedOperations => {
+            value.as_bool().map(|flag| flag.to_string()).ok_or_else(|| format!("{} must be true or false", element))
+        }
+        _ => match value.as_str() {
+            Some(status @ ("Active" | "Disabled" | "SendDisabled" | "ReceiveDisabled")) => Ok(status.to_string()),
+            _ => Err("Status must be Active, Disabled, SendDisabled or ReceiveDisabled".to_string()),
+        },
+    }
+}
+
+// Ordering comparisons only make sense for numbers (durations, counts and sizes)
+fn validate_condition(condition: &PropertyCondition) -> Result<(), String> {
+    let ordered = !matches!(condition.op, ComparisonOp::Eq | ComparisonOp::Ne);
+    if ordered && !condition.value.is_number() {
+        return Err(format!("{} can only be compared for equality", property_element(condition.property)));
+    }
+    Ok(())
+}
+
+fn condition_matches(condition: &PropertyCondition, description: &str) -> bool {
+    use std::cmp::Ordering;
+
+    let Some(current) = description_value(condition.property, description) else {
+        return false;
+    };
+    let ordering = match (&current, &condition.value) {
+        (serde_json::Value::Number(a), serde_json::Value::Number(b)) => {
+            a.as_f64().zip(b.as_f64()).and_then(|(a, b)| a.partial_cmp(&b))
+        }
+        _ if same_value(condition.property, Some(&current), &condition.value) => Some(Ordering::Equal),
+        _ => None,
+    };
+    match condition.op {
+        ComparisonOp::Eq => ordering == Some(Ordering::Equal),
+        ComparisonOp::Ne => ordering != Some(Ordering::Equal),
+        ComparisonOp::Lt => ordering == Some(Ordering::Less),
+        ComparisonOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        ComparisonOp::Gt => ordering == Some(Ordering::Greater),
+        ComparisonOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+// Status is compared without case, like the service treats it
+fn same_value(property: EntityProperty, current: Option<&serde_json::Value>, value: &serde_json::Value) -> bool {
+    match (property, current) {
+        (EntityProperty::Status, Some(serde_json::Value::String(a))) => {
+            value.as_str().is_some_and(|b| a.eq_ignore_ascii_case(b))
+        }
+        (_, Some(current)) => current == value,
+        (_, None) => false,
+    }
+}
+
+fn apply_diffs(description: &str, diffs: &[PropertyDiff]) -> Result<String, String> {
+    diffs.iter().try_fold(description.to_string(), |description, diff| {
+        let value = property_xml_value(diff.property, &diff.after)?;
+        set_description_element(&description, property_element(diff.property), &value)
+    })
+}
+
+// Replace the value of a top-level element of an entity description
+fn set_description_element(description: &str, element: &str, value: &str) -> Result<String, String> {
+    let re = regex::Regex::new(&format!(r#"<{0}>[^<]*</{0}>"#, element))
+        .map_err(|e| format!("Invalid element pattern: {}", e))?;
+    if !re.is_match(description) {
+        return Err(format!("Entity description has no <{}>", element));
     }
+    let replacement = format!("<{0}>{1}</{0}>", element, value);
+    Ok(re.replacen(description, 1, regex::NoExpand(&replacement)).into_owned())
+}
 
-    // Send message using azservicebus SDK
-    pub async fn send_message(
-        &self,
-        queue_name: Option<&str>,
-        topic_name: Option<&str>,
-        message: &ServiceBusMessage,
-    ) -> Result<(), String> {
-        use azservicebus::prelude::*;
-        
-        let connection_string = if let Some(ref parsed) = self.parsed_connection {
-            // Reconstruct connection string from parsed components
-            format!(
-                "Endpoint=sb://{}{}/;SharedAccessKeyName={};SharedAccessKey={}",
-                self.namespace,
-                self.endpoint_domain,
-                parsed.shared_access_key_name,
-                parsed.shared_access_key
-            )
-        } else {
-            return Err("Connection string not available for SDK".to_string());
-        };
+// GET with If-None-Match. Takes owned values so requests can run concurrently on a
+// JoinSet (see ServiceBusClient::get_entity_xml_if_changed).
+async fn get_xml_if_changed(
+    client: Client,
+    url: String,
+    auth_header: String,
+    operation: String,
+    etag: Option<String>,
+) -> Result<Option<(String, String)>, String> {
+    let mut request = client.get(&url).header("Authorization", &auth_header);
+    if let Some(etag) = &etag {
+        request = request.header("If-None-Match", etag);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to {}: {}", operation, e))?;
 
-        let entity_path = if let Some(q) = queue_name {
-            q.to_string()
-        } else if let Some(t) = topic_name {
-            t.to_string()
-        } else {
-            return Err("Either queue_name or topic_name must be provided".to_string());
-        };
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(http_error(&operation, status, &error_text));
+    }
 
-        eprintln!("[send_message] Using azservicebus SDK to send message to: {}", entity_path);
+    let server_etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string());
+    let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+    let new_etag = server_etag.unwrap_or_else(|| body_etag(&xml));
 
-        // Create ServiceBus client
-        let mut client = ServiceBusClient::new_from_connection_string(
-            &connection_string,
-            ServiceBusClientOptions::default(),
-        )
+    if etag.as_deref() == Some(new_etag.as_str()) {
+        return Ok(None);
+    }
+    Ok(Some((xml, new_etag)))
+}
+
+// Forwarding settings in an entity description that point at target (lower case).
+// Values are entity names or full sb://namespace/entity URLs.
+fn forward_references(entity_path: &str, description: &str, target: &str) -> Vec<ForwardReference> {
+    let Ok(re) = regex::Regex::new(
+        r"<(ForwardTo|ForwardDeadLetteredMessagesTo)>([^<]+)</(?:ForwardTo|ForwardDeadLetteredMessagesTo)>",
+    ) else {
+        return Vec::new();
+    };
+    re.captures_iter(description)
+        .filter(|cap| {
+            let value = cap[2].trim();
+            let name = match value.split_once("://") {
+                Some((_, rest)) => rest.split_once('/').map(|(_, path)| path).unwrap_or(""),
+                None => value,
+            };
+            name.trim_matches('/').eq_ignore_ascii_case(target)
+        })
+        .map(|cap| ForwardReference {
+            entity_path: entity_path.to_string(),
+            property: cap[1].to_string(),
+            forward_to: cap[2].trim().to_string(),
+        })
+        .collect()
+}
+
+// Number of entries in a management feed page
+async fn count_feed_entries(client: Client, url: String, auth_header: String) -> Result<u32, String> {
+    let response = client
+        .get(&url)
+        .header("Authorization", &auth_header)
+        .send()
         .await
-        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+        .map_err(|e| format!("Failed to list rules: {}", e))?;
 
-        // Create sender for queue or topic
-        let mut sender = client
-            .create_sender(&entity_path, ServiceBusSenderOptions::default())
-            .await
-            .map_err(|e| format!("Failed to create sender: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(http_error("list rules", status, &error_text));
+    }
 
-        // Convert message body to bytes
-        let body_bytes = match &message.body {
-            serde_json::Value::String(s) => s.as_bytes().to_vec(),
-            serde_json::Value::Object(_) | serde_json::Value::Array(_) => {
-                serde_json::to_vec(&message.body)
-                    .map_err(|e| format!("Failed to serialize message body: {}", e))?
-            }
-            serde_json::Value::Number(n) => n.to_string().as_bytes().to_vec(),
-            serde_json::Value::Bool(b) => b.to_string().as_bytes().to_vec(),
-            serde_json::Value::Null => Vec::new(),
-        };
+    let xml = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+    let re = regex::Regex::new(r#"<entry[\s>]"#).map_err(|e| e.to_string())?;
+    Ok(re.find_iter(&xml).count() as u32)
+}
 
-        // Create SDK message
-        let mut sdk_message = ServiceBusMessage::new(body_bytes);
+// A count from an entity description: <MessageCount> or one of the
+// <d2p1:...MessageCount> elements inside <CountDetails>
+fn message_count_detail(xml: &str, name: &str) -> Option<u64> {
+    let re = regex::Regex::new(&format!(r#"<(?:\w+:)?{0}>(\d+)</(?:\w+:)?{0}>"#, name)).ok()?;
+    re.captures(xml)?[1].parse().ok()
+}
 
-        // Set message properties
-        // Note: Some setters return Result, others return () - handle accordingly
-        if let Some(msg_id) = &message.message_id {
-            sdk_message.set_message_id(msg_id.clone())
-                .map_err(|e| format!("Failed to set message_id: {}", e))?;
+// rel="next" link of a feed page (serde_xml_rs doesn't parse the attributes reliably)
+fn next_link(xml: &str) -> Option<String> {
+    let link = regex::Regex::new(r#"<link\b[^>]*\brel="next"[^>]*>"#).ok()?.find(xml)?;
+    let href = regex::Regex::new(r#"\bhref="([^"]+)""#).ok()?.captures(link.as_str())?;
+    Some(href[1].replace("&amp;", "&"))
+}
+
+// <content> of the feed entry with the given title.
+// serde_xml_rs can't deserialize the nested description XML, so it is cut out of the raw page.
+fn entry_content(xml: &str, title: &str) -> Option<String> {
+    let re = regex::Regex::new(r#"(?s)<entry[^>]*>.*?<title[^>]*>([^<]+)</title>.*?<content[^>]*type="application/xml"[^>]*>(.*?)</content>"#).ok()?;
+    let content = re
+        .captures_iter(xml)
+        .find(|cap| cap.get(1).map(|m| m.as_str().trim()) == Some(title.trim()))
+        .and_then(|cap| cap.get(2).map(|m| m.as_str().to_string()));
+    content
+}
+
+// Entity path for message operations on a queue or a topic subscription
+fn message_entity_path(
+    queue_name: Option<&str>,
+    topic_name: Option<&str>,
+    subscription_name: Option<&str>,
+) -> Result<String, String> {
+    if let Some(q) = queue_name {
+        Ok(q.to_string())
+    } else if let (Some(t), Some(s)) = (topic_name, subscription_name) {
+        Ok(format!("{}/Subscriptions/{}", t, s))
+    } else {
+        Err("Either queue_name or (topic_name and subscription_name) must be provided".to_string())
+    }
+}
+
+const BODY_HEX_PREVIEW_BYTES: usize = 64;
+
+struct DecodedBody {
+    body: serde_json::Value,
+    body_encoding: Option<String>,
+    body_hex_preview: Option<String>,
+    body_envelope: Option<BodyEnvelope>,
+    body_size: Option<u64>,
+    body_truncated: Option<bool>,
+}
+
+// Decode a peeked body. Text is parsed as JSON or kept as a string; binary content is
+// returned base64-encoded with body_encoding "base64" and a hex preview. Strings in a legacy
+// DataContract envelope are unwrapped first. With max_body_bytes the body is not parsed at
+// all: callers that only show properties pass 0 to drop it, or a small limit for a preview.
+// body_size and body_truncated are only set when projecting.
+fn decode_peeked_body(body_bytes: &[u8], max_body_bytes: Option<u32>) -> DecodedBody {
+    let Some(limit) = max_body_bytes else {
+        if let Some((text, envelope)) = data_contract::unwrap(body_bytes) {
+            let body = serde_json::from_str::<serde_json::Value>(&text).unwrap_or(serde_json::Value::String(text));
+            return DecodedBody {
+                body,
+                body_encoding: None,
+                body_hex_preview: None,
+                body_envelope: Some(envelope),
+                body_size: None,
+                body_truncated: None,
+            };
         }
-        if let Some(content_type) = &message.content_type {
-            sdk_message.set_content_type(content_type.clone());
+        if let Some(text) = body_text(body_bytes) {
+            let body = serde_json::from_str::<serde_json::Value>(text)
+                .unwrap_or_else(|_| serde_json::Value::String(text.to_string()));
+            return DecodedBody {
+                body,
+                body_encoding: None,
+                body_hex_preview: None,
+                body_envelope: None,
+                body_size: None,
+                body_truncated: None,
+            };
         }
-        if let Some(corr_id) = &message.correlation_id {
-            sdk_message.set_correlation_id(corr_id.clone());
+        return binary_body(body_bytes, body_bytes, None, None);
+    };
+
+    let size = body_bytes.len() as u64;
+    if limit == 0 {
+        return DecodedBody {
+            body: serde_json::Value::Null,
+            body_encoding: None,
+            body_hex_preview: None,
+            body_envelope: None,
+            body_size: Some(size),
+            body_truncated: Some(size > 0),
+        };
+    }
+    let limit = (limit as usize).min(body_bytes.len());
+    let truncated = Some(limit < body_bytes.len());
+    let prefix = &body_bytes[..limit];
+    let text = match std::str::from_utf8(prefix) {
+        Ok(text) => Some(text),
+        // Cut inside a multi-byte character: keep the valid prefix
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&prefix[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    };
+    match text.filter(|text| !has_control_characters(text)) {
+        Some(text) => DecodedBody {
+            body: serde_json::Value::String(text.to_string()),
+            body_encoding: None,
+            body_hex_preview: None,
+            body_envelope: None,
+            body_size: Some(size),
+            body_truncated: truncated,
+        },
+        None => binary_body(prefix, body_bytes, Some(size), truncated),
+    }
+}
+
+// The body as text, or None when it is not UTF-8 or carries control characters (raw bytes)
+fn body_text(body_bytes: &[u8]) -> Option<&str> {
+    std::str::from_utf8(body_bytes).ok().filter(|text| !has_control_characters(text))
+}
+
+fn has_control_characters(text: &str) -> bool {
+    text.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+}
+
+// encoded is what goes in the body (the whole body, or the projected prefix)
+fn binary_body(encoded: &[u8], body_bytes: &[u8], body_size: Option<u64>, body_truncated: Option<bool>) -> DecodedBody {
+    use base64::Engine;
+    let preview_len = body_bytes.len().min(BODY_HEX_PREVIEW_BYTES);
+    let hex: Vec<String> = body_bytes[..preview_len].iter().map(|b| format!("{:02x}", b)).collect();
+    DecodedBody {
+        body: serde_json::Value::String(base64::engine::general_purpose::STANDARD.encode(encoded)),
+        body_encoding: Some("base64".to_string()),
+        body_hex_preview: Some(hex.join(" ")),
+        body_envelope: None,
+        body_size,
+        body_truncated,
+    }
+}
+
+// Replace a gzip/deflate body with its decoded content, keeping the bytes as received in
+// raw_body. Bodies that fail to decompress are left as they are.
+fn decompress_body(message: &mut ServiceBusMessage) {
+    use base64::Engine;
+    // Only whole binary bodies; projected previews are cut and cannot be inflated
+    if message.body_encoding.as_deref() != Some("base64") || message.body_truncated.is_some() {
+        return;
+    }
+    let Some(encoded) = message.body.as_str() else {
+        return;
+    };
+    let Ok(raw) = base64::engine::general_purpose::STANDARD.decode(encoded) else {
+        return;
+    };
+    let Some(compression) = compression::detect(message, &raw) else {
+        return;
+    };
+    let decompressed = match compression::decompress(&raw, compression) {
+        Ok(decompressed) => decompressed,
+        Err(e) => {
+            eprintln!("[decompress_body] {}", e);
+            return;
         }
-        if let Some(session_id) = &message.session_id {
-            sdk_message.set_session_id(session_id.clone())
-                .map_err(|e| format!("Failed to set session_id: {}", e))?;
+    };
+
+    let decoded = decode_peeked_body(&decompressed, None);
+    message.raw_body = Some(encoded.to_string());
+    message.body = decoded.body;
+    message.body_encoding = decoded.body_encoding;
+    message.body_hex_preview = decoded.body_hex_preview;
+    message.body_envelope = decoded.body_envelope;
+    message.body_compression = Some(compression);
+}
+
+// Weak ETag from the response body, for when the service does not send one
+fn body_etag(body: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(body.as_bytes());
+    format!("W/\"{}\"", digest.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+// The service refusing a receiver because the entity is a topic, e.g. "Cannot create a
+// message receiver on an entity of type Topic"
+fn is_topic_receive_error(error: &str) -> bool {
+    let lower = error.to_ascii_lowercase();
+    lower.contains("entity of type topic") || lower.contains("entity type topic") || lower.contains("is a topic")
+}
+
+// "orders/Subscriptions/audit" -> ("orders", "audit")
+// "orders/$deadletterqueue" -> "orders"
+fn dead_letter_parent(path: &str) -> Option<&str> {
+    let suffix = "/$deadletterqueue";
+    let split = path.len().checked_sub(suffix.len())?;
+    (path.is_char_boundary(split) && path[split..].eq_ignore_ascii_case(suffix)).then(|| &path[..split])
+}
+
+fn split_subscription_path(path: &str) -> Option<(&str, &str)> {
+    let lower = path.to_ascii_lowercase();
+    let index = lower.find("/subscriptions/")?;
+    let (topic, rest) = (&path[..index], &path[index + "/subscriptions/".len()..]);
+    (!topic.is_empty() && !rest.is_empty() && !rest.contains('/')).then_some((topic, rest))
+}
+
+// The service rejects a partition key that differs from the session id, or from the
+// via partition key when both are set
+fn check_partition_key(message: &ServiceBusMessage) -> Result<(), String> {
+    if let (Some(partition_key), Some(session_id)) = (&message.partition_key, &message.session_id) {
+        if partition_key != session_id {
+            return Err(format!("Partition key '{}' must match the session id '{}'", partition_key, session_id));
         }
-        if let Some(reply_to) = &message.reply_to {
-            sdk_message.set_reply_to(reply_to.clone());
+    }
+    match (&message.partition_key, &message.via_partition_key) {
+        (Some(partition_key), Some(via_partition_key)) if partition_key != via_partition_key => Err(format!(
+            "Partition key '{}' must match the via partition key '{}'",
+            partition_key, via_partition_key
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Message body as sent on the wire: wrapped again if it came in a DataContract envelope,
+// then compressed again if it arrived compressed. raw_body is only informational.
+pub(crate) fn message_body_bytes(message: &ServiceBusMessage) -> Result<Vec<u8>, String> {
+    let mut bytes = payload_bytes(message)?;
+    if let Some(envelope) = message.body_envelope {
+        let text = String::from_utf8(bytes)
+            .map_err(|_| "A body sent in a DataContract envelope must be text, not binary".to_string())?;
+        bytes = data_contract::wrap(&text, envelope);
+    }
+    match message.body_compression {
+        Some(compression) => compression::compress(&bytes, compression),
+        None => Ok(bytes),
+    }
+}
+
+// The payload: strings as-is, everything else as JSON.
+// A body_encoding of "base64" means the body string holds raw bytes.
+fn payload_bytes(message: &ServiceBusMessage) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+    let body = &message.body;
+    match message.body_encoding.as_deref() {
+        None => {}
+        Some("base64") => {
+            let encoded = body.as_str().ok_or("A base64-encoded body must be a string")?;
+            return base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| format!("Invalid base64 message body: {}", e));
         }
-        if let Some(reply_to_session_id) = &message.reply_to_session_id {
-            sdk_message.set_reply_to_session_id(reply_to_session_id.clone())
-                .map_err(|e| format!("Failed to set reply_to_session_id: {}", e))?;
+        Some(other) => return Err(format!("Unknown body encoding '{}' (expected \"base64\")", other)),
+    }
+    Ok(match body {
+        serde_json::Value::String(s) => s.as_bytes().to_vec(),
+        serde_json::Value::Object(_) | serde_json::Value::Array(_) => {
+            serde_json::to_vec(body)
+                .map_err(|e| format!("Failed to serialize message body: {}", e))?
         }
-        if let Some(subject) = &message.subject {
-            sdk_message.set_subject(subject.clone());
+        serde_json::Value::Number(n) => n.to_string().as_bytes().to_vec(),
+        serde_json::Value::Bool(b) => b.to_string().as_bytes().to_vec(),
+        serde_json::Value::Null => Vec::new(),
+    })
+}
+
+// A request of send_messages_batch: a run of batch elements, or the index of a message
+// that has to go on its own
+enum SendPart {
+    Batch(Vec<serde_json::Value>),
+    Single(usize),
+}
+
+// One element of a REST batch: {"Body": ..., "BrokerProperties": {...}, "UserProperties": {...}}.
+// The batch format has no per-message content type, so content_type is not carried.
+// The batch format carries bodies as JSON strings, so a body that isn't text gives None.
+fn batch_item(message: &ServiceBusMessage) -> Result<Option<serde_json::Value>, String> {
+    let broker_properties = broker_properties(message)?;
+    let Ok(body) = String::from_utf8(message_body_bytes(message)?) else {
+        return Ok(None);
+    };
+
+    let mut item = serde_json::json!({
+        "Body": body,
+        "BrokerProperties": broker_properties,
+    });
+    if let Some(serde_json::Value::Object(properties)) = &message.application_properties {
+        item["UserProperties"] = serde_json::Value::Object(properties.clone());
+    }
+    Ok(Some(item))
+}
+
+// The BrokerProperties of a send, for a batch element or the header of a single send
+fn broker_properties(message: &ServiceBusMessage) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    check_partition_key(message)?;
+    let mut broker_properties = serde_json::Map::new();
+    let mut set = |name: &str, value: Option<&String>| {
+        if let Some(value) = value {
+            broker_properties.insert(name.to_string(), serde_json::Value::String(value.clone()));
         }
-        if let Some(ttl) = message.time_to_live {
-            sdk_message.set_time_to_live(std::time::Duration::from_secs(ttl))
-                .map_err(|e| format!("Failed to set time_to_live: {}", e))?;
+    };
+    set("MessageId", message.message_id.as_ref());
+    set("CorrelationId", message.correlation_id.as_ref());
+    set("SessionId", message.session_id.as_ref());
+    set("Label", message.subject.as_ref());
+    set("ReplyTo", message.reply_to.as_ref());
+    set("ReplyToSessionId", message.reply_to_session_id.as_ref());
+    set("To", message.to.as_ref());
+    set("PartitionKey", message.partition_key.as_ref());
+    set("ViaPartitionKey", message.via_partition_key.as_ref());
+    if let Some(ttl) = message.time_to_live {
+        broker_properties.insert("TimeToLive".to_string(), serde_json::json!(ttl));
+    }
+    if let Some(scheduled_time) = &message.scheduled_enqueue_time_utc {
+        // REST expects RFC 1123 here
+        let parsed = parse_scheduled_time(scheduled_time)?;
+        let utc = chrono::DateTime::from_timestamp(parsed.unix_timestamp(), 0)
+            .ok_or_else(|| format!("Scheduled enqueue time '{}' is out of range", scheduled_time))?;
+        broker_properties.insert(
+            "ScheduledEnqueueTimeUtc".to_string(),
+            serde_json::Value::String(utc.format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+        );
+    }
+    Ok(broker_properties)
+}
+
+// Parse a ScheduledEnqueueTimeUtc (RFC 3339, or any form timestamps::parse reads) into
+// the SDK's time type
+fn parse_scheduled_time(value: &str) -> Result<time::OffsetDateTime, String> {
+    let parsed = timestamps::parse(value).ok_or_else(|| {
+        format!("Invalid scheduled enqueue time '{}' (expected RFC 3339, e.g. 2024-05-01T12:00:00Z)", value)
+    })?;
+    let nanos = parsed
+        .timestamp_nanos_opt()
+        .ok_or_else(|| format!("Scheduled enqueue time '{}' is out of range", value))?;
+    time::OffsetDateTime::from_unix_timestamp_nanos(nanos as i128)
+        .map_err(|e| format!("Scheduled enqueue time '{}' is out of range: {}", value, e))
+}
+
+// Properties fixed when an entity is created, as (field name, accessor). Azure answers
+// a change to any of them with a generic 400, so updates are checked up front.
+// EnableExpress is also immutable but is not modeled by the typed structs.
+type ImmutableProperty<T> = (&'static str, fn(&T) -> Option<bool>);
+
+const IMMUTABLE_QUEUE_PROPERTIES: &[ImmutableProperty<QueueProperties>] = &[
+    ("enablePartitioning", |p| p.enable_partitioning),
+    ("requiresSession", |p| p.requires_session),
+    ("requiresDuplicateDetection", |p| p.requires_duplicate_detection),
+];
+
+const IMMUTABLE_TOPIC_PROPERTIES: &[ImmutableProperty<TopicProperties>] = &[
+    ("enablePartitioning", |p| p.enable_partitioning),
+    ("requiresDuplicateDetection", |p| p.requires_duplicate_detection),
+];
+
+// Reject an update that sets an immutable property to something other than its current
+// value. Unset properties and unchanged values pass.
+fn check_immutable_properties<T>(
+    entity_type: &str,
+    entity_name: &str,
+    table: &[ImmutableProperty<T>],
+    requested: &T,
+    existing: &T,
+) -> Result<(), String> {
+    let changes: Vec<String> = table
+        .iter()
+        .filter_map(|(field, get)| match (get(requested), get(existing)) {
+            (Some(new), current) if Some(new) != current => Some(format!(
+                "{} ({} -> {})",
+                field,
+                current.map(|v| v.to_string()).unwrap_or_else(|| "unset".to_string()),
+                new
+            )),
+            _ => None,
+        })
+        .collect();
+
+    if changes.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Cannot update {} '{}': {} can only be set when the {} is created. Create a new {} to change {}.",
+        entity_type,
+        entity_name,
+        changes.join(", "),
+        entity_type,
+        entity_type,
+        if changes.len() == 1 { "it" } else { "them" }
+    ))
+}
+
+// Temporary queues are recognised by this name prefix
+const TEMPORARY_QUEUE_PREFIX: &str = "sbx-tmp-";
+const TEMPORARY_QUEUE_IDLE_SECONDS: u64 = 600;
+
+fn is_temporary_queue(name: &str) -> bool {
+    name.to_ascii_lowercase().starts_with(TEMPORARY_QUEUE_PREFIX)
+}
+
+fn validate_peek_options(options: &PeekOptions, http: bool) -> Result<(), String> {
+    if let Some(seconds) = options.timeout_seconds {
+        if seconds == 0 || seconds > MAX_PEEK_TIMEOUT_SECONDS {
+            return Err(format!("Peek timeout must be between 1 and {} seconds", MAX_PEEK_TIMEOUT_SECONDS));
         }
-        if let Some(to) = &message.to {
-            sdk_message.set_to(to.clone());
+    }
+    if let Some(page_size) = options.page_size {
+        let max = if http { MAX_HTTP_PEEK_PAGE_SIZE } else { MAX_AMQP_PEEK_PAGE_SIZE };
+        if page_size == 0 || page_size > max {
+            let transport = if http { "HTTP" } else { "AMQP" };
+            return Err(format!("Peek page size must be between 1 and {} over {}", max, transport));
         }
-        // Note: azservicebus SDK doesn't currently support setting application properties
-        // directly on ServiceBusMessage. If application properties are needed, they would
-        // need to be included in the message body or the SDK would need to be updated.
-        // For now, we skip setting application_properties from the message.
-
-        // Send the message
-        sender
-            .send_message(sdk_message)
-            .await
-            .map_err(|e| format!("Failed to send message: {}", e))?;
+    }
+    Ok(())
+}
 
-        eprintln!("[send_message] Message sent successfully");
+// Service Bus reserves names starting with $ for its own entities ($management, $cbs)
+// and sub-queues ($deadletterqueue, $Transfer)
+pub fn is_system_entity(path: &str) -> bool {
+    path.split('/').any(|segment| segment.starts_with('$'))
+}
 
-        // Cleanup
-        sender.dispose().await.map_err(|e| format!("Failed to dispose sender: {}", e))?;
-        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+fn without_system_entities<T>(mut items: Vec<T>, name: impl Fn(&T) -> &String) -> Vec<T> {
+    items.retain(|item| !is_system_entity(name(item)));
+    items
+}
 
-        Ok(())
+// Bulk and destructive operations take plain entity names. Sub-queues are reached
+// through the dead-letter flags and the DLQ/transfer-specific commands instead, so a
+// path like "orders/$deadletterqueue" can't slip into them.
+fn check_not_system_entity(operation: &str, path: &str) -> Result<(), String> {
+    if !is_system_entity(path) {
+        return Ok(());
     }
+    Err(CatalogError::new("system_entity", &[("operation", operation), ("entity", path)]).to_string())
+}
 
-    // Purge queue by receiving and completing messages using azservicebus SDK
-    pub async fn purge_queue(&self, queue_name: &str, purge_dead_letter: bool) -> Result<u32, String> {
-        use azservicebus::prelude::*;
-        
-        let connection_string = if let Some(ref parsed) = self.parsed_connection {
-            // Reconstruct connection string from parsed components
-            format!(
-                "Endpoint=sb://{}{}/;SharedAccessKeyName={};SharedAccessKey={}",
-                self.namespace,
-                self.endpoint_domain,
-                parsed.shared_access_key_name,
-                parsed.shared_access_key
-            )
-        } else {
-            return Err("Connection string not available for SDK".to_string());
-        };
+fn check_source_not_system_entity(operation: &str, source: &MessageSource) -> Result<(), String> {
+    [&source.queue_name, &source.topic_name, &source.subscription_name]
+        .into_iter()
+        .flatten()
+        .try_for_each(|name| check_not_system_entity(operation, name))
+}
 
-        // Create ServiceBus client with longer timeout to ensure we can receive existing messages
-        use azservicebus::ServiceBusRetryOptions;
-        let mut client_options = ServiceBusClientOptions::default();
-        // Increase try_timeout to ensure receive_messages has enough time to get existing messages
-        // Default might be too short and cause timeouts before existing messages are returned
-        client_options.retry_options = ServiceBusRetryOptions {
-            try_timeout: Duration::from_secs(30), // 30 second timeout for operations
-            ..ServiceBusRetryOptions::default()
-        };
-        
-        let mut client = ServiceBusClient::new_from_connection_string(
-            &connection_string,
-            client_options,
-        )
-        .await
-        .map_err(|e| format!("Failed to create ServiceBus client: {}", e))?;
+// Entity path for a message source, including the $deadletterqueue suffix
+fn source_entity_path(source: &MessageSource) -> Result<String, String> {
+    let path = message_entity_path(
+        source.queue_name.as_deref(),
+        source.topic_name.as_deref(),
+        source.subscription_name.as_deref(),
+    )?;
+    Ok(if source.dead_letter {
+        format!("{}/$deadletterqueue", path)
+    } else {
+        path
+    })
+}
 
-        // Create receiver options with subQueue for dead letter if needed
-        // Use ReceiveAndDelete mode for purging - messages are automatically deleted when received
-        // This is more efficient than PeekLock + complete, and avoids issues with receive_messages timing out
-        let receiver_options = if purge_dead_letter {
-            ServiceBusReceiverOptions {
-                sub_queue: azservicebus::SubQueue::DeadLetter,
-                receive_mode: azservicebus::ServiceBusReceiveMode::ReceiveAndDelete,
-                prefetch_count: 0, // No prefetch - we'll receive explicitly
-                identifier: None,
-            }
-        } else {
-            ServiceBusReceiverOptions {
-                receive_mode: azservicebus::ServiceBusReceiveMode::ReceiveAndDelete,
-                prefetch_count: 0, // No prefetch - we'll receive explicitly
-                sub_queue: azservicebus::SubQueue::None,
-                identifier: None,
-            }
-        };
+// Settlement applied by settle_by_sequence_number once the target message is locked
+enum LockedAction {
+    DeadLetter { reason: String, description: Option<String> },
+    Defer,
+}
 
-        // Create receiver for the queue (or dead letter queue)
-        let mut receiver = client
-            .create_receiver_for_queue(queue_name, receiver_options)
-            .await
-            .map_err(|e| format!("Failed to create receiver: {}", e))?;
-        
-        // First, peek to verify there are messages to purge
-        let peek_result = receiver.peek_messages(10, None).await;
-        match peek_result {
-            Ok(peeked_msgs) => {
-                if peeked_msgs.is_empty() {
-                    return Ok(0);
-                }
-            },
-            Err(_) => {
-                // Continue even if peek fails - will try to receive anyway
-            }
+impl LockedAction {
+    fn name(&self) -> &'static str {
+        match self {
+            LockedAction::DeadLetter { .. } => "dead_letter_message",
+            LockedAction::Defer => "defer_message",
         }
+    }
 
-        let mut purged_count = 0u32;
-        let batch_size = 100u32; // Process messages in batches of 100
-        let max_iterations = 100u32; // Safety limit to prevent infinite loops
-        let mut iteration = 0u32;
-        let mut consecutive_empty_receives = 0u32;
-        let max_consecutive_empty = 3u32; // Stop after 3 consecutive empty receives
-
-        // Keep receiving and completing messages until no more are available
-        // Strategy: Try receiving with a short timeout first to get existing messages
-        // If that times out, try one more time with a longer timeout to catch any in-flight messages
-        loop {
-            iteration += 1;
-            if iteration > max_iterations {
-                break;
-            }
-            
-            // Use a timeout to prevent hanging, but make it long enough to get existing messages
-            let receive_result = tokio::time::timeout(
-                Duration::from_secs(5),
-                receiver.receive_messages(batch_size)
-            ).await;
-            
-            let messages = match receive_result {
-                Ok(Ok(msgs)) => {
-                    if msgs.is_empty() {
-                        consecutive_empty_receives += 1;
-                    } else {
-                        consecutive_empty_receives = 0; // Reset on success
-                    }
-                    msgs
-                },
-                Ok(Err(e)) => {
-                    return Err(format!("Failed to receive messages: {}", e));
-                },
-                Err(_) => {
-                    // Timeout - no messages available
-                    consecutive_empty_receives += 1;
-                    
-                    if consecutive_empty_receives >= max_consecutive_empty {
-                        break;
-                    }
-                    continue;
-                }
-            };
+    // Operation name for dry run
+    fn label(&self) -> &'static str {
+        match self {
+            LockedAction::DeadLetter { .. } => "dead-letter message",
+            LockedAction::Defer => "defer message",
+        }
+    }
+}
 
-            if messages.is_empty() {
-                if consecutive_empty_receives >= max_consecutive_empty {
-                    break;
-                }
+// Convert a message received under lock into our message type
+fn received_message_to_message(
+    received: &azservicebus::prelude::ServiceBusReceivedMessage,
+) -> Result<ServiceBusMessage, String> {
+    use azservicebus::ServiceBusMessageState;
+
+    let body_bytes = received.body().map_err(|e| format!("Failed to get message body: {}", e))?;
+    let DecodedBody { body, body_encoding, body_hex_preview, body_envelope, .. } = decode_peeked_body(body_bytes, None);
+    let state = match received.state() {
+        ServiceBusMessageState::Active => "Active",
+        ServiceBusMessageState::Deferred => "Deferred",
+        ServiceBusMessageState::Scheduled => "Scheduled",
+    };
+
+    let mut message = ServiceBusMessage {
+        body,
+        message_id: received.message_id().as_ref().map(|id| id.to_string()),
+        correlation_id: received.correlation_id().as_ref().map(|id| id.to_string()),
+        content_type: received.content_type().map(|ct| ct.to_string()),
+        sequence_number: Some(received.sequence_number() as u64),
+        subject: received.subject().map(|s| s.to_string()),
+        reply_to: received.reply_to().map(|r| r.to_string()),
+        reply_to_session_id: received.reply_to_session_id().map(|s| s.to_string()),
+        session_id: received.session_id().map(|s| s.to_string()),
+        time_to_live: received.time_to_live().map(|ttl| ttl.as_secs()),
+        to: received.to().map(|t| t.to_string()),
+        application_properties: received.application_properties().map(application_properties_to_json),
+        delivery_count: received.delivery_count(),
+        enqueued_time_utc: Some(timestamps::from_offset_date_time(received.enqueued_time())),
+        locked_until_utc: Some(timestamps::from_offset_date_time(received.locked_until())),
+        dead_letter_reason: received.dead_letter_reason().map(|r| r.to_string()),
+        dead_letter_error_description: received.dead_letter_error_description().map(|d| d.to_string()),
+        dead_letter_source: received.dead_letter_source().map(|s| s.to_string()),
+        scheduled_enqueue_time_utc: None,
+        partition_key: received.partition_key().map(|k| k.to_string()),
+        via_partition_key: received.transaction_partition_key().map(|k| k.to_string()),
+        state: Some(state.to_string()),
+        body_size: None,
+        body_truncated: None,
+        body_encoding,
+        body_hex_preview,
+        body_envelope,
+        body_compression: None,
+        raw_body: None,
+        decoded_body: None,
+        decode_error: None,
+        parse_warning: None,
+        expires_at_utc: None,
+    };
+    decompress_body(&mut message);
+    Ok(message)
+}
+
+// Application properties the service adds when dead-lettering; dropped on resubmit
+const DEAD_LETTER_PROPERTIES: &[&str] = &["DeadLetterReason", "DeadLetterErrorDescription"];
+
+// Build an outgoing copy of a received message (body, system and application properties).
+// Messages leaving a DLQ lose their dead-letter properties so they look like fresh sends.
+fn forward_message(
+    received: &azservicebus::prelude::ServiceBusReceivedMessage,
+    from_dead_letter: bool,
+) -> Result<azservicebus::prelude::ServiceBusMessage, String> {
+    let body = received.body().map_err(|e| format!("Failed to get message body: {}", e))?;
+    let mut outgoing = azservicebus::prelude::ServiceBusMessage::new(body.to_vec());
+
+    if let Some(message_id) = received.message_id() {
+        outgoing
+            .set_message_id(message_id.to_string())
+            .map_err(|e| format!("Failed to set message_id: {}", e))?;
+    }
+    if let Some(content_type) = received.content_type() {
+        outgoing.set_content_type(content_type.to_string());
+    }
+    if let Some(correlation_id) = received.correlation_id() {
+        outgoing.set_correlation_id(correlation_id.to_string());
+    }
+    if let Some(session_id) = received.session_id() {
+        outgoing
+            .set_session_id(session_id.to_string())
+            .map_err(|e| format!("Failed to set session_id: {}", e))?;
+    }
+    if let Some(reply_to) = received.reply_to() {
+        outgoing.set_reply_to(reply_to.to_string());
+    }
+    if let Some(reply_to_session_id) = received.reply_to_session_id() {
+        outgoing
+            .set_reply_to_session_id(reply_to_session_id.to_string())
+            .map_err(|e| format!("Failed to set reply_to_session_id: {}", e))?;
+    }
+    if let Some(subject) = received.subject() {
+        outgoing.set_subject(subject.to_string());
+    }
+    if let Some(to) = received.to() {
+        outgoing.set_to(to.to_string());
+    }
+    if let Some(partition_key) = received.partition_key() {
+        outgoing
+            .set_partition_key(partition_key.to_string())
+            .map_err(|e| format!("Failed to set partition_key: {}", e))?;
+    }
+    if let Some(ttl) = received.time_to_live() {
+        outgoing
+            .set_time_to_live(ttl)
+            .map_err(|e| format!("Failed to set time_to_live: {}", e))?;
+    }
+    if let Some(properties) = received.application_properties() {
+        let target = outgoing.application_properties_mut();
+        for (key, value) in properties.0.iter() {
+            if from_dead_letter && DEAD_LETTER_PROPERTIES.contains(&key.as_str()) {
                 continue;
             }
+            target.0.insert(key.clone(), value.clone());
+        }
+    }
+
+    Ok(outgoing)
+}
+
+// Convert a JSON application property to the AMQP value the SDK sends
+fn json_to_simple_value(key: &str, value: &serde_json::Value) -> Result<azservicebus::SimpleValue, String> {
+    use azservicebus::SimpleValue;
+    Ok(match value {
+        serde_json::Value::String(s) => SimpleValue::from(s.clone()),
+        serde_json::Value::Bool(b) => SimpleValue::from(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => SimpleValue::from(i),
+            None => SimpleValue::from(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::Null => SimpleValue::Null,
+        _ => return Err(format!("Application property '{}' must be a string, number or boolean", key)),
+    })
+}
+
+fn application_properties_to_json(properties: &azservicebus::ApplicationProperties) -> serde_json::Value {
+    use azservicebus::SimpleValue;
+    let map = properties
+        .0
+        .iter()
+        .map(|(key, value)| {
+            let json = match value {
+                SimpleValue::Null => serde_json::Value::Null,
+                SimpleValue::Bool(b) => serde_json::json!(b),
+                SimpleValue::Int(i) => serde_json::json!(i),
+                SimpleValue::Long(i) => serde_json::json!(i),
+                SimpleValue::Uint(u) => serde_json::json!(u),
+                SimpleValue::Ulong(u) => serde_json::json!(u),
+                SimpleValue::Double(d) => serde_json::json!(f64::from(*d)),
+                SimpleValue::String(s) => serde_json::json!(s),
+                other => serde_json::json!(format!("{:?}", other)),
+            };
+            (key.clone(), json)
+        })
+        .collect();
+    serde_json::Value::Object(map)
+}
+
+// Headers the REST API sends that are not custom message properties
+const STANDARD_RESPONSE_HEADERS: &[&str] = &[
+    "brokerproperties",
+    "content-type",
+    "content-length",
+    "date",
+    "server",
+    "transfer-encoding",
+    "location",
+    "strict-transport-security",
+    "connection",
+    "cache-control",
+    "expires",
+    "pragma",
+    "etag",
+    "vary",
+];
+
+// The REST data plane returns each custom property as its own header. Strings (and
+// dates) are quoted, numbers and booleans are not.
+fn parse_application_property_headers(headers: &reqwest::header::HeaderMap) -> Option<serde_json::Value> {
+    let mut properties = serde_json::Map::new();
+    for (name, value) in headers {
+        let name = name.as_str();
+        if STANDARD_RESPONSE_HEADERS.contains(&name) || name.starts_with("x-ms-") || name.starts_with("access-control-") {
+            continue;
+        }
+        let Ok(raw) = value.to_str() else { continue };
+        let raw = raw.trim();
+        let parsed = if raw.len() >= 2 && raw.starts_with('"') && raw.ends_with('"') {
+            serde_json::Value::String(raw[1..raw.len() - 1].replace("\\\"", "\""))
+        } else if let Ok(b) = raw.parse::<bool>() {
+            serde_json::Value::Bool(b)
+        } else if let Ok(n) = raw.parse::<serde_json::Number>() {
+            serde_json::Value::Number(n)
+        } else {
+            serde_json::Value::String(raw.to_string())
+        };
+        properties.insert(name.to_string(), parsed);
+    }
+    (!properties.is_empty()).then_some(serde_json::Value::Object(properties))
+}
+
+// The REST receive endpoints return system properties as a JSON BrokerProperties header
+fn parse_broker_properties(headers: &reqwest::header::HeaderMap) -> Option<serde_json::Value> {
+    headers
+        .get("BrokerProperties")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok())
+}
+
+// Build a message from a REST receive response (body + BrokerProperties header)
+fn rest_message_from_response(body: &[u8], headers: &reqwest::header::HeaderMap) -> ServiceBusMessage {
+    let DecodedBody { body, body_encoding, body_hex_preview, body_envelope, .. } = decode_peeked_body(body, None);
+
+    let mut message = ServiceBusMessage {
+        body,
+        message_id: None,
+        content_type: headers
+            .get("Content-Type")
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.to_string()),
+        correlation_id: None,
+        session_id: None,
+        reply_to: None,
+        reply_to_session_id: None,
+        subject: None,
+        time_to_live: None,
+        to: None,
+        application_properties: parse_application_property_headers(headers),
+        delivery_count: None,
+        enqueued_time_utc: None,
+        locked_until_utc: None,
+        sequence_number: None,
+        dead_letter_reason: None,
+        dead_letter_error_description: None,
+        dead_letter_source: None,
+        scheduled_enqueue_time_utc: None,
+        partition_key: None,
+        via_partition_key: None,
+        state: None,
+        body_size: None,
+        body_truncated: None,
+        body_encoding,
+        body_hex_preview,
+        body_envelope,
+        body_compression: None,
+        raw_body: None,
+        decoded_body: None,
+        decode_error: None,
+        parse_warning: None,
+        expires_at_utc: None,
+    };
+    if let Some(props) = parse_broker_properties(headers) {
+        apply_broker_properties(&mut message, &props);
+    }
+    decompress_body(&mut message);
+    message
+}
 
-            // In ReceiveAndDelete mode, messages are automatically deleted when received
-            // No need to complete them - just count them
-            purged_count += messages.len() as u32;
+// Copy the system properties from a BrokerProperties JSON object onto a message.
+// Fields missing from the JSON keep their current value.
+fn apply_broker_properties(message: &mut ServiceBusMessage, props: &serde_json::Value) {
+    let set = |field: &mut Option<String>, name: &str| {
+        if let Some(value) = props.get(name).and_then(|v| v.as_str()) {
+            *field = Some(value.to_string());
         }
+    };
+
+    set(&mut message.message_id, "MessageId");
+    set(&mut message.correlation_id, "CorrelationId");
+    set(&mut message.session_id, "SessionId");
+    set(&mut message.reply_to, "ReplyTo");
+    set(&mut message.reply_to_session_id, "ReplyToSessionId");
+    set(&mut message.subject, "Label");
+    set(&mut message.to, "To");
+    set(&mut message.partition_key, "PartitionKey");
+    set(&mut message.via_partition_key, "ViaPartitionKey");
+    set(&mut message.enqueued_time_utc, "EnqueuedTimeUtc");
+    set(&mut message.locked_until_utc, "LockedUntilUtc");
+    set(&mut message.scheduled_enqueue_time_utc, "ScheduledEnqueueTimeUtc");
+    set(&mut message.dead_letter_reason, "DeadLetterReason");
+    set(&mut message.dead_letter_error_description, "DeadLetterErrorDescription");
+    set(&mut message.dead_letter_source, "DeadLetterSource");
+    set(&mut message.state, "State");
+    if let Some(v) = props.get("SequenceNumber").and_then(|v| v.as_u64()) {
+        message.sequence_number = Some(v);
+    }
+    if let Some(v) = props.get("DeliveryCount").and_then(|v| v.as_u64()) {
+        message.delivery_count = Some(v as u32);
+    }
+    // Seconds, possibly fractional
+    if let Some(v) = props.get("TimeToLive").and_then(|v| v.as_f64()) {
+        message.time_to_live = Some(v as u64);
+    }
+}
 
-        // Cleanup
-        receiver.dispose().await.map_err(|e| format!("Failed to dispose receiver: {}", e))?;
-        client.dispose().await.map_err(|e| format!("Failed to dispose client: {}", e))?;
+// A message removed by a receive-and-delete call: raw body plus response headers
+pub(crate) struct RestReceivedMessage {
+    pub body: Vec<u8>,
+    pub headers: reqwest::header::HeaderMap,
+}
 
-        Ok(purged_count)
-    }
+// Called with every batch drain_messages takes off the entity
+pub type OnDrainedBatch = std::sync::Arc<dyn Fn(&[ServiceBusMessage]) + Send + Sync>;
 
-    // Test connection by attempting to list queues (uses REST API)
-    pub async fn test_connection(&self) -> Result<bool, String> {
-        // Test by trying to list queues (limited to 1)
-        match self.list_queues().await {
-            Ok(_) => Ok(true),
-            Err(e) => {
-                eprintln!("Connection test failed: {}", e);
-                Ok(false)
-            }
+// One drain batch: batch_size concurrent receive-and-delete calls, handed to on_batch in
+// sequence order once all of them are done. Returns the batch and the last error.
+async fn drain_batch(
+    client: Client,
+    url: String,
+    auth_header: String,
+    batch_size: u32,
+    on_batch: OnDrainedBatch,
+) -> (Vec<ServiceBusMessage>, Option<String>) {
+    let mut tasks = tokio::task::JoinSet::new();
+    for _ in 0..batch_size {
+        tasks.spawn(receive_and_delete_head(client.clone(), url.clone(), auth_header.clone()));
+    }
+    let mut batch = Vec::new();
+    let mut error = None;
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(Ok(Some(received))) => batch.push(rest_message_from_response(&received.body, &received.headers)),
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => error = Some(e),
+            Err(e) => error = Some(format!("Receive task failed: {}", e)),
         }
     }
+    // Concurrent calls complete in any order
+    batch.sort_by_key(|message| message.sequence_number);
+    if !batch.is_empty() {
+        on_batch(&batch);
+    }
+    (batch, error)
+}
 
-    // Helper methods for XML parsing and generation
-    fn queue_entry_to_properties(&self, entry: &QueueEntry) -> Result<QueueProperties, String> {
-        // Helper function to parse ISO 8601 duration to seconds
-        fn duration_to_seconds(duration_str: &str) -> Option<u64> {
-            // Parse PT30S, PT1M, PT1H30M, etc.
-            let re = regex::Regex::new(r#"PT(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)S)?"#).ok()?;
-            let cap = re.captures(duration_str)?;
-            let hours: u64 = cap.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
-            let minutes: u64 = cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
-            let seconds: u64 = cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
-            Some(hours * 3600 + minutes * 60 + seconds)
-        }
-        
-        // Parse message counts from content XML
-        let mut message_count: Option<u64> = None;
-        let mut active_message_count: Option<u64> = None;
-        let mut dead_letter_message_count: Option<u64> = None;
-        let mut scheduled_message_count: Option<u64> = None;
-        let mut transfer_message_count: Option<u64> = None;
-        let mut transfer_dead_letter_message_count: Option<u64> = None;
-        
-        // Parse queue properties from content XML
-        let mut max_size_in_megabytes: Option<u64> = None;
-        let mut lock_duration_in_seconds: Option<u64> = None;
-        let mut max_delivery_count: Option<u32> = None;
-        let mut default_message_time_to_live_in_seconds: Option<u64> = None;
-        let mut dead_lettering_on_message_expiration: Option<bool> = None;
-        let mut duplicate_detection_history_time_window_in_seconds: Option<u64> = None;
-        let mut enable_batched_operations: Option<bool> = None;
-        let mut enable_partitioning: Option<bool> = None;
-        let mut requires_session: Option<bool> = None;
-        let mut requires_duplicate_detection: Option<bool> = None;
-        let mut size_in_bytes: Option<u64> = None;
-        
-        if let Some(ref content) = entry.content {
-            // Extract counts from CountDetails using regex
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:ActiveMessageCount>(\d+)</d2p1:ActiveMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                active_message_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:DeadLetterMessageCount>(\d+)</d2p1:DeadLetterMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                dead_letter_message_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:ScheduledMessageCount>(\d+)</d2p1:ScheduledMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                scheduled_message_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:TransferMessageCount>(\d+)</d2p1:TransferMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                transfer_message_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:TransferDeadLetterMessageCount>(\d+)</d2p1:TransferDeadLetterMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                transfer_dead_letter_message_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<MessageCount>(\d+)</MessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                message_count = cap[1].parse().ok();
-            }
-            
-            // Parse queue properties
-            if let Some(cap) = regex::Regex::new(r#"<MaxSizeInMegabytes>(\d+)</MaxSizeInMegabytes>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                max_size_in_megabytes = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<LockDuration>(.*?)</LockDuration>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                lock_duration_in_seconds = duration_to_seconds(&cap[1]);
-            }
-            if let Some(cap) = regex::Regex::new(r#"<MaxDeliveryCount>(\d+)</MaxDeliveryCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                max_delivery_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<DefaultMessageTimeToLive>(.*?)</DefaultMessageTimeToLive>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                default_message_time_to_live_in_seconds = duration_to_seconds(&cap[1]);
-            }
-            if let Some(cap) = regex::Regex::new(r#"<EnableDeadLetteringOnMessageExpiration>(true|false)</EnableDeadLetteringOnMessageExpiration>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                dead_lettering_on_message_expiration = Some(cap.get(1).map(|m| m.as_str() == "true").unwrap_or(false));
-            }
-            if let Some(cap) = regex::Regex::new(r#"<DuplicateDetectionHistoryTimeWindow>(.*?)</DuplicateDetectionHistoryTimeWindow>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                duplicate_detection_history_time_window_in_seconds = cap.get(1).and_then(|m| duration_to_seconds(m.as_str()));
-            }
-            if let Some(cap) = regex::Regex::new(r#"<EnableBatchedOperations>(true|false)</EnableBatchedOperations>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                enable_batched_operations = Some(cap.get(1).map(|m| m.as_str() == "true").unwrap_or(false));
-            }
-            if let Some(cap) = regex::Regex::new(r#"<EnablePartitioning>(true|false)</EnablePartitioning>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                enable_partitioning = Some(cap.get(1).map(|m| m.as_str() == "true").unwrap_or(false));
-            }
-            if let Some(cap) = regex::Regex::new(r#"<RequiresSession>(true|false)</RequiresSession>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                requires_session = Some(cap.get(1).map(|m| m.as_str() == "true").unwrap_or(false));
-            }
-            if let Some(cap) = regex::Regex::new(r#"<RequiresDuplicateDetection>(true|false)</RequiresDuplicateDetection>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                requires_duplicate_detection = Some(cap.get(1).map(|m| m.as_str() == "true").unwrap_or(false));
-            }
-            if let Some(cap) = regex::Regex::new(r#"<SizeInBytes>(\d+)</SizeInBytes>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                size_in_bytes = cap[1].parse().ok();
-            }
-        }
-        
-        Ok(QueueProperties {
-            name: entry.title.clone(),
-            max_size_in_megabytes,
-            lock_duration_in_seconds,
-            max_delivery_count,
-            default_message_time_to_live_in_seconds,
-            dead_lettering_on_message_expiration,
-            duplicate_detection_history_time_window_in_seconds,
-            enable_batched_operations,
-            enable_partitioning,
-            requires_session,
-            requires_duplicate_detection,
-            message_count,
-            active_message_count,
-            dead_letter_message_count,
-            scheduled_message_count,
-            transfer_message_count,
-            transfer_dead_letter_message_count,
-            size_in_bytes,
-        })
+// Single receive-and-delete call (DELETE /{entity}/messages/head).
+// Returns None when the entity is empty (204 No Content or an empty body).
+// Takes owned values so calls can run concurrently on a JoinSet.
+async fn receive_and_delete_head(client: Client, url: String, auth_header: String) -> Result<Option<RestReceivedMessage>, String> {
+    let response = client
+        .delete(&url)
+        .header("Authorization", &auth_header)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to receive message: {}", e))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NO_CONTENT {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(http_error("receive message", status, &error_text));
     }
 
-    fn queue_properties_to_xml(&self, queue_name: &str, properties: Option<&QueueProperties>, is_update: bool) -> Result<String, String> {
-        // Helper function to convert seconds to ISO 8601 duration (PT30S format)
-        fn seconds_to_duration(seconds: u64) -> String {
-            if seconds < 60 {
-                format!("PT{}S", seconds)
-            } else if seconds < 3600 {
-                let minutes = seconds / 60;
-                let secs = seconds % 60;
-                if secs == 0 {
-                    format!("PT{}M", minutes)
-                } else {
-                    format!("PT{}M{}S", minutes, secs)
-                }
-            } else {
-                let hours = seconds / 3600;
-                let remainder = seconds % 3600;
-                let minutes = remainder / 60;
-                let secs = remainder % 60;
-                if minutes == 0 && secs == 0 {
-                    format!("PT{}H", hours)
-                } else if secs == 0 {
-                    format!("PT{}H{}M", hours, minutes)
-                } else {
-                    format!("PT{}H{}M{}S", hours, minutes, secs)
-                }
-            }
-        }
-        
-        let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?><entry xmlns="http://www.w3.org/2005/Atom"><title>"#);
-        xml.push_str(queue_name);
-        xml.push_str(r#"</title><content type="application/xml"><QueueDescription xmlns="http://schemas.microsoft.com/netservices/2010/10/servicebus/connect">"#);
-        
-        if let Some(props) = properties {
-            if let Some(max_size) = props.max_size_in_megabytes {
-                xml.push_str(&format!("<MaxSizeInMegabytes>{}</MaxSizeInMegabytes>", max_size));
-            }
-            if let Some(lock_duration) = props.lock_duration_in_seconds {
-                xml.push_str(&format!("<LockDuration>{}</LockDuration>", seconds_to_duration(lock_duration)));
-            }
-            if let Some(max_delivery) = props.max_delivery_count {
-                xml.push_str(&format!("<MaxDeliveryCount>{}</MaxDeliveryCount>", max_delivery));
-            }
-            if let Some(ttl) = props.default_message_time_to_live_in_seconds {
-                xml.push_str(&format!("<DefaultMessageTimeToLive>{}</DefaultMessageTimeToLive>", seconds_to_duration(ttl)));
-            }
-            if let Some(dead_letter) = props.dead_lettering_on_message_expiration {
-                xml.push_str(&format!("<EnableDeadLetteringOnMessageExpiration>{}</EnableDeadLetteringOnMessageExpiration>", dead_letter));
-            }
-            if let Some(dup_window) = props.duplicate_detection_history_time_window_in_seconds {
-                xml.push_str(&format!("<DuplicateDetectionHistoryTimeWindow>{}</DuplicateDetectionHistoryTimeWindow>", seconds_to_duration(dup_window)));
-            }
-            if let Some(batched) = props.enable_batched_operations {
-                xml.push_str(&format!("<EnableBatchedOperations>{}</EnableBatchedOperations>", batched));
-            }
-            // Immutable properties - only include when creating, not when updating
-            if !is_update {
-                if let Some(partitioning) = props.enable_partitioning {
-                    xml.push_str(&format!("<EnablePartitioning>{}</EnablePartitioning>", partitioning));
-                }
-                if let Some(session) = props.requires_session {
-                    xml.push_str(&format!("<RequiresSession>{}</RequiresSession>", session));
-                }
-                if let Some(dup_detection) = props.requires_duplicate_detection {
-                    xml.push_str(&format!("<RequiresDuplicateDetection>{}</RequiresDuplicateDetection>", dup_detection));
-                }
-            }
-        }
-        
-        xml.push_str(r#"</QueueDescription></content></entry>"#);
-        
-        Ok(xml)
+    let headers = response.headers().clone();
+    let body = response.bytes().await.map_err(|e| format!("Failed to read response: {}", e))?.to_vec();
+    if body.iter().all(u8::is_ascii_whitespace) && !headers.contains_key("BrokerProperties") {
+        return Ok(None);
     }
+    Ok(Some(RestReceivedMessage { body, headers }))
+}
 
-    fn topic_entry_to_properties(&self, entry: &TopicEntry) -> Result<TopicProperties, String> {
-        Ok(TopicProperties {
-            name: entry.title.clone(),
-            max_size_in_megabytes: None,
-            default_message_time_to_live_in_seconds: None,
-            duplicate_detection_history_time_window_in_seconds: None,
-            enable_batched_operations: None,
-            enable_partitioning: None,
-            requires_duplicate_detection: None,
-            size_in_bytes: None,
-            subscription_count: None,
-        })
+// Empty feed handling
+// The service signals "nothing here" in several ways: 204 No Content, an empty body,
+// or a feed with no <entry> elements that serde_xml_rs can't always deserialize.
+// All list/peek paths go through these helpers so empty namespaces and queues
+// return empty vectors instead of "Failed to parse XML" errors.
+fn is_empty_body(body: &str) -> bool {
+    body.trim().is_empty()
+}
+
+fn parse_feed<T>(xml: &str) -> Result<T, String>
+where
+    T: serde::de::DeserializeOwned + Default,
+{
+    if is_empty_body(xml) {
+        return Ok(T::default());
     }
+    match from_str::<T>(xml) {
+        Ok(feed) => Ok(feed),
+        Err(_) if !xml.contains("<entry") => Ok(T::default()),
+        Err(e) => Err(format!("Failed to parse XML: {}", e)),
+    }
+}
 
-    fn topic_properties_to_xml(&self, topic_name: &str, _properties: Option<&TopicProperties>) -> Result<String, String> {
-        Ok(format!(r#"<?xml version="1.0" encoding="utf-8"?><entry xmlns="http://www.w3.org/2005/Atom"><title>{}</title></entry>"#, topic_name))
+// The <entry> elements of a feed that failed to parse as a whole, each parsed on its own;
+// the ones that still fail are read with raw_message_entry
+fn lenient_feed_entries(feed_xml: &str) -> Vec<MessageEntry> {
+    let Ok(entry_regex) = regex::Regex::new(r"(?s)<entry\b[^>]*>.*?</entry>") else {
+        return Vec::new();
+    };
+    entry_regex
+        .find_iter(feed_xml)
+        .map(|entry| {
+            from_str::<MessageEntry>(entry.as_str()).unwrap_or_else(|e| raw_message_entry(entry.as_str(), &e.to_string()))
+        })
+        .collect()
+}
+
+// What can still be read from an entry the XML parser rejects: the text of its content
+// and property elements
+fn raw_message_entry(entry_xml: &str, error: &str) -> MessageEntry {
+    let element = |name: &str| {
+        regex::Regex::new(&format!(r"(?s)<(?:\w+:)?{0}\b[^>]*>(.*?)</(?:\w+:)?{0}>", name))
+            .ok()
+            .and_then(|re| re.captures(entry_xml).map(|cap| unescape_xml(&cap[1])))
+    };
+    MessageEntry {
+        title: None,
+        content: element("content"),
+        broker_properties: element("BrokerProperties"),
+        user_properties: element("UserProperties"),
+        message_id: element("MessageId"),
+        correlation_id: element("CorrelationId"),
+        content_type: element("ContentType"),
+        sequence_number: element("SequenceNumber").and_then(|seq| seq.trim().parse().ok()),
+        parse_warning: Some(format!("The entry is not valid XML ({}); showing its raw content", error)),
     }
+}
 
-    fn subscription_entry_to_properties(&self, topic_name: &str, entry: &SubscriptionEntry) -> Result<SubscriptionProperties, String> {
-        // Parse message counts from content XML
-        let mut message_count: Option<u64> = None;
-        let mut active_message_count: Option<u64> = None;
-        let mut dead_letter_message_count: Option<u64> = None;
-        let mut transfer_message_count: Option<u64> = None;
-        let mut transfer_dead_letter_message_count: Option<u64> = None;
-        
-        if let Some(ref content) = entry.content {
-            // Extract counts from CountDetails using regex
-            // Format: <d2p1:ActiveMessageCount>0</d2p1:ActiveMessageCount>
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:ActiveMessageCount>(\d+)</d2p1:ActiveMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                active_message_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:DeadLetterMessageCount>(\d+)</d2p1:DeadLetterMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                dead_letter_message_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:TransferMessageCount>(\d+)</d2p1:TransferMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                transfer_message_count = cap[1].parse().ok();
-            }
-            if let Some(cap) = regex::Regex::new(r#"<d2p1:TransferDeadLetterMessageCount>(\d+)</d2p1:TransferDeadLetterMessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                transfer_dead_letter_message_count = cap[1].parse().ok();
-            }
-            // Also check for MessageCount (total)
-            if let Some(cap) = regex::Regex::new(r#"<MessageCount>(\d+)</MessageCount>"#)
-                .ok()
-                .and_then(|re| re.captures(content))
-            {
-                message_count = cap[1].parse().ok();
-            }
+// Entry content as the sender meant it: a CDATA wrapper the parser left in place is
+// removed, and content that was XML-escaped twice is unescaped when that makes it JSON
+fn clean_entry_content(content: String) -> (String, Option<String>) {
+    let trimmed = content.trim();
+    if let Some(inner) = trimmed.strip_prefix("<![CDATA[").and_then(|rest| rest.strip_suffix("]]>")) {
+        return (inner.to_string(), None);
+    }
+    if content.contains('&') && serde_json::from_str::<serde_json::Value>(&content).is_err() {
+        let unescaped = unescape_xml(&content);
+        if serde_json::from_str::<serde_json::Value>(&unescaped).is_ok() {
+            return (unescaped, Some("The body was XML-escaped and is shown unescaped".to_string()));
         }
-        
-        Ok(SubscriptionProperties {
-            topic_name: topic_name.to_string(),
-            subscription_name: entry.title.clone(),
-            max_delivery_count: None,
-            lock_duration_in_seconds: None,
-            default_message_time_to_live_in_seconds: None,
-            dead_lettering_on_message_expiration: None,
-            enable_batched_operations: None,
-            requires_session: None,
-            message_count,
-            active_message_count,
-            dead_letter_message_count,
-            transfer_message_count,
-            transfer_dead_letter_message_count,
-        })
     }
+    (content, None)
+}
 
-    fn subscription_properties_to_xml(&self, _topic_name: &str, subscription_name: &str, _properties: Option<&SubscriptionProperties>) -> Result<String, String> {
-        Ok(format!(r#"<?xml version="1.0" encoding="utf-8"?><entry xmlns="http://www.w3.org/2005/Atom"><title>{}</title></entry>"#, subscription_name))
+fn unescape_xml(text: &str) -> String {
+    let Ok(entity) = regex::Regex::new(r"&(#x[0-9A-Fa-f]+|#[0-9]+|lt|gt|quot|apos|amp);") else {
+        return text.to_string();
+    };
+    entity
+        .replace_all(text, |cap: &regex::Captures| {
+            let name = &cap[1];
+            let decoded = match name {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "amp" => Some('&'),
+                _ => match name.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => name[1..].parse().ok().and_then(char::from_u32),
+                },
+            };
+            decoded.map(String::from).unwrap_or_else(|| cap[0].to_string())
+        })
+        .into_owned()
+}
+
+// A text body that looks like JSON but isn't, most often a producer bug; left as text
+fn json_parse_warning(message: &ServiceBusMessage) -> Option<String> {
+    if message.body_encoding.is_some() || message.body_truncated == Some(true) {
+        return None;
+    }
+    if message.content_type.as_deref().is_some_and(|ct| !ct.contains("json")) {
+        return None;
     }
+    let text = message.body.as_str()?.trim();
+    if !(text.starts_with('{') || text.starts_with('[')) {
+        return None;
+    }
+    serde_json::from_str::<serde_json::Value>(text)
+        .err()
+        .map(|e| format!("The body looks like JSON but does not parse: {}", e))
 }
 
 // XML structures for parsing Azure Service Bus responses
 // These are used by the REST API implementation (peek_messages_rest)
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct QueueFeed {
     #[serde(rename = "entry", default)]
     entries: Vec<QueueEntry>,
@@ -1932,7 +6158,7 @@ struct QueueEntry {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct TopicFeed {
     #[serde(rename = "entry", default)]
     entries: Vec<TopicEntry>,
@@ -1941,13 +6167,15 @@ struct TopicFeed {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct TopicEntry {
     title: String,
+    #[serde(skip)]
+    content: Option<String>,
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct SubscriptionFeed {
     #[serde(rename = "entry", default)]
     entries: Vec<SubscriptionEntry>,
@@ -1955,7 +6183,7 @@ struct SubscriptionFeed {
     links: Vec<FeedLink>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct MessageFeed {
     #[serde(rename = "entry", default)]
     entries: Vec<MessageEntry>,
@@ -1970,6 +6198,8 @@ struct MessageEntry {
     content: Option<String>,
     #[serde(rename = "BrokerProperties", default)]
     broker_properties: Option<String>,
+    #[serde(rename = "UserProperties", default)]
+    user_properties: Option<String>,
     #[serde(rename = "MessageId", default)]
     message_id: Option<String>,
     #[serde(rename = "CorrelationId", default)]
@@ -1978,6 +6208,9 @@ struct MessageEntry {
     content_type: Option<String>,
     #[serde(rename = "SequenceNumber", default)]
     sequence_number: Option<i64>,
+    // Set for entries recovered by lenient_feed_entries
+    #[serde(skip)]
+    parse_warning: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,73 @@
+// Timestamps crossing the API: one parser for every form the service and the SDK hand
+// us, and one output form, RFC 3339 in UTC with milliseconds ("2024-05-01T12:00:00.123Z").
+// Converting to the user's display time zone (settings.displayTimeZone) only happens in
+// format_timestamps, never in the values the commands return.
+
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+
+// Enqueue times come as RFC 3339, as RFC 2822 (HTTP transport) or in the time crate's
+// display format ("2024-01-02 3:04:05.6 +00:00:00", AMQP peek)
+pub fn parse(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+        return Some(parsed.to_utc());
+    }
+    if let Ok(parsed) = DateTime::parse_from_rfc2822(value) {
+        return Some(parsed.to_utc());
+    }
+    // chrono can't parse an offset with seconds
+    let offset_seconds = regex::Regex::new(r"^(.+ [+-]\d{2}:\d{2}):\d{2}$").ok()?;
+    let value = offset_seconds.captures(value).map(|cap| cap[1].to_string()).unwrap_or_else(|| value.to_string());
+    DateTime::parse_from_str(&value, "%Y-%m-%d %H:%M:%S%.f %:z").ok().map(|parsed| parsed.to_utc())
+}
+
+pub fn format(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// The API form of a timestamp in any form parse accepts; anything else is kept as is
+pub fn normalize(value: &str) -> String {
+    parse(value).map(format).unwrap_or_else(|| value.to_string())
+}
+
+// SDK times (AMQP) in the API form
+pub fn from_offset_date_time(time: time::OffsetDateTime) -> String {
+    DateTime::from_timestamp(time.unix_timestamp(), time.nanosecond())
+        .map(format)
+        .unwrap_or_else(|| time.to_string())
+}
+
+// "local" (the machine's zone), "utc", or a fixed offset such as "+05:30"
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayTimeZone {
+    Local,
+    Utc,
+    Fixed(FixedOffset),
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+impl DisplayTimeZone {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("local") {
+            return Ok(DisplayTimeZone::Local);
+        }
+        if value.eq_ignore_ascii_case("utc") || value == "Z" {
+            return Ok(DisplayTimeZone::Utc);
+        }
+        value
+            .parse::<FixedOffset>()
+            .map(DisplayTimeZone::Fixed)
+            .map_err(|_| format!("Invalid time zone '{}': expected local, utc or an offset like +05:30", value))
+    }
+
+    // RFC 3339 with the zone's offset, so the result still parses back to the same instant
+    pub fn format(&self, time: DateTime<Utc>) -> String {
+        match self {
+            DisplayTimeZone::Local => time.with_timezone(&chrono::Local).to_rfc3339_opts(SecondsFormat::Millis, false),
+            DisplayTimeZone::Utc => format(time),
+            DisplayTimeZone::Fixed(offset) => time.with_timezone(offset).to_rfc3339_opts(SecondsFormat::Millis, false),
+        }
+    }
+}
@@ -17,11 +17,158 @@ pub struct ServiceBusConnection {
     pub client_id: Option<String>,
     pub created_at: i64,
     pub updated_at: i64,
+    // Computed when listing: the connection authenticates with the namespace owner key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uses_root_manage_key: Option<bool>,
+    // Training sandbox connection: operations run in memory, never against Azure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub training_mode: Option<bool>,
+    // Data-plane transport for peek/send/receive. Unset keeps the per-operation
+    // defaults (AMQP for peek and send, HTTP for receive).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transport: Option<Transport>,
+    // User-chosen accent color ("#rrggbb") shown next to the connection name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
 }
 
 #[allow(dead_code)] // Used by main app, not test binary
+impl ServiceBusConnection {
+    pub fn label(&self) -> ConnectionLabel {
+        ConnectionLabel {
+            connection_id: self.id.clone(),
+            name: self.name.clone(),
+            color: self.color.clone(),
+        }
+    }
+}
+
+// Identifies the connection a listing or monitor result came from, so views that mix
+// connections can label rows without looking the connection up again
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct ConnectionLabel {
+    pub connection_id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+// Entities that carry the label of the connection they were listed from
+#[allow(dead_code)] // Used by main app, not test binary
+pub trait ConnectionLabeled {
+    fn set_connection(&mut self, label: ConnectionLabel);
+}
+
+// AMQP goes through the azservicebus SDK; HTTP uses the REST data plane, which
+// works through proxies that block AMQP but lacks sessions and true peek paging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Amqp,
+    Http,
+}
+
+// What a connection's credential turned out to be allowed to do (probe_connection_access)
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionAccess {
+    // Listing entities and reading or changing their settings
+    pub can_manage: bool,
+    // Why management calls fail, when they do
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub management_error: Option<String>,
+    // EntityPath of an entity-scoped connection string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_path: Option<String>,
+    // "queue", "topic" or "subscription", when entity_path could be identified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_kind: Option<String>,
+    // Whether entity_path can be peeked; unknown for a topic, whose messages are only
+    // readable through a subscription
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub can_listen: Option<bool>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStringExplanation {
+    pub endpoint: String,
+    pub namespace: String,
+    pub endpoint_domain: String,
+    pub cloud: String,
+    pub policy_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_path: Option<String>,
+    // "namespace" or "entity"
+    pub scope: String,
+    pub is_root_manage_key: bool,
+    pub notes: Vec<String>,
+}
+
+// What a SharedAccessSignature token grants and whether it is still usable
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SasTokenInspection {
+    // Decoded sr: the token is valid for this URI and everything below it
+    pub resource_uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_path: Option<String>,
+    // skn
+    pub policy_name: String,
+    // se, in seconds since the Unix epoch
+    pub expiry: i64,
+    // se as RFC 3339
+    pub expires_at: String,
+    // Negative once expired
+    pub expires_in_seconds: i64,
+    pub expired: bool,
+    // None when no key was provided to check the signature against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_valid: Option<bool>,
+    pub notes: Vec<String>,
+}
+
+// Claims of an Azure AD access token that matter when Service Bus answers 401/403.
+// The signature is not verified - this is for troubleshooting only.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AadTokenInspection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    // Object id of the user, service principal or managed identity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    // upn / preferred_username / unique_name, whichever is present
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_name: Option<String>,
+    pub roles: Vec<String>,
+    pub scopes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    pub expired: bool,
+    pub notes: Vec<String>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct QueueProperties {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -38,12 +185,36 @@ pub struct QueueProperties {
     pub duplicate_detection_history_time_window_in_seconds: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enable_batched_operations: Option<bool>,
+    // The queue is deleted after being idle this long (minimum 300)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_delete_on_idle_in_seconds: Option<u64>,
+    // "Active", "Disabled", "SendDisabled" or "ReceiveDisabled"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enable_partitioning: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub requires_session: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub requires_duplicate_detection: Option<bool>,
+    // Premium namespaces only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_message_size_in_kilobytes: Option<u64>,
+    // On update an empty string removes the forward or the metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_dead_lettered_messages_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_metadata: Option<String>,
+    // Read from the service only, as it returns them (RFC 3339); accessed_at is absent
+    // until the queue is first used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessed_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message_count: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -58,10 +229,14 @@ pub struct QueueProperties {
     pub transfer_dead_letter_message_count: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub size_in_bytes: Option<u64>,
+    // Set on listing responses only; ignored on create/update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection: Option<ConnectionLabel>,
 }
 
 #[allow(dead_code)] // Used by main app, not test binary
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TopicProperties {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -76,14 +251,38 @@ pub struct TopicProperties {
     pub enable_partitioning: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub requires_duplicate_detection: Option<bool>,
+    // The topic is deleted after being idle this long (minimum 300)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_delete_on_idle_in_seconds: Option<u64>,
+    // "Active", "Disabled", "SendDisabled" or "ReceiveDisabled"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support_ordering: Option<bool>,
+    // Premium namespaces only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_message_size_in_kilobytes: Option<u64>,
+    // On update an empty string removes the metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_metadata: Option<String>,
+    // Read from the service only, as it returns them (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessed_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub size_in_bytes: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub subscription_count: Option<u64>,
+    // Set on listing responses only; ignored on create/update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection: Option<ConnectionLabel>,
 }
 
 #[allow(dead_code)] // Used by main app, not test binary
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubscriptionProperties {
     pub topic_name: String,
@@ -107,9 +306,101 @@ pub struct SubscriptionProperties {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dead_letter_message_count: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_message_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub transfer_message_count: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transfer_dead_letter_message_count: Option<u64>,
+    // Only filled in by list_subscriptions_page with include_rule_counts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_count: Option<u32>,
+    // Set on listing responses only; ignored on create/update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection: Option<ConnectionLabel>,
+}
+
+// An entity that auto-forwards to another one
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardReference {
+    // "queue" or "topic/Subscriptions/subscription"
+    pub entity_path: String,
+    // "ForwardTo" or "ForwardDeadLetteredMessagesTo"
+    pub property: String,
+    pub forward_to: String,
+}
+
+// One page of a topic's subscriptions; request the next one with skip + items.len()
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionPage {
+    pub topic_name: String,
+    pub skip: u32,
+    pub items: Vec<SubscriptionProperties>,
+    pub has_more: bool,
+}
+
+impl ConnectionLabeled for QueueProperties {
+    fn set_connection(&mut self, label: ConnectionLabel) {
+        self.connection = Some(label);
+    }
+}
+
+impl ConnectionLabeled for TopicProperties {
+    fn set_connection(&mut self, label: ConnectionLabel) {
+        self.connection = Some(label);
+    }
+}
+
+impl ConnectionLabeled for SubscriptionProperties {
+    fn set_connection(&mut self, label: ConnectionLabel) {
+        self.connection = Some(label);
+    }
+}
+
+// Filter of a subscription rule. filter_type is "sql" or "correlation"; a correlation
+// filter matches when every field that is set equals the message's value.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleFilter {
+    pub filter_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sql_expression: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to_session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    // Application properties to match (string, number or boolean values)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<std::collections::HashMap<String, serde_json::Value>>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionRule {
+    // Defaults to "$Default" for the initial rule of a new subscription
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub filter: RuleFilter,
+    // SQL action applied to matching messages, e.g. "SET sys.Label = 'routed'"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sql_action: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -148,5 +439,580 @@ pub struct ServiceBusMessage {
     pub dead_letter_reason: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dead_letter_error_description: Option<String>,
+    // Entity the message was dead-lettered from, when it was auto-forwarded into this DLQ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_letter_source: Option<String>,
+    // RFC 3339; when set on send, the message is scheduled instead of enqueued immediately
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_enqueue_time_utc: Option<String>,
+    // Must match session_id when both are set (partitioned entities)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition_key: Option<String>,
+    // Partition of the entity a message is sent via (transactions, send-via); must match
+    // partition_key when both are set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub via_partition_key: Option<String>,
+    // "Active", "Deferred" or "Scheduled"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    // Set when the body was cut down by a peek with max_body_bytes: the original size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_truncated: Option<bool>,
+    // "base64" when body is a base64 string of raw bytes; absent for text and JSON bodies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_encoding: Option<String>,
+    // Space-separated hex of the first bytes of a binary body, set on peek/receive only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_hex_preview: Option<String>,
+    // Set when body was unwrapped from a legacy .NET DataContract string; re-applied on send
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_envelope: Option<BodyEnvelope>,
+    // Set when body was decompressed on peek/receive; the body is compressed again on send
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_compression: Option<BodyCompression>,
+    // Base64 of the body bytes as received, when body_compression is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_body: Option<String>,
+    // JSON rendering from a matching body decoder rule (Protobuf, ...); display only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded_body: Option<serde_json::Value>,
+    // Why a matching decoder rule could not decode the body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decode_error: Option<String>,
+    // Set when the body or its properties could not be read as sent; the body is then
+    // the raw text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_warning: Option<String>,
+    // When the message expires by its own and the entity's TTL, set on peek for display;
+    // absent when it never expires (including in a dead-letter queue)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at_utc: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyCompression {
+    Gzip,
+    Deflate,
+}
+
+impl BodyCompression {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BodyCompression::Gzip => "gzip",
+            BodyCompression::Deflate => "deflate",
+        }
+    }
+}
+
+// How the legacy .NET SDK (BrokeredMessage) serialized a string body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BodyEnvelope {
+    // <string xmlns="http://schemas.microsoft.com/2003/10/Serialization/">...</string>
+    DataContractXml,
+    // The same element in .NET Binary XML (the SDK default)
+    DataContractBinary,
+}
+
+
+// Tuning for a peek; unset fields keep the defaults
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PeekOptions {
+    // 0 drops bodies, n keeps an n-byte preview (AMQP only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<u32>,
+    // Server-side wait over HTTP (default 60), time limit per page over AMQP (default none)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u32>,
+    // Messages per request: at most 32 over HTTP (the default); over AMQP the default is
+    // everything in one request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+}
+
+// Where to receive messages from: a queue, or a topic subscription, optionally its DLQ
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSource {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_name: Option<String>,
+    #[serde(default)]
+    pub dead_letter: bool,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveMessagesProgress {
+    pub source: String,
+    pub destination: String,
+    pub requested: u32,
+    pub moved: u32,
+    // Sequence number of the last message completed on the source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_sequence_number: Option<u64>,
+    // True once the move finished (count reached or source drained)
+    pub done: bool,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyMessagesProgress {
+    pub source: String,
+    pub destination: String,
+    pub requested: u32,
+    pub copied: u32,
+    // Where a later copy would carry on; unset once the source ran out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+    // True once the copy finished (count reached or end of the source)
+    pub done: bool,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterAllProgress {
+    pub source: String,
+    pub reason: String,
+    // The active count when the operation started, or max_count; messages arriving
+    // later are left alone
+    pub requested: u32,
+    pub dead_lettered: u32,
+    pub done: bool,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteMessagesProgress {
+    pub source: String,
+    pub requested: u32,
+    // Sequence numbers removed so far
+    pub deleted: Vec<u64>,
+    // Messages locked while looking for the targets
+    pub scanned: u32,
+    // Targets not seen before the scan ended; only set once done
+    pub not_found: Vec<u64>,
+    pub done: bool,
+}
+
+// Per-receiver checkpoint for a parallel dead-letter drain
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiverCheckpoint {
+    pub receiver_id: u32,
+    pub moved: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_sequence_number: Option<u64>,
+    pub finished: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterDrainProgress {
+    pub source: String,
+    pub destination: String,
+    pub moved: u32,
+    pub checkpoints: Vec<ReceiverCheckpoint>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterDrainReport {
+    pub source: String,
+    pub destination: String,
+    pub receivers: u32,
+    pub requested: u32,
+    pub moved: u32,
+    pub dead_letter_count_before: u64,
+    pub dead_letter_count_after: u64,
+    // Every receiver finished without an error, so each completion on the DLQ followed
+    // a successful send and moved counts exactly the messages resubmitted
+    pub verified: bool,
+    pub checkpoints: Vec<ReceiverCheckpoint>,
+    pub notes: Vec<String>,
+}
+
+// One page of a management listing, reported while the rest is still loading
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListingProgress<T> {
+    // "queues", "topics" or "subscriptions"
+    pub entity_type: String,
+    // Topic name when listing subscriptions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub page: u32,
+    // Entities loaded so far, including this page
+    pub loaded: u64,
+    pub has_more: bool,
+    pub items: Vec<T>,
+}
+
+// One scheduled message moved to a new enqueue time. The service assigns a new
+// sequence number when the message is scheduled again.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RescheduledMessage {
+    pub old_sequence_number: u64,
+    pub new_sequence_number: u64,
+    pub scheduled_enqueue_time_utc: String,
+}
+
+// A session found on a session-enabled queue or subscription
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub session_id: String,
+    // Messages counted by peeking, up to a cap
+    pub message_count: u32,
+    // True when the session holds more messages than were counted
+    pub more_messages: bool,
+}
+
+// Session state is an opaque byte array; it is shown as text when it is valid UTF-8
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionState {
+    pub session_id: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub base64: String,
+}
+
+// Outcome of a request/reply round trip. reply is None when the timeout expired.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestReplyResult {
+    // The request as sent, with the message_id and reply_to that were used
+    pub request: ServiceBusMessage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<ServiceBusMessage>,
+    pub reply_queue: String,
+    pub elapsed_ms: u64,
+    pub timed_out: bool,
+    // Messages on the reply queue that did not correlate and were left there
+    pub unmatched_replies: u32,
+}
+
+// Result of a conditional entity refresh. When modified is false the cached copy is
+// still current and entity is omitted.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityRefresh<T> {
+    pub modified: bool,
+    // Send back as `etag` on the next refresh
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity: Option<T>,
+}
+
+// Settings applied to many subscriptions of a topic at once. Unset fields are left as
+// they are on each subscription.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_duration_in_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_delivery_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_message_time_to_live_in_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_lettering_on_message_expiration: Option<bool>,
+}
+
+// Outcome for one subscription of a bulk settings change
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionSettingsResult {
+    pub subscription_name: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityKind {
+    Queue,
+    Topic,
+    Subscription,
+}
+
+// An element of an entity description that bulk edits can match on and change.
+// Durations are given in seconds, sizes in megabytes and Status as a string.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityProperty {
+    LockDuration,
+    MaxDeliveryCount,
+    DefaultMessageTimeToLive,
+    DeadLetteringOnMessageExpiration,
+    DuplicateDetectionHistoryTimeWindow,
+    MaxSizeInMegabytes,
+    AutoDeleteOnIdle,
+    EnableBatchedOperations,
+    Status,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ComparisonOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyCondition {
+    pub property: EntityProperty,
+    pub op: ComparisonOp,
+    pub value: serde_json::Value,
+}
+
+// Which entities a bulk edit touches: all conditions must hold. Entities that don't
+// have a property (a topic has no LockDuration) never match a condition on it.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityFilter {
+    // Empty matches every kind
+    #[serde(default)]
+    pub kinds: Vec<EntityKind>,
+    // Regular expression over the entity path ("orders", "events/Subscriptions/audit")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_pattern: Option<String>,
+    #[serde(default)]
+    pub conditions: Vec<PropertyCondition>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyAssignment {
+    pub property: EntityProperty,
+    pub value: serde_json::Value,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyDiff {
+    pub property: EntityProperty,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<serde_json::Value>,
+    pub after: serde_json::Value,
+}
+
+// One matched entity of a bulk edit, with what changes (or changed) on it
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityEditResult {
+    pub kind: EntityKind,
+    pub entity_path: String,
+    pub changes: Vec<PropertyDiff>,
+    pub applied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkEditReport {
+    // False for a preview: nothing was written
+    pub applied: bool,
+    pub scanned_count: u32,
+    pub matched_count: u32,
+    // Matched, but already had every new value
+    pub unchanged_count: u32,
+    pub updated_count: u32,
+    pub failed_count: u32,
+    // Matched entities that need (or got) a change, and those that failed
+    pub entities: Vec<EntityEditResult>,
+}
+
+// Payload of the "bulk-edit-progress" event, sent after each entity
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkEditProgress {
+    pub entity_path: String,
+    // 1-based position among the entities being checked
+    pub index: u32,
+    pub total: u32,
+}
+
+// An entity to estimate the backlog of: a queue, a topic or a topic subscription.
+// etag is the one returned by the previous estimate.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BacklogEntity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+}
+
+// Runtime counts of one entity. When modified is false the counts from the previous
+// estimate are still current and are omitted.
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BacklogEstimate {
+    pub entity_path: String,
+    pub modified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_message_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_letter_message_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_message_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer_message_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer_dead_letter_message_count: Option<u64>,
+    // Set when this entity could not be read; the others are unaffected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
+// Messages taken off an entity by drain_messages, in the order they were received
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DrainResult {
+    pub entity_path: String,
+    pub messages: Vec<ServiceBusMessage>,
+    // Why the drain stopped before max_count or an empty entity; the messages above
+    // were removed all the same
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// A message received in peek-lock mode; the lock token settles it later
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockedMessage {
+    #[serde(flatten)]
+    pub message: ServiceBusMessage,
+    pub lock_token: String,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizationRule {
+    pub key_name: String,
+    // Any of "Manage", "Send", "Listen"
+    pub rights: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secondary_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_time: Option<String>,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SasKeyRotationResult {
+    pub entity_path: String,
+    pub key_name: String,
+    // "primary" or "secondary"
+    pub key_type: String,
+    // Connection string for the rotated key, for sharing with other consumers
+    pub new_connection_string: String,
+    // Stored connections that used the rotated key and were rewritten in the keychain
+    pub updated_connection_ids: Vec<String>,
+    // Stored connections for this rule whose key no longer matches either key
+    pub invalid_connection_ids: Vec<String>,
+    // Only planned (dry run): new_connection_string is empty and nothing was stored
+    pub dry_run: bool,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeastPrivilegePolicyResult {
+    pub entity_path: String,
+    pub rule: AuthorizationRule,
+    pub connection_string: String,
+    // True when connection_string was saved as a new connection
+    pub switched: bool,
+    // Id of that connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
+    // Only planned (dry run): connection_string is empty and nothing was stored
+    pub dry_run: bool,
+}
+
+#[allow(dead_code)] // Used by main app, not test binary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageCountVerification {
+    pub entity_path: String,
+    // "peek" or "purge"
+    pub operation: String,
+    pub dead_letter: bool,
+    // Messages the operation returned (peek) or removed (purge)
+    pub observed_count: u64,
+    // Active or dead-letter count reported by the service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_count: Option<u64>,
+    pub matches: bool,
+    // Likely explanations when the numbers don't line up
+    pub reasons: Vec<String>,
+}
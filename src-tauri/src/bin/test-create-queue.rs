@@ -1,12 +1,16 @@
 // Declare modules with path attributes to point to the actual module files
 #[path = "../azure/mod.rs"]
 mod azure;
+#[path = "../cli.rs"]
+mod cli;
 
 // Test script to verify queue creation works correctly
 // This script helps verify that we can create queues with properties via the REST API
 //
 // Usage:
-//   cargo run --bin test-create-queue -- <connection_string> <queue_name>
+//   cargo run --bin test-create-queue -- <connection_string> <queue_name> [--json]
+//
+// Exit codes follow src/cli.rs; --json prints failures as a single JSON line on stderr.
 //
 // Example:
 //   cargo run --bin test-create-queue -- 'Endpoint=sb://...;SharedAccessKeyName=...;SharedAccessKey=...' test-queue-123
@@ -14,18 +18,24 @@ mod azure;
 use azure::auth::{get_endpoint_domain, get_namespace_from_endpoint, parse_connection_string};
 use azure::types::{QueueProperties, ServiceBusConnection};
 use azure::servicebus::ServiceBusClient;
-use std::env;
+use cli::CliError;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
     // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
+    let (args, json) = cli::parse_args();
     
+    if let Err(e) = run(&args).await {
+        cli::exit_with_error(e, json);
+    }
+}
+
+async fn run(args: &[String]) -> Result<(), CliError> {
     if args.len() < 3 {
-        eprintln!("Usage: {} <connection_string> <queue_name>", args[0]);
+        eprintln!("Usage: {} <connection_string> <queue_name> [--json]", args[0]);
         eprintln!("\nExample:");
         eprintln!("  {} 'Endpoint=sb://...;SharedAccessKeyName=...;SharedAccessKey=...' test-queue-123", args[0]);
-        std::process::exit(1);
+        return Err(CliError::usage("Missing required arguments: <connection_string> <queue_name>"));
     }
     
     let connection_string = &args[1];
@@ -39,7 +49,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Parse connection string
     println!("[1/6] Parsing connection string...");
-    let parsed = parse_connection_string(connection_string)?;
+    let parsed = parse_connection_string(connection_string).map_err(CliError::usage)?;
     println!("✓ Connection string parsed successfully");
     println!("  Endpoint: {}", parsed.endpoint);
     println!("  Key Name: {}", parsed.shared_access_key_name);
@@ -65,6 +75,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         client_id: None,
         created_at: chrono::Utc::now().timestamp(),
         updated_at: chrono::Utc::now().timestamp(),
+        uses_root_manage_key: None,
+        training_mode: None,
+        transport: None,
+        color: None,
     };
     println!("✓ Connection created");
     println!();
@@ -86,9 +100,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         dead_lettering_on_message_expiration: Some(true),
         duplicate_detection_history_time_window_in_seconds: Some(600),
         enable_batched_operations: Some(true),
+        auto_delete_on_idle_in_seconds: None,
+        status: None,
         enable_partitioning: Some(false),
         requires_session: Some(false),
         requires_duplicate_detection: Some(false),
+        max_message_size_in_kilobytes: None,
+        forward_to: None,
+        forward_dead_lettered_messages_to: None,
+        user_metadata: None,
+        created_at: None,
+        updated_at: None,
+        accessed_at: None,
         message_count: None,
         active_message_count: None,
         dead_letter_message_count: None,
@@ -96,6 +119,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         transfer_message_count: None,
         transfer_dead_letter_message_count: None,
         size_in_bytes: None,
+        connection: None,
     };
     
     match client.create_queue(queue_name, Some(&properties)).await {
@@ -103,9 +127,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("✓ Queue creation request sent successfully");
         }
         Err(e) => {
-            eprintln!("\n❌ FAILED: Queue creation failed with error:");
-            eprintln!("   {}", e);
-            std::process::exit(1);
+            return Err(CliError::from(e).context("Queue creation failed"));
         }
     }
     println!();
@@ -131,12 +153,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         Err(e) => {
-            eprintln!("\n❌ FAILED: Queue was not created or cannot be retrieved:");
-            eprintln!("   {}", e);
             eprintln!("\n   This suggests the create_queue call may have succeeded");
             eprintln!("   but the queue was not actually created, or there's an issue");
             eprintln!("   with the XML format sent to Azure.");
-            std::process::exit(1);
+            return Err(CliError::from(e).context("Queue was not created or cannot be retrieved"));
         }
     }
     
@@ -1,31 +1,41 @@
 // Declare modules with path attributes to point to the actual module files
 #[path = "../azure/mod.rs"]
 mod azure;
+#[path = "../cli.rs"]
+mod cli;
 
 // Test script to debug Azure Service Bus message peeking
 // This script helps verify that we can peek multiple messages from a queue
 //
 // Usage:
-//   cargo run --bin test-peek -- <connection_string> <queue_name> [max_count]
+//   cargo run --bin test-peek -- <connection_string> <queue_name> [max_count] [--json]
+//
+// Exit codes follow src/cli.rs; --json prints failures as a single JSON line on stderr.
 //
 // Example:
 //   cargo run --bin test-peek -- 'Endpoint=sb://...;SharedAccessKeyName=...;SharedAccessKey=...' myqueue 10
 
 use azure::auth::{get_endpoint_domain, get_namespace_from_endpoint, parse_connection_string};
-use azure::types::ServiceBusConnection;
+use azure::types::{PeekOptions, ServiceBusConnection};
 use azure::servicebus::ServiceBusClient;
-use std::env;
+use cli::CliError;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
     // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
+    let (args, json) = cli::parse_args();
     
+    if let Err(e) = run(&args).await {
+        cli::exit_with_error(e, json);
+    }
+}
+
+async fn run(args: &[String]) -> Result<(), CliError> {
     if args.len() < 3 {
-        eprintln!("Usage: {} <connection_string> <queue_name> [max_count]", args[0]);
+        eprintln!("Usage: {} <connection_string> <queue_name> [max_count] [--json]", args[0]);
         eprintln!("\nExample:");
         eprintln!("  {} 'Endpoint=sb://...;SharedAccessKeyName=...;SharedAccessKey=...' myqueue 10", args[0]);
-        std::process::exit(1);
+        return Err(CliError::usage("Missing required arguments: <connection_string> <queue_name>"));
     }
     
     let connection_string = &args[1];
@@ -43,7 +53,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Parse connection string
     println!("[1/5] Parsing connection string...");
-    let parsed = parse_connection_string(connection_string)?;
+    let parsed = parse_connection_string(connection_string).map_err(CliError::usage)?;
     println!("✓ Connection string parsed successfully");
     println!("  Endpoint: {}", parsed.endpoint);
     println!("  Key Name: {}", parsed.shared_access_key_name);
@@ -69,6 +79,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         client_id: None,
         created_at: chrono::Utc::now().timestamp(),
         updated_at: chrono::Utc::now().timestamp(),
+        uses_root_manage_key: None,
+        training_mode: None,
+        transport: None,
+        color: None,
     };
     println!("✓ Connection created");
     println!();
@@ -82,7 +96,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Test peeking messages using SDK
     println!("[5/5] Peeking messages using azservicebus SDK...");
     println!("----------------------------------------");
-    let messages = client.peek_messages_sdk(Some(queue_name), None, None, max_count).await?;
+    let messages = client.peek_messages_sdk(Some(queue_name), None, None, max_count, None, &PeekOptions::default()).await?;
     println!("----------------------------------------\n");
     
     // Display results
@@ -94,19 +108,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Assertion: Verify we peeked more than 1 message
     if messages.is_empty() {
-        eprintln!("\n❌ ASSERTION FAILED: No messages found in queue '{}'", queue_name);
-        eprintln!("   Cannot verify that peek returns multiple messages.");
+        eprintln!("\n   Cannot verify that peek returns multiple messages.");
         eprintln!("   Please ensure the queue has at least 2 messages for testing.");
-        std::process::exit(1);
+        return Err(CliError::new(
+            cli::ErrorKind::Failure,
+            format!("ASSERTION FAILED: No messages found in queue '{}'", queue_name),
+        ));
     } else if messages.len() == 1 {
-        eprintln!("\n❌ ASSERTION FAILED: Expected more than 1 message, but got only 1");
-        eprintln!("   This indicates the peek operation may only be returning a single message.");
+        eprintln!("\n   This indicates the peek operation may only be returning a single message.");
         eprintln!("   Please check the peek_messages implementation.");
-        eprintln!("\n   Message found:");
         if let Some(ref msg_id) = messages[0].message_id {
+            eprintln!("\n   Message found:");
             eprintln!("     MessageId: {}", msg_id);
         }
-        std::process::exit(1);
+        return Err(CliError::partial("ASSERTION FAILED: Expected more than 1 message, but got only 1"));
     }
     
     println!("✓ Assertion passed: Successfully peeked {} messages (expected > 1)", messages.len());
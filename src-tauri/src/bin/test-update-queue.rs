@@ -1,12 +1,16 @@
 // Declare modules with path attributes to point to the actual module files
 #[path = "../azure/mod.rs"]
 mod azure;
+#[path = "../cli.rs"]
+mod cli;
 
 // Test script to verify queue update works correctly
 // This script helps verify that we can update queues with properties via the REST API
 //
 // Usage:
-//   cargo run --bin test-update-queue -- <connection_string> <queue_name>
+//   cargo run --bin test-update-queue -- <connection_string> <queue_name> [--json]
+//
+// Exit codes follow src/cli.rs; --json prints failures as a single JSON line on stderr.
 //
 // Example:
 //   cargo run --bin test-update-queue -- 'Endpoint=sb://...;SharedAccessKeyName=...;SharedAccessKey=...' test-queue
@@ -14,18 +18,24 @@ mod azure;
 use azure::auth::{get_endpoint_domain, get_namespace_from_endpoint, parse_connection_string};
 use azure::types::{QueueProperties, ServiceBusConnection};
 use azure::servicebus::ServiceBusClient;
-use std::env;
+use cli::CliError;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
     // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
+    let (args, json) = cli::parse_args();
     
+    if let Err(e) = run(&args).await {
+        cli::exit_with_error(e, json);
+    }
+}
+
+async fn run(args: &[String]) -> Result<(), CliError> {
     if args.len() < 3 {
-        eprintln!("Usage: {} <connection_string> <queue_name>", args[0]);
+        eprintln!("Usage: {} <connection_string> <queue_name> [--json]", args[0]);
         eprintln!("\nExample:");
         eprintln!("  {} 'Endpoint=sb://...;SharedAccessKeyName=...;SharedAccessKey=...' test-queue", args[0]);
-        std::process::exit(1);
+        return Err(CliError::usage("Missing required arguments: <connection_string> <queue_name>"));
     }
     
     let connection_string = &args[1];
@@ -39,7 +49,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Parse connection string
     println!("[1/6] Parsing connection string...");
-    let parsed = parse_connection_string(connection_string)?;
+    let parsed = parse_connection_string(connection_string).map_err(CliError::usage)?;
     println!("✓ Connection string parsed successfully");
     println!("  Endpoint: {}", parsed.endpoint);
     println!("  Key Name: {}", parsed.shared_access_key_name);
@@ -65,6 +75,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         client_id: None,
         created_at: chrono::Utc::now().timestamp(),
         updated_at: chrono::Utc::now().timestamp(),
+        uses_root_manage_key: None,
+        training_mode: None,
+        transport: None,
+        color: None,
     };
     println!("✓ Connection created");
     println!();
@@ -89,10 +103,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             queue
         }
         Err(e) => {
-            eprintln!("\n❌ FAILED: Queue not found or cannot be retrieved:");
-            eprintln!("   {}", e);
             eprintln!("\n   Please ensure the queue '{}' exists.", queue_name);
-            std::process::exit(1);
+            return Err(CliError::from(e).context("Queue not found or cannot be retrieved"));
         }
     };
     println!();
@@ -108,10 +120,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         dead_lettering_on_message_expiration: Some(true),
         duplicate_detection_history_time_window_in_seconds: Some(600),
         enable_batched_operations: Some(true),
+        auto_delete_on_idle_in_seconds: None,
+        status: None,
         // Use existing values for immutable properties
         enable_partitioning: existing_queue.enable_partitioning,
         requires_session: existing_queue.requires_session,
         requires_duplicate_detection: existing_queue.requires_duplicate_detection,
+        max_message_size_in_kilobytes: None,
+        forward_to: None,
+        forward_dead_lettered_messages_to: None,
+        user_metadata: None,
+        created_at: None,
+        updated_at: None,
+        accessed_at: None,
         message_count: existing_queue.message_count,
         active_message_count: existing_queue.active_message_count,
         dead_letter_message_count: existing_queue.dead_letter_message_count,
@@ -119,6 +140,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         transfer_message_count: existing_queue.transfer_message_count,
         transfer_dead_letter_message_count: existing_queue.transfer_dead_letter_message_count,
         size_in_bytes: existing_queue.size_in_bytes,
+        connection: None,
     };
     
     match client.update_queue(queue_name, &update_properties).await {
@@ -126,9 +148,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("✓ Queue update request sent successfully");
         }
         Err(e) => {
-            eprintln!("\n❌ FAILED: Queue update failed with error:");
-            eprintln!("   {}", e);
-            std::process::exit(1);
+            return Err(CliError::from(e).context("Queue update failed"));
         }
     }
     
@@ -150,9 +170,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         Err(e) => {
-            eprintln!("\n❌ FAILED: Queue was not updated or cannot be retrieved:");
-            eprintln!("   {}", e);
-            std::process::exit(1);
+            // The update itself was accepted, so this is only a partial success
+            return Err(CliError::partial(format!("Queue was updated but cannot be retrieved for verification: {}", e)));
         }
     }
     
@@ -0,0 +1,135 @@
+// Shared helpers for the CLI/test binaries
+// Gives every binary the same exit codes and an optional `--json` error format,
+// so scripts and pipelines can branch on the failure type instead of grepping stderr.
+//
+// Exit codes:
+//   0  success
+//   1  general failure
+//   2  usage error (bad or missing arguments)
+//   3  authentication/authorization failure (401/403, bad SAS key)
+//   4  entity not found (404)
+//   5  throttled or server busy (429/503)
+//   6  partial success (the operation ran but did not fully meet expectations)
+
+use serde::Serialize;
+
+#[allow(dead_code)] // Not every binary produces every kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Failure,
+    Usage,
+    AuthFailure,
+    NotFound,
+    Throttled,
+    PartialSuccess,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Failure => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::AuthFailure => 3,
+            ErrorKind::NotFound => 4,
+            ErrorKind::Throttled => 5,
+            ErrorKind::PartialSuccess => 6,
+        }
+    }
+
+    // Classify an error message produced by ServiceBusClient.
+    // Client errors are formatted as "Failed to ...: <status> - <body>", so the
+    // HTTP status code (or the Service Bus error text) is the best signal we have.
+    pub fn classify(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        let has_status = |code: &str| {
+            regex::Regex::new(&format!(r"\b{}\b", code))
+                .map(|re| re.is_match(message))
+                .unwrap_or(false)
+        };
+
+        if has_status("401") || has_status("403") || lower.contains("unauthorized") || lower.contains("forbidden") {
+            ErrorKind::AuthFailure
+        } else if has_status("404") || lower.contains("not found") || lower.contains("messagingentitynotfound") {
+            ErrorKind::NotFound
+        } else if has_status("429") || has_status("503") || lower.contains("throttl") || lower.contains("serverbusy") {
+            ErrorKind::Throttled
+        } else {
+            ErrorKind::Failure
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub exit_code: i32,
+    pub message: String,
+}
+
+#[allow(dead_code)] // Not every binary produces every kind
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        CliError {
+            kind,
+            exit_code: kind.exit_code(),
+            message: message.into(),
+        }
+    }
+
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Usage, message)
+    }
+
+    pub fn partial(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::PartialSuccess, message)
+    }
+
+    // Attach context to an error while keeping its classification
+    pub fn context(self, context: &str) -> Self {
+        CliError {
+            message: format!("{}: {}", context, self.message),
+            ..self
+        }
+    }
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::new(ErrorKind::classify(&message), message)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(e: serde_json::Error) -> Self {
+        CliError::new(ErrorKind::Failure, format!("Failed to serialize output: {}", e))
+    }
+}
+
+// Split `--json` out of the raw arguments, returning the remaining positional args
+pub fn parse_args() -> (Vec<String>, bool) {
+    let mut json = false;
+    let args = std::env::args()
+        .filter(|arg| {
+            if arg == "--json" {
+                json = true;
+                false
+            } else {
+                true
+            }
+        })
+        .collect();
+    (args, json)
+}
+
+// Report the error on stderr (human-readable or a single JSON line) and exit with its code
+pub fn exit_with_error(error: CliError, json: bool) -> ! {
+    if json {
+        let payload = serde_json::json!({ "error": &error });
+        eprintln!("{}", payload);
+    } else {
+        eprintln!("\n❌ FAILED ({:?}, exit code {}):", error.kind, error.exit_code);
+        eprintln!("   {}", error.message);
+    }
+    std::process::exit(error.exit_code);
+}
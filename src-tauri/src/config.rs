@@ -0,0 +1,122 @@
+// App settings persisted as JSON in the platform config directory
+// The file lives at the same path Tauri resolves for app_config_dir(), so the desktop
+// app and the CLI binaries read the same preferences. Connection secrets are NOT stored
+// here - they stay in the OS keychain.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const APP_IDENTIFIER: &str = "com.bishoylabib.servicebusexplorer";
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+// Largest page the peek/receive APIs accept per call
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppSettings {
+    // "system", "light" or "dark" - a hint for the frontend, applied there
+    pub theme: String,
+    // Default number of messages to peek/receive
+    pub default_page_size: u32,
+    // Default number of entities per page when listing queues/subscriptions
+    pub default_list_page_size: u32,
+    // "all" (confirm every change), "destructive" (purge, delete, dead-letter) or "none"
+    pub confirmation_level: String,
+    pub telemetry_opt_in: bool,
+    // Record and log mutating requests instead of sending them (see azure::dry_run)
+    pub dry_run: bool,
+    // "local", "utc" or a fixed offset ("+05:30") used by format_timestamps; commands
+    // always return timestamps in UTC
+    pub display_time_zone: String,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            theme: "system".to_string(),
+            default_page_size: 100,
+            default_list_page_size: 100,
+            confirmation_level: "destructive".to_string(),
+            telemetry_opt_in: false,
+            dry_run: false,
+            display_time_zone: "local".to_string(),
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !matches!(self.theme.as_str(), "system" | "light" | "dark") {
+            return Err(format!("Invalid theme '{}': expected system, light or dark", self.theme));
+        }
+        if !matches!(self.confirmation_level.as_str(), "all" | "destructive" | "none") {
+            return Err(format!(
+                "Invalid confirmation level '{}': expected all, destructive or none",
+                self.confirmation_level
+            ));
+        }
+        if self.default_page_size == 0 || self.default_page_size > MAX_PAGE_SIZE {
+            return Err(format!("Default page size must be between 1 and {}", MAX_PAGE_SIZE));
+        }
+        if self.default_list_page_size == 0 || self.default_list_page_size > MAX_PAGE_SIZE {
+            return Err(format!("Default list page size must be between 1 and {}", MAX_PAGE_SIZE));
+        }
+        crate::azure::timestamps::DisplayTimeZone::parse(&self.display_time_zone)?;
+        Ok(())
+    }
+}
+
+// Platform config directory for the app (mirrors Tauri's app_config_dir)
+pub fn config_dir() -> Result<PathBuf, String> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    base.map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "Could not determine the config directory".to_string())
+}
+
+pub fn settings_path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join(SETTINGS_FILE_NAME))
+}
+
+// Load settings, falling back to defaults when the file does not exist yet.
+// Unknown or missing fields are tolerated so older files keep working.
+pub fn load_settings() -> Result<AppSettings, String> {
+    let path = settings_path()?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(AppSettings::default()),
+        Err(e) => return Err(format!("Failed to read settings from {}: {}", path.display(), e)),
+    };
+
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse settings in {}: {}", path.display(), e))
+}
+
+// Validate and write settings (write to a temp file, then rename, so a crash never
+// leaves a truncated file behind)
+pub fn save_settings(settings: &AppSettings) -> Result<(), String> {
+    settings.validate()?;
+
+    let path = settings_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory {}: {}", dir.display(), e))?;
+    }
+
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)
+        .map_err(|e| format!("Failed to write settings to {}: {}", tmp_path.display(), e))?;
+    std::fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to save settings to {}: {}", path.display(), e))
+}
@@ -0,0 +1,333 @@
+// Avro binary decoding against a writer schema (.avsc JSON)
+//
+// Covers the full type system: primitives, records, enums, arrays, maps, unions and
+// fixed, with named types and namespaces. Logical types decode as their underlying
+// type. bytes and fixed values are shown base64-encoded; unions show the branch value.
+
+use std::collections::HashMap;
+
+// Stop runaway block counts (e.g. arrays of nulls, which take no bytes each)
+const MAX_ITEMS: u64 = 1_000_000;
+
+pub struct AvroSchema {
+    nodes: Vec<Node>,
+    names: HashMap<String, usize>,
+}
+
+enum Node {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Record(Vec<(String, usize)>),
+    Enum(Vec<String>),
+    Array(usize),
+    Map(usize),
+    Union(Vec<usize>),
+    Fixed(usize),
+    // A named type used before (or while) it is defined, resolved when decoding
+    Ref(String),
+}
+
+impl AvroSchema {
+    pub fn parse(schema: &str) -> Result<AvroSchema, String> {
+        let json: serde_json::Value =
+            serde_json::from_str(schema).map_err(|e| format!("Schema is not valid JSON: {}", e))?;
+        let mut parsed = AvroSchema { nodes: Vec::new(), names: HashMap::new() };
+        parsed.parse_node(&json, None)?;
+        // Every reference must name a type defined somewhere in the schema
+        for node in &parsed.nodes {
+            if let Node::Ref(name) = node {
+                if !parsed.names.contains_key(name) {
+                    return Err(format!("Schema references unknown type '{}'", name));
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    // The root schema is always node 0
+    pub fn decode(&self, body: &[u8]) -> Result<serde_json::Value, String> {
+        let mut reader = Reader { bytes: body, position: 0 };
+        let value = self.decode_node(0, &mut reader)?;
+        let trailing = body.len() - reader.position;
+        if trailing > 0 {
+            return Err(format!("{} bytes left over after decoding; is this the writer schema?", trailing));
+        }
+        Ok(value)
+    }
+
+    fn push(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    fn parse_node(&mut self, json: &serde_json::Value, namespace: Option<&str>) -> Result<usize, String> {
+        match json {
+            serde_json::Value::String(name) => Ok(self.parse_type_name(name, namespace)),
+            serde_json::Value::Array(branches) => {
+                let index = self.push(Node::Union(Vec::new()));
+                let branches = branches
+                    .iter()
+                    .map(|branch| self.parse_node(branch, namespace))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.nodes[index] = Node::Union(branches);
+                Ok(index)
+            }
+            serde_json::Value::Object(object) => {
+                let type_name = match object.get("type") {
+                    Some(serde_json::Value::String(type_name)) => type_name.as_str(),
+                    // {"type": {...}} or {"type": [...]}: the wrapper adds nothing
+                    Some(inner) => return self.parse_node(inner, namespace),
+                    None => return Err("Schema object has no \"type\"".to_string()),
+                };
+                match type_name {
+                    "record" | "error" | "enum" | "fixed" => self.parse_named(object, type_name, namespace),
+                    "array" => {
+                        let items = object.get("items").ok_or("Array schema has no \"items\"")?;
+                        let index = self.push(Node::Array(0));
+                        let items = self.parse_node(items, namespace)?;
+                        self.nodes[index] = Node::Array(items);
+                        Ok(index)
+                    }
+                    "map" => {
+                        let values = object.get("values").ok_or("Map schema has no \"values\"")?;
+                        let index = self.push(Node::Map(0));
+                        let values = self.parse_node(values, namespace)?;
+                        self.nodes[index] = Node::Map(values);
+                        Ok(index)
+                    }
+                    // Primitives, possibly annotated with a logicalType
+                    other => Ok(self.parse_type_name(other, namespace)),
+                }
+            }
+            other => Err(format!("Unexpected schema element: {}", other)),
+        }
+    }
+
+    fn parse_type_name(&mut self, name: &str, namespace: Option<&str>) -> usize {
+        let node = match name {
+            "null" => Node::Null,
+            "boolean" => Node::Boolean,
+            "int" => Node::Int,
+            "long" => Node::Long,
+            "float" => Node::Float,
+            "double" => Node::Double,
+            "bytes" => Node::Bytes,
+            "string" => Node::String,
+            named => Node::Ref(full_name(named, namespace)),
+        };
+        self.push(node)
+    }
+
+    fn parse_named(
+        &mut self,
+        object: &serde_json::Map<String, serde_json::Value>,
+        type_name: &str,
+        namespace: Option<&str>,
+    ) -> Result<usize, String> {
+        let name = object
+            .get("name")
+            .and_then(|n| n.as_str())
+            .ok_or_else(|| format!("{} schema has no \"name\"", type_name))?;
+        let namespace = object.get("namespace").and_then(|n| n.as_str()).or(namespace);
+        let full_name = full_name(name, namespace);
+        // Names inside a type resolve against that type's namespace
+        let inner_namespace = full_name.rsplit_once('.').map(|(ns, _)| ns.to_string());
+
+        // Register before parsing fields so recursive records can refer to themselves
+        let index = self.push(Node::Null);
+        self.names.insert(full_name.clone(), index);
+
+        self.nodes[index] = match type_name {
+            "enum" => {
+                let symbols = object
+                    .get("symbols")
+                    .and_then(|s| s.as_array())
+                    .ok_or_else(|| format!("Enum '{}' has no \"symbols\"", full_name))?;
+                Node::Enum(symbols.iter().filter_map(|s| s.as_str().map(|s| s.to_string())).collect())
+            }
+            "fixed" => {
+                let size = object
+                    .get("size")
+                    .and_then(|s| s.as_u64())
+                    .ok_or_else(|| format!("Fixed '{}' has no \"size\"", full_name))?;
+                Node::Fixed(size as usize)
+            }
+            _ => {
+                let fields = object
+                    .get("fields")
+                    .and_then(|f| f.as_array())
+                    .ok_or_else(|| format!("Record '{}' has no \"fields\"", full_name))?;
+                let mut parsed = Vec::with_capacity(fields.len());
+                for field in fields {
+                    let field_name = field
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .ok_or_else(|| format!("A field of '{}' has no \"name\"", full_name))?;
+                    let field_type = field
+                        .get("type")
+                        .ok_or_else(|| format!("Field '{}.{}' has no \"type\"", full_name, field_name))?;
+                    let field_index = self.parse_node(field_type, inner_namespace.as_deref())?;
+                    parsed.push((field_name.to_string(), field_index));
+                }
+                Node::Record(parsed)
+            }
+        };
+        Ok(index)
+    }
+
+    fn decode_node(&self, index: usize, reader: &mut Reader) -> Result<serde_json::Value, String> {
+        use serde_json::Value;
+        Ok(match &self.nodes[index] {
+            Node::Null => Value::Null,
+            Node::Boolean => Value::Bool(reader.byte()? != 0),
+            Node::Int | Node::Long => Value::from(reader.long()?),
+            Node::Float => {
+                let bytes = reader.take(4)?;
+                serde_json::json!(f32::from_le_bytes(bytes.try_into().map_err(|_| "Truncated float")?))
+            }
+            Node::Double => {
+                let bytes = reader.take(8)?;
+                serde_json::json!(f64::from_le_bytes(bytes.try_into().map_err(|_| "Truncated double")?))
+            }
+            Node::Bytes => {
+                let len = reader.length()?;
+                Value::String(base64_encode(reader.take(len)?))
+            }
+            Node::String => {
+                let len = reader.length()?;
+                let text = std::str::from_utf8(reader.take(len)?).map_err(|e| format!("Invalid UTF-8 string: {}", e))?;
+                Value::String(text.to_string())
+            }
+            Node::Record(fields) => {
+                let mut object = serde_json::Map::new();
+                for (name, field) in fields {
+                    object.insert(name.clone(), self.decode_node(*field, reader)?);
+                }
+                Value::Object(object)
+            }
+            Node::Enum(symbols) => {
+                let symbol = reader.long()?;
+                let symbol = usize::try_from(symbol)
+                    .ok()
+                    .and_then(|i| symbols.get(i))
+                    .ok_or_else(|| format!("Enum index {} is out of range", symbol))?;
+                Value::String(symbol.clone())
+            }
+            Node::Array(items) => {
+                let mut values = Vec::new();
+                self.read_blocks(reader, |reader| {
+                    values.push(self.decode_node(*items, reader)?);
+                    Ok(())
+                })?;
+                Value::Array(values)
+            }
+            Node::Map(values) => {
+                let mut object = serde_json::Map::new();
+                self.read_blocks(reader, |reader| {
+                    let len = reader.length()?;
+                    let key = std::str::from_utf8(reader.take(len)?)
+                        .map_err(|e| format!("Invalid UTF-8 map key: {}", e))?
+                        .to_string();
+                    object.insert(key, self.decode_node(*values, reader)?);
+                    Ok(())
+                })?;
+                Value::Object(object)
+            }
+            Node::Union(branches) => {
+                let branch = reader.long()?;
+                let branch = usize::try_from(branch)
+                    .ok()
+                    .and_then(|i| branches.get(i))
+                    .ok_or_else(|| format!("Union branch {} is out of range", branch))?;
+                self.decode_node(*branch, reader)?
+            }
+            Node::Fixed(size) => Value::String(base64_encode(reader.take(*size)?)),
+            Node::Ref(name) => {
+                let target = self.names.get(name).ok_or_else(|| format!("Unknown type '{}'", name))?;
+                self.decode_node(*target, reader)?
+            }
+        })
+    }
+
+    // Arrays and maps are written as blocks: a count (negative when followed by the
+    // block's byte size), the items, and a final zero count
+    fn read_blocks(
+        &self,
+        reader: &mut Reader,
+        mut read_item: impl FnMut(&mut Reader) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let mut total = 0u64;
+        loop {
+            let count = reader.long()?;
+            if count == 0 {
+                return Ok(());
+            }
+            if count < 0 {
+                reader.long()?;
+            }
+            total += count.unsigned_abs();
+            if total > MAX_ITEMS {
+                return Err(format!("More than {} items in an array or map", MAX_ITEMS));
+            }
+            for _ in 0..count.unsigned_abs() {
+                read_item(reader)?;
+            }
+        }
+    }
+}
+
+fn full_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) if !name.contains('.') && !namespace.is_empty() => format!("{}.{}", namespace, name),
+        _ => name.to_string(),
+    }
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.position).ok_or("Unexpected end of body")?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.position.checked_add(len).filter(|end| *end <= self.bytes.len()).ok_or("Unexpected end of body")?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    // Zig-zag encoded variable-length integer
+    fn long(&mut self) -> Result<i64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+        Err("Integer is longer than 10 bytes".to_string())
+    }
+
+    fn length(&mut self) -> Result<usize, String> {
+        let len = self.long()?;
+        usize::try_from(len).map_err(|_| format!("Negative length {}", len))
+    }
+}
@@ -0,0 +1,138 @@
+// AES-GCM payload encryption, for producers that encrypt message bodies
+//
+// An encrypted body is the 12-byte nonce followed by the ciphertext and its 16-byte tag.
+// Keys are 128 or 256 bits. They are kept in the keychain under an id (loaded into the
+// process by main.rs), or read from a Key Vault secret holding the key as base64.
+
+use crate::azure::types::ServiceBusConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+const NONCE_LEN: usize = 12;
+const KEY_VAULT_API_VERSION: &str = "7.4";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum EncryptionKey {
+    #[serde(rename_all = "camelCase")]
+    Keychain { key_id: String },
+    // vault_url is "https://<vault>.vault.azure.net"
+    #[serde(rename_all = "camelCase")]
+    KeyVault { vault_url: String, secret_name: String },
+}
+
+static KEYCHAIN_KEYS: OnceLock<Mutex<HashMap<String, Vec<u8>>>> = OnceLock::new();
+
+fn keychain_keys() -> std::sync::MutexGuard<'static, HashMap<String, Vec<u8>>> {
+    let mutex = KEYCHAIN_KEYS.get_or_init(|| Mutex::new(HashMap::new()));
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Replace the keys known by id with the ones stored in the keychain (base64 by id).
+// Keys that aren't valid are skipped and logged.
+pub fn set_keychain_keys(keys: &HashMap<String, String>) {
+    let mut loaded = HashMap::new();
+    for (key_id, encoded) in keys {
+        match parse_key(encoded) {
+            Ok(key) => {
+                loaded.insert(key_id.clone(), key);
+            }
+            Err(e) => eprintln!("[encryption] Keychain key '{}': {}", key_id, e),
+        }
+    }
+    *keychain_keys() = loaded;
+}
+
+// A base64 AES key of 128 or 256 bits
+pub fn parse_key(encoded: &str) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+    let key = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| "Encryption key must be base64".to_string())?;
+    match key.len() {
+        16 | 32 => Ok(key),
+        n => Err(format!("Encryption key must be 128 or 256 bits, got {} bits", n * 8)),
+    }
+}
+
+pub async fn resolve_key(connection: &ServiceBusConnection, key: &EncryptionKey) -> Result<Vec<u8>, String> {
+    match key {
+        EncryptionKey::Keychain { key_id } => keychain_keys()
+            .get(key_id)
+            .cloned()
+            .ok_or_else(|| format!("Encryption key '{}' is not in the keychain", key_id)),
+        EncryptionKey::KeyVault { vault_url, secret_name } => {
+            let secret = fetch_secret(connection, vault_url, secret_name).await?;
+            parse_key(&secret).map_err(|e| format!("Key Vault secret '{}': {}", secret_name, e))
+        }
+    }
+}
+
+// Azure AD connections use their own credential, others DefaultAzureCredential
+async fn fetch_secret(connection: &ServiceBusConnection, vault_url: &str, secret_name: &str) -> Result<String, String> {
+    use crate::azure::auth::{acquire_aad_token_for_scope, acquire_default_token, KEY_VAULT_SCOPE};
+
+    let token = if connection.use_azure_ad.unwrap_or(false) {
+        acquire_aad_token_for_scope(connection, KEY_VAULT_SCOPE).await?
+    } else {
+        acquire_default_token(KEY_VAULT_SCOPE).await?
+    };
+    let url = format!(
+        "{}/secrets/{}?api-version={}",
+        vault_url.trim().trim_end_matches('/'),
+        urlencoding::encode(secret_name),
+        KEY_VAULT_API_VERSION
+    );
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Key Vault {}: {}", vault_url, e))?;
+    let status = response.status();
+    let body = response.text().await.map_err(|e| format!("Failed to read secret '{}': {}", secret_name, e))?;
+    if !status.is_success() {
+        return Err(format!("Key Vault returned {} for secret '{}': {}", status, secret_name, body));
+    }
+    let secret: serde_json::Value =
+        serde_json::from_str(&body).map_err(|e| format!("Invalid Key Vault response: {}", e))?;
+    secret["value"]
+        .as_str()
+        .map(|value| value.to_string())
+        .ok_or_else(|| format!("Key Vault secret '{}' has no value", secret_name))
+}
+
+pub fn encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = match key.len() {
+        16 => aes_gcm::Aes128Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to set up encryption: {}", e))?
+            .encrypt(aes_gcm::Nonce::from_slice(&nonce), plaintext),
+        _ => aes_gcm::Aes256Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to set up encryption: {}", e))?
+            .encrypt(aes_gcm::Nonce::from_slice(&nonce), plaintext),
+    }
+    .map_err(|e| format!("Failed to encrypt message body: {}", e))?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+pub fn decrypt(key: &[u8], body: &[u8]) -> Result<Vec<u8>, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+
+    if body.len() < NONCE_LEN + 16 {
+        return Err("Body is too short to be AES-GCM encrypted".to_string());
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    match key.len() {
+        16 => aes_gcm::Aes128Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to set up decryption: {}", e))?
+            .decrypt(aes_gcm::Nonce::from_slice(nonce), ciphertext),
+        _ => aes_gcm::Aes256Gcm::new_from_slice(key)
+            .map_err(|e| format!("Failed to set up decryption: {}", e))?
+            .decrypt(aes_gcm::Nonce::from_slice(nonce), ciphertext),
+    }
+    .map_err(|_| "Decryption failed: wrong key, or the body was not encrypted with it".to_string())
+}
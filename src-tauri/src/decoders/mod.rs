@@ -0,0 +1,463 @@
+// Body decoders: render binary payloads (Protobuf, Avro) as JSON for display
+//
+// Rules map messages to a decoder by entity, subject and/or content type. Rules and
+// registered schema files live in the config directory next to settings.json. Decoding never
+// replaces body: the JSON goes to decoded_body, so a resend still sends the original bytes.
+// Encrypted bodies are decrypted first (see encryption.rs) and then decoded like any other.
+
+mod avro;
+mod encryption;
+mod protobuf;
+mod schema_registry;
+
+pub use encryption::{parse_key, set_keychain_keys, EncryptionKey};
+
+use crate::azure::types::{ServiceBusConnection, ServiceBusMessage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const DECODERS_FILE_NAME: &str = "decoders.json";
+const SCHEMA_DIR_NAME: &str = "decoders";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DecoderConfig {
+    pub descriptor_sets: Vec<DescriptorSetInfo>,
+    pub avro_schemas: Vec<AvroSchemaInfo>,
+    pub rules: Vec<DecoderRule>,
+}
+
+// A compiled Protobuf descriptor set (protoc --descriptor_set_out --include_imports)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DescriptorSetInfo {
+    pub id: String,
+    // File name it was registered from, for display
+    pub name: String,
+    pub message_types: Vec<String>,
+}
+
+// An Avro schema (.avsc) used as the writer schema for every message it decodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvroSchemaInfo {
+    pub id: String,
+    // File name it was registered from, for display
+    pub name: String,
+}
+
+// A rule applies when every match field that is set equals the message's value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecoderRule {
+    pub name: String,
+    // Queue or topic name; a topic rule covers all of its subscriptions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    // Compared without parameters and ignoring case, e.g. "application/x-protobuf"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub decoder: BodyDecoder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BodyDecoder {
+    #[serde(rename_all = "camelCase")]
+    Protobuf { descriptor_set_id: String, message_type: String },
+    #[serde(rename_all = "camelCase")]
+    Avro { schema_id: String },
+    // Writer schema looked up by the id each message carries
+    #[serde(rename_all = "camelCase")]
+    AvroRegistry { endpoint: String },
+    // AES-GCM decrypt, then decode the plaintext with then (JSON or text without it)
+    #[serde(rename_all = "camelCase")]
+    Decrypt {
+        key: EncryptionKey,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        then: Option<Box<BodyDecoder>>,
+    },
+}
+
+impl DecoderRule {
+    fn matches(&self, message: &ServiceBusMessage, entity: Option<&str>) -> bool {
+        // Entity names are case-insensitive in Service Bus
+        let entity_matches = match &self.entity {
+            Some(rule_entity) => entity.is_some_and(|e| e.eq_ignore_ascii_case(rule_entity)),
+            None => true,
+        };
+        let subject_matches = match &self.subject {
+            Some(subject) => message.subject.as_deref() == Some(subject.as_str()),
+            None => true,
+        };
+        let content_type_matches = match &self.content_type {
+            Some(content_type) => message
+                .content_type
+                .as_deref()
+                .map(media_type)
+                .is_some_and(|m| m.eq_ignore_ascii_case(media_type(content_type))),
+            None => true,
+        };
+        entity_matches && subject_matches && content_type_matches
+    }
+}
+
+fn media_type(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or_default().trim()
+}
+
+fn decoders_path() -> Result<PathBuf, String> {
+    Ok(crate::config::config_dir()?.join(DECODERS_FILE_NAME))
+}
+
+fn schema_path(file_name: &str) -> Result<PathBuf, String> {
+    Ok(crate::config::config_dir()?.join(SCHEMA_DIR_NAME).join(file_name))
+}
+
+pub fn load_config() -> Result<DecoderConfig, String> {
+    let path = decoders_path()?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(DecoderConfig::default()),
+        Err(e) => return Err(format!("Failed to read decoders from {}: {}", path.display(), e)),
+    };
+    serde_json::from_str(&contents).map_err(|e| format!("Failed to parse decoders in {}: {}", path.display(), e))
+}
+
+// Written to a temp file and renamed, like settings.json
+fn save_config(config: &DecoderConfig) -> Result<(), String> {
+    let path = decoders_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory {}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(config).map_err(|e| format!("Failed to serialize decoders: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)
+        .map_err(|e| format!("Failed to write decoders to {}: {}", tmp_path.display(), e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to save decoders to {}: {}", path.display(), e))
+}
+
+// Copy a descriptor set into the config directory. Registering the same file twice
+// returns the existing entry.
+pub fn add_descriptor_set(path: &Path) -> Result<DescriptorSetInfo, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let message_types = protobuf::message_types(&bytes)?;
+    let id = content_id(&bytes);
+
+    let mut config = load_config()?;
+    if let Some(existing) = config.descriptor_sets.iter().find(|set| set.id == id) {
+        return Ok(existing.clone());
+    }
+
+    let stored = schema_path(&format!("{}.pb", id))?;
+    if let Some(dir) = stored.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&stored, &bytes).map_err(|e| format!("Failed to write {}: {}", stored.display(), e))?;
+
+    let info = DescriptorSetInfo {
+        id,
+        name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        message_types,
+    };
+    config.descriptor_sets.push(info.clone());
+    save_config(&config)?;
+    Ok(info)
+}
+
+pub fn remove_descriptor_set(id: &str) -> Result<(), String> {
+    let mut config = load_config()?;
+    if let Some(rule) = config.rules.iter().find(|rule| rule.decoder.descriptor_set_id() == Some(id)) {
+        return Err(format!("Descriptor set is still used by decoder rule '{}'", rule.name));
+    }
+    let before = config.descriptor_sets.len();
+    config.descriptor_sets.retain(|set| set.id != id);
+    if config.descriptor_sets.len() == before {
+        return Err(format!("Descriptor set '{}' not found", id));
+    }
+    save_config(&config)?;
+
+    let stored = schema_path(&format!("{}.pb", id))?;
+    match std::fs::remove_file(&stored) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to delete {}: {}", stored.display(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Copy an Avro schema into the config directory. Registering the same file twice
+// returns the existing entry.
+pub fn add_avro_schema(path: &Path) -> Result<AvroSchemaInfo, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    avro::AvroSchema::parse(&text).map_err(|e| format!("{} is not a valid Avro schema: {}", path.display(), e))?;
+    let id = content_id(text.as_bytes());
+
+    let mut config = load_config()?;
+    if let Some(existing) = config.avro_schemas.iter().find(|schema| schema.id == id) {
+        return Ok(existing.clone());
+    }
+
+    let stored = schema_path(&format!("{}.avsc", id))?;
+    if let Some(dir) = stored.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&stored, &text).map_err(|e| format!("Failed to write {}: {}", stored.display(), e))?;
+
+    let info = AvroSchemaInfo {
+        id,
+        name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+    };
+    config.avro_schemas.push(info.clone());
+    save_config(&config)?;
+    Ok(info)
+}
+
+pub fn remove_avro_schema(id: &str) -> Result<(), String> {
+    let mut config = load_config()?;
+    if let Some(rule) = config.rules.iter().find(|rule| rule.decoder.avro_schema_id() == Some(id)) {
+        return Err(format!("Avro schema is still used by decoder rule '{}'", rule.name));
+    }
+    let before = config.avro_schemas.len();
+    config.avro_schemas.retain(|schema| schema.id != id);
+    if config.avro_schemas.len() == before {
+        return Err(format!("Avro schema '{}' not found", id));
+    }
+    save_config(&config)?;
+
+    let stored = schema_path(&format!("{}.avsc", id))?;
+    match std::fs::remove_file(&stored) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to delete {}: {}", stored.display(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Replace all rules. Every rule must match on something and point at a known type.
+pub fn set_rules(rules: Vec<DecoderRule>) -> Result<DecoderConfig, String> {
+    let mut config = load_config()?;
+    for rule in &rules {
+        if rule.entity.is_none() && rule.subject.is_none() && rule.content_type.is_none() {
+            return Err(format!(
+                "Decoder rule '{}' must match on an entity, subject or content type",
+                rule.name
+            ));
+        }
+        validate_decoder(&config, &rule.name, &rule.decoder)?;
+        if let BodyDecoder::Decrypt { then: Some(then), .. } = &rule.decoder {
+            if matches!(**then, BodyDecoder::Decrypt { .. }) {
+                return Err(format!("Decoder rule '{}': a body can only be decrypted once", rule.name));
+            }
+            validate_decoder(&config, &rule.name, then)?;
+        }
+    }
+    config.rules = rules;
+    save_config(&config)?;
+    Ok(config)
+}
+
+fn validate_decoder(config: &DecoderConfig, rule_name: &str, decoder: &BodyDecoder) -> Result<(), String> {
+    match decoder {
+        BodyDecoder::Protobuf { descriptor_set_id, message_type } => {
+            let set = config
+                .descriptor_sets
+                .iter()
+                .find(|set| &set.id == descriptor_set_id)
+                .ok_or_else(|| format!("Decoder rule '{}': descriptor set '{}' not found", rule_name, descriptor_set_id))?;
+            if !set.message_types.contains(message_type) {
+                return Err(format!(
+                    "Decoder rule '{}': message type '{}' is not in {}",
+                    rule_name, message_type, set.name
+                ));
+            }
+        }
+        BodyDecoder::Avro { schema_id } => {
+            if !config.avro_schemas.iter().any(|schema| &schema.id == schema_id) {
+                return Err(format!("Decoder rule '{}': Avro schema '{}' not found", rule_name, schema_id));
+            }
+        }
+        BodyDecoder::AvroRegistry { endpoint } => {
+            if endpoint.trim().is_empty() {
+                return Err(format!("Decoder rule '{}': schema registry endpoint is required", rule_name));
+            }
+        }
+        BodyDecoder::Decrypt { key: EncryptionKey::Keychain { key_id }, .. } => {
+            if key_id.trim().is_empty() {
+                return Err(format!("Decoder rule '{}': an encryption key id is required", rule_name));
+            }
+        }
+        BodyDecoder::Decrypt { key: EncryptionKey::KeyVault { vault_url, secret_name }, .. } => {
+            if vault_url.trim().is_empty() || secret_name.trim().is_empty() {
+                return Err(format!("Decoder rule '{}': a Key Vault URL and secret name are required", rule_name));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl BodyDecoder {
+    fn descriptor_set_id(&self) -> Option<&str> {
+        match self {
+            BodyDecoder::Protobuf { descriptor_set_id, .. } => Some(descriptor_set_id),
+            BodyDecoder::Decrypt { then, .. } => then.as_deref().and_then(BodyDecoder::descriptor_set_id),
+            BodyDecoder::Avro { .. } | BodyDecoder::AvroRegistry { .. } => None,
+        }
+    }
+
+    fn avro_schema_id(&self) -> Option<&str> {
+        match self {
+            BodyDecoder::Avro { schema_id } => Some(schema_id),
+            BodyDecoder::Decrypt { then, .. } => then.as_deref().and_then(BodyDecoder::avro_schema_id),
+            BodyDecoder::Protobuf { .. } | BodyDecoder::AvroRegistry { .. } => None,
+        }
+    }
+}
+
+// Fill decoded_body (or decode_error) for every message a rule matches. The first
+// matching rule wins. `entity` is the queue or topic the messages came from. Problems
+// loading the configuration are logged, never surfaced: decoding is a display nicety
+// and must not break peeking.
+pub async fn decode_messages(
+    messages: &mut [ServiceBusMessage],
+    connection: &ServiceBusConnection,
+    entity: Option<&str>,
+) {
+    let config = match load_config() {
+        Ok(config) if !config.rules.is_empty() => config,
+        Ok(_) => return,
+        Err(e) => {
+            eprintln!("[decoders] {}", e);
+            return;
+        }
+    };
+
+    let mut pools = HashMap::new();
+    let mut avro_schemas = HashMap::new();
+    let mut keys = HashMap::new();
+    for message in messages.iter_mut() {
+        let Some(rule) = config.rules.iter().find(|rule| rule.matches(message, entity)) else {
+            continue;
+        };
+        let Some(mut bytes) = body_bytes(message) else {
+            continue;
+        };
+        let mut decoder = Some(&rule.decoder);
+        if let BodyDecoder::Decrypt { key, then } = &rule.decoder {
+            if !keys.contains_key(key) {
+                keys.insert(key.clone(), encryption::resolve_key(connection, key).await);
+            }
+            match keys[key].as_ref().map_err(|e| e.clone()).and_then(|key| encryption::decrypt(key, &bytes)) {
+                Ok(plaintext) => bytes = plaintext,
+                Err(e) => {
+                    message.decode_error = Some(format!("{}: {}", rule.name, e));
+                    continue;
+                }
+            }
+            decoder = then.as_deref();
+        }
+        let decoded = match decoder {
+            None => plaintext_value(&bytes),
+            Some(BodyDecoder::Decrypt { .. }) => Err("A body can only be decrypted once".to_string()),
+            Some(BodyDecoder::Protobuf { descriptor_set_id, message_type }) => pools
+                .entry(descriptor_set_id.clone())
+                .or_insert_with(|| {
+                    read_schema_file(&format!("{}.pb", descriptor_set_id)).and_then(|bytes| protobuf::load_pool(&bytes))
+                })
+                .as_ref()
+                .map_err(|e| e.clone())
+                .and_then(|pool| protobuf::decode(pool, message_type, &bytes)),
+            Some(BodyDecoder::Avro { schema_id }) => avro_schemas
+                .entry(schema_id.clone())
+                .or_insert_with(|| {
+                    read_schema_file(&format!("{}.avsc", schema_id))
+                        .and_then(|bytes| avro::AvroSchema::parse(&String::from_utf8_lossy(&bytes)))
+                })
+                .as_ref()
+                .map_err(|e| e.clone())
+                .and_then(|schema| schema.decode(&bytes)),
+            Some(BodyDecoder::AvroRegistry { endpoint }) => {
+                decode_with_registry(connection, endpoint, message.content_type.as_deref(), &bytes).await
+            }
+        };
+        match decoded {
+            Ok(value) => message.decoded_body = Some(value),
+            Err(e) => message.decode_error = Some(format!("{}: {}", rule.name, e)),
+        }
+    }
+}
+
+async fn decode_with_registry(
+    connection: &ServiceBusConnection,
+    endpoint: &str,
+    content_type: Option<&str>,
+    bytes: &[u8],
+) -> Result<serde_json::Value, String> {
+    let (schema_id, datum) = schema_registry::split_schema_id(content_type, bytes)?;
+    let schema = schema_registry::fetch_schema(connection, endpoint, &schema_id).await?;
+    avro::AvroSchema::parse(&schema)
+        .map_err(|e| format!("Schema {} is not a valid Avro schema: {}", schema_id, e))?
+        .decode(datum)
+}
+
+// Decrypted bodies without a decoder: JSON when they parse, text otherwise
+fn plaintext_value(bytes: &[u8]) -> Result<serde_json::Value, String> {
+    if let Ok(value) = serde_json::from_slice(bytes) {
+        return Ok(value);
+    }
+    String::from_utf8(bytes.to_vec())
+        .map(serde_json::Value::String)
+        .map_err(|_| "Decrypted body is binary: add a decoder for it to the rule".to_string())
+}
+
+// Encrypt the body as it would otherwise be sent. The result is a base64 body without
+// envelope or compression: those are applied to the plaintext before it is encrypted.
+pub async fn encrypt_message(
+    message: &ServiceBusMessage,
+    connection: &ServiceBusConnection,
+    key: &EncryptionKey,
+) -> Result<ServiceBusMessage, String> {
+    use base64::Engine;
+
+    let key = encryption::resolve_key(connection, key).await?;
+    let plaintext = crate::azure::servicebus::message_body_bytes(message)?;
+    let ciphertext = encryption::encrypt(&key, &plaintext)?;
+    Ok(ServiceBusMessage {
+        body: base64::engine::general_purpose::STANDARD.encode(ciphertext).into(),
+        body_encoding: Some("base64".to_string()),
+        body_envelope: None,
+        body_compression: None,
+        raw_body: None,
+        decoded_body: None,
+        ..message.clone()
+    })
+}
+
+fn read_schema_file(file_name: &str) -> Result<Vec<u8>, String> {
+    let path = schema_path(file_name)?;
+    std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+// The bytes as received: base64 bodies are decoded, text bodies are used as-is.
+// Bodies already parsed as JSON (or cut short by a projection) are not decodable.
+fn body_bytes(message: &ServiceBusMessage) -> Option<Vec<u8>> {
+    use base64::Engine;
+    if message.body_truncated == Some(true) {
+        return None;
+    }
+    let body = message.body.as_str()?;
+    match message.body_encoding.as_deref() {
+        Some("base64") => base64::engine::general_purpose::STANDARD.decode(body).ok(),
+        _ => Some(body.as_bytes().to_vec()),
+    }
+}
+
+fn content_id(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(bytes);
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,28 @@
+// Protobuf decoding against a user-supplied FileDescriptorSet
+
+use prost_reflect::{DescriptorPool, DynamicMessage};
+
+pub fn load_pool(descriptor_set: &[u8]) -> Result<DescriptorPool, String> {
+    DescriptorPool::decode(descriptor_set)
+        .map_err(|e| format!("Not a valid Protobuf descriptor set: {}", e))
+}
+
+// Fully qualified names of every message type in the set, e.g. "orders.v1.OrderPlaced"
+pub fn message_types(descriptor_set: &[u8]) -> Result<Vec<String>, String> {
+    let pool = load_pool(descriptor_set)?;
+    let mut names: Vec<String> = pool.all_messages().map(|m| m.full_name().to_string()).collect();
+    if names.is_empty() {
+        return Err("The descriptor set does not define any message types".to_string());
+    }
+    names.sort();
+    Ok(names)
+}
+
+pub fn decode(pool: &DescriptorPool, message_type: &str, body: &[u8]) -> Result<serde_json::Value, String> {
+    let descriptor = pool
+        .get_message_by_name(message_type)
+        .ok_or_else(|| format!("Message type '{}' is not in the descriptor set", message_type))?;
+    let message = DynamicMessage::decode(descriptor, body)
+        .map_err(|e| format!("Body is not a valid {}: {}", message_type, e))?;
+    serde_json::to_value(&message).map_err(|e| format!("Failed to convert {} to JSON: {}", message_type, e))
+}
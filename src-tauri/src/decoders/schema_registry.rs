@@ -0,0 +1,86 @@
+// Writer schemas from an Azure Schema Registry (an Event Hubs namespace)
+//
+// Producers using the Azure Schema Registry Avro serializer either put the schema id in
+// the content type ("avro/binary+<id>") or, in the older format, prefix the body with a
+// 4-byte zero format indicator and the 32-character id. Schemas are immutable per id,
+// so fetched schemas are cached for the life of the process.
+
+use crate::azure::types::ServiceBusConnection;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+const API_VERSION: &str = "2022-10";
+const CONTENT_TYPE_PREFIX: &str = "avro/binary+";
+const LEGACY_ID_LENGTH: usize = 32;
+
+static SCHEMAS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn cached_schemas() -> std::sync::MutexGuard<'static, HashMap<String, String>> {
+    let mutex = SCHEMAS.get_or_init(|| Mutex::new(HashMap::new()));
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// The schema id and the Avro datum that follows it
+pub fn split_schema_id<'a>(content_type: Option<&str>, bytes: &'a [u8]) -> Result<(String, &'a [u8]), String> {
+    if let Some(id) = content_type.and_then(|ct| ct.trim().strip_prefix(CONTENT_TYPE_PREFIX)) {
+        return Ok((id.trim().to_string(), bytes));
+    }
+    if bytes.len() >= 4 + LEGACY_ID_LENGTH && bytes[..4] == [0, 0, 0, 0] {
+        let id = std::str::from_utf8(&bytes[4..4 + LEGACY_ID_LENGTH])
+            .map_err(|_| "Schema id in the body preamble is not text".to_string())?;
+        return Ok((id.to_string(), &bytes[4 + LEGACY_ID_LENGTH..]));
+    }
+    Err(format!(
+        "No schema id: expected a \"{}<id>\" content type or a schema registry preamble",
+        CONTENT_TYPE_PREFIX
+    ))
+}
+
+// Accepts a bare host name ("contoso.servicebus.windows.net") or a URL
+fn registry_url(endpoint: &str, schema_id: &str) -> String {
+    let endpoint = endpoint.trim().trim_end_matches('/');
+    let base = if endpoint.contains("://") {
+        endpoint.to_string()
+    } else {
+        format!("https://{}", endpoint)
+    };
+    format!(
+        "{}/$schemaGroups/$schemas/{}?api-version={}",
+        base,
+        urlencoding::encode(schema_id),
+        API_VERSION
+    )
+}
+
+// The registry only accepts Azure AD tokens, taken from the connection's credential
+pub async fn fetch_schema(connection: &ServiceBusConnection, endpoint: &str, schema_id: &str) -> Result<String, String> {
+    let cache_key = format!("{}|{}", endpoint.trim().to_lowercase(), schema_id);
+    if let Some(schema) = cached_schemas().get(&cache_key) {
+        return Ok(schema.clone());
+    }
+
+    if !connection.use_azure_ad.unwrap_or(false) {
+        return Err("Schema registry lookups need an Azure AD connection".to_string());
+    }
+    let token =
+        crate::azure::auth::acquire_aad_token_for_scope(connection, crate::azure::auth::SCHEMA_REGISTRY_SCOPE).await?;
+
+    let url = registry_url(endpoint, schema_id);
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach schema registry {}: {}", endpoint, e))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read schema {}: {}", schema_id, e))?;
+    if !status.is_success() {
+        return Err(format!("Schema registry returned {} for schema {}: {}", status, schema_id, body));
+    }
+
+    cached_schemas().insert(cache_key, body.clone());
+    Ok(body)
+}
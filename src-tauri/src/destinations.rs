@@ -0,0 +1,352 @@
+// Export destinations besides the local filesystem: an Azure Blob container or an S3
+// bucket, so DLQ archives and reports can land where the whole team reads them
+//
+// Exports are written to a temporary staging directory exactly as they would be
+// locally, then each file is uploaded as one object under the destination's prefix, so
+// every export format works unchanged. Secrets never travel with the destination: a
+// blob container uses a SAS token stored in the keychain under credential_id, or Azure AD
+// (DefaultAzureCredential, needing a Storage Blob Data Contributor role) without one; an
+// S3 bucket uses an access key pair stored the same way.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const BLOB_API_VERSION: &str = "2021-08-06";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ExportDestination {
+    #[serde(rename_all = "camelCase")]
+    AzureBlob {
+        account: String,
+        container: String,
+        // Folder inside the container that every object name is relative to
+        #[serde(default)]
+        prefix: Option<String>,
+        // Keychain entry holding a SAS token; Azure AD is used without one
+        #[serde(default)]
+        credential_id: Option<String>,
+        // Blob service URL, for sovereign clouds and Azurite; defaults to
+        // https://<account>.blob.core.windows.net
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    S3 {
+        bucket: String,
+        region: String,
+        #[serde(default)]
+        prefix: Option<String>,
+        // Keychain entry holding the access key pair
+        credential_id: String,
+        // S3-compatible service URL, addressed path-style; defaults to AWS
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
+}
+
+impl ExportDestination {
+    pub fn credential_id(&self) -> Option<&str> {
+        match self {
+            ExportDestination::AzureBlob { credential_id, .. } => credential_id.as_deref(),
+            ExportDestination::S3 { credential_id, .. } => Some(credential_id),
+        }
+    }
+}
+
+// What store_export_credential keeps in the keychain: {"sasToken": "..."} or
+// {"s3": {"accessKeyId": ..., "secretAccessKey": ...}}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportCredential {
+    // A container SAS token allowing create and write, with or without the leading "?"
+    SasToken(String),
+    #[serde(rename_all = "camelCase")]
+    S3 {
+        access_key_id: String,
+        secret_access_key: String,
+        // For temporary (STS) credentials
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_token: Option<String>,
+    },
+}
+
+enum Auth {
+    SasToken(String),
+    Bearer(String),
+    S3 {
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+    },
+}
+
+// Uploads files to one destination with credentials resolved up front
+pub struct Uploader {
+    destination: ExportDestination,
+    auth: Auth,
+    http: reqwest::Client,
+}
+
+// A temporary directory an export is written to before it is uploaded. Removed when
+// dropped, which includes an operation being cancelled.
+pub struct Staging {
+    dir: PathBuf,
+}
+
+impl Staging {
+    pub fn new() -> Result<Self, String> {
+        let dir = std::env::temp_dir().join(format!("servicebusexplorer-export-{:016x}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(Staging { dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            eprintln!("[destinations] Failed to remove {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+impl Uploader {
+    // Check the destination and resolve its credential; Azure AD tokens are acquired here
+    pub async fn connect(
+        destination: &ExportDestination,
+        credential: Option<ExportCredential>,
+    ) -> Result<Self, String> {
+        let auth = match (destination, credential) {
+            (ExportDestination::AzureBlob { account, container, .. }, credential) => {
+                if account.trim().is_empty() || container.trim().is_empty() {
+                    return Err("A storage account and container are required".to_string());
+                }
+                match credential {
+                    Some(ExportCredential::SasToken(token)) => {
+                        Auth::SasToken(token.trim().trim_start_matches('?').to_string())
+                    }
+                    Some(ExportCredential::S3 { .. }) => {
+                        return Err("The credential is an S3 access key, not a SAS token".to_string())
+                    }
+                    None => {
+                        use crate::azure::auth::{acquire_default_token, STORAGE_SCOPE};
+                        Auth::Bearer(acquire_default_token(STORAGE_SCOPE).await?)
+                    }
+                }
+            }
+            (ExportDestination::S3 { bucket, region, .. }, credential) => {
+                if bucket.trim().is_empty() || region.trim().is_empty() {
+                    return Err("An S3 bucket and region are required".to_string());
+                }
+                match credential {
+                    Some(ExportCredential::S3 { access_key_id, secret_access_key, session_token }) => {
+                        Auth::S3 { access_key_id, secret_access_key, session_token }
+                    }
+                    _ => return Err("An S3 destination needs an access key credential".to_string()),
+                }
+            }
+        };
+        Ok(Uploader { destination: destination.clone(), auth, http: reqwest::Client::new() })
+    }
+
+    // URL of the object for name, without any credential
+    pub fn location(&self, name: &str) -> String {
+        match &self.destination {
+            ExportDestination::AzureBlob { account, container, prefix, endpoint, .. } => {
+                let endpoint = endpoint.clone().unwrap_or_else(|| format!("https://{}.blob.core.windows.net", account));
+                format!(
+                    "{}/{}/{}",
+                    endpoint.trim_end_matches('/'),
+                    container,
+                    encode_key(&object_key(prefix.as_deref(), name))
+                )
+            }
+            ExportDestination::S3 { bucket, region, prefix, endpoint, .. } => {
+                let key = encode_key(&object_key(prefix.as_deref(), name));
+                match endpoint {
+                    Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+                    None => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key),
+                }
+            }
+        }
+    }
+
+    // Upload bytes as name (relative to the prefix); returns the object's location
+    pub async fn upload(&self, name: &str, bytes: Vec<u8>) -> Result<String, String> {
+        let location = self.location(name);
+        let request = match &self.auth {
+            Auth::SasToken(token) => self
+                .http
+                .put(format!("{}?{}", location, token))
+                .header("x-ms-blob-type", "BlockBlob")
+                .header("x-ms-version", BLOB_API_VERSION),
+            Auth::Bearer(token) => self
+                .http
+                .put(&location)
+                .bearer_auth(token)
+                .header("x-ms-blob-type", "BlockBlob")
+                .header("x-ms-version", BLOB_API_VERSION),
+            Auth::S3 { access_key_id, secret_access_key, session_token } => {
+                let ExportDestination::S3 { region, .. } = &self.destination else {
+                    return Err("An S3 access key can only be used with an S3 destination".to_string());
+                };
+                let headers = s3_signed_headers(
+                    &location,
+                    region,
+                    access_key_id,
+                    secret_access_key,
+                    session_token.as_deref(),
+                    &bytes,
+                    chrono::Utc::now(),
+                )?;
+                headers.into_iter().fold(self.http.put(&location), |request, (name, value)| request.header(name, value))
+            }
+        };
+
+        let response = request
+            .header("Content-Type", content_type(name))
+            .body(bytes)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to upload {}: {}", location, e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(crate::azure::errors::http_error(&format!("upload {}", location), status, &body));
+        }
+        Ok(location)
+    }
+
+    pub async fn upload_file(&self, name: &str, path: &Path) -> Result<String, String> {
+        let bytes = tokio::fs::read(path).await.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.upload(name, bytes).await
+    }
+
+    // Upload every file directly in directory as "<remote_directory>/<file name>"
+    pub async fn upload_directory(&self, directory: &Path, remote_directory: &str) -> Result<u32, String> {
+        let entries =
+            std::fs::read_dir(directory).map_err(|e| format!("Failed to read {}: {}", directory.display(), e))?;
+        let mut uploaded = 0u32;
+        for entry in entries {
+            let path = entry.map_err(|e| format!("Failed to read {}: {}", directory.display(), e))?.path();
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()).filter(|_| path.is_file()) else {
+                continue;
+            };
+            self.upload_file(&format!("{}/{}", remote_directory, file_name), &path).await?;
+            uploaded += 1;
+        }
+        eprintln!("[destinations] Uploaded {} files to {}", uploaded, self.location(remote_directory));
+        Ok(uploaded)
+    }
+
+    // Write name's file into a staging directory with write, then upload it.
+    // Returns the location and the bytes written.
+    pub async fn upload_written(
+        &self,
+        name: &str,
+        write: impl FnOnce(&Path) -> Result<u64, String>,
+    ) -> Result<(String, u64), String> {
+        let staging = Staging::new()?;
+        let file_name = name.rsplit('/').find(|segment| !segment.is_empty()).unwrap_or("export");
+        let local = staging.path().join(file_name);
+        let bytes_written = write(&local)?;
+        let location = self.upload_file(name, &local).await?;
+        Ok((location, bytes_written))
+    }
+}
+
+// "<prefix>/<name>" without empty, "." or ".." segments
+fn object_key(prefix: Option<&str>, name: &str) -> String {
+    prefix
+        .unwrap_or_default()
+        .split('/')
+        .chain(name.split('/'))
+        .filter(|segment| !matches!(*segment, "" | "." | ".."))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Percent-encode each segment, keeping the slashes (the encoding S3 signs, too)
+fn encode_key(key: &str) -> String {
+    key.split('/').map(|segment| urlencoding::encode(segment).into_owned()).collect::<Vec<_>>().join("/")
+}
+
+fn content_type(name: &str) -> &'static str {
+    match name.rsplit('.').next().unwrap_or_default().to_ascii_lowercase().as_str() {
+        "json" => "application/json",
+        "ndjson" => "application/x-ndjson",
+        "csv" => "text/csv",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        _ => "application/octet-stream",
+    }
+}
+
+// AWS Signature Version 4 headers for a PUT of body to url
+fn s3_signed_headers(
+    url: &str,
+    region: &str,
+    access_key_id: &str,
+    secret_access_key: &str,
+    session_token: Option<&str>,
+    body: &[u8],
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<(&'static str, String)>, String> {
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let hmac = |key: &[u8], data: &str| -> Result<Vec<u8>, String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|e| format!("Failed to create HMAC: {}", e))?;
+        mac.update(data.as_bytes());
+        Ok(mac.finalize().into_bytes().to_vec())
+    };
+
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid S3 URL {}: {}", url, e))?;
+    let host = match (parsed.host_str(), parsed.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(format!("Invalid S3 URL {}", url)),
+    };
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = hex(&Sha256::digest(body));
+
+    // Header names in the order S3 expects them signed (sorted)
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", payload_hash.clone()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = session_token {
+        headers.push(("x-amz-security-token", token.to_string()));
+    }
+    let canonical_headers: String =
+        headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request =
+        format!("PUT\n{}\n\n{}\n{}\n{}", parsed.path(), canonical_headers, signed_headers, payload_hash);
+
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign =
+        format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex(&Sha256::digest(canonical_request.as_bytes())));
+    let key = hmac(format!("AWS4{}", secret_access_key).as_bytes(), &date)?;
+    let key = hmac(&key, region)?;
+    let key = hmac(&key, "s3")?;
+    let key = hmac(&key, "aws4_request")?;
+    let signature = hex(&hmac(&key, &string_to_sign)?);
+
+    // reqwest sets Host itself
+    headers.retain(|(name, _)| *name != "host");
+    headers.push((
+        "Authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    Ok(headers)
+}
@@ -0,0 +1,203 @@
+// Expiry preview: when each message on an entity will expire by its time to live
+//
+// A message's effective TTL is the smaller of its own TimeToLive and the entity's
+// DefaultMessageTimeToLive, counted from the time it was enqueued. The broker removes
+// expired messages lazily (or moves them to the dead-letter queue when
+// DeadLetteringOnMessageExpiration is on), so a peek can still return messages that
+// are already past their expiry. Messages in a dead-letter queue never expire.
+//
+// Peeks fill in expires_at_utc on each message with the same rules (annotate_expiry).
+
+use crate::azure::sandbox;
+use crate::azure::servicebus::ServiceBusClient;
+use crate::azure::timestamps;
+use crate::azure::types::{MessageSource, ServiceBusConnection, ServiceBusMessage};
+use crate::state::ClientCache;
+use serde::{Deserialize, Serialize};
+
+const PAGE_SIZE: u32 = 250;
+const DEFAULT_MAX_SCAN: u32 = 5000;
+// TimeSpan.MaxValue, the service's "never expires"
+const NEVER_EXPIRES_SECONDS: u64 = 10_675_199 * 86_400;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageExpiry {
+    pub message: ServiceBusMessage,
+    // Effective TTL; absent when the message never expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_to_live_in_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at_utc: Option<String>,
+    // Negative once expired
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in_seconds: Option<i64>,
+    pub expired: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiryPreview {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_message_time_to_live_in_seconds: Option<u64>,
+    // Whether expired messages go to the dead-letter queue instead of being dropped
+    pub dead_lettering_on_message_expiration: bool,
+    pub scanned_count: u32,
+    pub messages: Vec<MessageExpiry>,
+    // Where to continue when the scan stopped at max_scan
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+}
+
+// Peek from from_sequence_number (default: the head) and work out each message's
+// expiry. With expiring_within_seconds only messages that expire within that window,
+// or already have, are returned.
+pub async fn preview_expiry(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+    expiring_within_seconds: Option<u64>,
+) -> Result<ExpiryPreview, String> {
+    let max_scan = max_scan.unwrap_or(DEFAULT_MAX_SCAN);
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let (default_ttl, dead_lettering) = match (&client, &source.queue_name, &source.topic_name, &source.subscription_name) {
+        (Some(client), Some(queue), _, _) => {
+            let queue = client.get_queue(queue).await?;
+            (queue.default_message_time_to_live_in_seconds, queue.dead_lettering_on_message_expiration)
+        }
+        (Some(client), None, Some(topic), Some(subscription)) => {
+            let subscription = client.get_subscription(topic, subscription).await?;
+            (subscription.default_message_time_to_live_in_seconds, subscription.dead_lettering_on_message_expiration)
+        }
+        (None, Some(queue), _, _) => {
+            let queue = sandbox::get_queue(queue)?;
+            (queue.default_message_time_to_live_in_seconds, queue.dead_lettering_on_message_expiration)
+        }
+        (None, _, _, _) => {
+            return Err("[training] Topics and subscriptions are not available in the training sandbox".to_string())
+        }
+        _ => return Err("A queue or a topic subscription is required".to_string()),
+    };
+
+    let now = chrono::Utc::now();
+    let mut preview = ExpiryPreview {
+        default_message_time_to_live_in_seconds: default_ttl,
+        dead_lettering_on_message_expiration: dead_lettering.unwrap_or(false),
+        scanned_count: 0,
+        messages: Vec::new(),
+        next_sequence_number: None,
+    };
+    let mut next_sequence_number = from_sequence_number.unwrap_or(0);
+    while preview.scanned_count < max_scan {
+        let count = (max_scan - preview.scanned_count).min(PAGE_SIZE);
+        let page = match &client {
+            Some(client) => client.peek_source(source, count, Some(next_sequence_number), None).await?,
+            None => sandbox::peek_source(source, count, Some(next_sequence_number))?,
+        };
+        let full_page = page.len() as u32 == count;
+
+        for message in page {
+            preview.scanned_count += 1;
+            if let Some(seq) = message.sequence_number {
+                next_sequence_number = seq + 1;
+            }
+            let expiry = message_expiry(message, default_ttl, source.dead_letter, now);
+            let wanted = match expiring_within_seconds {
+                Some(window) => expiry.expires_in_seconds.is_some_and(|left| left <= window as i64),
+                None => true,
+            };
+            if wanted {
+                preview.messages.push(expiry);
+            }
+        }
+        if !full_page {
+            return Ok(preview);
+        }
+    }
+
+    // Stopped at max_scan with more messages possibly left
+    preview.next_sequence_number = Some(next_sequence_number);
+    Ok(preview)
+}
+
+fn message_expiry(
+    mut message: ServiceBusMessage,
+    default_ttl: Option<u64>,
+    dead_letter: bool,
+    now: chrono::DateTime<chrono::Utc>,
+) -> MessageExpiry {
+    let (ttl, expires_at) = effective_expiry(&message, default_ttl, dead_letter);
+    let expires_in_seconds = expires_at.map(|at| (at - now).num_seconds());
+    message.expires_at_utc = expires_at.map(timestamps::format);
+
+    MessageExpiry {
+        time_to_live_in_seconds: ttl,
+        expires_at_utc: message.expires_at_utc.clone(),
+        expires_in_seconds,
+        expired: expires_at.is_some_and(|at| at <= now),
+        message,
+    }
+}
+
+// Set expires_at_utc on peeked messages. default_ttl is the entity's
+// DefaultMessageTimeToLive, when known.
+pub fn annotate_expiry(messages: &mut [ServiceBusMessage], default_ttl: Option<u64>, dead_letter: bool) {
+    for message in messages {
+        let (_, expires_at) = effective_expiry(message, default_ttl, dead_letter);
+        message.expires_at_utc = expires_at.map(timestamps::format);
+    }
+}
+
+// The DefaultMessageTimeToLive of a queue or subscription. None when it can't be read:
+// the messages' own TTLs are then used alone.
+pub async fn default_time_to_live(
+    client: &ServiceBusClient,
+    queue_name: Option<&str>,
+    topic_name: Option<&str>,
+    subscription_name: Option<&str>,
+) -> Option<u64> {
+    let ttl = match (queue_name, topic_name, subscription_name) {
+        (Some(queue), _, _) => client.get_queue(queue).await.map(|q| q.default_message_time_to_live_in_seconds),
+        (None, Some(topic), Some(subscription)) => client
+            .get_subscription(topic, subscription)
+            .await
+            .map(|s| s.default_message_time_to_live_in_seconds),
+        _ => return None,
+    };
+    ttl.unwrap_or_else(|e| {
+        eprintln!("[expiry] Could not read the default time to live: {}", e);
+        None
+    })
+}
+
+// Effective TTL and expiry time; both absent when the message never expires
+fn effective_expiry(
+    message: &ServiceBusMessage,
+    default_ttl: Option<u64>,
+    dead_letter: bool,
+) -> (Option<u64>, Option<chrono::DateTime<chrono::Utc>>) {
+    let ttl = match (message.time_to_live, default_ttl) {
+        (Some(own), Some(default)) => Some(own.min(default)),
+        (own, default) => own.or(default),
+    }
+    .filter(|ttl| *ttl < NEVER_EXPIRES_SECONDS && !dead_letter);
+
+    // A scheduled message's clock starts when it is enqueued at its scheduled time
+    let start = if message.state.as_deref() == Some("Scheduled") {
+        message.scheduled_enqueue_time_utc.as_deref().or(message.enqueued_time_utc.as_deref())
+    } else {
+        message.enqueued_time_utc.as_deref()
+    };
+    let expires_at = match (ttl, start.and_then(timestamps::parse)) {
+        (Some(ttl), Some(start)) => start.checked_add_signed(chrono::Duration::seconds(ttl as i64)),
+        _ => None,
+    };
+    (ttl, expires_at)
+}
@@ -0,0 +1,886 @@
+// Export peeked messages to files, for sharing repro payloads, and import them back
+//
+// Messages are peeked (never received), so exporting leaves the entity untouched.
+// Every field of ServiceBusMessage is written, including system and application
+// properties. Importing drops the broker-assigned fields again before sending.
+//
+// For very large entities an export can keep only the first body_preview_bytes of each
+// body. Those messages are marked bodyTruncated, cannot be imported, and can be filled
+// in later with hydrate_messages for the sequence numbers that turn out to matter.
+//
+// CSV and XLSX exports are for people reviewing messages in a spreadsheet: one row per
+// message with the chosen columns (system properties, application properties and fields
+// picked out of JSON bodies), each optionally renamed. They cannot be imported.
+
+use crate::azure::sandbox;
+use crate::azure::timestamps;
+use crate::azure::types::{MessageSource, ServiceBusConnection, ServiceBusMessage};
+use crate::state::ClientCache;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const PAGE_SIZE: u32 = 250;
+const BODY_PREVIEW_CHARS: usize = 200;
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const DEFAULT_MAX_DEAD_LETTERS_PER_ENTITY: u32 = 10_000;
+// Excel refuses longer cell text
+const XLSX_MAX_CELL_CHARS: usize = 32_767;
+const XLSX_MAX_COLUMN_WIDTH: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    // A single JSON array
+    Json,
+    // One JSON object per line
+    Ndjson,
+    // One row per message with the selected columns, for spreadsheets
+    Csv,
+    // The same rows as an Excel workbook, with a bold, frozen and filterable header row
+    Xlsx,
+}
+
+// A CSV/XLSX column: "sequenceNumber", ..., {"property": "<name>"} for an application
+// property or {"bodyField": "<path>"} for a field of a JSON body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CsvColumn {
+    SequenceNumber,
+    EnqueuedTime,
+    MessageId,
+    CorrelationId,
+    SessionId,
+    Subject,
+    ContentType,
+    DeliveryCount,
+    DeadLetterReason,
+    BodyPreview,
+    Property(String),
+    To,
+    ReplyTo,
+    PartitionKey,
+    TimeToLive,
+    ScheduledEnqueueTime,
+    LockedUntil,
+    DeadLetterErrorDescription,
+    DeadLetterSource,
+    State,
+    // Dotted path ("order.lines.0.sku") or JSON pointer ("/order/lines/0/sku")
+    BodyField(String),
+}
+
+// A column as selected for an export, optionally under a header of the user's choosing:
+// a CsvColumn, or {"column": <CsvColumn>, "header": "Customer"}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExportColumn {
+    Mapped { column: CsvColumn, header: String },
+    Plain(CsvColumn),
+}
+
+impl ExportColumn {
+    fn column(&self) -> &CsvColumn {
+        match self {
+            ExportColumn::Mapped { column, .. } | ExportColumn::Plain(column) => column,
+        }
+    }
+
+    fn header(&self) -> &str {
+        match self {
+            ExportColumn::Mapped { header, .. } => header,
+            ExportColumn::Plain(column) => column.header(),
+        }
+    }
+}
+
+impl CsvColumn {
+    fn header(&self) -> &str {
+        match self {
+            CsvColumn::SequenceNumber => "SequenceNumber",
+            CsvColumn::EnqueuedTime => "EnqueuedTimeUtc",
+            CsvColumn::MessageId => "MessageId",
+            CsvColumn::CorrelationId => "CorrelationId",
+            CsvColumn::SessionId => "SessionId",
+            CsvColumn::Subject => "Subject",
+            CsvColumn::ContentType => "ContentType",
+            CsvColumn::DeliveryCount => "DeliveryCount",
+            CsvColumn::DeadLetterReason => "DeadLetterReason",
+            CsvColumn::BodyPreview => "BodyPreview",
+            CsvColumn::Property(name) => name,
+            CsvColumn::To => "To",
+            CsvColumn::ReplyTo => "ReplyTo",
+            CsvColumn::PartitionKey => "PartitionKey",
+            CsvColumn::TimeToLive => "TimeToLiveSeconds",
+            CsvColumn::ScheduledEnqueueTime => "ScheduledEnqueueTimeUtc",
+            CsvColumn::LockedUntil => "LockedUntilUtc",
+            CsvColumn::DeadLetterErrorDescription => "DeadLetterErrorDescription",
+            CsvColumn::DeadLetterSource => "DeadLetterSource",
+            CsvColumn::State => "State",
+            CsvColumn::BodyField(path) => path,
+        }
+    }
+
+    // Written as numbers rather than text in XLSX
+    fn is_numeric(&self) -> bool {
+        matches!(self, CsvColumn::SequenceNumber | CsvColumn::DeliveryCount | CsvColumn::TimeToLive)
+    }
+
+    fn value(&self, message: &ServiceBusMessage) -> String {
+        match self {
+            CsvColumn::SequenceNumber => message.sequence_number.map(|n| n.to_string()).unwrap_or_default(),
+            CsvColumn::EnqueuedTime => message.enqueued_time_utc.clone().unwrap_or_default(),
+            CsvColumn::MessageId => message.message_id.clone().unwrap_or_default(),
+            CsvColumn::CorrelationId => message.correlation_id.clone().unwrap_or_default(),
+            CsvColumn::SessionId => message.session_id.clone().unwrap_or_default(),
+            CsvColumn::Subject => message.subject.clone().unwrap_or_default(),
+            CsvColumn::ContentType => message.content_type.clone().unwrap_or_default(),
+            CsvColumn::DeliveryCount => message.delivery_count.map(|n| n.to_string()).unwrap_or_default(),
+            CsvColumn::DeadLetterReason => message.dead_letter_reason.clone().unwrap_or_default(),
+            CsvColumn::BodyPreview => {
+                let body = match &message.body {
+                    serde_json::Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                body.chars().take(BODY_PREVIEW_CHARS).collect()
+            }
+            CsvColumn::Property(name) => match message.application_properties.as_ref().and_then(|p| p.get(name)) {
+                Some(serde_json::Value::String(text)) => text.clone(),
+                Some(serde_json::Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            },
+            CsvColumn::To => message.to.clone().unwrap_or_default(),
+            CsvColumn::ReplyTo => message.reply_to.clone().unwrap_or_default(),
+            CsvColumn::PartitionKey => message.partition_key.clone().unwrap_or_default(),
+            CsvColumn::TimeToLive => message.time_to_live.map(|n| n.to_string()).unwrap_or_default(),
+            CsvColumn::ScheduledEnqueueTime => message.scheduled_enqueue_time_utc.clone().unwrap_or_default(),
+            CsvColumn::LockedUntil => message.locked_until_utc.clone().unwrap_or_default(),
+            CsvColumn::DeadLetterErrorDescription => message.dead_letter_error_description.clone().unwrap_or_default(),
+            CsvColumn::DeadLetterSource => message.dead_letter_source.clone().unwrap_or_default(),
+            CsvColumn::State => message.state.clone().unwrap_or_default(),
+            CsvColumn::BodyField(path) => match body_field(message, path) {
+                Some(serde_json::Value::String(text)) => text,
+                Some(serde_json::Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            },
+        }
+    }
+}
+
+// The body as JSON (the decoded body when a decoder rule produced one). Bodies kept as
+// text are parsed first, in case they are JSON sent with a non-JSON content type; binary
+// bodies are None.
+pub fn json_body(message: &ServiceBusMessage) -> Option<std::borrow::Cow<'_, serde_json::Value>> {
+    use std::borrow::Cow;
+
+    if let Some(decoded) = &message.decoded_body {
+        return Some(Cow::Borrowed(decoded));
+    }
+    match &message.body {
+        _ if message.body_encoding.is_some() => None,
+        serde_json::Value::String(text) => serde_json::from_str(text).ok().map(Cow::Owned),
+        other => Some(Cow::Borrowed(other)),
+    }
+}
+
+// A field of the body as json_body reads it
+pub fn body_field(message: &ServiceBusMessage, path: &str) -> Option<serde_json::Value> {
+    let body = json_body(message)?;
+    let body = body.as_ref();
+    if path.starts_with('/') {
+        return body.pointer(path).cloned();
+    }
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(body, |value, segment| match value {
+            serde_json::Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+            other => other.get(segment),
+        })
+        .cloned()
+}
+
+pub fn default_csv_columns() -> Vec<ExportColumn> {
+    [
+        CsvColumn::SequenceNumber,
+        CsvColumn::EnqueuedTime,
+        CsvColumn::MessageId,
+        CsvColumn::Subject,
+        CsvColumn::BodyPreview,
+    ]
+    .into_iter()
+    .map(ExportColumn::Plain)
+    .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub path: String,
+    pub format: ExportFormat,
+    pub message_count: u32,
+    pub bytes_written: u64,
+    // Messages whose body was cut down to the preview
+    pub truncated_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HydrateResult {
+    pub path: String,
+    pub format: ExportFormat,
+    // Messages whose full body was written back
+    pub hydrated: u32,
+    // Requested sequence numbers no longer on the entity (received or expired since)
+    pub missing: Vec<u64>,
+    // Requested sequence numbers that are not in the file
+    pub not_in_file: Vec<u64>,
+    pub bytes_written: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub path: String,
+    pub format: ExportFormat,
+    pub message_count: u32,
+}
+
+// One dead-letter queue in a namespace-wide export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterExportEntry {
+    pub entity_path: String,
+    // File name inside the export directory; absent when the export of this entity failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    // Dead-letter count from the listing, before peeking
+    pub dead_letter_count: u64,
+    pub message_count: u32,
+    pub bytes_written: u64,
+    // More dead letters than max_per_entity were waiting
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Written to manifest.json next to the exported files, and returned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterExportManifest {
+    pub namespace: String,
+    pub exported_at: String,
+    pub directory: String,
+    pub entities: Vec<DeadLetterExportEntry>,
+    pub total_messages: u64,
+    pub failed_entities: u32,
+}
+
+// Payload of the "export-dead-letters-progress" event, sent after each entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterExportProgress {
+    pub entity_path: String,
+    // 1-based position among the entities that have dead letters
+    pub index: u32,
+    pub total: u32,
+    pub total_messages: u64,
+}
+
+// Export every queue and subscription DLQ that has messages to its own NDJSON file in
+// directory, then write manifest.json. An entity that fails is recorded in the manifest
+// and the export moves on to the next one.
+pub async fn export_dead_letters(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    directory: &Path,
+    max_per_entity: Option<u32>,
+    on_progress: &(dyn Fn(&DeadLetterExportProgress) + Send + Sync),
+) -> Result<DeadLetterExportManifest, String> {
+    if !directory.is_absolute() {
+        return Err(format!("Export directory must be absolute: {}", directory.display()));
+    }
+    std::fs::create_dir_all(directory)
+        .map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+    let max_per_entity = max_per_entity.unwrap_or(DEFAULT_MAX_DEAD_LETTERS_PER_ENTITY);
+
+    // (source, entity path, file name stem, dead-letter count)
+    let mut targets: Vec<(MessageSource, String, String, u64)> = Vec::new();
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+    let queues = match &client {
+        Some(client) => client.list_queues().await?,
+        None => sandbox::list_queues()?,
+    };
+    for queue in queues {
+        let count = queue.dead_letter_message_count.unwrap_or(0);
+        if count > 0 {
+            let source = MessageSource {
+                queue_name: Some(queue.name.clone()),
+                topic_name: None,
+                subscription_name: None,
+                dead_letter: true,
+            };
+            targets.push((source, queue.name.clone(), format!("queue-{}", queue.name), count));
+        }
+    }
+    // The sandbox only has queues
+    if let Some(client) = &client {
+        for topic in client.list_topics().await? {
+            for subscription in client.list_subscriptions(&topic.name).await? {
+                let count = subscription.dead_letter_message_count.unwrap_or(0);
+                if count > 0 {
+                    let source = MessageSource {
+                        queue_name: None,
+                        topic_name: Some(topic.name.clone()),
+                        subscription_name: Some(subscription.subscription_name.clone()),
+                        dead_letter: true,
+                    };
+                    let path = format!("{}/Subscriptions/{}", topic.name, subscription.subscription_name);
+                    let stem = format!("subscription-{}-{}", topic.name, subscription.subscription_name);
+                    targets.push((source, path, stem, count));
+                }
+            }
+        }
+    }
+
+    let mut manifest = DeadLetterExportManifest {
+        namespace: connection.name.clone(),
+        exported_at: timestamps::format(chrono::Utc::now()),
+        directory: directory.display().to_string(),
+        entities: Vec::new(),
+        total_messages: 0,
+        failed_entities: 0,
+    };
+    let total = targets.len() as u32;
+    let mut used_names = std::collections::HashSet::new();
+    for (index, (source, entity_path, stem, dead_letter_count)) in targets.into_iter().enumerate() {
+        let file = unique_file_name(&stem, &mut used_names);
+        let mut entry = DeadLetterExportEntry {
+            entity_path: entity_path.clone(),
+            file: None,
+            dead_letter_count,
+            message_count: 0,
+            bytes_written: 0,
+            truncated: false,
+            error: None,
+        };
+        let exported = match peek_messages(clients, connection, &source, max_per_entity, None, None).await {
+            Ok(messages) => write_messages(&directory.join(&file), &messages, ExportFormat::Ndjson, &[])
+                .map(|bytes_written| (messages.len() as u32, bytes_written)),
+            Err(e) => Err(e),
+        };
+        match exported {
+            Ok((message_count, bytes_written)) => {
+                entry.file = Some(file);
+                entry.message_count = message_count;
+                entry.bytes_written = bytes_written;
+                entry.truncated = message_count >= max_per_entity && dead_letter_count > message_count as u64;
+                manifest.total_messages += message_count as u64;
+            }
+            Err(e) => {
+                eprintln!("[export_dead_letters] Failed to export {}: {}", entity_path, e);
+                entry.error = Some(e);
+                manifest.failed_entities += 1;
+            }
+        }
+        manifest.entities.push(entry);
+        on_progress(&DeadLetterExportProgress {
+            entity_path,
+            index: index as u32 + 1,
+            total,
+            total_messages: manifest.total_messages,
+        });
+    }
+
+    write_manifest(directory, &manifest)?;
+    eprintln!(
+        "[export_dead_letters] Exported {} messages from {} entities to {}",
+        manifest.total_messages,
+        total,
+        directory.display()
+    );
+    Ok(manifest)
+}
+
+// (Re)write manifest.json in directory
+pub fn write_manifest(directory: &Path, manifest: &DeadLetterExportManifest) -> Result<(), String> {
+    let manifest_path = directory.join(MANIFEST_FILE_NAME);
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    std::fs::write(&manifest_path, json).map_err(|e| format!("Failed to write {}: {}", manifest_path.display(), e))
+}
+
+// "<stem>.ndjson" with characters that aren't safe in file names replaced; names that
+// collide after the replacement get a numeric suffix
+fn unique_file_name(stem: &str, used: &mut std::collections::HashSet<String>) -> String {
+    let safe: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    let mut name = format!("{}.ndjson", safe);
+    let mut suffix = 2;
+    while !used.insert(name.to_ascii_lowercase()) {
+        name = format!("{}-{}.ndjson", safe, suffix);
+        suffix += 1;
+    }
+    name
+}
+
+// Peek up to max_count messages from the head of a source, paging by sequence number.
+// With body_preview_bytes only that much of each body is kept. on_progress gets the
+// number peeked so far after every page.
+pub async fn peek_messages(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    max_count: u32,
+    body_preview_bytes: Option<u32>,
+    on_progress: Option<&(dyn Fn(u32) + Send + Sync)>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let mut messages: Vec<ServiceBusMessage> = Vec::new();
+    let mut from_sequence_number = 0u64;
+    while (messages.len() as u32) < max_count {
+        let count = (max_count - messages.len() as u32).min(PAGE_SIZE);
+        let mut page = match &client {
+            Some(client) => client.peek_source(source, count, Some(from_sequence_number), body_preview_bytes).await?,
+            None => sandbox::peek_source(source, count, Some(from_sequence_number))?,
+        };
+        let Some(last) = page.last().and_then(|m| m.sequence_number) else {
+            break;
+        };
+        from_sequence_number = last + 1;
+        let full_page = page.len() as u32 == count;
+        if let Some(limit) = body_preview_bytes {
+            page.iter_mut().for_each(|message| truncate_body(message, limit));
+        }
+        messages.extend(page);
+        if let Some(callback) = on_progress {
+            callback(messages.len() as u32);
+        }
+        if !full_page {
+            break;
+        }
+    }
+    Ok(messages)
+}
+
+// Cut a body that was peeked in full (over HTTP, or in the sandbox) down to limit bytes
+// of its text, the way a peek with max_body_bytes does
+fn truncate_body(message: &mut ServiceBusMessage, limit: u32) {
+    if message.body_size.is_some() {
+        return;
+    }
+    let text = match &message.body {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    let mut end = (limit as usize).min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    message.body_size = Some(text.len() as u64);
+    message.body_truncated = Some(end < text.len());
+    message.body = if limit == 0 { serde_json::Value::Null } else { serde_json::Value::String(text[..end].to_string()) };
+    message.decoded_body = None;
+}
+
+// Replace truncated bodies in an exported JSON or NDJSON file with the full bodies,
+// peeked again from source. sequence_numbers defaults to every truncated message in
+// the file. The file is rewritten in place in its own format.
+pub async fn hydrate_messages(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    path: &Path,
+    sequence_numbers: Option<Vec<u64>>,
+    on_progress: Option<&(dyn Fn(u32) + Send + Sync)>,
+) -> Result<HydrateResult, String> {
+    let (mut messages, format) = read_messages(path)?;
+    let mut wanted: Vec<u64> = sequence_numbers.unwrap_or_else(|| {
+        messages
+            .iter()
+            .filter(|m| m.body_truncated == Some(true))
+            .filter_map(|m| m.sequence_number)
+            .collect()
+    });
+    wanted.sort_unstable();
+    wanted.dedup();
+
+    let positions: std::collections::HashMap<u64, usize> = messages
+        .iter()
+        .enumerate()
+        .filter_map(|(index, m)| m.sequence_number.map(|seq| (seq, index)))
+        .collect();
+    let (wanted, not_in_file): (Vec<u64>, Vec<u64>) = wanted.into_iter().partition(|seq| positions.contains_key(seq));
+
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+    let mut hydrated = 0u32;
+    let mut missing = Vec::new();
+    for (done, sequence_number) in wanted.into_iter().enumerate() {
+        // A peek from a sequence number returns the first message at or after it
+        let page = match &client {
+            Some(client) => client.peek_source(source, 1, Some(sequence_number), None).await?,
+            None => sandbox::peek_source(source, 1, Some(sequence_number))?,
+        };
+        match page.into_iter().find(|m| m.sequence_number == Some(sequence_number)) {
+            Some(full) => {
+                let message = &mut messages[positions[&sequence_number]];
+                message.body = full.body;
+                message.body_encoding = full.body_encoding;
+                message.body_hex_preview = full.body_hex_preview;
+                message.body_envelope = full.body_envelope;
+                message.body_size = None;
+                message.body_truncated = None;
+                hydrated += 1;
+            }
+            None => missing.push(sequence_number),
+        }
+        if let Some(callback) = on_progress {
+            callback(done as u32 + 1);
+        }
+    }
+
+    // Written next to the original first, so a failure halfway leaves the export intact
+    let staging = path.with_extension("hydrating");
+    let bytes_written = write_messages(&staging, &messages, format, &[])?;
+    std::fs::rename(&staging, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+    eprintln!(
+        "[hydrate_messages] {}: {} hydrated, {} missing, {} not in file",
+        path.display(),
+        hydrated,
+        missing.len(),
+        not_in_file.len()
+    );
+    Ok(HydrateResult {
+        path: path.display().to_string(),
+        format,
+        hydrated,
+        missing,
+        not_in_file,
+        bytes_written,
+    })
+}
+
+// columns only applies to CSV and XLSX
+pub fn write_messages(
+    path: &Path,
+    messages: &[ServiceBusMessage],
+    format: ExportFormat,
+    columns: &[ExportColumn],
+) -> Result<u64, String> {
+    if !path.is_absolute() {
+        return Err(format!("Export path must be absolute: {}", path.display()));
+    }
+
+    let file = std::fs::File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut writer = std::io::BufWriter::new(file);
+    let write_error = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, messages)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            writeln!(writer).map_err(write_error)?;
+        }
+        ExportFormat::Ndjson => {
+            for message in messages {
+                serde_json::to_writer(&mut writer, message)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                writeln!(writer).map_err(write_error)?;
+            }
+        }
+        ExportFormat::Csv => {
+            if columns.is_empty() {
+                return Err("At least one CSV column must be selected".to_string());
+            }
+            let header: Vec<String> = columns.iter().map(|c| csv_field(c.header())).collect();
+            write!(writer, "{}\r\n", header.join(",")).map_err(write_error)?;
+            for message in messages {
+                let row: Vec<String> = columns.iter().map(|c| csv_field(&c.column().value(message))).collect();
+                write!(writer, "{}\r\n", row.join(",")).map_err(write_error)?;
+            }
+        }
+        ExportFormat::Xlsx => {
+            if columns.is_empty() {
+                return Err("At least one column must be selected".to_string());
+            }
+            let sheet_name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Messages");
+            write_xlsx(&mut writer, sheet_name, messages, columns)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+    }
+    writer.flush().map_err(write_error)?;
+
+    std::fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+// Read messages written by write_messages; the format is detected from the first character
+pub fn read_messages(path: &Path) -> Result<(Vec<ServiceBusMessage>, ExportFormat), String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    // XLSX files are zip archives
+    if bytes.starts_with(b"PK") {
+        return Err(format!("{} is an Excel export; only JSON and NDJSON exports can be read back", path.display()));
+    }
+    let contents =
+        String::from_utf8(bytes).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    if contents.trim_start().starts_with('[') {
+        let messages = serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid message file {}: {}", path.display(), e))?;
+        return Ok((messages, ExportFormat::Json));
+    }
+
+    let mut messages = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = serde_json::from_str(line)
+            .map_err(|e| format!("Invalid message on line {} of {}: {}", index + 1, path.display(), e))?;
+        messages.push(message);
+    }
+    Ok((messages, ExportFormat::Ndjson))
+}
+
+// Strip what the broker assigned on the original entity so the message can be sent again
+pub fn prepare_for_send(
+    messages: Vec<ServiceBusMessage>,
+    regenerate_message_ids: bool,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    messages
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut message)| {
+            // A body cut down by max_body_bytes would silently be replayed incomplete
+            if message.body_truncated == Some(true) {
+                return Err(format!("Message {} was exported with a truncated body and cannot be imported", index + 1));
+            }
+            if regenerate_message_ids {
+                message.message_id =
+                    Some(format!("{:016x}{:016x}", rand::random::<u64>(), rand::random::<u64>()));
+            }
+            message.delivery_count = None;
+            message.enqueued_time_utc = None;
+            message.locked_until_utc = None;
+            message.sequence_number = None;
+            message.dead_letter_reason = None;
+            message.dead_letter_error_description = None;
+            message.dead_letter_source = None;
+            message.state = None;
+            message.body_size = None;
+            message.body_truncated = None;
+            message.body_hex_preview = None;
+            message.raw_body = None;
+            message.decoded_body = None;
+            message.decode_error = None;
+            message.parse_warning = None;
+            message.expires_at_utc = None;
+            Ok(message)
+        })
+        .collect()
+}
+
+// Send to a queue or topic. With max_messages_per_second, messages go out in one batch
+// per second; otherwise they are sent as fast as the batch size limit allows.
+pub async fn send_messages(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    queue_name: Option<&str>,
+    topic_name: Option<&str>,
+    messages: &[ServiceBusMessage],
+    max_messages_per_second: Option<u32>,
+) -> Result<u32, String> {
+    if sandbox::is_training(connection) {
+        let queue_name =
+            queue_name.ok_or("[training] Topics and subscriptions are not available in the training sandbox")?;
+        if messages.iter().any(|m| m.scheduled_enqueue_time_utc.is_some()) {
+            return Err("Scheduled messages are not supported in the training sandbox".to_string());
+        }
+        for message in messages {
+            sandbox::send_message(queue_name, message)?;
+        }
+        return Ok(messages.len() as u32);
+    }
+
+    let client = clients.get(connection).await?;
+    let chunk_size = match max_messages_per_second {
+        Some(0) => return Err("max_messages_per_second must be greater than 0".to_string()),
+        Some(rate) => rate as usize,
+        None => messages.len().max(1),
+    };
+
+    let mut sent = 0u32;
+    for (index, chunk) in messages.chunks(chunk_size).enumerate() {
+        let started = Instant::now();
+        sent += client
+            .send_messages_batch(queue_name, topic_name, chunk)
+            .await
+            .map_err(|e| format!("Import stopped after {} messages: {}", sent, e))?;
+
+        let is_last = (index + 1) * chunk_size >= messages.len();
+        if max_messages_per_second.is_some() && !is_last {
+            let elapsed = started.elapsed();
+            if elapsed < Duration::from_secs(1) {
+                tokio::time::sleep(Duration::from_secs(1) - elapsed).await;
+            }
+        }
+    }
+    Ok(sent)
+}
+
+// A single-sheet workbook: a bold header row that stays in view and has filters, text
+// cells as inline strings and numeric columns as numbers
+fn write_xlsx<W: std::io::Write + std::io::Seek>(
+    writer: W,
+    sheet_name: &str,
+    messages: &[ServiceBusMessage],
+    columns: &[ExportColumn],
+) -> Result<(), String> {
+    use zip::write::SimpleFileOptions;
+
+    // Sheet names are at most 31 characters and may not contain []:*?/\
+    let sheet_name: String = sheet_name
+        .chars()
+        .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(31)
+        .collect();
+    let sheet_name = if sheet_name.trim().is_empty() { "Messages".to_string() } else { sheet_name };
+
+    let rows: Vec<Vec<String>> = messages
+        .iter()
+        .map(|message| columns.iter().map(|c| c.column().value(message)).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let longest = rows.iter().map(|row| row[index].chars().count()).max().unwrap_or(0);
+            longest.max(column.header().chars().count()).clamp(8, XLSX_MAX_COLUMN_WIDTH) + 2
+        })
+        .collect();
+
+    let mut sheet = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+    );
+    sheet.push_str(r#"<sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><cols>"#);
+    for (index, width) in widths.iter().enumerate() {
+        sheet.push_str(&format!(r#"<col min="{0}" max="{0}" width="{1}" customWidth="1"/>"#, index + 1, width));
+    }
+    sheet.push_str("</cols><sheetData>");
+    let header: Vec<String> = columns.iter().map(|c| c.header().to_string()).collect();
+    sheet.push_str(&xlsx_row(1, &header, None));
+    for (index, row) in rows.iter().enumerate() {
+        sheet.push_str(&xlsx_row(index + 2, row, Some(columns)));
+    }
+    sheet.push_str("</sheetData>");
+    let (last_column, last_row) = (xlsx_column_name(columns.len() - 1), rows.len() + 1);
+    sheet.push_str(&format!(r#"<autoFilter ref="A1:{}{}"/></worksheet>"#, last_column, last_row));
+
+    let workbook = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets><definedNames><definedName name="_xlnm._FilterDatabase" localSheetId="0" hidden="1">'{}'!$A$1:${}${}</definedName></definedNames></workbook>"#,
+        xml_text(&sheet_name),
+        xml_text(&sheet_name.replace('\'', "''")),
+        last_column,
+        last_row
+    );
+    let parts = [
+        (
+            "[Content_Types].xml",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#.to_string(),
+        ),
+        (
+            "_rels/.rels",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#.to_string(),
+        ),
+        ("xl/workbook.xml", workbook),
+        (
+            "xl/_rels/workbook.xml.rels",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#.to_string(),
+        ),
+        (
+            // Style 1 is the bold header
+            "xl/styles.xml",
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs></styleSheet>"#.to_string(),
+        ),
+        ("xl/worksheets/sheet1.xml", sheet),
+    ];
+
+    let mut zip = zip::ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, contents) in parts {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(contents.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// One <row>; the header row (no columns) is bold, and numeric columns hold numbers
+fn xlsx_row(number: usize, values: &[String], columns: Option<&[ExportColumn]>) -> String {
+    let mut row = format!(r#"<row r="{}">"#, number);
+    for (index, value) in values.iter().enumerate() {
+        if value.is_empty() {
+            continue;
+        }
+        let cell = format!("{}{}", xlsx_column_name(index), number);
+        let numeric = columns.is_some_and(|columns| columns[index].column().is_numeric());
+        if numeric && value.parse::<f64>().is_ok() {
+            row.push_str(&format!(r#"<c r="{}"><v>{}</v></c>"#, cell, value));
+        } else {
+            let text: String = value.chars().take(XLSX_MAX_CELL_CHARS).collect();
+            let style = if columns.is_none() { r#" s="1""# } else { "" };
+            row.push_str(&format!(r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#, cell, style, xml_text(&text)));
+        }
+    }
+    row.push_str("</row>");
+    row
+}
+
+// 0 -> A, 25 -> Z, 26 -> AA
+fn xlsx_column_name(index: usize) -> String {
+    let mut name = String::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        name.insert(0, (b'A' + rem as u8) as char);
+        n = (n - 1) / 26;
+    }
+    name
+}
+
+// Escaped for XML, without the control characters XML 1.0 does not allow
+fn xml_text(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// RFC 4180 quoting: fields with commas, quotes or line breaks are quoted, quotes doubled
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,140 @@
+// License status, checked once at startup and then every REFRESH_INTERVAL in the
+// background, so check_license_status answers from memory
+//
+// The App Store check reads the receipt and verifies it with Apple, which needs the
+// network. A check that can't confirm a purchase the previous check confirmed keeps the
+// purchased status, so going offline never locks a paying user out. Every change is
+// emitted as a "license-changed" event.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tauri::Emitter;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// Field names are snake_case: the frontend reads them as returned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseStatus {
+    pub is_trial: bool,
+    pub is_purchased: bool,
+    pub is_expired: bool,
+    pub days_remaining: i32,
+    pub trial_start_date: Option<i64>,
+}
+
+impl LicenseStatus {
+    fn purchased() -> Self {
+        LicenseStatus {
+            is_trial: false,
+            is_purchased: true,
+            is_expired: false,
+            days_remaining: -1,
+            trial_start_date: None,
+        }
+    }
+
+    // Trial tracking is handled in the frontend via localStorage; this only says the
+    // app was not bought
+    #[allow(dead_code)] // Only reached on macOS
+    fn trial() -> Self {
+        LicenseStatus {
+            is_trial: true,
+            is_purchased: false,
+            is_expired: false,
+            days_remaining: 3,
+            trial_start_date: Some(chrono::Utc::now().timestamp()),
+        }
+    }
+
+    fn same_as(&self, other: &LicenseStatus) -> bool {
+        (self.is_trial, self.is_purchased, self.is_expired, self.days_remaining)
+            == (other.is_trial, other.is_purchased, other.is_expired, other.days_remaining)
+    }
+}
+
+// Cheap to clone; held in AppState
+#[derive(Clone, Default)]
+pub struct LicenseManager {
+    // None until the first check finishes
+    status: Arc<RwLock<Option<LicenseStatus>>>,
+}
+
+impl LicenseManager {
+    // The last checked status, or the unchecked default while the first check runs
+    pub fn status(&self) -> LicenseStatus {
+        let status = self.status.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        status.clone().unwrap_or_else(unchecked_status)
+    }
+
+    // Check now, e.g. after a purchase, and emit "license-changed" when the outcome differs
+    pub async fn refresh(&self, app: &tauri::AppHandle) -> LicenseStatus {
+        let previous = self.status.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        // The receipt check blocks on file and network I/O
+        let last = previous.clone();
+        let status = match tokio::task::spawn_blocking(move || check(last.as_ref())).await {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("[licensing] License check failed: {}", e);
+                previous.clone().unwrap_or_else(unchecked_status)
+            }
+        };
+
+        *self.status.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(status.clone());
+        if !previous.is_some_and(|previous| previous.same_as(&status)) {
+            if let Err(e) = app.emit("license-changed", status.clone()) {
+                eprintln!("[licensing] Failed to emit license change: {}", e);
+            }
+        }
+        status
+    }
+
+    // Check at startup, then every REFRESH_INTERVAL for as long as the app runs
+    pub fn start(&self, app: tauri::AppHandle) {
+        let manager = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                manager.refresh(&app).await;
+                tokio::time::sleep(REFRESH_INTERVAL).await;
+            }
+        });
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn unchecked_status() -> LicenseStatus {
+    LicenseStatus::trial()
+}
+
+// Non-macOS platforms: always purchased (no restrictions)
+#[cfg(not(target_os = "macos"))]
+fn unchecked_status() -> LicenseStatus {
+    LicenseStatus::purchased()
+}
+
+#[cfg(target_os = "macos")]
+fn check(previous: Option<&LicenseStatus>) -> LicenseStatus {
+    let verified = match crate::storekit::read_receipt() {
+        Ok(Some(receipt)) => crate::storekit::verify_receipt_with_apple(&receipt),
+        Ok(None) => return LicenseStatus::trial(),
+        Err(e) => Err(e),
+    };
+    match verified {
+        Ok(true) => LicenseStatus::purchased(),
+        // Apple could not be reached or did not answer: keep what we knew
+        _ if previous.is_some_and(|p| p.is_purchased) => {
+            eprintln!("[licensing] Could not confirm the purchase, keeping the previous status");
+            LicenseStatus::purchased()
+        }
+        Ok(false) => LicenseStatus::trial(),
+        Err(e) => {
+            eprintln!("[licensing] Error checking purchase status: {}", e);
+            LicenseStatus::trial()
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn check(_previous: Option<&LicenseStatus>) -> LicenseStatus {
+    LicenseStatus::purchased()
+}
@@ -1,80 +1,43 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 
 #[cfg(target_os = "macos")]
 mod storekit;
 
+mod analytics;
+mod audit;
 mod azure;
+mod config;
+mod decoders;
+mod destinations;
+mod expiry;
+mod export;
+mod licensing;
+mod migrate;
+mod monitor;
+mod operations;
+mod peek_stream;
+mod reconnect;
+mod repeat_send;
+mod resend;
+mod schema;
+mod search;
+mod sharing;
+mod state;
+mod templates;
+mod timeline;
 // Keychain module is no longer used - we use tauri-plugin-keyring directly in commands
 
 use azure::types::*;
-use azure::servicebus::ServiceBusClient;
+use azure::sandbox;
+use state::AppState;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct LicenseStatus {
-    is_trial: bool,
-    is_purchased: bool,
-    is_expired: bool,
-    days_remaining: i32,
-    trial_start_date: Option<i64>,
-}
-
-
-#[cfg(target_os = "macos")]
-#[tauri::command]
-fn check_license_status() -> Result<LicenseStatus, String> {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    
-    // Check if app was purchased via App Store
-    let is_purchased = match storekit::check_purchase_status() {
-        Ok(true) => true,
-        Ok(false) => false,
-        Err(e) => {
-            eprintln!("Error checking purchase status: {}", e);
-            false
-        }
-    };
-    
-    if is_purchased {
-        return Ok(LicenseStatus {
-            is_trial: false,
-            is_purchased: true,
-            is_expired: false,
-            days_remaining: -1,
-            trial_start_date: None,
-        });
-    }
-    
-    // Not purchased - return trial status
-    // Note: Trial tracking is handled in the frontend via localStorage
-    // This function only checks App Store purchase status
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-    
-    Ok(LicenseStatus {
-        is_trial: true,
-        is_purchased: false,
-        is_expired: false,
-        days_remaining: 3,
-        trial_start_date: Some(now),
-    })
-}
-
-#[cfg(not(target_os = "macos"))]
+// Answered from the license cache, which is kept fresh in the background (see licensing.rs)
 #[tauri::command]
-fn check_license_status() -> Result<LicenseStatus, String> {
-    // Non-macOS platforms: always return purchased (no restrictions)
-    Ok(LicenseStatus {
-        is_trial: false,
-        is_purchased: true,
-        is_expired: false,
-        days_remaining: -1,
-        trial_start_date: None,
-    })
+fn check_license_status(state: tauri::State<'_, AppState>) -> Result<licensing::LicenseStatus, String> {
+    Ok(state.license.status())
 }
 
 #[cfg(target_os = "macos")]
@@ -90,30 +53,11 @@ fn initiate_purchase() -> Result<(), String> {
     Err("Purchases are only available on macOS".to_string())
 }
 
-#[cfg(target_os = "macos")]
-#[tauri::command]
-fn verify_receipt() -> Result<bool, String> {
-    // Read receipt from app bundle
-    match storekit::read_receipt() {
-        Ok(Some(receipt_data)) => {
-            // Verify receipt with Apple's servers
-            storekit::verify_receipt_with_apple(&receipt_data)
-        }
-        Ok(None) => {
-            // No receipt found
-            Ok(false)
-        }
-        Err(e) => {
-            Err(format!("Failed to read receipt: {}", e))
-        }
-    }
-}
-
-#[cfg(not(target_os = "macos"))]
+// Check the App Store receipt again now, e.g. right after a purchase; updates the
+// license cache and emits "license-changed" when the outcome differs
 #[tauri::command]
-fn verify_receipt() -> Result<bool, String> {
-    // Non-macOS: always return true (no restrictions)
-    Ok(true)
+async fn verify_receipt(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.license.refresh(&app).await.is_purchased)
 }
 
 #[tauri::command]
@@ -195,6 +139,7 @@ fn get_connection_string(
 #[tauri::command]
 fn delete_connection_string(
     app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
     connection_id: String
 ) -> Result<(), String> {
     use tauri_plugin_keyring::KeyringExt;
@@ -214,6 +159,7 @@ fn delete_connection_string(
     
     // Remove the connection
     all_connections.remove(&connection_id);
+    state.clients.forget(&connection_id);
     
     // Store updated connections back
     let json_data = serde_json::to_string(&all_connections)
@@ -226,6 +172,192 @@ fn delete_connection_string(
     Ok(())
 }
 
+// Export destination credentials share the keychain service with connection strings,
+// under their own entry
+const EXPORT_CREDENTIALS_ACCOUNT: &str = "export_credentials";
+
+type ExportCredentials = std::collections::HashMap<String, destinations::ExportCredential>;
+
+fn load_export_credentials(app: &tauri::AppHandle) -> ExportCredentials {
+    use tauri_plugin_keyring::KeyringExt;
+
+    match app.keyring().get_password("com.azureservicebusexplorer", EXPORT_CREDENTIALS_ACCOUNT) {
+        Ok(Some(json_data)) => serde_json::from_str(&json_data).unwrap_or_default(),
+        _ => Default::default(),
+    }
+}
+
+fn save_export_credentials(
+    app: &tauri::AppHandle,
+    credentials: &ExportCredentials,
+) -> Result<(), String> {
+    use tauri_plugin_keyring::KeyringExt;
+
+    let json_data = serde_json::to_string(credentials)
+        .map_err(|e| format!("Failed to serialize export credentials: {}", e))?;
+    app.keyring()
+        .set_password("com.azureservicebusexplorer", EXPORT_CREDENTIALS_ACCOUNT, &json_data)
+        .map_err(|e| format!("Failed to store export credentials in keychain: {}", e))
+}
+
+// Keep a SAS token or S3 access key for export destinations that name credential_id
+#[tauri::command]
+fn store_export_credential(
+    app: tauri::AppHandle,
+    credential_id: String,
+    credential: destinations::ExportCredential,
+) -> Result<(), String> {
+    let mut credentials = load_export_credentials(&app);
+    credentials.insert(credential_id, credential);
+    save_export_credentials(&app, &credentials)
+}
+
+#[tauri::command]
+fn delete_export_credential(app: tauri::AppHandle, credential_id: String) -> Result<(), String> {
+    let mut credentials = load_export_credentials(&app);
+    credentials.remove(&credential_id);
+    save_export_credentials(&app, &credentials)
+}
+
+// Message encryption keys (base64 by key id), mirrored into the decoders on every change
+const ENCRYPTION_KEYS_ACCOUNT: &str = "encryption_keys";
+
+fn load_encryption_keys(app: &tauri::AppHandle) -> std::collections::HashMap<String, String> {
+    use tauri_plugin_keyring::KeyringExt;
+
+    match app.keyring().get_password("com.azureservicebusexplorer", ENCRYPTION_KEYS_ACCOUNT) {
+        Ok(Some(json_data)) => serde_json::from_str(&json_data).unwrap_or_default(),
+        _ => Default::default(),
+    }
+}
+
+fn save_encryption_keys(app: &tauri::AppHandle, keys: &std::collections::HashMap<String, String>) -> Result<(), String> {
+    use tauri_plugin_keyring::KeyringExt;
+
+    let json_data =
+        serde_json::to_string(keys).map_err(|e| format!("Failed to serialize encryption keys: {}", e))?;
+    app.keyring()
+        .set_password("com.azureservicebusexplorer", ENCRYPTION_KEYS_ACCOUNT, &json_data)
+        .map_err(|e| format!("Failed to store encryption keys in keychain: {}", e))?;
+    decoders::set_keychain_keys(keys);
+    Ok(())
+}
+
+// Ids only: key material never goes back to the UI
+#[tauri::command]
+fn list_encryption_keys(app: tauri::AppHandle) -> Vec<String> {
+    let mut ids: Vec<String> = load_encryption_keys(&app).into_keys().collect();
+    ids.sort();
+    ids
+}
+
+// key is a base64 AES key of 128 or 256 bits, for decoder rules and sends that name key_id
+#[tauri::command]
+fn store_encryption_key(app: tauri::AppHandle, key_id: String, key: String) -> Result<(), String> {
+    if key_id.trim().is_empty() {
+        return Err("An encryption key id is required".to_string());
+    }
+    decoders::parse_key(&key)?;
+    let mut keys = load_encryption_keys(&app);
+    keys.insert(key_id, key.trim().to_string());
+    save_encryption_keys(&app, &keys)
+}
+
+#[tauri::command]
+fn delete_encryption_key(app: tauri::AppHandle, key_id: String) -> Result<(), String> {
+    let mut keys = load_encryption_keys(&app);
+    keys.remove(&key_id);
+    save_encryption_keys(&app, &keys)
+}
+
+// An uploader for destination with its keychain credential, or None for a local export
+async fn export_uploader(
+    app: &tauri::AppHandle,
+    destination: Option<&destinations::ExportDestination>,
+) -> Result<Option<destinations::Uploader>, String> {
+    let Some(destination) = destination else {
+        return Ok(None);
+    };
+    let credential = match destination.credential_id() {
+        Some(id) => Some(
+            load_export_credentials(app)
+                .remove(id)
+                .ok_or_else(|| format!("Export credential '{}' not found", id))?,
+        ),
+        None => None,
+    };
+    destinations::Uploader::connect(destination, credential).await.map(Some)
+}
+
+// Copy a connection's secret to the clipboard without it ever reaching the frontend.
+// kind is "connectionString" or "sasToken" (a fresh token for the namespace or EntityPath,
+// valid for sas_expiry_seconds, default 1 hour). With clear_after_seconds the clipboard
+// is emptied later, unless something else has been copied in the meantime.
+#[tauri::command]
+fn copy_secret(
+    app: tauri::AppHandle,
+    connection_id: String,
+    kind: String,
+    clear_after_seconds: Option<u64>,
+    sas_expiry_seconds: Option<u64>,
+) -> Result<(), String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let connection_string = get_connection_string(app.clone(), connection_id.clone())?;
+    let (secret, description) = match kind.as_str() {
+        "connectionString" => (connection_string, "connection string".to_string()),
+        "sasToken" => {
+            let parsed = azure::auth::parse_connection_string(&connection_string)?;
+            let namespace = azure::auth::get_namespace_from_endpoint(&parsed.endpoint)?;
+            let domain = azure::auth::get_endpoint_domain(&parsed.endpoint)?;
+            let resource_uri = match &parsed.entity_path {
+                Some(path) => format!("https://{}{}/{}", namespace, domain, path),
+                None => format!("https://{}{}/", namespace, domain),
+            };
+            let expiry_seconds = sas_expiry_seconds.unwrap_or(3600);
+            let token = azure::auth::generate_sas_token(
+                &resource_uri,
+                &parsed.shared_access_key_name,
+                &parsed.shared_access_key,
+                expiry_seconds,
+            )?;
+            (token, format!("SAS token for {} valid {}s", resource_uri, expiry_seconds))
+        }
+        other => return Err(format!("Unknown secret kind '{}': expected connectionString or sasToken", other)),
+    };
+
+    app.clipboard()
+        .write_text(secret.clone())
+        .map_err(|e| format!("Failed to write to clipboard: {}", e))?;
+
+    let clear_note = clear_after_seconds
+        .map(|secs| format!(", clipboard clears in {}s", secs))
+        .unwrap_or_default();
+    audit::record_or_log("secret_copied", Some(&connection_id), &format!("Copied {}{}", description, clear_note));
+
+    if let Some(secs) = clear_after_seconds {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+            // Leave the clipboard alone if the user has copied something else since
+            let still_ours = app.clipboard().read_text().map(|text| text == secret).unwrap_or(false);
+            if still_ours {
+                match app.clipboard().write_text(String::new()) {
+                    Ok(()) => audit::record_or_log("clipboard_cleared", Some(&connection_id), "Cleared copied secret"),
+                    Err(e) => eprintln!("[copy_secret] Failed to clear clipboard: {}", e),
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_audit_log(limit: Option<usize>) -> Result<Vec<audit::AuditEvent>, String> {
+    audit::read_recent(limit.unwrap_or(200))
+}
+
 #[tauri::command]
 fn list_connection_ids() -> Result<Vec<String>, String> {
     // The keyring plugin doesn't support listing all entries
@@ -344,6 +476,15 @@ fn store_connection(
     
     const SERVICE_NAME: &str = "com.azureservicebusexplorer";
     const CONNECTIONS_ACCOUNT: &str = "all_connection_objects";
+
+    if let Some(color) = &connection.color {
+        let is_hex_color = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if !is_hex_color {
+            return Err(format!("Invalid connection color '{}': expected #rrggbb", color));
+        }
+    }
     
     // Load existing connections
     let mut all_connections: HashMap<String, ServiceBusConnection> = match app.keyring().get_password(SERVICE_NAME, CONNECTIONS_ACCOUNT) {
@@ -384,7 +525,14 @@ fn get_all_connections(
             let all_connections: HashMap<String, ServiceBusConnection> = serde_json::from_str(&json_data)
                 .map_err(|e| format!("Failed to parse connections: {}", e))?;
             
-            Ok(all_connections.into_values().collect())
+            // Flag connections that run on the namespace owner key so the UI can nudge towards a scoped policy
+            Ok(all_connections
+                .into_values()
+                .map(|mut connection| {
+                    connection.uses_root_manage_key = Some(azure::auth::uses_root_manage_key(&connection));
+                    connection
+                })
+                .collect())
         }
         Ok(None) => Ok(Vec::new()),
         Err(e) => Err(format!("Failed to get connections from keychain: {}", e))
@@ -394,6 +542,7 @@ fn get_all_connections(
 #[tauri::command]
 fn delete_connection(
     app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
     connection_id: String
 ) -> Result<(), String> {
     use tauri_plugin_keyring::KeyringExt;
@@ -413,6 +562,7 @@ fn delete_connection(
     
     // Remove the connection
     all_connections.remove(&connection_id);
+    state.clients.forget(&connection_id);
     
     // Store updated connections back
     let json_data = serde_json::to_string(&all_connections)
@@ -425,155 +575,2466 @@ fn delete_connection(
     Ok(())
 }
 
-// Azure Service Bus commands
-#[tauri::command]
-async fn list_queues(connection: ServiceBusConnection) -> Result<Vec<QueueProperties>, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.list_queues().await
+// Keychain helpers for commands that rewrite stored connections in bulk
+const KEYCHAIN_SERVICE_NAME: &str = "com.azureservicebusexplorer";
+const KEYCHAIN_CONNECTION_STRINGS_ACCOUNT: &str = "all_connections";
+const KEYCHAIN_CONNECTIONS_ACCOUNT: &str = "all_connection_objects";
+
+fn load_keychain_map<T: serde::de::DeserializeOwned>(
+    app: &tauri::AppHandle,
+    account: &str,
+) -> Result<std::collections::HashMap<String, T>, String> {
+    use tauri_plugin_keyring::KeyringExt;
+
+    match app.keyring().get_password(KEYCHAIN_SERVICE_NAME, account) {
+        Ok(Some(json_data)) => serde_json::from_str(&json_data)
+            .map_err(|e| format!("Failed to parse keychain entry '{}': {}", account, e)),
+        Ok(None) => Ok(std::collections::HashMap::new()),
+        Err(e) => Err(format!("Failed to read keychain entry '{}': {}", account, e)),
+    }
 }
 
-#[tauri::command]
-async fn list_queues_page(
-    connection: ServiceBusConnection,
-    skip: Option<u32>,
-    top: Option<u32>,
-) -> Result<Vec<QueueProperties>, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.list_queues_page(skip, top).await
+fn save_keychain_map<T: Serialize>(
+    app: &tauri::AppHandle,
+    account: &str,
+    map: &std::collections::HashMap<String, T>,
+) -> Result<(), String> {
+    use tauri_plugin_keyring::KeyringExt;
+
+    let json_data = serde_json::to_string(map)
+        .map_err(|e| format!("Failed to serialize keychain entry '{}': {}", account, e))?;
+    app.keyring()
+        .set_password(KEYCHAIN_SERVICE_NAME, account, &json_data)
+        .map_err(|e| format!("Failed to write keychain entry '{}': {}", account, e))
 }
 
+// Body decoders
 #[tauri::command]
-async fn get_queue(connection: ServiceBusConnection, queue_name: String) -> Result<QueueProperties, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.get_queue(&queue_name).await
+fn get_body_decoders() -> Result<decoders::DecoderConfig, String> {
+    decoders::load_config()
 }
 
+// path is a FileDescriptorSet built with protoc --descriptor_set_out --include_imports
 #[tauri::command]
-async fn create_queue(connection: ServiceBusConnection, queue_name: String, properties: Option<QueueProperties>) -> Result<(), String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.create_queue(&queue_name, properties.as_ref()).await
+fn add_protobuf_descriptor_set(path: String) -> Result<decoders::DescriptorSetInfo, String> {
+    decoders::add_descriptor_set(std::path::Path::new(&path))
 }
 
 #[tauri::command]
-async fn update_queue(connection: ServiceBusConnection, queue_name: String, properties: QueueProperties) -> Result<(), String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.update_queue(&queue_name, &properties).await
+fn remove_descriptor_set(descriptor_set_id: String) -> Result<(), String> {
+    decoders::remove_descriptor_set(&descriptor_set_id)
 }
 
+// path is an Avro schema (.avsc) used as the writer schema
 #[tauri::command]
-async fn delete_queue(connection: ServiceBusConnection, queue_name: String) -> Result<(), String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.delete_queue(&queue_name).await
+fn add_avro_schema(path: String) -> Result<decoders::AvroSchemaInfo, String> {
+    decoders::add_avro_schema(std::path::Path::new(&path))
 }
 
 #[tauri::command]
-async fn list_topics(connection: ServiceBusConnection) -> Result<Vec<TopicProperties>, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.list_topics().await
+fn remove_avro_schema(schema_id: String) -> Result<(), String> {
+    decoders::remove_avro_schema(&schema_id)
 }
 
 #[tauri::command]
-async fn get_topic(connection: ServiceBusConnection, topic_name: String) -> Result<TopicProperties, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.get_topic(&topic_name).await
+fn set_body_decoder_rules(rules: Vec<decoders::DecoderRule>) -> Result<decoders::DecoderConfig, String> {
+    decoders::set_rules(rules)
 }
 
+// Expert mode: raw Atom XML of a queue, topic or topic/Subscriptions/sub
 #[tauri::command]
-async fn create_topic(connection: ServiceBusConnection, topic_name: String, properties: Option<TopicProperties>) -> Result<(), String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.create_topic(&topic_name, properties.as_ref()).await
+async fn get_entity_xml(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    entity_path: String,
+) -> Result<String, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Raw XML is not available in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.get_entity_xml(&entity_path).await
 }
 
 #[tauri::command]
-async fn update_topic(connection: ServiceBusConnection, topic_name: String, properties: TopicProperties) -> Result<(), String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.update_topic(&topic_name, &properties).await
+async fn update_entity_xml(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    entity_path: String,
+    xml: String,
+) -> Result<String, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Raw XML is not available in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.update_entity_xml(&entity_path, &xml).await
 }
 
+// Regenerate a key of an entity-level SAS policy and fix up every stored connection that used it
 #[tauri::command]
-async fn delete_topic(connection: ServiceBusConnection, topic_name: String) -> Result<(), String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.delete_topic(&topic_name).await
+async fn rotate_sas_key(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    entity_path: Option<String>,
+    key_name: String,
+    key_type: String,
+) -> Result<SasKeyRotationResult, String> {
+    use azure::authorization::{apply_rotated_key, StoredKeyStatus};
+
+    if sandbox::is_training(&connection) {
+        return Err("Access keys are not available in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+
+    // Fall back to the EntityPath of an entity-scoped connection string
+    let entity_path = entity_path
+        .filter(|p| !p.trim().is_empty())
+        .or_else(|| {
+            connection
+                .connection_string
+                .as_deref()
+                .and_then(|cs| azure::auth::parse_connection_string(cs).ok())
+                .and_then(|parsed| parsed.entity_path)
+        })
+        .ok_or("Namespace-level policies are managed through Azure Resource Manager and can't be rotated with SAS credentials. Specify the queue or topic that owns the policy.")?;
+
+    let (rule, old_key) = client
+        .regenerate_authorization_rule_key(&entity_path, &key_name, &key_type)
+        .await?;
+    let new_key = if key_type == "primary" { rule.primary_key.clone() } else { rule.secondary_key.clone() }
+        .ok_or("Rotated rule has no key")?;
+
+    // Under dry run the new key was never applied, so nothing is stored and the
+    // result only lists the connections that would be rewritten
+    let dry_run = azure::dry_run::is_enabled();
+    let new_connection_string = if dry_run {
+        String::new()
+    } else {
+        azure::auth::build_connection_string(
+            &format!("{}{}", client.namespace(), client.endpoint_domain()),
+            &key_name,
+            &new_key,
+            Some(&entity_path),
+        )?
+    };
+
+    let mut updated_connection_ids = Vec::new();
+    let mut invalid_connection_ids = Vec::new();
+    let mut check = |id: &str, connection_string: &mut String| -> bool {
+        match apply_rotated_key(connection_string, client.namespace(), &entity_path, &rule, &old_key, &new_key) {
+            StoredKeyStatus::Updated(updated) => {
+                *connection_string = updated;
+                updated_connection_ids.push(id.to_string());
+                true
+            }
+            StoredKeyStatus::Invalid => {
+                invalid_connection_ids.push(id.to_string());
+                false
+            }
+            StoredKeyStatus::StillValid | StoredKeyStatus::Unrelated => false,
+        }
+    };
+
+    // Legacy connection string entry
+    let mut connection_strings: std::collections::HashMap<String, String> =
+        load_keychain_map(&app, KEYCHAIN_CONNECTION_STRINGS_ACCOUNT)?;
+    let mut strings_changed = false;
+    for (id, connection_string) in connection_strings.iter_mut() {
+        strings_changed |= check(id, connection_string);
+    }
+
+    // Full connection objects
+    let mut connections: std::collections::HashMap<String, ServiceBusConnection> =
+        load_keychain_map(&app, KEYCHAIN_CONNECTIONS_ACCOUNT)?;
+    let mut connections_changed = false;
+    let now = chrono::Utc::now().timestamp_millis();
+    for (id, stored) in connections.iter_mut() {
+        if let Some(connection_string) = stored.connection_string.as_mut() {
+            if check(id, connection_string) {
+                stored.updated_at = now;
+                connections_changed = true;
+            }
+        }
+    }
+
+    if strings_changed && !dry_run {
+        save_keychain_map(&app, KEYCHAIN_CONNECTION_STRINGS_ACCOUNT, &connection_strings)?;
+    }
+    if connections_changed && !dry_run {
+        save_keychain_map(&app, KEYCHAIN_CONNECTIONS_ACCOUNT, &connections)?;
+    }
+
+    // The same connection usually appears in both entries
+    updated_connection_ids.sort();
+    updated_connection_ids.dedup();
+    invalid_connection_ids.sort();
+    invalid_connection_ids.dedup();
+
+    Ok(SasKeyRotationResult {
+        entity_path,
+        key_name,
+        key_type,
+        new_connection_string,
+        updated_connection_ids,
+        invalid_connection_ids,
+        dry_run,
+    })
 }
 
+// Create a Listen/Send-only policy on an entity. With switch_connection the scoped
+// connection string is saved as a new connection next to the one it was created from,
+// which keeps its namespace-wide access.
 #[tauri::command]
-async fn list_subscriptions(connection: ServiceBusConnection, topic_name: String) -> Result<Vec<SubscriptionProperties>, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.list_subscriptions(&topic_name).await
+async fn create_least_privilege_policy(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    entity_path: String,
+    key_name: String,
+    rights: Vec<String>,
+    switch_connection: bool,
+) -> Result<LeastPrivilegePolicyResult, String> {
+    if rights.iter().any(|r| r == "Manage") {
+        return Err("A least-privilege policy can only grant Listen and/or Send".to_string());
+    }
+    if sandbox::is_training(&connection) {
+        return Err("Access policies are not available in the training sandbox".to_string());
+    }
+
+    let client = state.client(&connection).await?;
+    let rule = client.create_authorization_rule(&entity_path, &key_name, &rights).await?;
+    // Under dry run the policy was only planned: its key is not real
+    if azure::dry_run::is_enabled() {
+        return Ok(LeastPrivilegePolicyResult {
+            entity_path,
+            rule,
+            connection_string: String::new(),
+            switched: false,
+            connection_id: None,
+            dry_run: true,
+        });
+    }
+    let connection_string = azure::auth::build_connection_string(
+        &format!("{}{}", client.namespace(), client.endpoint_domain()),
+        &key_name,
+        rule.primary_key.as_deref().unwrap_or_default(),
+        Some(&entity_path),
+    )?;
+
+    // The policy exists by now, so a failed save is reported rather than returned
+    let scoped_connection_id = if switch_connection {
+        match store_scoped_connection(&app, &connection, &entity_path, &key_name, &connection_string) {
+            Ok(id) => Some(id),
+            Err(e) => {
+                eprintln!("[create_least_privilege_policy] Failed to save the scoped connection: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(LeastPrivilegePolicyResult {
+        entity_path,
+        rule,
+        connection_string,
+        switched: scoped_connection_id.is_some(),
+        connection_id: scoped_connection_id,
+        dry_run: false,
+    })
 }
 
-#[tauri::command]
-async fn create_subscription(connection: ServiceBusConnection, topic_name: String, subscription_name: String, properties: Option<SubscriptionProperties>) -> Result<(), String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.create_subscription(&topic_name, &subscription_name, properties.as_ref()).await
+// Save an entity-scoped connection string as a new connection derived from connection
+fn store_scoped_connection(
+    app: &tauri::AppHandle,
+    connection: &ServiceBusConnection,
+    entity_path: &str,
+    key_name: &str,
+    connection_string: &str,
+) -> Result<String, String> {
+    let mut connections: std::collections::HashMap<String, ServiceBusConnection> =
+        load_keychain_map(app, KEYCHAIN_CONNECTIONS_ACCOUNT)?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let scoped = ServiceBusConnection {
+        id: templates::random_uuid(),
+        name: format!("{} / {} ({})", connection.name, entity_path, key_name),
+        connection_string: Some(connection_string.to_string()),
+        namespace: None,
+        use_azure_ad: None,
+        tenant_id: None,
+        client_id: None,
+        created_at: now,
+        updated_at: now,
+        uses_root_manage_key: None,
+        training_mode: None,
+        transport: connection.transport,
+        color: connection.color.clone(),
+    };
+    let id = scoped.id.clone();
+    connections.insert(id.clone(), scoped);
+    save_keychain_map(app, KEYCHAIN_CONNECTIONS_ACCOUNT, &connections)?;
+    Ok(id)
 }
 
+// Package a Listen-only connection to one queue or topic for a teammate, encrypted with
+// a passphrase. key_name (default "explorer-readonly") is created when missing and
+// must grant nothing but Listen when it exists.
 #[tauri::command]
-async fn peek_messages(
+async fn export_read_only_bundle(
+    state: tauri::State<'_, AppState>,
     connection: ServiceBusConnection,
-    queue_name: Option<String>,
-    topic_name: Option<String>,
-    subscription_name: Option<String>,
-    max_count: u32,
-) -> Result<Vec<ServiceBusMessage>, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.peek_messages(
-        queue_name.as_deref(),
-        topic_name.as_deref(),
-        subscription_name.as_deref(),
-        max_count,
-    ).await
+    entity_path: String,
+    key_name: Option<String>,
+    path: String,
+    passphrase: String,
+) -> Result<sharing::ReadOnlyBundleResult, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Read-only bundles are not available in the training sandbox".to_string());
+    }
+    let key_name = key_name.filter(|k| !k.trim().is_empty()).unwrap_or_else(|| sharing::DEFAULT_POLICY_NAME.to_string());
+    let client = state.client(&connection).await?;
+    let (rule, created_policy) = sharing::ensure_listen_only_policy(&client, &entity_path, &key_name).await?;
+    // Nothing is written under dry run: a policy it created is only planned
+    if azure::dry_run::is_enabled() {
+        return Ok(sharing::ReadOnlyBundleResult {
+            path,
+            entity_path,
+            key_name,
+            created_policy,
+            bytes_written: 0,
+            dry_run: true,
+        });
+    }
+    let connection_string = azure::auth::build_connection_string(
+        &format!("{}{}", client.namespace(), client.endpoint_domain()),
+        &key_name,
+        rule.primary_key.as_deref().ok_or("The policy has no primary key")?,
+        Some(&entity_path),
+    )?;
+
+    let bundle = sharing::ReadOnlyBundle {
+        name: format!("{} / {}", connection.name, entity_path),
+        entity_path: entity_path.clone(),
+        key_name: key_name.clone(),
+        connection_string,
+        created_at: azure::timestamps::format(chrono::Utc::now()),
+    };
+    let bytes_written = sharing::write_bundle(std::path::Path::new(&path), &bundle, &passphrase)?;
+    audit::record_or_log(
+        "read_only_bundle_exported",
+        Some(&connection.id),
+        &format!("Exported a read-only bundle for '{}' with policy '{}'", entity_path, key_name),
+    );
+    Ok(sharing::ReadOnlyBundleResult { path, entity_path, key_name, created_policy, bytes_written, dry_run: false })
 }
 
+// Decrypt a read-only bundle into a new connection; the frontend saves it like any other
 #[tauri::command]
-async fn peek_dead_letter_messages(
-    connection: ServiceBusConnection,
-    queue_name: Option<String>,
-    topic_name: Option<String>,
-    subscription_name: Option<String>,
-    max_count: u32,
-) -> Result<Vec<ServiceBusMessage>, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.peek_dead_letter_messages_sdk(
-        queue_name.as_deref(),
-        topic_name.as_deref(),
-        subscription_name.as_deref(),
-        max_count,
-    ).await
+fn open_read_only_bundle(path: String, passphrase: String) -> Result<ServiceBusConnection, String> {
+    let bundle = sharing::read_bundle(std::path::Path::new(&path), &passphrase)?;
+    sharing::bundle_connection(&bundle)
 }
 
+// Move a stored connection to a renamed or migrated namespace. The connection keeps its
+// id, so everything keyed by it (favorites, watchlists, monitors, audit entries) still
+// applies. Give new_namespace to keep the policy and key, or connection_string when the
+// key changed as well. Nothing is saved unless the new namespace answers.
 #[tauri::command]
-async fn send_message(
-    connection: ServiceBusConnection,
-    queue_name: Option<String>,
-    topic_name: Option<String>,
-    message: ServiceBusMessage,
-) -> Result<(), String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.send_message(
-        queue_name.as_deref(),
-        topic_name.as_deref(),
-        &message,
-    ).await
+async fn rebind_connection(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection_id: String,
+    new_namespace: Option<String>,
+    connection_string: Option<String>,
+) -> Result<ServiceBusConnection, String> {
+    let mut connections: std::collections::HashMap<String, ServiceBusConnection> =
+        load_keychain_map(&app, KEYCHAIN_CONNECTIONS_ACCOUNT)?;
+    let stored = connections
+        .get(&connection_id)
+        .cloned()
+        .ok_or_else(|| format!("Connection '{}' not found", connection_id))?;
+    if stored.training_mode.unwrap_or(false) {
+        return Err("The training connection can't be rebound".to_string());
+    }
+    let new_namespace = new_namespace.map(|ns| ns.trim().to_string()).filter(|ns| !ns.is_empty());
+
+    let mut rebound = stored.clone();
+    if stored.use_azure_ad.unwrap_or(false) {
+        let namespace = new_namespace.ok_or("A new namespace is required for an Azure AD connection")?;
+        // Stored as the bare name; ServiceBusClient adds the domain itself
+        let name = if namespace.contains("://") || namespace.contains('.') {
+            azure::auth::get_namespace_from_endpoint(&namespace)?
+        } else {
+            namespace
+        };
+        rebound.namespace = Some(name);
+    } else {
+        let connection_string = match (connection_string, new_namespace) {
+            (Some(connection_string), _) => {
+                azure::auth::parse_connection_string(&connection_string)?;
+                connection_string.trim().to_string()
+            }
+            (None, Some(namespace)) => {
+                let current = stored.connection_string.as_deref().ok_or("The stored connection has no connection string")?;
+                azure::auth::rebind_connection_string(current, &namespace)?
+            }
+            (None, None) => return Err("Specify a new namespace or connection string".to_string()),
+        };
+        let parsed = azure::auth::parse_connection_string(&connection_string)?;
+        if rebound.namespace.is_some() {
+            rebound.namespace = Some(azure::auth::get_namespace_from_endpoint(&parsed.endpoint)?);
+        }
+        rebound.connection_string = Some(connection_string);
+    }
+
+    let client = state.client(&rebound).await?;
+    client
+        .list_queues_page(None, Some(1))
+        .await
+        .map_err(|e| format!("The new namespace could not be reached, nothing was changed: {}", e))?;
+
+    rebound.uses_root_manage_key = None;
+    rebound.updated_at = chrono::Utc::now().timestamp_millis();
+    if let Some(connection_string) = &rebound.connection_string {
+        let mut connection_strings: std::collections::HashMap<String, String> =
+            load_keychain_map(&app, KEYCHAIN_CONNECTION_STRINGS_ACCOUNT)?;
+        if connection_strings.contains_key(&connection_id) {
+            connection_strings.insert(connection_id.clone(), connection_string.clone());
+            save_keychain_map(&app, KEYCHAIN_CONNECTION_STRINGS_ACCOUNT, &connection_strings)?;
+        }
+    }
+    connections.insert(connection_id.clone(), rebound.clone());
+    save_keychain_map(&app, KEYCHAIN_CONNECTIONS_ACCOUNT, &connections)?;
+
+    audit::record_or_log(
+        "connection_rebound",
+        Some(&connection_id),
+        &format!("Moved from {} to {}", stored_namespace(&stored), client.namespace()),
+    );
+    Ok(rebound)
 }
 
-#[tauri::command]
-async fn purge_queue(connection: ServiceBusConnection, queue_name: String, purge_dead_letter: bool) -> Result<u32, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.purge_queue(&queue_name, purge_dead_letter).await
+// Namespace of a stored connection for log lines; never includes the key
+fn stored_namespace(connection: &ServiceBusConnection) -> String {
+    match (&connection.namespace, &connection.connection_string) {
+        (Some(namespace), _) => namespace.clone(),
+        (None, Some(connection_string)) => azure::auth::parse_connection_string(connection_string)
+            .and_then(|parsed| azure::auth::get_namespace_from_endpoint(&parsed.endpoint))
+            .unwrap_or_else(|_| "?".to_string()),
+        (None, None) => "?".to_string(),
+    }
+}
+
+// Azure Service Bus commands
+
+// With stream_progress, listings emit every page as an "entity-list-progress" event
+fn emit_listing_progress<T: Serialize + Clone>(app: &tauri::AppHandle, progress: &ListingProgress<T>) {
+    use tauri::Emitter;
+
+    if let Err(e) = app.emit("entity-list-progress", progress.clone()) {
+        eprintln!("[listing] Failed to emit progress: {}", e);
+    }
+}
+
+// Stamp listing results with the connection they came from
+fn with_connection_label<T: ConnectionLabeled>(
+    items: Result<Vec<T>, String>,
+    connection: &ServiceBusConnection,
+) -> Result<Vec<T>, String> {
+    let mut items = items?;
+    let label = connection.label();
+    for item in &mut items {
+        item.set_connection(label.clone());
+    }
+    Ok(items)
 }
 
 #[tauri::command]
-async fn test_connection(connection: ServiceBusConnection) -> Result<bool, String> {
-    let client = ServiceBusClient::create(&connection).await?;
-    client.test_connection().await
+async fn list_queues(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    stream_progress: Option<bool>,
+) -> Result<Vec<QueueProperties>, String> {
+    if sandbox::is_training(&connection) {
+        return with_connection_label(sandbox::list_queues(), &connection);
+    }
+    let client = state.client(&connection).await?;
+    let queues = if stream_progress.unwrap_or(false) {
+        let on_progress = |progress: &ListingProgress<QueueProperties>| emit_listing_progress(&app, progress);
+        client.list_queues_with_progress(Some(&on_progress)).await
+    } else {
+        client.list_queues().await
+    };
+    // Listen-only, entity-scoped credentials can't list; show the entity they are for
+    let queues = match queues {
+        Err(e) if client.entity_path().is_some() && azure::errors::is_access_denied(&e) => {
+            client.scoped_queues().await
+        }
+        queues => queues,
+    };
+    with_connection_label(queues, &connection)
 }
 
-fn main() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_keyring::init())
-        .invoke_handler(tauri::generate_handler![
+#[tauri::command]
+async fn list_queues_page(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    skip: Option<u32>,
+    top: Option<u32>,
+) -> Result<Vec<QueueProperties>, String> {
+    if sandbox::is_training(&connection) {
+        let queues = sandbox::list_queues()?;
+        let page = queues
+            .into_iter()
+            .skip(skip.unwrap_or(0) as usize)
+            .take(top.unwrap_or(100) as usize)
+            .collect();
+        return with_connection_label(Ok(page), &connection);
+    }
+    let client = state.client(&connection).await?;
+    with_connection_label(client.list_queues_page(skip, top).await, &connection)
+}
+
+#[tauri::command]
+async fn get_queue(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: String,
+) -> Result<QueueProperties, String> {
+    if sandbox::is_training(&connection) {
+        return sandbox::get_queue(&queue_name);
+    }
+    let client = state.client(&connection).await?;
+    client.get_queue(&queue_name).await
+}
+
+// Counts only, for dashboards that refresh many entities every few seconds
+#[tauri::command]
+async fn estimate_backlog(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    entities: Vec<BacklogEntity>,
+) -> Result<Vec<BacklogEstimate>, String> {
+    if sandbox::is_training(&connection) {
+        return Ok(entities.iter().map(sandbox::estimate_backlog).collect());
+    }
+    let client = state.client(&connection).await?;
+    Ok(client.estimate_backlog(&entities).await)
+}
+
+#[tauri::command]
+async fn refresh_queue(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: String,
+    etag: Option<String>,
+) -> Result<EntityRefresh<QueueProperties>, String> {
+    if sandbox::is_training(&connection) {
+        return Ok(EntityRefresh {
+            modified: true,
+            etag: None,
+            entity: Some(sandbox::get_queue(&queue_name)?),
+        });
+    }
+    let client = state.client(&connection).await?;
+    client.refresh_queue(&queue_name, etag.as_deref()).await
+}
+
+#[tauri::command]
+async fn create_queue(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: String,
+    properties: Option<QueueProperties>,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return sandbox::create_queue(&queue_name, properties.as_ref());
+    }
+    let client = state.client(&connection).await?;
+    client.create_queue(&queue_name, properties.as_ref()).await
+}
+
+#[tauri::command]
+async fn create_temporary_queue(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    auto_delete_on_idle_seconds: Option<u64>,
+) -> Result<QueueProperties, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Temporary queues are not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.create_temporary_queue(auto_delete_on_idle_seconds).await
+}
+
+#[tauri::command]
+async fn list_temporary_queues(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+) -> Result<Vec<QueueProperties>, String> {
+    if sandbox::is_training(&connection) {
+        return Ok(Vec::new());
+    }
+    let client = state.client(&connection).await?;
+    client.list_temporary_queues().await
+}
+
+#[tauri::command]
+async fn request_reply(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    message: ServiceBusMessage,
+    reply_queue: Option<String>,
+    timeout_seconds: Option<u32>,
+) -> Result<RequestReplyResult, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Request/reply testing is not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.request_reply(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        &message,
+        reply_queue.as_deref(),
+        timeout_seconds.unwrap_or(30),
+    ).await
+}
+
+#[tauri::command]
+async fn update_queue(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: String,
+    properties: QueueProperties,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return sandbox::update_queue(&queue_name, &properties);
+    }
+    let client = state.client(&connection).await?;
+    client.update_queue(&queue_name, &properties).await
+}
+
+// Refuses (SBX-1070) while other entities forward to the queue, unless force is set
+#[tauri::command]
+async fn delete_queue(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        // Nothing forwards in the sandbox
+        return sandbox::delete_queue(&queue_name);
+    }
+    let client = state.client(&connection).await?;
+    if !force.unwrap_or(false) {
+        client.check_not_forwarded_to(&queue_name).await?;
+    }
+    client.delete_queue(&queue_name).await
+}
+
+#[tauri::command]
+async fn list_topics(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    stream_progress: Option<bool>,
+) -> Result<Vec<TopicProperties>, String> {
+    if sandbox::is_training(&connection) {
+        return Ok(Vec::new());
+    }
+    let client = state.client(&connection).await?;
+    let topics = if stream_progress.unwrap_or(false) {
+        let on_progress = |progress: &ListingProgress<TopicProperties>| emit_listing_progress(&app, progress);
+        client.list_topics_with_progress(Some(&on_progress)).await
+    } else {
+        client.list_topics().await
+    };
+    let topics = match topics {
+        Err(e) if client.entity_path().is_some() && azure::errors::is_access_denied(&e) => {
+            client.scoped_topics().await
+        }
+        topics => topics,
+    };
+    with_connection_label(topics, &connection)
+}
+
+#[tauri::command]
+async fn get_topic(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+) -> Result<TopicProperties, String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!("[training] Topic '{}' does not exist in the training sandbox", topic_name));
+    }
+    let client = state.client(&connection).await?;
+    client.get_topic(&topic_name).await
+}
+
+#[tauri::command]
+async fn refresh_topic(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    etag: Option<String>,
+) -> Result<EntityRefresh<TopicProperties>, String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!("[training] Topic '{}' does not exist in the training sandbox", topic_name));
+    }
+    let client = state.client(&connection).await?;
+    client.refresh_topic(&topic_name, etag.as_deref()).await
+}
+
+#[tauri::command]
+async fn create_topic(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    properties: Option<TopicProperties>,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return Err("Topics are not available in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.create_topic(&topic_name, properties.as_ref()).await
+}
+
+#[tauri::command]
+async fn update_topic(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    properties: TopicProperties,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!("[training] Topic '{}' does not exist in the training sandbox", topic_name));
+    }
+    let client = state.client(&connection).await?;
+    client.update_topic(&topic_name, &properties).await
+}
+
+// Refuses (SBX-1070) while other entities forward to the topic, unless force is set
+#[tauri::command]
+async fn delete_topic(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!("[training] Topic '{}' does not exist in the training sandbox", topic_name));
+    }
+    let client = state.client(&connection).await?;
+    if !force.unwrap_or(false) {
+        client.check_not_forwarded_to(&topic_name).await?;
+    }
+    client.delete_topic(&topic_name).await
+}
+
+// Queues and subscriptions that auto-forward to a queue or topic, to warn before deleting it
+#[tauri::command]
+async fn find_forward_references(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    entity_name: String,
+) -> Result<Vec<ForwardReference>, String> {
+    if sandbox::is_training(&connection) {
+        // The sandbox has no auto-forwarding
+        return Ok(Vec::new());
+    }
+    let client = state.client(&connection).await?;
+    client.find_forward_references(&entity_name).await
+}
+
+#[tauri::command]
+async fn list_subscriptions(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    stream_progress: Option<bool>,
+) -> Result<Vec<SubscriptionProperties>, String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!("[training] Topic '{}' does not exist in the training sandbox", topic_name));
+    }
+    let client = state.client(&connection).await?;
+    let subscriptions = if stream_progress.unwrap_or(false) {
+        let on_progress = |progress: &ListingProgress<SubscriptionProperties>| emit_listing_progress(&app, progress);
+        client.list_subscriptions_with_progress(&topic_name, Some(&on_progress)).await
+    } else {
+        client.list_subscriptions(&topic_name).await
+    };
+    let subscriptions = match subscriptions {
+        Err(e) if client.entity_path().is_some() && azure::errors::is_access_denied(&e) => {
+            Ok(client.scoped_subscriptions(&topic_name))
+        }
+        subscriptions => subscriptions,
+    };
+    with_connection_label(subscriptions, &connection)
+}
+
+// One page with settings and counts, so a topic view can render its table from one call
+#[tauri::command]
+async fn list_subscriptions_page(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    skip: Option<u32>,
+    top: Option<u32>,
+    include_rule_counts: Option<bool>,
+) -> Result<SubscriptionPage, String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!("[training] Topic '{}' does not exist in the training sandbox", topic_name));
+    }
+    let client = state.client(&connection).await?;
+    let mut page = client
+        .list_subscriptions_page(&topic_name, skip.unwrap_or(0), top.unwrap_or(100), include_rule_counts.unwrap_or(true))
+        .await?;
+    page.items = with_connection_label(Ok(page.items), &connection)?;
+    Ok(page)
+}
+
+#[tauri::command]
+async fn refresh_subscription(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    subscription_name: String,
+    etag: Option<String>,
+) -> Result<EntityRefresh<SubscriptionProperties>, String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!(
+            "[training] Subscription '{}/{}' does not exist in the training sandbox",
+            topic_name, subscription_name
+        ));
+    }
+    let client = state.client(&connection).await?;
+    client.refresh_subscription(&topic_name, &subscription_name, etag.as_deref()).await
+}
+
+#[tauri::command]
+async fn create_subscription(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    subscription_name: String,
+    properties: Option<SubscriptionProperties>,
+    initial_rule: Option<SubscriptionRule>,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!("[training] Topic '{}' does not exist in the training sandbox", topic_name));
+    }
+    let client = state.client(&connection).await?;
+    client.create_subscription(&topic_name, &subscription_name, properties.as_ref(), initial_rule.as_ref()).await
+}
+
+// Without subscription_names every subscription of the topic is updated
+#[tauri::command]
+async fn apply_settings_to_subscriptions(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    topic_name: String,
+    subscription_names: Option<Vec<String>>,
+    settings: SubscriptionSettings,
+) -> Result<Vec<SubscriptionSettingsResult>, String> {
+    if sandbox::is_training(&connection) {
+        return Err(format!("[training] Topic '{}' does not exist in the training sandbox", topic_name));
+    }
+    let client = state.client(&connection).await?;
+    client.apply_settings_to_subscriptions(&topic_name, subscription_names.as_deref(), &settings).await
+}
+
+// Without apply the report is a preview of what would change. Progress is emitted as
+// "bulk-edit-progress" events after every entity checked.
+#[tauri::command]
+async fn bulk_edit_entities(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    filter: EntityFilter,
+    changes: Vec<PropertyAssignment>,
+    apply: bool,
+) -> Result<BulkEditReport, String> {
+    use tauri::Emitter;
+
+    if sandbox::is_training(&connection) {
+        return Err("Entity properties cannot be edited in the training sandbox".to_string());
+    }
+    let emitter = app.clone();
+    let description = format!("{} {} property changes", if apply { "Apply" } else { "Preview" }, changes.len());
+    let connection_id = connection.id.clone();
+    let clients = state.clients.clone();
+    let report = operations::run_reporting(&app, "bulk-edit", description, |reporter| async move {
+        let client = clients.get(&connection).await?;
+        let on_progress = |progress: &BulkEditProgress| {
+            reporter.report(progress.index as u64, Some(progress.total as u64));
+            if let Err(e) = emitter.emit("bulk-edit-progress", progress.clone()) {
+                eprintln!("[bulk_edit_entities] Failed to emit progress: {}", e);
+            }
+        };
+        client.bulk_edit_entities(&filter, &changes, apply, &on_progress).await
+    })
+    .await?;
+    if report.applied && report.updated_count > 0 {
+        let updated: Vec<&str> =
+            report.entities.iter().filter(|entity| entity.applied).map(|entity| entity.entity_path.as_str()).collect();
+        audit::record_or_log(
+            "entities_updated",
+            Some(&connection_id),
+            &format!("Bulk edited {} entities: {}", updated.len(), updated.join(", ")),
+        );
+    }
+    Ok(report)
+}
+
+// Run peeked messages through the body decoder rules for their queue or topic
+async fn with_decoded_bodies(
+    messages: Result<Vec<ServiceBusMessage>, String>,
+    connection: &ServiceBusConnection,
+    entity: Option<&str>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    let mut messages = messages?;
+    decoders::decode_messages(&mut messages, connection, entity).await;
+    Ok(messages)
+}
+
+// A peek from the head is cross-checked against the runtime counts afterwards, see
+// emit_count_verification
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn peek_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    max_count: u32,
+    from_sequence_number: Option<u64>,
+    max_body_bytes: Option<u32>,
+    timeout_seconds: Option<u32>,
+    page_size: Option<u32>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    if sandbox::is_training(&connection) {
+        let queue = training_queue(queue_name.as_deref())?;
+        let mut messages = sandbox::peek_messages(queue, false, max_count, from_sequence_number)?;
+        let default_ttl = sandbox::get_queue(queue).ok().and_then(|q| q.default_message_time_to_live_in_seconds);
+        expiry::annotate_expiry(&mut messages, default_ttl, false);
+        return Ok(messages);
+    }
+    let client = state.client(&connection).await?;
+    let options = PeekOptions { max_body_bytes, timeout_seconds, page_size };
+    let messages = client.peek_messages_with_options(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        max_count,
+        from_sequence_number,
+        &options,
+    ).await;
+    let mut messages =
+        with_decoded_bodies(messages, &connection, queue_name.as_deref().or(topic_name.as_deref())).await?;
+    let default_ttl = expiry::default_time_to_live(
+        &client,
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+    ).await;
+    expiry::annotate_expiry(&mut messages, default_ttl, false);
+    // Counts say nothing about a peek that started part way through the entity
+    if from_sequence_number.is_none() {
+        let source = MessageSource { queue_name, topic_name, subscription_name, dead_letter: false };
+        emit_count_verification(&app, client, source, "peek", messages.len() as u64, Some(max_count as u64));
+    }
+    Ok(messages)
+}
+
+// Compare what a peek or purge saw with the entity's runtime counts and emit the result
+// as a "message-count-verification" event, so "the queue says 52 but I only see 50"
+// comes with the likely reasons. Runs in the background: the extra entity lookup
+// never delays the messages.
+fn emit_count_verification(
+    app: &tauri::AppHandle,
+    client: std::sync::Arc<azure::servicebus::ServiceBusClient>,
+    source: MessageSource,
+    operation: &'static str,
+    observed_count: u64,
+    requested_count: Option<u64>,
+) {
+    use tauri::Emitter;
+
+    // Nothing was peeked or removed under dry run
+    if azure::dry_run::is_enabled() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let verification = client.verify_message_counts(
+            source.queue_name.as_deref(),
+            source.topic_name.as_deref(),
+            source.subscription_name.as_deref(),
+            source.dead_letter,
+            operation,
+            observed_count,
+            requested_count,
+        ).await;
+        match verification {
+            Ok(verification) => {
+                if let Err(e) = app.emit("message-count-verification", verification) {
+                    eprintln!("[{}] Failed to emit count verification: {}", operation, e);
+                }
+            }
+            Err(e) => eprintln!("[{}] Failed to verify message counts: {}", operation, e),
+        }
+    });
+}
+
+// Peek without holding the whole result: chunks are emitted as "peek-messages-chunk"
+// events and the stream waits for ack_peek_chunk when too many are unacknowledged
+#[tauri::command]
+async fn stream_peek_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    options: Option<peek_stream::PeekStreamOptions>,
+) -> Result<peek_stream::PeekStreamSummary, String> {
+    use tauri::Emitter;
+
+    let on_chunk = |chunk: &peek_stream::PeekChunk| {
+        if let Err(e) = app.emit("peek-messages-chunk", chunk.clone()) {
+            eprintln!("[stream_peek_messages] Failed to emit chunk: {}", e);
+        }
+    };
+    peek_stream::stream_peek(&state.clients, &connection, &source, &options.unwrap_or_default(), &on_chunk).await
+}
+
+#[tauri::command]
+fn ack_peek_chunk(stream_id: String, chunk_index: u32) -> Result<(), String> {
+    peek_stream::ack(&stream_id, chunk_index)
+}
+
+#[tauri::command]
+fn cancel_peek_stream(stream_id: String) -> Result<(), String> {
+    peek_stream::cancel(&stream_id)
+}
+
+#[tauri::command]
+async fn peek_dead_letter_messages(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    max_count: u32,
+    max_body_bytes: Option<u32>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    if sandbox::is_training(&connection) {
+        return sandbox::peek_messages(training_queue(queue_name.as_deref())?, true, max_count, None);
+    }
+    let client = state.client(&connection).await?;
+    let messages = client.peek_dead_letter_messages_sdk(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        max_count,
+        max_body_bytes,
+    ).await;
+    with_decoded_bodies(messages, &connection, queue_name.as_deref().or(topic_name.as_deref())).await
+}
+
+#[tauri::command]
+async fn peek_transfer_dead_letter_messages(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    max_count: u32,
+    max_body_bytes: Option<u32>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    if sandbox::is_training(&connection) {
+        // The sandbox has no auto-forwarding, so nothing ever lands here
+        return Ok(Vec::new());
+    }
+    let client = state.client(&connection).await?;
+    let messages = client.peek_transfer_dead_letter_messages(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        max_count,
+        max_body_bytes,
+    ).await;
+    with_decoded_bodies(messages, &connection, queue_name.as_deref().or(topic_name.as_deref())).await
+}
+
+// With encrypt_with the body is AES-GCM encrypted before it is sent
+#[tauri::command]
+async fn send_message(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    message: ServiceBusMessage,
+    encrypt_with: Option<decoders::EncryptionKey>,
+) -> Result<Option<u64>, String> {
+    let message = match &encrypt_with {
+        Some(key) => decoders::encrypt_message(&message, &connection, key).await?,
+        None => message,
+    };
+    if sandbox::is_training(&connection) {
+        if message.scheduled_enqueue_time_utc.is_some() {
+            return Err("Scheduled messages are not supported in the training sandbox".to_string());
+        }
+        return sandbox::send_message(training_queue(queue_name.as_deref())?, &message).map(|_| None);
+    }
+    let client = state.client(&connection).await?;
+    client.send_message(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        &message,
+    ).await
+}
+
+// Cancellable: see operations.rs. With encrypt_with every body is encrypted, as in send_message,
+// and the messages are sent one at a time: ciphertext is binary, which a batch can't carry.
+#[tauri::command]
+async fn send_messages_batch(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    messages: Vec<ServiceBusMessage>,
+    encrypt_with: Option<decoders::EncryptionKey>,
+) -> Result<u32, String> {
+    let description = format!(
+        "Send {} messages to {}",
+        messages.len(),
+        queue_name.as_deref().or(topic_name.as_deref()).unwrap_or("?")
+    );
+    let clients = state.clients.clone();
+    operations::run(&app, "send", description, async move {
+        let mut messages = messages;
+        if let Some(key) = &encrypt_with {
+            for message in messages.iter_mut() {
+                *message = decoders::encrypt_message(message, &connection, key).await?;
+            }
+        }
+        if sandbox::is_training(&connection) {
+            let queue = training_queue(queue_name.as_deref())?;
+            for message in &messages {
+                sandbox::send_message(queue, message)?;
+            }
+            return Ok(messages.len() as u32);
+        }
+        let client = clients.get(&connection).await?;
+        if encrypt_with.is_some() {
+            let mut sent = 0u32;
+            for (index, message) in messages.iter().enumerate() {
+                client
+                    .send_message(queue_name.as_deref(), topic_name.as_deref(), message)
+                    .await
+                    .map_err(|e| format!("Message {}: {} ({} already sent)", index + 1, e, sent))?;
+                sent += 1;
+            }
+            return Ok(sent);
+        }
+        client.send_messages_batch(
+            queue_name.as_deref(),
+            topic_name.as_deref(),
+            &messages,
+        ).await
+    })
+    .await
+}
+
+// Message templates
+#[tauri::command]
+fn list_message_templates() -> Result<Vec<templates::MessageTemplate>, String> {
+    templates::list_templates()
+}
+
+// Without an id the template is created
+#[tauri::command]
+fn save_message_template(template: templates::MessageTemplate) -> Result<templates::MessageTemplate, String> {
+    templates::save_template(template)
+}
+
+#[tauri::command]
+fn delete_message_template(template_id: String) -> Result<(), String> {
+    templates::delete_template(&template_id)
+}
+
+// The messages a send would produce, without advancing the counters
+#[tauri::command]
+fn preview_message_template(template_id: String, count: Option<u32>) -> Result<Vec<ServiceBusMessage>, String> {
+    templates::expand_template(&template_id, count.unwrap_or(1), false)
+}
+
+// Expand and send count messages (default 1). Returns the messages as sent.
+#[tauri::command]
+async fn send_message_template(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    template_id: String,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    count: Option<u32>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    let messages = templates::expand_template(&template_id, count.unwrap_or(1), true)?;
+    if sandbox::is_training(&connection) {
+        let queue_name = training_queue(queue_name.as_deref())?;
+        for message in &messages {
+            sandbox::send_message(queue_name, message)?;
+        }
+        return Ok(messages);
+    }
+    let client = state.client(&connection).await?;
+    for (index, message) in messages.iter().enumerate() {
+        client
+            .send_message(queue_name.as_deref(), topic_name.as_deref(), message)
+            .await
+            .map_err(|e| format!("Message {} of {}: {}", index + 1, messages.len(), e))?;
+    }
+    Ok(messages)
+}
+
+#[tauri::command]
+async fn cancel_scheduled_message(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    sequence_number: u64,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return Err("Scheduled messages are not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.cancel_scheduled_message(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        sequence_number,
+    ).await
+}
+
+#[tauri::command]
+async fn peek_scheduled_messages(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    max_count: u32,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    if sandbox::is_training(&connection) {
+        return Ok(Vec::new());
+    }
+    let client = state.client(&connection).await?;
+    let mut messages = client.peek_scheduled_messages(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        max_count,
+    ).await?;
+    let default_ttl = expiry::default_time_to_live(
+        &client,
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+    ).await;
+    expiry::annotate_expiry(&mut messages, default_ttl, false);
+    Ok(messages)
+}
+
+#[tauri::command]
+async fn reschedule_messages(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: String,
+    sequence_numbers: Vec<u64>,
+    shift_seconds: i64,
+) -> Result<Vec<RescheduledMessage>, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Scheduled messages are not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.reschedule_messages(&queue_name, &sequence_numbers, shift_seconds).await
+}
+
+#[tauri::command]
+async fn list_sessions(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    max_sessions: Option<u32>,
+) -> Result<Vec<SessionInfo>, String> {
+    if sandbox::is_training(&connection) {
+        // Training queues are not session-enabled
+        return Ok(Vec::new());
+    }
+    let client = state.client(&connection).await?;
+    client.list_sessions(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        max_sessions.unwrap_or(100),
+    ).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn peek_session_messages(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    session_id: String,
+    max_count: u32,
+    from_sequence_number: Option<u64>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Sessions are not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    let mut messages = client.peek_session_messages(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        &session_id,
+        max_count,
+        from_sequence_number,
+    ).await?;
+    let default_ttl = expiry::default_time_to_live(
+        &client,
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+    ).await;
+    expiry::annotate_expiry(&mut messages, default_ttl, false);
+    Ok(messages)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn receive_session_messages(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    session_id: String,
+    max_count: u32,
+    wait_time_seconds: Option<u32>,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Sessions are not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.receive_session_messages(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        &session_id,
+        max_count,
+        wait_time_seconds,
+    ).await
+}
+
+#[tauri::command]
+async fn get_session_state(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    session_id: String,
+) -> Result<SessionState, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Sessions are not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.get_session_state(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        &session_id,
+    ).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn set_session_state(
+    app_state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    session_id: String,
+    state: String,
+    encoding: Option<String>,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return Err("Sessions are not supported in the training sandbox".to_string());
+    }
+    let client = app_state.client(&connection).await?;
+    client.set_session_state(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        &session_id,
+        &state,
+        encoding.as_deref(),
+    ).await
+}
+
+// Cancellable: see operations.rs. The result is cross-checked against the runtime
+// counts afterwards, see emit_count_verification
+#[tauri::command]
+async fn purge_queue(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: String,
+    purge_dead_letter: bool,
+) -> Result<u32, String> {
+    let description = format!("Purge {}{}", queue_name, if purge_dead_letter { "/$deadletterqueue" } else { "" });
+    let clients = state.clients.clone();
+    let emitter = app.clone();
+    operations::run_reporting(&app, "purge", description, |progress| async move {
+        if sandbox::is_training(&connection) {
+            return sandbox::purge_queue(&queue_name, purge_dead_letter);
+        }
+        let client = clients.get(&connection).await?;
+        // The runtime count is only an estimate: messages keep arriving during a purge
+        let total_estimate = client
+            .runtime_counts(Some(&queue_name), None, None)
+            .await
+            .ok()
+            .and_then(|(_, counts)| if purge_dead_letter { counts.dead_letter } else { counts.active });
+        let on_progress = |purged: u32| progress.report(purged as u64, total_estimate);
+        let purged = client.purge_queue(&queue_name, purge_dead_letter, Some(&on_progress)).await?;
+        let source = MessageSource {
+            queue_name: Some(queue_name),
+            topic_name: None,
+            subscription_name: None,
+            dead_letter: purge_dead_letter,
+        };
+        emit_count_verification(&emitter, client, source, "purge", purged as u64, None);
+        Ok(purged)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn receive_messages(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    max_count: u32,
+    wait_time_seconds: Option<u32>,
+) -> Result<Vec<LockedMessage>, String> {
+    if sandbox::is_training(&connection) {
+        return sandbox::receive_messages(training_queue(queue_name.as_deref())?, max_count);
+    }
+    let client = state.client(&connection).await?;
+    client.receive_messages(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        max_count,
+        wait_time_seconds,
+    ).await
+}
+
+#[tauri::command]
+async fn complete_message(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    sequence_number: u64,
+    lock_token: String,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return sandbox::complete_message(training_queue(queue_name.as_deref())?, &lock_token);
+    }
+    let client = state.client(&connection).await?;
+    client.complete_message(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        sequence_number,
+        &lock_token,
+    ).await
+}
+
+#[tauri::command]
+async fn abandon_message(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    sequence_number: u64,
+    lock_token: String,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return sandbox::abandon_message(training_queue(queue_name.as_deref())?, &lock_token);
+    }
+    let client = state.client(&connection).await?;
+    client.abandon_message(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        sequence_number,
+        &lock_token,
+    ).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn dead_letter_message(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    sequence_number: u64,
+    dead_letter_reason: String,
+    dead_letter_error_description: Option<String>,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return sandbox::dead_letter_message(
+            training_queue(queue_name.as_deref())?,
+            sequence_number,
+            &dead_letter_reason,
+            dead_letter_error_description.as_deref(),
+        );
+    }
+    let client = state.client(&connection).await?;
+    client.dead_letter_message(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        sequence_number,
+        &dead_letter_reason,
+        dead_letter_error_description.as_deref(),
+    ).await
+}
+
+#[tauri::command]
+async fn defer_message(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    sequence_number: u64,
+) -> Result<(), String> {
+    if sandbox::is_training(&connection) {
+        return Err("Deferring messages is not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.defer_message(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        sequence_number,
+    ).await
+}
+
+#[tauri::command]
+async fn receive_deferred_messages(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    sequence_numbers: Vec<u64>,
+    complete: bool,
+) -> Result<Vec<ServiceBusMessage>, String> {
+    if sandbox::is_training(&connection) {
+        return Err("Deferring messages is not supported in the training sandbox".to_string());
+    }
+    let client = state.client(&connection).await?;
+    client.receive_deferred_messages(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        &sequence_numbers,
+        complete,
+    ).await
+}
+
+// Peek up to max_count messages (queue, subscription or DLQ) and write them to path.
+// columns selects the CSV columns; the defaults are used when it is omitted.
+// body_preview_bytes keeps only that much of each body (see hydrate_export).
+// With destination, path is the object name under its prefix (see destinations.rs).
+// Cancellable: see operations.rs
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn export_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    max_count: u32,
+    path: String,
+    format: export::ExportFormat,
+    columns: Option<Vec<export::ExportColumn>>,
+    body_preview_bytes: Option<u32>,
+    destination: Option<destinations::ExportDestination>,
+) -> Result<export::ExportResult, String> {
+    let columns = columns.unwrap_or_else(export::default_csv_columns);
+    let uploader = export_uploader(&app, destination.as_ref()).await?;
+    let clients = state.clients.clone();
+    operations::run_reporting(&app, "export", format!("Export to {}", path), |progress| async move {
+        let on_progress = |peeked: u32| progress.report(peeked as u64, Some(max_count as u64));
+        let messages =
+            export::peek_messages(&clients, &connection, &source, max_count, body_preview_bytes, Some(&on_progress)).await?;
+        let write = |local: &std::path::Path| export::write_messages(local, &messages, format, &columns);
+        let (path, bytes_written) = match &uploader {
+            Some(uploader) => uploader.upload_written(&path, write).await?,
+            None => {
+                let bytes_written = write(std::path::Path::new(&path))?;
+                (path, bytes_written)
+            }
+        };
+        Ok(export::ExportResult {
+            path,
+            format,
+            message_count: messages.len() as u32,
+            bytes_written,
+            truncated_count: messages.iter().filter(|m| m.body_truncated == Some(true)).count() as u32,
+        })
+    })
+    .await
+}
+
+// Fill in the full bodies of a JSON or NDJSON export made with body_preview_bytes,
+// for the given sequence numbers or every truncated message. Cancellable: see operations.rs
+#[tauri::command]
+async fn hydrate_export(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    path: String,
+    sequence_numbers: Option<Vec<u64>>,
+) -> Result<export::HydrateResult, String> {
+    let total = sequence_numbers.as_ref().map(|s| s.len() as u64);
+    let clients = state.clients.clone();
+    operations::run_reporting(&app, "hydrate", format!("Hydrate {}", path), |progress| async move {
+        let on_progress = |hydrated: u32| progress.report(hydrated as u64, total);
+        export::hydrate_messages(&clients, &connection, &source, std::path::Path::new(&path), sequence_numbers, Some(&on_progress))
+            .await
+    })
+    .await
+}
+
+// Every queue and subscription DLQ with messages to its own NDJSON file plus a
+// manifest.json; progress is emitted as "export-dead-letters-progress" events.
+// With destination, directory is the folder under its prefix the files are uploaded to.
+// Cancellable: see operations.rs
+#[tauri::command]
+async fn export_dead_letters(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    directory: String,
+    max_per_entity: Option<u32>,
+    destination: Option<destinations::ExportDestination>,
+) -> Result<export::DeadLetterExportManifest, String> {
+    use tauri::Emitter;
+
+    let emitter = app.clone();
+    let uploader = export_uploader(&app, destination.as_ref()).await?;
+    let description = match &uploader {
+        Some(uploader) => format!("Export dead letters to {}", uploader.location(&directory)),
+        None => format!("Export dead letters to {}", directory),
+    };
+    let clients = state.clients.clone();
+    operations::run_reporting(&app, "export", description, |reporter| async move {
+        let on_progress = |progress: &export::DeadLetterExportProgress| {
+            reporter.report(progress.total_messages, None);
+            if let Err(e) = emitter.emit("export-dead-letters-progress", progress.clone()) {
+                eprintln!("[export_dead_letters] Failed to emit progress: {}", e);
+            }
+        };
+        let Some(uploader) = uploader else {
+            let directory = std::path::Path::new(&directory);
+            return export::export_dead_letters(&clients, &connection, directory, max_per_entity, &on_progress).await;
+        };
+        let staging = destinations::Staging::new()?;
+        let mut manifest =
+            export::export_dead_letters(&clients, &connection, staging.path(), max_per_entity, &on_progress).await?;
+        manifest.directory = uploader.location(&directory);
+        export::write_manifest(staging.path(), &manifest)?;
+        uploader.upload_directory(staging.path(), &directory).await?;
+        Ok(manifest)
+    })
+    .await
+}
+
+// Send every message in a JSON or NDJSON file (as written by export_messages) to a queue or topic.
+// Cancellable: see operations.rs
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn import_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    path: String,
+    regenerate_message_ids: bool,
+    max_messages_per_second: Option<u32>,
+) -> Result<export::ImportResult, String> {
+    let (messages, format) = export::read_messages(std::path::Path::new(&path))?;
+    let messages = export::prepare_for_send(messages, regenerate_message_ids)?;
+    let clients = state.clients.clone();
+    operations::run(&app, "import", format!("Import {}", path), async move {
+        let message_count = export::send_messages(
+            &clients,
+            &connection,
+            queue_name.as_deref(),
+            topic_name.as_deref(),
+            &messages,
+            max_messages_per_second,
+        ).await?;
+        Ok(export::ImportResult {
+            path,
+            format,
+            message_count,
+        })
+    })
+    .await
+}
+
+// New messages are emitted as "monitor-messages" events; "monitor-stopped" follows a stop or failure
+#[tauri::command]
+async fn start_monitor(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    poll_interval_ms: Option<u64>,
+    from_sequence_number: Option<u64>,
+    max_body_bytes: Option<u32>,
+) -> Result<monitor::MonitorInfo, String> {
+    monitor::start(app, state.clients.clone(), connection, source, poll_interval_ms, from_sequence_number, max_body_bytes).await
+}
+
+#[tauri::command]
+fn stop_monitor(monitor_id: String) -> Result<(), String> {
+    monitor::stop(&monitor_id)
+}
+
+#[tauri::command]
+fn list_monitors() -> Vec<monitor::MonitorInfo> {
+    monitor::list()
+}
+
+// Progress is emitted as "search-messages-progress" events after every page.
+// Cancellable: see operations.rs
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn search_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    query: search::MessageQuery,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+    max_matches: Option<u32>,
+) -> Result<search::SearchResult, String> {
+    use tauri::Emitter;
+
+    let emitter = app.clone();
+    let description = format!("Search {}", monitor::source_label(&source));
+    let clients = state.clients.clone();
+    operations::run(&app, "search", description, async move {
+        let on_progress = |progress: &search::SearchProgress| {
+            if let Err(e) = emitter.emit("search-messages-progress", progress.clone()) {
+                eprintln!("[search_messages] Failed to emit progress: {}", e);
+            }
+        };
+        search::search_messages(&clients, &connection, &source, query, from_sequence_number, max_scan, max_matches, &on_progress)
+            .await
+    })
+    .await
+}
+
+// Send an edited copy of a peeked message; patch is a JSON merge patch over the message.
+// Without queue_name/topic_name the copy goes back to the source's queue or topic.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn resend_message(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    sequence_number: u64,
+    patch: Option<serde_json::Value>,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    new_message_id: Option<bool>,
+) -> Result<ServiceBusMessage, String> {
+    resend::resend_message(
+        &state.clients,
+        &connection,
+        &source,
+        sequence_number,
+        patch.as_ref(),
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        new_message_id.unwrap_or(false),
+    )
+    .await
+}
+
+// Progress is emitted as "send-repeated-progress" events; the first one, sent before
+// any message, carries the run id for cancel_send_repeated
+#[tauri::command]
+async fn send_message_repeated(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    message: ServiceBusMessage,
+    options: repeat_send::RepeatSendOptions,
+) -> Result<repeat_send::RepeatSendProgress, String> {
+    use tauri::Emitter;
+
+    let on_progress = |progress: &repeat_send::RepeatSendProgress| {
+        if let Err(e) = app.emit("send-repeated-progress", progress.clone()) {
+            eprintln!("[send_message_repeated] Failed to emit progress: {}", e);
+        }
+    };
+    repeat_send::send_repeated(
+        &state.clients,
+        &connection,
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        &message,
+        &options,
+        &on_progress,
+    )
+    .await
+}
+
+#[tauri::command]
+fn cancel_send_repeated(run_id: String) -> Result<(), String> {
+    repeat_send::cancel(&run_id)
+}
+
+// Counts by reason, content type, subject, source and enqueue hour; progress is
+// emitted as "analyze-dead-letters-progress" events after every page
+#[tauri::command]
+async fn analyze_dead_letter_queue(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+) -> Result<analytics::DeadLetterAnalysis, String> {
+    use tauri::Emitter;
+
+    let on_progress = |progress: &analytics::DeadLetterAnalysisProgress| {
+        if let Err(e) = app.emit("analyze-dead-letters-progress", progress.clone()) {
+            eprintln!("[analyze_dead_letter_queue] Failed to emit progress: {}", e);
+        }
+    };
+    analytics::analyze_dead_letter_queue(&state.clients, &connection, &source, from_sequence_number, max_scan, &on_progress).await
+}
+
+// Message counts by one broker field or application property; progress is emitted as
+// "group-messages-progress" events after every page
+#[tauri::command]
+async fn group_messages_by_property(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    group_by: analytics::GroupBy,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+) -> Result<analytics::MessageGroups, String> {
+    use tauri::Emitter;
+
+    let on_progress = |progress: &analytics::MessageGroupsProgress| {
+        if let Err(e) = app.emit("group-messages-progress", progress.clone()) {
+            eprintln!("[group_messages_by_property] Failed to emit progress: {}", e);
+        }
+    };
+    analytics::group_messages(&state.clients, &connection, &source, &group_by, from_sequence_number, max_scan, &on_progress).await
+}
+
+// MessageIds (and with by_body_hash, bodies) seen more than once; progress is emitted
+// as "find-duplicates-progress" events after every page
+#[tauri::command]
+async fn find_duplicate_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    by_body_hash: Option<bool>,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+) -> Result<analytics::DuplicateReport, String> {
+    use tauri::Emitter;
+
+    let on_progress = |progress: &analytics::DuplicateReportProgress| {
+        if let Err(e) = app.emit("find-duplicates-progress", progress.clone()) {
+            eprintln!("[find_duplicate_messages] Failed to emit progress: {}", e);
+        }
+    };
+    let by_body_hash = by_body_hash.unwrap_or(false);
+    analytics::find_duplicates(&state.clients, &connection, &source, by_body_hash, from_sequence_number, max_scan, &on_progress).await
+}
+
+// Gaps, repeats and out-of-order deliveries in a sequence number the producer embeds,
+// per stream_by value when given; progress is emitted as "analyze-ordering-progress"
+// events after every page
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn analyze_message_ordering(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    sequence_field: analytics::SequenceField,
+    stream_by: Option<analytics::GroupBy>,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+) -> Result<analytics::OrderingReport, String> {
+    use tauri::Emitter;
+
+    let on_progress = |progress: &analytics::OrderingReportProgress| {
+        if let Err(e) = app.emit("analyze-ordering-progress", progress.clone()) {
+            eprintln!("[analyze_message_ordering] Failed to emit progress: {}", e);
+        }
+    };
+    analytics::analyze_ordering(
+        &state.clients,
+        &connection,
+        &source,
+        &sequence_field,
+        stream_by.as_ref(),
+        from_sequence_number,
+        max_scan,
+        &on_progress,
+    )
+    .await
+}
+
+// A JSON Schema inferred from up to sample_size message bodies (see schema.rs), also
+// written to path when given
+#[tauri::command]
+async fn infer_message_schema(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    sample_size: Option<u32>,
+    path: Option<String>,
+) -> Result<schema::InferredSchema, String> {
+    schema::infer_schema(&state.clients, &connection, &source, sample_size, path.as_deref().map(std::path::Path::new)).await
+}
+
+// Expiry per peeked message; expiring_within_seconds keeps only soon-to-expire ones
+#[tauri::command]
+async fn preview_message_expiry(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+    expiring_within_seconds: Option<u64>,
+) -> Result<expiry::ExpiryPreview, String> {
+    expiry::preview_expiry(&state.clients, &connection, &source, from_sequence_number, max_scan, expiring_within_seconds).await
+}
+
+// Delivery history of one message, as far as the service keeps it (see timeline.rs)
+#[tauri::command]
+async fn get_redelivery_timeline(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    sequence_number: u64,
+) -> Result<timeline::RedeliveryTimeline, String> {
+    timeline::redelivery_timeline(&state.clients, &connection, &source, sequence_number).await
+}
+
+// Progress is emitted as "move-messages-progress" events after every batch.
+// Cancellable: see operations.rs
+#[tauri::command]
+async fn move_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    destination: String,
+    count: u32,
+) -> Result<MoveMessagesProgress, String> {
+    use tauri::Emitter;
+
+    let emitter = app.clone();
+    let description = format!("Move {} messages from {} to {}", count, monitor::source_label(&source), destination);
+    let clients = state.clients.clone();
+    operations::run_reporting(&app, "move", description, |reporter| async move {
+        let on_progress = |progress: &MoveMessagesProgress| {
+            reporter.report(progress.moved as u64, Some(progress.requested as u64));
+            if let Err(e) = emitter.emit("move-messages-progress", progress.clone()) {
+                eprintln!("[move_messages] Failed to emit progress: {}", e);
+            }
+        };
+        if sandbox::is_training(&connection) {
+            let progress = sandbox::move_messages(&source, &destination, count)?;
+            on_progress(&progress);
+            return Ok(progress);
+        }
+        let client = clients.get(&connection).await?;
+        client.move_messages(&source, &destination, count, &on_progress).await
+    })
+    .await
+}
+
+// Send copies of peeked messages to destination, leaving the originals for their consumers.
+// Progress is emitted as "copy-messages-progress" events after every page.
+// Cancellable: see operations.rs
+#[tauri::command]
+async fn copy_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    destination: String,
+    count: u32,
+    from_sequence_number: Option<u64>,
+) -> Result<CopyMessagesProgress, String> {
+    use tauri::Emitter;
+
+    let emitter = app.clone();
+    let description = format!("Copy {} messages from {} to {}", count, monitor::source_label(&source), destination);
+    let clients = state.clients.clone();
+    operations::run_reporting(&app, "copy", description, |reporter| async move {
+        let on_progress = |progress: &CopyMessagesProgress| {
+            reporter.report(progress.copied as u64, Some(progress.requested as u64));
+            if let Err(e) = emitter.emit("copy-messages-progress", progress.clone()) {
+                eprintln!("[copy_messages] Failed to emit progress: {}", e);
+            }
+        };
+        resend::copy_messages(&clients, &connection, &source, &destination, count, from_sequence_number, &on_progress).await
+    })
+    .await
+}
+
+// Dead-letter every active message of a queue or subscription with reason.
+// Progress is emitted as "dead-letter-all-progress" events after every batch.
+// Cancellable: see operations.rs
+#[tauri::command]
+async fn dead_letter_all_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    reason: String,
+    description: Option<String>,
+    max_count: Option<u32>,
+) -> Result<DeadLetterAllProgress, String> {
+    use tauri::Emitter;
+
+    let emitter = app.clone();
+    let label = monitor::source_label(&source);
+    let connection_id = connection.id.clone();
+    let clients = state.clients.clone();
+    let result = operations::run_reporting(&app, "dead-letter", format!("Dead-letter {}", label), |reporter| async move {
+        if sandbox::is_training(&connection) {
+            let queue = training_queue(source.queue_name.as_deref())?;
+            let dead_lettered =
+                sandbox::dead_letter_all(queue, &reason, description.as_deref(), max_count.unwrap_or(u32::MAX))?;
+            return Ok(DeadLetterAllProgress {
+                source: monitor::source_label(&source),
+                reason,
+                requested: dead_lettered,
+                dead_lettered,
+                done: true,
+            });
+        }
+        let client = clients.get(&connection).await?;
+        let on_progress = |progress: &DeadLetterAllProgress| {
+            reporter.report(progress.dead_lettered as u64, Some(progress.requested as u64));
+            if let Err(e) = emitter.emit("dead-letter-all-progress", progress.clone()) {
+                eprintln!("[dead_letter_all_messages] Failed to emit progress: {}", e);
+            }
+        };
+        client.dead_letter_all(&source, &reason, description.as_deref(), max_count, &on_progress).await
+    })
+    .await;
+    if let Ok(progress) = &result {
+        audit::record_or_log(
+            "messages_dead_lettered",
+            Some(&connection_id),
+            &format!("Dead-lettered {} messages of {}: {}", progress.dead_lettered, label, progress.reason),
+        );
+    }
+    result
+}
+
+// Disable sends to a queue, wait for its consumers to drain it and move the rest to
+// options.destination (see migrate.rs). Progress is emitted as "migrate-queue-progress"
+// events. Cancellable: see operations.rs
+#[tauri::command]
+async fn drain_and_migrate_queue(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: String,
+    options: migrate::MigrationOptions,
+) -> Result<migrate::MigrationProgress, String> {
+    use tauri::Emitter;
+
+    if sandbox::is_training(&connection) {
+        return Err("Queue status is not available in the training sandbox".to_string());
+    }
+    let emitter = app.clone();
+    let description = format!("Drain {} and migrate it to {}", queue_name, options.destination);
+    let connection_id = connection.id.clone();
+    let clients = state.clients.clone();
+    let result = operations::run_reporting(&app, "migrate", description, |reporter| async move {
+        let client = clients.get(&connection).await?;
+        let on_progress = |progress: &migrate::MigrationProgress| {
+            let drained = progress.initial_active_count.saturating_sub(progress.active_count);
+            reporter.report(drained + progress.moved as u64, Some(progress.initial_active_count));
+            if let Err(e) = emitter.emit("migrate-queue-progress", progress.clone()) {
+                eprintln!("[drain_and_migrate_queue] Failed to emit progress: {}", e);
+            }
+        };
+        migrate::drain_and_migrate(&client, &queue_name, &options, &on_progress).await
+    })
+    .await;
+    if let Ok(progress) = &result {
+        audit::record_or_log(
+            "queue_migrated",
+            Some(&connection_id),
+            &format!(
+                "Disabled sends to {} and moved {} messages and {} dead letters to {}",
+                progress.queue_name, progress.moved, progress.moved_dead_letters, progress.destination
+            ),
+        );
+    }
+    result
+}
+
+// Progress is emitted as "delete-messages-progress" events after every batch.
+// max_scan (default 1000) caps how many messages are locked while searching.
+#[tauri::command]
+async fn delete_messages_by_sequence(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    sequence_numbers: Vec<u64>,
+    max_scan: Option<u32>,
+) -> Result<DeleteMessagesProgress, String> {
+    use tauri::Emitter;
+
+    let on_progress = |progress: &DeleteMessagesProgress| {
+        if let Err(e) = app.emit("delete-messages-progress", progress.clone()) {
+            eprintln!("[delete_messages_by_sequence] Failed to emit progress: {}", e);
+        }
+    };
+    if sandbox::is_training(&connection) {
+        let progress = sandbox::delete_messages_by_sequence(&source, &sequence_numbers)?;
+        on_progress(&progress);
+        return Ok(progress);
+    }
+    let client = state.client(&connection).await?;
+    client
+        .delete_messages_by_sequence(&source, &sequence_numbers, max_scan.unwrap_or(1000), &on_progress)
+        .await
+}
+
+// Receive-and-delete up to max_count messages and return them, so they can be saved
+// before they are gone. Each batch is also emitted as a "drained-messages" event the
+// moment it is removed; a drain cancelled part way (see operations.rs) still finishes
+// and emits the batch in flight, so it loses nothing.
+#[tauri::command]
+async fn drain_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    max_count: u32,
+) -> Result<DrainResult, String> {
+    use tauri::Emitter;
+
+    let description = format!("Drain {}", monitor::source_label(&source));
+    let clients = state.clients.clone();
+    let emitter = app.clone();
+    operations::run_reporting(&app, "drain", description, |progress| async move {
+        if sandbox::is_training(&connection) {
+            let queue = training_queue(source.queue_name.as_deref())?;
+            let messages = sandbox::drain_messages(queue, source.dead_letter, max_count)?;
+            return Ok(DrainResult { entity_path: monitor::source_label(&source), messages, error: None });
+        }
+        let client = clients.get(&connection).await?;
+        let drained = std::sync::atomic::AtomicU64::new(0);
+        let entity_path = monitor::source_label(&source);
+        let on_batch = std::sync::Arc::new(move |batch: &[ServiceBusMessage]| {
+            let payload = DrainResult { entity_path: entity_path.clone(), messages: batch.to_vec(), error: None };
+            if let Err(e) = emitter.emit("drained-messages", payload) {
+                eprintln!("[drain_messages] Failed to emit drained messages: {}", e);
+            }
+            let total = drained.fetch_add(batch.len() as u64, std::sync::atomic::Ordering::SeqCst) + batch.len() as u64;
+            progress.report(total, Some(max_count as u64));
+        });
+        client.drain_messages(&source, max_count, on_batch).await
+    })
+    .await
+}
+
+// Progress is emitted as "drain-dead-letters-progress" events with every receiver's checkpoint.
+// Cancellable: see operations.rs
+#[tauri::command]
+async fn drain_dead_letters(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    destination: Option<String>,
+    receivers: Option<u32>,
+    count: Option<u32>,
+) -> Result<DeadLetterDrainReport, String> {
+    use tauri::Emitter;
+
+    let emitter = app.clone();
+    let description = format!("Drain dead letters of {}", monitor::source_label(&source));
+    let clients = state.clients.clone();
+    operations::run(&app, "drain", description, async move {
+        let on_progress = std::sync::Arc::new(move |progress: &DeadLetterDrainProgress| {
+            if let Err(e) = emitter.emit("drain-dead-letters-progress", progress.clone()) {
+                eprintln!("[drain_dead_letters] Failed to emit progress: {}", e);
+            }
+        });
+        if sandbox::is_training(&connection) {
+            let report = sandbox::drain_dead_letters(&source, destination.as_deref(), count)?;
+            on_progress(&DeadLetterDrainProgress {
+                source: report.source.clone(),
+                destination: report.destination.clone(),
+                moved: report.moved,
+                checkpoints: report.checkpoints.clone(),
+            });
+            return Ok(report);
+        }
+        let client = clients.get(&connection).await?;
+        client
+            .drain_dead_letters(&source, destination.as_deref(), receivers.unwrap_or(4), count, on_progress)
+            .await
+    })
+    .await
+}
+
+// Long-running operations
+#[tauri::command]
+fn list_operations() -> Vec<operations::OperationInfo> {
+    operations::list()
+}
+
+// Abort an operation by the id from its "operation-started" event
+#[tauri::command]
+fn cancel_operation(operation_id: String) -> Result<(), String> {
+    operations::cancel(&operation_id)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn verify_message_counts(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+    queue_name: Option<String>,
+    topic_name: Option<String>,
+    subscription_name: Option<String>,
+    dead_letter: bool,
+    operation: String,
+    observed_count: u64,
+    requested_count: Option<u64>,
+) -> Result<MessageCountVerification, String> {
+    if sandbox::is_training(&connection) {
+        let queue = training_queue(queue_name.as_deref())?;
+        let counts = sandbox::runtime_counts(queue)?;
+        return azure::diagnostics::compare_message_counts(
+            queue,
+            &operation,
+            dead_letter,
+            observed_count,
+            requested_count,
+            &counts,
+        );
+    }
+    let client = state.client(&connection).await?;
+    client.verify_message_counts(
+        queue_name.as_deref(),
+        topic_name.as_deref(),
+        subscription_name.as_deref(),
+        dead_letter,
+        &operation,
+        observed_count,
+        requested_count,
+    ).await
+}
+
+#[tauri::command]
+async fn test_connection(state: tauri::State<'_, AppState>, connection: ServiceBusConnection) -> Result<bool, String> {
+    if sandbox::is_training(&connection) {
+        return Ok(true);
+    }
+    let client = state.client(&connection).await?;
+    client.test_connection().await
+}
+
+// Which features the connection's credential allows, so the UI can hide management
+// (create, edit, delete, rules, policies) for Listen-only credentials
+#[tauri::command]
+async fn probe_connection_access(
+    state: tauri::State<'_, AppState>,
+    connection: ServiceBusConnection,
+) -> Result<ConnectionAccess, String> {
+    if sandbox::is_training(&connection) {
+        return Ok(ConnectionAccess {
+            can_manage: true,
+            management_error: None,
+            entity_path: None,
+            entity_kind: None,
+            can_listen: None,
+        });
+    }
+    let client = state.client(&connection).await?;
+    client.probe_access().await
+}
+
+// Connection string helpers
+#[tauri::command]
+fn build_connection_string(
+    namespace: String,
+    policy_name: String,
+    key: String,
+    entity_path: Option<String>,
+) -> Result<String, String> {
+    azure::auth::build_connection_string(&namespace, &policy_name, &key, entity_path.as_deref())
+}
+
+#[tauri::command]
+fn explain_connection_string(connection_string: String) -> Result<ConnectionStringExplanation, String> {
+    azure::auth::explain_connection_string(&connection_string)
+}
+
+#[tauri::command]
+fn inspect_sas_token(token: String, key: Option<String>) -> Result<SasTokenInspection, String> {
+    azure::auth::inspect_sas_token(&token, key.as_deref())
+}
+
+// Decode the Azure AD token for a connection; a pasted token is decoded as-is
+#[tauri::command]
+async fn inspect_aad_token(connection: ServiceBusConnection, token: Option<String>) -> Result<AadTokenInspection, String> {
+    let token = match token {
+        Some(token) => token,
+        None if sandbox::is_training(&connection) => {
+            return Err("Azure AD tokens are not available in the training sandbox".to_string())
+        }
+        None => azure::auth::acquire_aad_token(&connection).await?,
+    };
+    azure::auth::inspect_aad_token(&token)
+}
+
+#[tauri::command]
+fn get_settings() -> Result<config::AppSettings, String> {
+    config::load_settings()
+}
+
+#[tauri::command]
+fn set_settings(settings: config::AppSettings) -> Result<config::AppSettings, String> {
+    config::save_settings(&settings)?;
+    azure::dry_run::set_enabled(settings.dry_run);
+    Ok(settings)
+}
+
+// Timestamps returned by other commands, shown in time_zone (default: the displayTimeZone
+// setting). Values that aren't timestamps come back as null.
+#[tauri::command]
+fn format_timestamps(values: Vec<String>, time_zone: Option<String>) -> Result<Vec<Option<String>>, String> {
+    let time_zone = match time_zone {
+        Some(time_zone) => time_zone,
+        None => config::load_settings()?.display_time_zone,
+    };
+    let zone = azure::timestamps::DisplayTimeZone::parse(&time_zone)?;
+    Ok(values.iter().map(|value| azure::timestamps::parse(value).map(|time| zone.format(time))).collect())
+}
+
+// Requests skipped while dry run was on, oldest first
+#[tauri::command]
+fn get_dry_run_requests() -> Vec<azure::dry_run::PlannedRequest> {
+    azure::dry_run::planned_requests()
+}
+
+#[tauri::command]
+fn clear_dry_run_requests() {
+    azure::dry_run::clear()
+}
+
+#[tauri::command]
+fn describe_error(message: String) -> azure::errors::CatalogError {
+    azure::errors::describe_error(&message)
+}
+
+#[tauri::command]
+fn get_error_catalog() -> Vec<azure::errors::ErrorCatalogEntry> {
+    azure::errors::CATALOG.to_vec()
+}
+
+// Training sandbox commands
+// Sandbox connections are routed to azure::sandbox by the message/queue commands above.
+// Only queues exist there; topic operations return nothing or a "[training]" error.
+fn training_queue(queue_name: Option<&str>) -> Result<&str, String> {
+    queue_name.ok_or_else(|| "[training] Topics and subscriptions are not available in the training sandbox".to_string())
+}
+
+#[tauri::command]
+fn get_training_connection() -> ServiceBusConnection {
+    sandbox::training_connection()
+}
+
+#[tauri::command]
+fn reset_training_sandbox() -> Result<(), String> {
+    sandbox::reset()
+}
+
+#[tauri::command]
+fn resubmit_training_dead_letters(queue_name: String, max_count: u32) -> Result<u32, String> {
+    sandbox::resubmit_dead_letters(&queue_name, max_count)
+}
+
+fn main() {
+    tauri::Builder::default()
+        .manage(AppState::default())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_keyring::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .invoke_handler(tauri::generate_handler![
             // License commands
             check_license_status,
             initiate_purchase,
@@ -583,6 +3044,13 @@ fn main() {
             store_connection_string,
             get_connection_string,
             delete_connection_string,
+            store_export_credential,
+            delete_export_credential,
+            list_encryption_keys,
+            store_encryption_key,
+            delete_encryption_key,
+            copy_secret,
+            get_audit_log,
             list_connection_ids,
             get_all_connection_strings,
             store_all_connection_strings,
@@ -594,26 +3062,140 @@ fn main() {
             list_queues,
             list_queues_page,
             get_queue,
+            refresh_queue,
+            estimate_backlog,
             create_queue,
+            create_temporary_queue,
+            list_temporary_queues,
+            request_reply,
             update_queue,
             delete_queue,
             list_topics,
             get_topic,
+            refresh_topic,
             create_topic,
             update_topic,
             delete_topic,
+            find_forward_references,
             list_subscriptions,
+            list_subscriptions_page,
+            refresh_subscription,
             create_subscription,
+            apply_settings_to_subscriptions,
+            bulk_edit_entities,
             peek_messages,
+            stream_peek_messages,
+            ack_peek_chunk,
+            cancel_peek_stream,
             peek_dead_letter_messages,
+            peek_transfer_dead_letter_messages,
             send_message,
+            send_messages_batch,
+            resend_message,
+            send_message_repeated,
+            cancel_send_repeated,
+            cancel_scheduled_message,
+            peek_scheduled_messages,
+            reschedule_messages,
+            list_sessions,
+            peek_session_messages,
+            receive_session_messages,
+            get_session_state,
+            set_session_state,
             purge_queue,
+            receive_messages,
+            complete_message,
+            abandon_message,
+            dead_letter_message,
+            defer_message,
+            receive_deferred_messages,
+            search_messages,
+            preview_message_expiry,
+            get_redelivery_timeline,
+            analyze_dead_letter_queue,
+            group_messages_by_property,
+            find_duplicate_messages,
+            analyze_message_ordering,
+            infer_message_schema,
+            move_messages,
+            copy_messages,
+            delete_messages_by_sequence,
+            dead_letter_all_messages,
+            drain_and_migrate_queue,
+            export_messages,
+            hydrate_export,
+            export_dead_letters,
+            import_messages,
+            start_monitor,
+            stop_monitor,
+            list_monitors,
+            drain_dead_letters,
+            drain_messages,
+            list_operations,
+            cancel_operation,
+            verify_message_counts,
             test_connection,
+            probe_connection_access,
+            // Connection string helpers
+            build_connection_string,
+            explain_connection_string,
+            inspect_sas_token,
+            inspect_aad_token,
+            rotate_sas_key,
+            get_entity_xml,
+            update_entity_xml,
+            create_least_privilege_policy,
+            export_read_only_bundle,
+            open_read_only_bundle,
+            rebind_connection,
+            // Settings commands
+            get_settings,
+            set_settings,
+            format_timestamps,
+            get_dry_run_requests,
+            clear_dry_run_requests,
+            // Body decoders
+            get_body_decoders,
+            add_protobuf_descriptor_set,
+            remove_descriptor_set,
+            add_avro_schema,
+            remove_avro_schema,
+            set_body_decoder_rules,
+            // Message templates
+            list_message_templates,
+            save_message_template,
+            delete_message_template,
+            preview_message_template,
+            send_message_template,
+            // Error catalog
+            describe_error,
+            get_error_catalog,
+            // Training sandbox
+            get_training_connection,
+            reset_training_sandbox,
+            resubmit_training_dead_letters,
         ])
-        .setup(|_app| {
+        .setup(|app| {
+            use tauri::Manager;
+
+            match config::load_settings() {
+                Ok(settings) => azure::dry_run::set_enabled(settings.dry_run),
+                Err(e) => eprintln!("[settings] {}", e),
+            }
+            decoders::set_keychain_keys(&load_encryption_keys(app.handle()));
+            app.state::<AppState>().license.start(app.handle().clone());
+            reconnect::start(app.handle().clone(), app.state::<AppState>().clients.clone());
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            use tauri::Manager;
+
+            // Stop monitors and abort running operations before the process goes away
+            if let tauri::RunEvent::Exit = event {
+                app.state::<AppState>().shutdown();
+            }
+        });
 }
 
@@ -0,0 +1,185 @@
+// Drain and migrate: retire a queue by letting its consumers finish and moving what
+// they leave behind to another entity
+//
+// 1. Sends to the queue are disabled (Status=SendDisabled), so producers that were not
+//    repointed at the destination fail fast instead of adding to the backlog.
+// 2. The active count is polled while consumers work the backlog off. When it stops
+//    falling for stall_seconds (consumers paused, scaled to zero or already moved over),
+//    or max_wait_seconds pass, waiting ends and the rest is transferred instead.
+// 3. Whatever is still active, and with include_dead_letters the DLQ, is moved to the
+//    destination with move_messages.
+//
+// The queue is left SendDisabled, including when a later step fails or the operation is
+// cancelled; previous_status says what to restore if the migration is abandoned.
+//
+// Under dry run sends are never really disabled, so consumers are not waited for: the
+// transfers are planned (see move_messages) and the result comes back straight away.
+
+use crate::azure::dry_run;
+use crate::azure::servicebus::ServiceBusClient;
+use crate::azure::types::{MessageSource, MoveMessagesProgress, QueueProperties};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_STALL_SECONDS: u64 = 120;
+const DEFAULT_MAX_WAIT_SECONDS: u64 = 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationOptions {
+    // Queue or topic the remaining messages are moved to
+    pub destination: String,
+    // How long the active count may stay flat before consumers count as paused
+    #[serde(default)]
+    pub stall_seconds: Option<u64>,
+    #[serde(default)]
+    pub max_wait_seconds: Option<u64>,
+    #[serde(default)]
+    pub include_dead_letters: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MigrationPhase {
+    DisablingSends,
+    WaitingForConsumers,
+    Transferring,
+    TransferringDeadLetters,
+    Done,
+}
+
+// Payload of the "migrate-queue-progress" event and the drain_and_migrate_queue result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationProgress {
+    pub queue_name: String,
+    pub destination: String,
+    pub phase: MigrationPhase,
+    // Status before sends were disabled
+    pub previous_status: String,
+    pub initial_active_count: u64,
+    pub active_count: u64,
+    pub dead_letter_count: u64,
+    pub waited_seconds: u64,
+    // Waiting ended before the active count reached zero: it stopped falling, or
+    // max_wait_seconds passed
+    pub consumers_stalled: bool,
+    // Moved by the transfer steps
+    pub moved: u32,
+    pub moved_dead_letters: u32,
+    // Only planned (dry run): nothing was disabled, waited for or moved
+    pub dry_run: bool,
+}
+
+pub async fn drain_and_migrate(
+    client: &ServiceBusClient,
+    queue_name: &str,
+    options: &MigrationOptions,
+    on_progress: &(dyn Fn(&MigrationProgress) + Send + Sync),
+) -> Result<MigrationProgress, String> {
+    let destination = options.destination.trim();
+    if destination.is_empty() {
+        return Err("A destination queue or topic is required".to_string());
+    }
+    if destination.eq_ignore_ascii_case(queue_name) {
+        return Err("Source and destination must be different entities".to_string());
+    }
+    let queue = client.get_queue(queue_name).await?;
+    // move_messages receives without a session
+    if queue.requires_session == Some(true) {
+        return Err(format!("'{}' requires sessions and cannot be drained by this workflow", queue_name));
+    }
+    let previous_status = queue.status.clone().unwrap_or_else(|| "Active".to_string());
+    if matches!(previous_status.as_str(), "Disabled" | "ReceiveDisabled") {
+        return Err(format!("'{}' is {}: consumers could not drain it", queue_name, previous_status));
+    }
+
+    let initial_active_count = queue.active_message_count.unwrap_or(0);
+    let mut progress = MigrationProgress {
+        queue_name: queue_name.to_string(),
+        destination: destination.to_string(),
+        phase: MigrationPhase::DisablingSends,
+        previous_status,
+        initial_active_count,
+        active_count: initial_active_count,
+        dead_letter_count: queue.dead_letter_message_count.unwrap_or(0),
+        waited_seconds: 0,
+        consumers_stalled: false,
+        moved: 0,
+        moved_dead_letters: 0,
+        dry_run: dry_run::is_enabled(),
+    };
+    on_progress(&progress);
+    if progress.previous_status != "SendDisabled" {
+        let send_disabled = QueueProperties {
+            name: queue_name.to_string(),
+            status: Some("SendDisabled".to_string()),
+            ..Default::default()
+        };
+        client.update_queue(queue_name, &send_disabled).await?;
+    }
+    eprintln!("[drain_and_migrate] Disabled sends to {}, waiting for consumers", queue_name);
+
+    progress.phase = MigrationPhase::WaitingForConsumers;
+    let stall = Duration::from_secs(options.stall_seconds.unwrap_or(DEFAULT_STALL_SECONDS));
+    let max_wait = Duration::from_secs(options.max_wait_seconds.unwrap_or(DEFAULT_MAX_WAIT_SECONDS));
+    let started = Instant::now();
+    let mut lowest = u64::MAX;
+    let mut last_drop = started;
+    while !progress.dry_run {
+        let (_, counts) = client.runtime_counts(Some(queue_name), None, None).await?;
+        progress.active_count = counts.active.unwrap_or(0);
+        progress.dead_letter_count = counts.dead_letter.unwrap_or(0);
+        progress.waited_seconds = started.elapsed().as_secs();
+        on_progress(&progress);
+        if progress.active_count == 0 {
+            break;
+        }
+        if progress.active_count < lowest {
+            lowest = progress.active_count;
+            last_drop = Instant::now();
+        }
+        if last_drop.elapsed() >= stall || started.elapsed() >= max_wait {
+            progress.consumers_stalled = true;
+            break;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    let mut source = MessageSource {
+        queue_name: Some(queue_name.to_string()),
+        topic_name: None,
+        subscription_name: None,
+        dead_letter: false,
+    };
+    if progress.active_count > 0 {
+        progress.phase = MigrationPhase::Transferring;
+        on_progress(&progress);
+        let count = progress.active_count.min(u32::MAX as u64) as u32;
+        let snapshot = progress.clone();
+        let on_move = |moved: &MoveMessagesProgress| {
+            on_progress(&MigrationProgress { moved: moved.moved, ..snapshot.clone() });
+        };
+        progress.moved = client.move_messages(&source, destination, count, &on_move).await?.moved;
+    }
+    if options.include_dead_letters && progress.dead_letter_count > 0 {
+        progress.phase = MigrationPhase::TransferringDeadLetters;
+        on_progress(&progress);
+        source.dead_letter = true;
+        let count = progress.dead_letter_count.min(u32::MAX as u64) as u32;
+        let snapshot = progress.clone();
+        let on_move = |moved: &MoveMessagesProgress| {
+            on_progress(&MigrationProgress { moved_dead_letters: moved.moved, ..snapshot.clone() });
+        };
+        progress.moved_dead_letters = client.move_messages(&source, destination, count, &on_move).await?.moved;
+    }
+
+    progress.phase = MigrationPhase::Done;
+    on_progress(&progress);
+    eprintln!(
+        "[drain_and_migrate] Migrated {} to {}: {} moved, {} dead letters moved, consumers stalled: {}",
+        queue_name, destination, progress.moved, progress.moved_dead_letters, progress.consumers_stalled
+    );
+    Ok(progress)
+}
@@ -0,0 +1,297 @@
+// Live tail of an entity: a background task peeks past the last sequence number it
+// has seen and emits new messages as "monitor-messages" events.
+//
+// Peek only sees messages that are still on the entity, so anything a consumer
+// completes between two polls is never reported.
+//
+// A monitor that can't reach the namespace keeps going for up to MAX_OUTAGE: it waits
+// for a reconnect (see reconnect.rs) or RETRY_INTERVAL, takes a new client from the
+// cache (emptied by the reconnect) and picks up where it stopped. Any other error stops it.
+
+use crate::azure::sandbox;
+use crate::azure::servicebus::ServiceBusClient;
+use crate::azure::timestamps;
+use crate::azure::types::{ConnectionLabel, MessageSource, ServiceBusConnection, ServiceBusMessage};
+use crate::reconnect;
+use crate::state::ClientCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const PAGE_SIZE: u32 = 100;
+const MIN_POLL_INTERVAL_MS: u64 = 500;
+const DEFAULT_POLL_INTERVAL_MS: u64 = 2000;
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+const MAX_OUTAGE: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+    pub monitor_id: String,
+    pub connection_id: String,
+    pub connection: ConnectionLabel,
+    pub source: MessageSource,
+    pub poll_interval_ms: u64,
+    pub started_at: String,
+    // Next sequence number to peek from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+    pub messages_seen: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MonitorMessages<'a> {
+    monitor_id: &'a str,
+    connection: &'a ConnectionLabel,
+    messages: &'a [ServiceBusMessage],
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MonitorStopped<'a> {
+    monitor_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+struct Monitor {
+    info: MonitorInfo,
+    stop: Arc<AtomicBool>,
+}
+
+static MONITORS: OnceLock<Mutex<HashMap<String, Monitor>>> = OnceLock::new();
+
+fn with_monitors<T>(f: impl FnOnce(&mut HashMap<String, Monitor>) -> T) -> T {
+    let mutex = MONITORS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut monitors = mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut monitors)
+}
+
+// Entity path of a source, e.g. "orders/Subscriptions/audit/$deadletterqueue"
+pub fn source_label(source: &MessageSource) -> String {
+    let path = match (&source.queue_name, &source.topic_name, &source.subscription_name) {
+        (Some(q), _, _) => q.clone(),
+        (None, Some(t), Some(s)) => format!("{}/Subscriptions/{}", t, s),
+        _ => "?".to_string(),
+    };
+    if source.dead_letter {
+        format!("{}/$deadletterqueue", path)
+    } else {
+        path
+    }
+}
+
+// Start tailing an entity. Without from_sequence_number only messages that arrive
+// after the start are reported.
+pub async fn start(
+    app: tauri::AppHandle,
+    clients: ClientCache,
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    poll_interval_ms: Option<u64>,
+    from_sequence_number: Option<u64>,
+    max_body_bytes: Option<u32>,
+) -> Result<MonitorInfo, String> {
+    let monitor_id = format!("{}:{}", connection.id, source_label(&source));
+    if with_monitors(|monitors| monitors.contains_key(&monitor_id)) {
+        return Err(format!("'{}' is already being monitored", source_label(&source)));
+    }
+
+    let training = sandbox::is_training(&connection);
+    let client = if training { None } else { Some(clients.get(&connection).await?) };
+    let poll = MonitorPoll { clients, client, connection: connection.clone(), source: source.clone(), max_body_bytes };
+
+    // Skip what is already there so only new arrivals are reported
+    let next_sequence_number = match from_sequence_number {
+        Some(seq) => seq,
+        None => {
+            let mut next = 0u64;
+            loop {
+                let page = poll.peek(next).await?;
+                match page.last().and_then(|m| m.sequence_number) {
+                    Some(last) => next = last + 1,
+                    None => break,
+                }
+                if (page.len() as u32) < PAGE_SIZE {
+                    break;
+                }
+            }
+            next
+        }
+    };
+
+    let info = MonitorInfo {
+        monitor_id: monitor_id.clone(),
+        connection_id: connection.id.clone(),
+        connection: connection.label(),
+        source,
+        poll_interval_ms: poll_interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS).max(MIN_POLL_INTERVAL_MS),
+        started_at: timestamps::format(chrono::Utc::now()),
+        next_sequence_number: Some(next_sequence_number),
+        messages_seen: 0,
+    };
+    let stop = Arc::new(AtomicBool::new(false));
+    let inserted = with_monitors(|monitors| {
+        if monitors.contains_key(&monitor_id) {
+            return false;
+        }
+        monitors.insert(monitor_id.clone(), Monitor { info: info.clone(), stop: stop.clone() });
+        true
+    });
+    if !inserted {
+        return Err(format!("'{}' is already being monitored", source_label(&info.source)));
+    }
+
+    eprintln!("[monitor] Started {} from sequence number {}", monitor_id, next_sequence_number);
+    tauri::async_runtime::spawn(run(
+        app,
+        poll,
+        monitor_id,
+        info.connection.clone(),
+        next_sequence_number,
+        info.poll_interval_ms,
+        stop,
+    ));
+    Ok(info)
+}
+
+pub fn stop(monitor_id: &str) -> Result<(), String> {
+    with_monitors(|monitors| match monitors.remove(monitor_id) {
+        Some(monitor) => {
+            monitor.stop.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(format!("No monitor with id '{}'", monitor_id)),
+    })
+}
+
+pub fn stop_all() {
+    with_monitors(|monitors| {
+        for (_, monitor) in monitors.drain() {
+            monitor.stop.store(true, Ordering::SeqCst);
+        }
+    })
+}
+
+pub fn list() -> Vec<MonitorInfo> {
+    with_monitors(|monitors| monitors.values().map(|m| m.info.clone()).collect())
+}
+
+struct MonitorPoll {
+    clients: ClientCache,
+    client: Option<Arc<ServiceBusClient>>,
+    // For schema registry lookups by the body decoders
+    connection: ServiceBusConnection,
+    source: MessageSource,
+    max_body_bytes: Option<u32>,
+}
+
+impl MonitorPoll {
+    async fn peek(&self, from_sequence_number: u64) -> Result<Vec<ServiceBusMessage>, String> {
+        match &self.client {
+            Some(client) => {
+                client
+                    .peek_source(&self.source, PAGE_SIZE, Some(from_sequence_number), self.max_body_bytes)
+                    .await
+            }
+            None => sandbox::peek_source(&self.source, PAGE_SIZE, Some(from_sequence_number)),
+        }
+    }
+
+    // Replace the client after a reconnect; the old one is kept if this fails
+    async fn rebuild_client(&mut self) {
+        if self.client.is_none() {
+            return;
+        }
+        match self.clients.get(&self.connection).await {
+            Ok(client) => self.client = Some(client),
+            Err(e) => eprintln!("[monitor] Failed to rebuild client: {}", e),
+        }
+    }
+}
+
+async fn run(
+    app: tauri::AppHandle,
+    mut poll: MonitorPoll,
+    monitor_id: String,
+    connection: ConnectionLabel,
+    mut next_sequence_number: u64,
+    poll_interval_ms: u64,
+    stop: Arc<AtomicBool>,
+) {
+    use tauri::Emitter;
+
+    let mut reconnects = reconnect::subscribe();
+    let mut outage_started: Option<Instant> = None;
+    let mut failure = None;
+    while !stop.load(Ordering::SeqCst) {
+        // A client from before a reconnect holds dead connections
+        if reconnects.has_changed().unwrap_or(false) {
+            reconnects.borrow_and_update();
+            poll.rebuild_client().await;
+        }
+        // Drain everything new before waiting for the next poll
+        let mut disconnected = false;
+        loop {
+            let mut page = match poll.peek(next_sequence_number).await {
+                Ok(page) => page,
+                Err(e) => {
+                    let outage = *outage_started.get_or_insert_with(Instant::now);
+                    if reconnect::is_network_error(&e) && outage.elapsed() < MAX_OUTAGE {
+                        eprintln!("[monitor] {} lost its connection, retrying: {}", monitor_id, e);
+                        reconnect::report_network_error();
+                        disconnected = true;
+                    } else {
+                        failure = Some(e);
+                    }
+                    break;
+                }
+            };
+            outage_started = None;
+            // A stop during the peek wins over emitting its result
+            if page.is_empty() || stop.load(Ordering::SeqCst) {
+                break;
+            }
+            if let Some(last) = page.last().and_then(|m| m.sequence_number) {
+                next_sequence_number = last + 1;
+            }
+            let entity = poll.source.queue_name.as_deref().or(poll.source.topic_name.as_deref());
+            crate::decoders::decode_messages(&mut page, &poll.connection, entity).await;
+            let payload = MonitorMessages { monitor_id: &monitor_id, connection: &connection, messages: &page };
+            if let Err(e) = app.emit("monitor-messages", &payload) {
+                eprintln!("[monitor] Failed to emit messages: {}", e);
+            }
+            with_monitors(|monitors| {
+                if let Some(monitor) = monitors.get_mut(&monitor_id) {
+                    monitor.info.next_sequence_number = Some(next_sequence_number);
+                    monitor.info.messages_seen += page.len() as u64;
+                }
+            });
+            if (page.len() as u32) < PAGE_SIZE {
+                break;
+            }
+        }
+        if failure.is_some() {
+            break;
+        }
+        if disconnected {
+            reconnect::wait(&mut reconnects, RETRY_INTERVAL).await;
+            poll.rebuild_client().await;
+            continue;
+        }
+        tokio::time::sleep(Duration::from_millis(poll_interval_ms)).await;
+    }
+
+    // A failed monitor removes itself; a stopped one was already removed
+    if failure.is_some() {
+        with_monitors(|monitors| monitors.remove(&monitor_id));
+    }
+    eprintln!("[monitor] Stopped {}{}", monitor_id, failure.as_deref().map(|e| format!(": {}", e)).unwrap_or_default());
+    let payload = MonitorStopped { monitor_id: &monitor_id, error: failure.as_deref() };
+    if let Err(e) = app.emit("monitor-stopped", &payload) {
+        eprintln!("[monitor] Failed to emit stop: {}", e);
+    }
+}
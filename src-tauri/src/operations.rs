@@ -0,0 +1,194 @@
+// Registry of long-running operations (purge, export, import, bulk send, search, move,
+// copy, drain, dead-letter, migrate, bulk-edit) so the UI can stop one that runs away
+//
+// Each operation runs as its own task. "operation-started" carries its id before any
+// work is done, and cancel() aborts the task at its next await point: whatever was
+// already received, sent or written stays done, and a peek-locked message that was
+// in flight is unlocked again when its lock expires. "operation-finished" follows in
+// every case.
+//
+// Operations started with run_reporting also emit "operation-progress" as they go, at
+// most every PROGRESS_INTERVAL, so the UI can show a progress bar instead of a spinner.
+
+use crate::azure::timestamps;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationInfo {
+    pub operation_id: String,
+    // "purge", "export", "hydrate", "import", "send", "search", "move", "copy", "drain",
+    // "dead-letter", "migrate" or "bulk-edit"
+    pub kind: String,
+    pub description: String,
+    pub started_at: String,
+}
+
+// Payload of the "operation-finished" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationFinished {
+    pub operation_id: String,
+    pub kind: String,
+    pub cancelled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Payload of the "operation-progress" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationProgress {
+    pub operation_id: String,
+    pub kind: String,
+    // Messages purged, exported, hydrated or moved so far
+    pub processed: u64,
+    // Unset when the total isn't known up front
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_estimate: Option<u64>,
+    // Messages per second since the start
+    pub rate: f64,
+}
+
+// Handed to an operation's task to report how far it got; cheap to clone
+#[derive(Clone)]
+pub struct ProgressReporter {
+    inner: Arc<Reporter>,
+}
+
+struct Reporter {
+    app: tauri::AppHandle,
+    operation_id: String,
+    kind: String,
+    started: Instant,
+    last_emitted: Mutex<Option<Instant>>,
+}
+
+impl ProgressReporter {
+    pub fn report(&self, processed: u64, total_estimate: Option<u64>) {
+        let reporter = &self.inner;
+        let finished = total_estimate.is_some_and(|total| processed >= total);
+        {
+            let mut last_emitted = reporter.last_emitted.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if !finished && last_emitted.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            *last_emitted = Some(Instant::now());
+        }
+
+        let elapsed = reporter.started.elapsed().as_secs_f64();
+        let progress = OperationProgress {
+            operation_id: reporter.operation_id.clone(),
+            kind: reporter.kind.clone(),
+            processed,
+            total_estimate,
+            rate: if elapsed > 0.0 { processed as f64 / elapsed } else { 0.0 },
+        };
+        if let Err(e) = reporter.app.emit("operation-progress", progress) {
+            eprintln!("[operations] Failed to emit progress: {}", e);
+        }
+    }
+}
+
+struct Operation {
+    info: OperationInfo,
+    abort: tokio::task::AbortHandle,
+}
+
+static OPERATIONS: OnceLock<Mutex<HashMap<String, Operation>>> = OnceLock::new();
+
+fn with_operations<T>(f: impl FnOnce(&mut HashMap<String, Operation>) -> T) -> T {
+    let mutex = OPERATIONS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut operations = mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut operations)
+}
+
+// Run task as a cancellable operation and return its result, or an error when it
+// was cancelled
+pub async fn run<T, F>(app: &tauri::AppHandle, kind: &str, description: String, task: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T, String>> + Send + 'static,
+{
+    run_reporting(app, kind, description, |_| task).await
+}
+
+// Same as run, with a reporter for "operation-progress" events passed to the task
+pub async fn run_reporting<T, F, Fut>(app: &tauri::AppHandle, kind: &str, description: String, task: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(ProgressReporter) -> Fut,
+    Fut: Future<Output = Result<T, String>> + Send + 'static,
+{
+    let info = OperationInfo {
+        operation_id: format!("{:016x}", rand::random::<u64>()),
+        kind: kind.to_string(),
+        description,
+        started_at: timestamps::format(chrono::Utc::now()),
+    };
+    let operation_id = info.operation_id.clone();
+    let reporter = ProgressReporter {
+        inner: Arc::new(Reporter {
+            app: app.clone(),
+            operation_id: operation_id.clone(),
+            kind: info.kind.clone(),
+            started: Instant::now(),
+            last_emitted: Mutex::new(None),
+        }),
+    };
+    let handle = tokio::spawn(task(reporter));
+    with_operations(|operations| {
+        operations.insert(operation_id.clone(), Operation { info: info.clone(), abort: handle.abort_handle() })
+    });
+    if let Err(e) = app.emit("operation-started", info.clone()) {
+        eprintln!("[operations] Failed to emit start: {}", e);
+    }
+
+    let (result, cancelled) = match handle.await {
+        Ok(result) => (result, false),
+        Err(e) if e.is_cancelled() => (Err(format!("{} was cancelled", info.description)), true),
+        Err(e) => (Err(format!("{} failed: {}", info.description, e)), false),
+    };
+    with_operations(|operations| operations.remove(&operation_id));
+    if cancelled {
+        eprintln!("[operations] Cancelled {} ({})", operation_id, info.description);
+    }
+
+    let finished = OperationFinished {
+        operation_id,
+        kind: info.kind,
+        cancelled,
+        error: result.as_ref().err().cloned(),
+    };
+    if let Err(e) = app.emit("operation-finished", finished) {
+        eprintln!("[operations] Failed to emit finish: {}", e);
+    }
+    result
+}
+
+pub fn cancel(operation_id: &str) -> Result<(), String> {
+    with_operations(|operations| match operations.get(operation_id) {
+        Some(operation) => {
+            operation.abort.abort();
+            Ok(())
+        }
+        None => Err(format!("No running operation with id '{}'", operation_id)),
+    })
+}
+
+pub fn cancel_all() {
+    with_operations(|operations| operations.values().for_each(|operation| operation.abort.abort()))
+}
+
+pub fn list() -> Vec<OperationInfo> {
+    let mut operations: Vec<OperationInfo> = with_operations(|operations| operations.values().map(|o| o.info.clone()).collect());
+    operations.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    operations
+}
@@ -0,0 +1,223 @@
+// Streaming peek for very large entities: pages of messages go to the frontend as
+// "peek-messages-chunk" events instead of one result holding every message
+//
+// Backpressure: at most `window` chunks may be unacknowledged. The frontend calls
+// ack(stream_id, chunk_index) once it has taken a chunk, and the stream waits for that
+// before peeking further, so a slow view never makes the backend buffer ahead.
+// Nothing is kept once a chunk has been emitted.
+
+use crate::azure::sandbox;
+use crate::azure::servicebus::ServiceBusClient;
+use crate::azure::types::{MessageSource, ServiceBusConnection, ServiceBusMessage};
+use crate::state::ClientCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+const DEFAULT_CHUNK_SIZE: u32 = 100;
+const MAX_CHUNK_SIZE: u32 = 500;
+const DEFAULT_WINDOW: u32 = 4;
+// A stream whose chunks stop being acknowledged is given up on
+const ACK_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PeekStreamOptions {
+    // Unset peeks until the entity is exhausted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_sequence_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<u32>,
+    // Chunks that may be in flight without an ack
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<u32>,
+}
+
+// Payload of the "peek-messages-chunk" event. The last event of a stream has done set
+// (and may have no messages); it needs no ack.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeekChunk {
+    pub stream_id: String,
+    pub chunk_index: u32,
+    pub messages: Vec<ServiceBusMessage>,
+    // Messages delivered so far, this chunk included
+    pub delivered: u32,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeekStreamSummary {
+    pub stream_id: String,
+    pub delivered: u32,
+    pub chunks: u32,
+    pub cancelled: bool,
+    // Where to continue when the stream stopped before the end of the entity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+}
+
+struct StreamState {
+    // Chunks acknowledged so far (index of the next expected ack)
+    acked: AtomicU32,
+    cancelled: AtomicBool,
+    changed: tokio::sync::Notify,
+}
+
+static STREAMS: OnceLock<Mutex<HashMap<String, Arc<StreamState>>>> = OnceLock::new();
+
+fn with_streams<T>(f: impl FnOnce(&mut HashMap<String, Arc<StreamState>>) -> T) -> T {
+    let mutex = STREAMS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut streams = mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut streams)
+}
+
+fn stream(stream_id: &str) -> Result<Arc<StreamState>, String> {
+    with_streams(|streams| streams.get(stream_id).cloned()).ok_or_else(|| format!("No peek stream with id '{}'", stream_id))
+}
+
+// Acknowledge chunks up to and including chunk_index
+pub fn ack(stream_id: &str, chunk_index: u32) -> Result<(), String> {
+    let state = stream(stream_id)?;
+    state.acked.fetch_max(chunk_index + 1, Ordering::SeqCst);
+    state.changed.notify_waiters();
+    Ok(())
+}
+
+// Stop a stream before its next chunk
+pub fn cancel(stream_id: &str) -> Result<(), String> {
+    let state = stream(stream_id)?;
+    state.cancelled.store(true, Ordering::SeqCst);
+    state.changed.notify_waiters();
+    Ok(())
+}
+
+pub fn cancel_all() {
+    with_streams(|streams| {
+        for state in streams.values() {
+            state.cancelled.store(true, Ordering::SeqCst);
+            state.changed.notify_waiters();
+        }
+    })
+}
+
+pub async fn stream_peek(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    options: &PeekStreamOptions,
+    on_chunk: &(dyn Fn(&PeekChunk) + Send + Sync),
+) -> Result<PeekStreamSummary, String> {
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let stream_id = format!("{:016x}", rand::random::<u64>());
+    let state = Arc::new(StreamState { acked: AtomicU32::new(0), cancelled: AtomicBool::new(false), changed: Default::default() });
+    with_streams(|streams| streams.insert(stream_id.clone(), state.clone()));
+
+    let result = run(&client, connection, source, &stream_id, &state, options, on_chunk).await;
+    with_streams(|streams| streams.remove(&stream_id));
+    let summary = result?;
+    eprintln!(
+        "[stream_peek] Stream {}: {} messages in {} chunks{}",
+        stream_id,
+        summary.delivered,
+        summary.chunks,
+        if summary.cancelled { " (cancelled)" } else { "" }
+    );
+    Ok(summary)
+}
+
+async fn run(
+    client: &Option<Arc<ServiceBusClient>>,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    stream_id: &str,
+    state: &StreamState,
+    options: &PeekStreamOptions,
+    on_chunk: &(dyn Fn(&PeekChunk) + Send + Sync),
+) -> Result<PeekStreamSummary, String> {
+    let chunk_size = options.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).clamp(1, MAX_CHUNK_SIZE);
+    let window = options.window.unwrap_or(DEFAULT_WINDOW).max(1);
+    let max_count = options.max_count.unwrap_or(u32::MAX);
+    let entity = source.queue_name.as_deref().or(source.topic_name.as_deref());
+    let mut summary = PeekStreamSummary {
+        stream_id: stream_id.to_string(),
+        delivered: 0,
+        chunks: 0,
+        cancelled: false,
+        next_sequence_number: None,
+    };
+    let mut next_sequence_number = options.from_sequence_number.unwrap_or(0);
+    let mut exhausted = false;
+
+    while summary.delivered < max_count {
+        // Wait until the frontend has caught up to within `window` chunks
+        loop {
+            let changed = state.changed.notified();
+            if state.cancelled.load(Ordering::SeqCst) || summary.chunks < state.acked.load(Ordering::SeqCst) + window {
+                break;
+            }
+            if tokio::time::timeout(ACK_TIMEOUT, changed).await.is_err() {
+                return Err(format!(
+                    "Peek stream stopped after {} messages: chunks were not acknowledged for {}s",
+                    summary.delivered,
+                    ACK_TIMEOUT.as_secs()
+                ));
+            }
+        }
+        if state.cancelled.load(Ordering::SeqCst) {
+            summary.cancelled = true;
+            break;
+        }
+
+        let count = (max_count - summary.delivered).min(chunk_size);
+        let mut page = match client {
+            Some(client) => client.peek_source(source, count, Some(next_sequence_number), options.max_body_bytes).await?,
+            None => sandbox::peek_source(source, count, Some(next_sequence_number))?,
+        };
+        if let Some(last) = page.last().and_then(|m| m.sequence_number) {
+            next_sequence_number = last + 1;
+        }
+        exhausted = (page.len() as u32) < count;
+        if page.is_empty() {
+            break;
+        }
+        crate::decoders::decode_messages(&mut page, connection, entity).await;
+
+        summary.delivered += page.len() as u32;
+        on_chunk(&PeekChunk {
+            stream_id: stream_id.to_string(),
+            chunk_index: summary.chunks,
+            messages: page,
+            delivered: summary.delivered,
+            done: false,
+        });
+        summary.chunks += 1;
+        if exhausted {
+            break;
+        }
+    }
+
+    if !exhausted {
+        summary.next_sequence_number = Some(next_sequence_number);
+    }
+    on_chunk(&PeekChunk {
+        stream_id: stream_id.to_string(),
+        chunk_index: summary.chunks,
+        messages: Vec::new(),
+        delivered: summary.delivered,
+        done: true,
+    });
+    Ok(summary)
+}
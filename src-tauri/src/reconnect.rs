@@ -0,0 +1,148 @@
+// Reconnect after the machine sleeps
+//
+// After a resume, pooled HTTP connections are dead and tokens signed before the sleep
+// may have expired, so every monitor used to fail on its next poll and stop. A watchdog
+// notices the resume (the wall clock jumping well past its tick) or a burst of network
+// errors, drops the cached clients and bumps a generation that monitors watch: they
+// build new clients and carry on from the sequence number they had reached. The
+// frontend gets a single "reconnected" event instead of one failure per tail.
+
+use crate::azure::timestamps;
+use crate::state::ClientCache;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::watch;
+
+const TICK: Duration = Duration::from_secs(5);
+// A tick this much longer than TICK means the machine was asleep (monotonic timers
+// stop during sleep, the wall clock doesn't)
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+const FAILURE_WINDOW: Duration = Duration::from_secs(30);
+const FAILURE_THRESHOLD: usize = 5;
+// The errors that follow a reconnect come from requests made before it
+const MIN_RECONNECT_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReconnectReason {
+    Resume,
+    NetworkErrors,
+}
+
+// Payload of the "reconnected" event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reconnected {
+    pub reason: ReconnectReason,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asleep_seconds: Option<u64>,
+    // Monitors that carry on with new clients
+    pub monitors: usize,
+    pub at: String,
+}
+
+struct Watchdog {
+    app: tauri::AppHandle,
+    clients: ClientCache,
+}
+
+#[derive(Default)]
+struct Failures {
+    recent: VecDeque<Instant>,
+    last_reconnect: Option<Instant>,
+}
+
+static WATCHDOG: OnceLock<Watchdog> = OnceLock::new();
+static FAILURES: OnceLock<Mutex<Failures>> = OnceLock::new();
+static GENERATION: OnceLock<watch::Sender<u64>> = OnceLock::new();
+
+fn with_failures<T>(f: impl FnOnce(&mut Failures) -> T) -> T {
+    let mutex = FAILURES.get_or_init(|| Mutex::new(Failures::default()));
+    let mut failures = mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut failures)
+}
+
+fn generation() -> &'static watch::Sender<u64> {
+    GENERATION.get_or_init(|| watch::channel(0).0)
+}
+
+// Start watching for resumes; called once from setup
+pub fn start(app: tauri::AppHandle, clients: ClientCache) {
+    if WATCHDOG.set(Watchdog { app, clients }).is_err() {
+        return;
+    }
+    tauri::async_runtime::spawn(async {
+        loop {
+            let before = SystemTime::now();
+            tokio::time::sleep(TICK).await;
+            let elapsed = SystemTime::now().duration_since(before).unwrap_or_default();
+            if elapsed > TICK + SLEEP_THRESHOLD {
+                reconnect(ReconnectReason::Resume, Some(elapsed - TICK));
+            }
+        }
+    });
+}
+
+// Changes whenever the clients were dropped, so long-running tasks rebuild theirs
+pub fn subscribe() -> watch::Receiver<u64> {
+    generation().subscribe()
+}
+
+// Wait for the next reconnect, or for at most timeout
+pub async fn wait(receiver: &mut watch::Receiver<u64>, timeout: Duration) {
+    let _ = tokio::time::timeout(timeout, receiver.changed()).await;
+}
+
+// Whether an error means the namespace could not be reached, as opposed to a refusal
+pub fn is_network_error(error: &str) -> bool {
+    matches!(crate::azure::errors::describe_error(error).key.as_str(), "network_error" | "timeout")
+}
+
+// Count a network error; enough of them in FAILURE_WINDOW reconnect everything
+pub fn report_network_error() {
+    let burst = with_failures(|failures| {
+        let now = Instant::now();
+        failures.recent.push_back(now);
+        while failures.recent.front().is_some_and(|at| now.duration_since(*at) > FAILURE_WINDOW) {
+            failures.recent.pop_front();
+        }
+        failures.recent.len() >= FAILURE_THRESHOLD
+    });
+    if burst {
+        reconnect(ReconnectReason::NetworkErrors, None);
+    }
+}
+
+fn reconnect(reason: ReconnectReason, asleep: Option<Duration>) {
+    use tauri::Emitter;
+
+    let Some(watchdog) = WATCHDOG.get() else {
+        return;
+    };
+    let due = with_failures(|failures| {
+        if failures.last_reconnect.is_some_and(|at| at.elapsed() < MIN_RECONNECT_INTERVAL) {
+            return false;
+        }
+        failures.last_reconnect = Some(Instant::now());
+        failures.recent.clear();
+        true
+    });
+    if !due {
+        return;
+    }
+
+    watchdog.clients.clear();
+    generation().send_modify(|generation| *generation += 1);
+    let payload = Reconnected {
+        reason,
+        asleep_seconds: asleep.map(|asleep| asleep.as_secs()),
+        monitors: crate::monitor::list().len(),
+        at: timestamps::format(chrono::Utc::now()),
+    };
+    eprintln!("[reconnect] Reconnecting ({:?}, {} monitors)", reason, payload.monitors);
+    if let Err(e) = watchdog.app.emit("reconnected", &payload) {
+        eprintln!("[reconnect] Failed to emit reconnect: {}", e);
+    }
+}
@@ -0,0 +1,193 @@
+// Send many copies of one message at a steady rate, for load-testing consumers
+//
+// Copies that are due together go out as one batch (at most MAX_BATCH), so high rates
+// don't need a request per message. Each run has an id, reported with every progress
+// event, that cancel() takes; a cancelled run stops before its next batch.
+
+use crate::azure::sandbox;
+use crate::azure::types::{ServiceBusConnection, ServiceBusMessage};
+use crate::state::ClientCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const MAX_COUNT: u32 = 100_000;
+const MAX_BATCH: u32 = 100;
+// Longest sleep between cancellation checks while waiting for the next copy
+const MAX_WAIT: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepeatSendOptions {
+    pub count: u32,
+    // Unset sends as fast as the batches allow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_per_second: Option<f64>,
+    // A new random MessageId per copy
+    #[serde(default)]
+    pub new_message_id: bool,
+    // Application property set to the copy's number, counting from 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter_property: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepeatSendProgress {
+    pub run_id: String,
+    pub entity: String,
+    pub requested: u32,
+    pub sent: u32,
+    pub elapsed_ms: u64,
+    pub cancelled: bool,
+    // True once the run finished, was cancelled or failed
+    pub done: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+static RUNS: OnceLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = OnceLock::new();
+
+fn with_runs<T>(f: impl FnOnce(&mut HashMap<String, Arc<AtomicBool>>) -> T) -> T {
+    let mutex = RUNS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut runs = mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut runs)
+}
+
+pub fn cancel(run_id: &str) -> Result<(), String> {
+    with_runs(|runs| match runs.get(run_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(format!("No repeated send with id '{}'", run_id)),
+    })
+}
+
+pub fn cancel_all() {
+    with_runs(|runs| runs.values().for_each(|cancelled| cancelled.store(true, Ordering::SeqCst)))
+}
+
+// Send options.count copies of message. A failed batch ends the run; the copies sent
+// before it stay sent.
+pub async fn send_repeated(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    queue_name: Option<&str>,
+    topic_name: Option<&str>,
+    message: &ServiceBusMessage,
+    options: &RepeatSendOptions,
+    on_progress: &(dyn Fn(&RepeatSendProgress) + Send + Sync),
+) -> Result<RepeatSendProgress, String> {
+    let RepeatSendOptions { count, rate_per_second, .. } = *options;
+    if count == 0 || count > MAX_COUNT {
+        return Err(format!("Count must be between 1 and {}", MAX_COUNT));
+    }
+    if let Some(rate) = rate_per_second {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err("Rate must be a positive number of messages per second".to_string());
+        }
+    }
+    if message.scheduled_enqueue_time_utc.is_some() {
+        return Err("Scheduled messages can't be sent repeatedly".to_string());
+    }
+    let entity = queue_name.or(topic_name).ok_or("Either queue_name or topic_name must be provided")?;
+
+    let client = if sandbox::is_training(connection) {
+        if queue_name.is_none() {
+            return Err("[training] Topics and subscriptions are not available in the training sandbox".to_string());
+        }
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let run_id = format!("{:016x}", rand::random::<u64>());
+    let cancelled = Arc::new(AtomicBool::new(false));
+    with_runs(|runs| runs.insert(run_id.clone(), cancelled.clone()));
+
+    let started = Instant::now();
+    let mut progress = RepeatSendProgress {
+        run_id: run_id.clone(),
+        entity: entity.to_string(),
+        requested: count,
+        sent: 0,
+        elapsed_ms: 0,
+        cancelled: false,
+        done: false,
+        error: None,
+    };
+    // Reported before anything is sent so the caller has the id to cancel with
+    on_progress(&progress);
+    eprintln!("[send_repeated] Run {}: {} copies to {} at {:?}/s", run_id, count, entity, rate_per_second);
+
+    while progress.sent < count {
+        if cancelled.load(Ordering::SeqCst) {
+            progress.cancelled = true;
+            break;
+        }
+
+        // Copy i (from 0) is due i / rate seconds after the start
+        let due = match rate_per_second {
+            Some(rate) => ((started.elapsed().as_secs_f64() * rate).floor() as u32).saturating_add(1).min(count),
+            None => count,
+        };
+        if due <= progress.sent {
+            let next_at = Duration::from_secs_f64(progress.sent as f64 / rate_per_second.unwrap_or(1.0));
+            tokio::time::sleep(next_at.saturating_sub(started.elapsed()).min(MAX_WAIT)).await;
+            continue;
+        }
+
+        let batch_size = (due - progress.sent).min(MAX_BATCH);
+        let batch: Vec<ServiceBusMessage> =
+            (progress.sent + 1..=progress.sent + batch_size).map(|number| copy(message, number, options)).collect();
+        let result = match &client {
+            Some(client) => client.send_messages_batch(queue_name, topic_name, &batch).await.map(|_| ()),
+            None => batch.iter().try_for_each(|m| sandbox::send_message(entity, m)),
+        };
+        if let Err(e) = result {
+            progress.error = Some(e);
+            break;
+        }
+
+        progress.sent += batch_size;
+        progress.elapsed_ms = started.elapsed().as_millis() as u64;
+        on_progress(&progress);
+    }
+
+    with_runs(|runs| runs.remove(&run_id));
+    progress.elapsed_ms = started.elapsed().as_millis() as u64;
+    progress.done = true;
+    on_progress(&progress);
+    eprintln!(
+        "[send_repeated] Run {}: sent {} of {} in {} ms{}",
+        run_id,
+        progress.sent,
+        count,
+        progress.elapsed_ms,
+        if progress.cancelled { " (cancelled)" } else { "" }
+    );
+
+    match progress.error.take() {
+        Some(e) => Err(format!("Repeated send stopped after {} messages: {}", progress.sent, e)),
+        None => Ok(progress),
+    }
+}
+
+fn copy(message: &ServiceBusMessage, number: u32, options: &RepeatSendOptions) -> ServiceBusMessage {
+    let mut copy = message.clone();
+    if options.new_message_id {
+        copy.message_id = Some(crate::templates::random_uuid());
+    }
+    if let Some(name) = &options.counter_property {
+        let mut properties = match copy.application_properties.take() {
+            Some(serde_json::Value::Object(properties)) => properties,
+            _ => serde_json::Map::new(),
+        };
+        properties.insert(name.clone(), serde_json::Value::from(number));
+        copy.application_properties = Some(serde_json::Value::Object(properties));
+    }
+    copy
+}
@@ -0,0 +1,185 @@
+// Clone-and-edit resend: peek one message, patch it and send the copy
+//
+// The patch is a JSON merge patch (RFC 7396) over the message as the UI sees it
+// (camelCase ServiceBusMessage), so {"body": {"status": "retry"}} edits one field of
+// a JSON body, {"applicationProperties": {"attempt": 2}} adds or replaces a property
+// and null removes a field. The original message is left where it is.
+//
+// copy_messages does the same, unpatched, for a run of messages: they are peeked, not
+// received, so the source's consumers keep getting every one of them, e.g. to fan
+// production traffic into a test queue.
+
+use crate::azure::sandbox;
+use crate::azure::types::{CopyMessagesProgress, MessageSource, ServiceBusConnection, ServiceBusMessage};
+use crate::state::ClientCache;
+
+const COPY_PAGE_SIZE: u32 = 100;
+
+// Peek message sequence_number from source, apply patch and send it to queue_name or
+// topic_name (default: the source's queue or topic). Returns the message as sent.
+#[allow(clippy::too_many_arguments)]
+pub async fn resend_message(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    sequence_number: u64,
+    patch: Option<&serde_json::Value>,
+    queue_name: Option<&str>,
+    topic_name: Option<&str>,
+    new_message_id: bool,
+) -> Result<ServiceBusMessage, String> {
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let page = match &client {
+        Some(client) => client.peek_source(source, 1, Some(sequence_number), None).await?,
+        None => sandbox::peek_source(source, 1, Some(sequence_number))?,
+    };
+    let original = page
+        .into_iter()
+        .find(|m| m.sequence_number == Some(sequence_number))
+        .ok_or_else(|| format!("Message {} not found (already received or expired?)", sequence_number))?;
+
+    let mut message = match patch {
+        Some(patch) => apply_patch(original, patch)?,
+        None => original,
+    };
+    if new_message_id {
+        message.message_id = Some(crate::templates::random_uuid());
+    }
+    let message = crate::export::prepare_for_send(vec![message], false)?.remove(0);
+
+    let (queue_name, topic_name) = match (queue_name, topic_name) {
+        (None, None) => (source.queue_name.as_deref(), source.topic_name.as_deref()),
+        destination => destination,
+    };
+    match &client {
+        Some(client) => client.send_message(queue_name, topic_name, &message).await.map(|_| ())?,
+        None => {
+            let queue_name = queue_name
+                .ok_or("[training] Topics and subscriptions are not available in the training sandbox")?;
+            sandbox::send_message(queue_name, &message)?
+        }
+    }
+    eprintln!(
+        "[resend_message] Resent {} as {:?} to {}",
+        sequence_number,
+        message.message_id,
+        queue_name.or(topic_name).unwrap_or("?")
+    );
+    Ok(message)
+}
+
+// Peek up to count messages from source, starting at from_sequence_number (default: the
+// head), and send copies to destination (a queue or topic). A failure stops the copy;
+// the messages copied so far stay sent.
+pub async fn copy_messages(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    destination: &str,
+    count: u32,
+    from_sequence_number: Option<u64>,
+    on_progress: &(dyn Fn(&CopyMessagesProgress) + Send + Sync),
+) -> Result<CopyMessagesProgress, String> {
+    if source.queue_name.is_none() && (source.topic_name.is_none() || source.subscription_name.is_none()) {
+        return Err("A queue or a topic subscription is required".to_string());
+    }
+    let source_path = crate::monitor::source_label(source);
+    if destination.trim().is_empty() {
+        return Err("A destination queue or topic is required".to_string());
+    }
+    // Peeking forward through the destination would copy the copies
+    let into_itself = [&source.queue_name, &source.topic_name]
+        .into_iter()
+        .flatten()
+        .any(|name| name.eq_ignore_ascii_case(destination));
+    if into_itself && !source.dead_letter {
+        return Err("Source and destination must be different entities".to_string());
+    }
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let mut progress = CopyMessagesProgress {
+        source: source_path.clone(),
+        destination: destination.to_string(),
+        requested: count,
+        copied: 0,
+        next_sequence_number: from_sequence_number,
+        done: false,
+    };
+    eprintln!("[copy_messages] Copying up to {} messages from {} to {}", count, source_path, destination);
+
+    let mut next_sequence_number = from_sequence_number.unwrap_or(0);
+    while progress.copied < count {
+        let page_size = (count - progress.copied).min(COPY_PAGE_SIZE);
+        let page = match &client {
+            Some(client) => client.peek_source(source, page_size, Some(next_sequence_number), None).await?,
+            None => sandbox::peek_source(source, page_size, Some(next_sequence_number))?,
+        };
+        let exhausted = (page.len() as u32) < page_size;
+        if let Some(seq) = page.last().and_then(|m| m.sequence_number) {
+            next_sequence_number = seq + 1;
+        }
+
+        let copies = crate::export::prepare_for_send(page, false)?;
+        let sent = match &client {
+            Some(client) if !copies.is_empty() => client.send_messages_batch(Some(destination), None, &copies).await,
+            Some(_) => Ok(0),
+            None => copies.iter().try_for_each(|m| sandbox::send_message(destination, m)).map(|_| copies.len() as u32),
+        };
+        match sent {
+            Ok(sent) => progress.copied += sent,
+            Err(e) => return Err(format!("Copy stopped after {} messages: {}", progress.copied, e)),
+        }
+        progress.next_sequence_number = if exhausted { None } else { Some(next_sequence_number) };
+        if exhausted {
+            break;
+        }
+        on_progress(&progress);
+    }
+
+    progress.done = true;
+    on_progress(&progress);
+    eprintln!("[copy_messages] Copied {} messages", progress.copied);
+    Ok(progress)
+}
+
+fn apply_patch(message: ServiceBusMessage, patch: &serde_json::Value) -> Result<ServiceBusMessage, String> {
+    if !patch.is_object() {
+        return Err("The patch must be a JSON object".to_string());
+    }
+    // A base64 body can't be merged field by field
+    if message.body_encoding.is_some() && patch.get("body").is_some() {
+        return Err("The body of this message is binary and cannot be patched".to_string());
+    }
+    let mut value = serde_json::to_value(&message).map_err(|e| format!("Failed to serialize message: {}", e))?;
+    merge_patch(&mut value, patch);
+    serde_json::from_value(value).map_err(|e| format!("The patched message is invalid: {}", e))
+}
+
+// RFC 7396: objects merge recursively, null removes a member, anything else replaces
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    if let serde_json::Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+// Infer a JSON Schema from a sample of message bodies, to document what actually flows
+// through an entity
+//
+// Bodies are peeked from the head, read as export::json_body reads them (decoded bodies
+// included) and merged into one shape: every type a field was seen with, whether it was
+// present in every object that could hold it (required), the items of arrays and the
+// "date-time" format when every string value was an RFC 3339 timestamp. Bodies that
+// aren't JSON are only counted. The schema only knows what the sample contained, so a
+// field that is optional but always set in the sample comes out as required.
+
+use crate::azure::types::{MessageSource, ServiceBusConnection};
+use crate::state::ClientCache;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const DEFAULT_SAMPLE_SIZE: u32 = 200;
+const MAX_SAMPLE_SIZE: u32 = 5_000;
+const SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InferredSchema {
+    pub entity_path: String,
+    pub sampled_count: u32,
+    // Bodies merged into the schema; the rest weren't JSON
+    pub json_count: u32,
+    pub non_json_count: u32,
+    pub schema: serde_json::Value,
+    // Where the schema was written, when a path was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+// Everything seen at one position in the bodies
+#[derive(Default)]
+struct Shape {
+    // Values seen here, of any type
+    count: u32,
+    null: bool,
+    boolean: bool,
+    integer: bool,
+    number: bool,
+    string: bool,
+    // Every string so far was an RFC 3339 timestamp
+    date_time: bool,
+    array: bool,
+    items: Option<Box<Shape>>,
+    // Objects seen here, and their fields
+    objects: u32,
+    properties: BTreeMap<String, Shape>,
+}
+
+impl Shape {
+    fn add(&mut self, value: &serde_json::Value) {
+        use serde_json::Value;
+
+        self.count += 1;
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(number) if number.is_i64() || number.is_u64() => self.integer = true,
+            Value::Number(_) => self.number = true,
+            Value::String(text) => {
+                let is_date_time = chrono::DateTime::parse_from_rfc3339(text).is_ok();
+                self.date_time = is_date_time && (self.date_time || !self.string);
+                self.string = true;
+            }
+            Value::Array(values) => {
+                self.array = true;
+                let items = self.items.get_or_insert_with(Default::default);
+                values.iter().for_each(|value| items.add(value));
+            }
+            Value::Object(fields) => {
+                self.objects += 1;
+                for (name, value) in fields {
+                    self.properties.entry(name.clone()).or_default().add(value);
+                }
+            }
+        }
+    }
+
+    fn to_schema(&self) -> serde_json::Value {
+        let mut schema = serde_json::Map::new();
+        let mut types = Vec::new();
+        if self.objects > 0 {
+            types.push("object");
+            let properties: serde_json::Map<String, serde_json::Value> =
+                self.properties.iter().map(|(name, shape)| (name.clone(), shape.to_schema())).collect();
+            // Present in every object seen here
+            let required: Vec<&String> =
+                self.properties.iter().filter(|(_, shape)| shape.count == self.objects).map(|(name, _)| name).collect();
+            schema.insert("properties".to_string(), properties.into());
+            if !required.is_empty() {
+                schema.insert("required".to_string(), serde_json::json!(required));
+            }
+        }
+        if self.array {
+            types.push("array");
+            // Only empty arrays were seen: the items are unknown
+            if let Some(items) = self.items.as_ref().filter(|items| items.count > 0) {
+                schema.insert("items".to_string(), items.to_schema());
+            }
+        }
+        if self.string {
+            types.push("string");
+            if self.date_time {
+                schema.insert("format".to_string(), "date-time".into());
+            }
+        }
+        // A field seen as both is a number
+        if self.number {
+            types.push("number");
+        } else if self.integer {
+            types.push("integer");
+        }
+        if self.boolean {
+            types.push("boolean");
+        }
+        if self.null {
+            types.push("null");
+        }
+        match types.as_slice() {
+            [] => {}
+            [single] => {
+                schema.insert("type".to_string(), (*single).into());
+            }
+            several => {
+                schema.insert("type".to_string(), serde_json::json!(several));
+            }
+        }
+        serde_json::Value::Object(schema)
+    }
+}
+
+// Peek up to sample_size bodies (default DEFAULT_SAMPLE_SIZE) from the head of source and
+// infer their schema; with path, the schema is also written there
+pub async fn infer_schema(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    sample_size: Option<u32>,
+    path: Option<&Path>,
+) -> Result<InferredSchema, String> {
+    if source.queue_name.is_none() && (source.topic_name.is_none() || source.subscription_name.is_none()) {
+        return Err("A queue or a topic subscription is required".to_string());
+    }
+    if let Some(path) = path.filter(|path| !path.is_absolute()) {
+        return Err(format!("Schema path must be absolute: {}", path.display()));
+    }
+    let sample_size = sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE);
+    if sample_size == 0 || sample_size > MAX_SAMPLE_SIZE {
+        return Err(format!("sample_size must be between 1 and {}", MAX_SAMPLE_SIZE));
+    }
+    let entity_path = crate::monitor::source_label(source);
+
+    let messages = crate::export::peek_messages(clients, connection, source, sample_size, None, None).await?;
+    let mut shape = Shape::default();
+    let mut non_json_count = 0u32;
+    for message in &messages {
+        match crate::export::json_body(message) {
+            Some(body) => shape.add(&body),
+            None => non_json_count += 1,
+        }
+    }
+
+    let mut schema = serde_json::Map::new();
+    schema.insert("$schema".to_string(), SCHEMA_DRAFT.into());
+    schema.insert("title".to_string(), entity_path.clone().into());
+    schema.insert(
+        "description".to_string(),
+        format!("Inferred from {} message bodies on {}", shape.count, chrono::Utc::now().to_rfc3339()).into(),
+    );
+    if let serde_json::Value::Object(inferred) = shape.to_schema() {
+        schema.extend(inferred);
+    }
+    let schema = serde_json::Value::Object(schema);
+
+    if let Some(path) = path {
+        let json = serde_json::to_string_pretty(&schema).map_err(|e| format!("Failed to serialize schema: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    eprintln!(
+        "[infer_schema] Inferred a schema for {} from {} of {} messages",
+        entity_path,
+        shape.count,
+        messages.len()
+    );
+    Ok(InferredSchema {
+        entity_path,
+        sampled_count: messages.len() as u32,
+        json_count: shape.count,
+        non_json_count,
+        schema,
+        path: path.map(|path| path.display().to_string()),
+    })
+}
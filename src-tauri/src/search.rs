@@ -0,0 +1,199 @@
+// Search an entity's messages: peek page by page from the head and filter locally
+//
+// Service Bus has no server-side message query, so every message up to max_scan is
+// peeked. Peeking never locks or removes anything, so a search is always safe to run.
+
+use crate::azure::sandbox;
+use crate::azure::types::{MessageSource, ServiceBusConnection, ServiceBusMessage};
+use crate::state::ClientCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const PAGE_SIZE: u32 = 250;
+const DEFAULT_MAX_SCAN: u32 = 50_000;
+const DEFAULT_MAX_MATCHES: u32 = 500;
+
+// A message matches when every criterion that is set matches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MessageQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_contains: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_regex: Option<String>,
+    // Applies to body_contains, message_id and correlation_id; body_regex uses (?i)
+    pub case_sensitive: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    // Application property name -> value, compared as text
+    pub properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub scanned_count: u32,
+    pub matches: Vec<ServiceBusMessage>,
+    // Where to continue when the scan stopped at max_scan or max_matches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_sequence_number: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchProgress {
+    pub scanned_count: u32,
+    pub match_count: u32,
+}
+
+// The compiled form of a MessageQuery
+struct Matcher {
+    query: MessageQuery,
+    regex: Option<regex::Regex>,
+}
+
+impl Matcher {
+    fn new(query: MessageQuery) -> Result<Matcher, String> {
+        let has_criteria = query.body_contains.is_some()
+            || query.body_regex.is_some()
+            || query.message_id.is_some()
+            || query.correlation_id.is_some()
+            || !query.properties.is_empty();
+        if !has_criteria {
+            return Err("A search needs at least one criterion".to_string());
+        }
+        let regex = match &query.body_regex {
+            Some(pattern) => Some(
+                regex::RegexBuilder::new(pattern)
+                    .case_insensitive(!query.case_sensitive)
+                    .build()
+                    .map_err(|e| format!("Invalid body regex: {}", e))?,
+            ),
+            None => None,
+        };
+        Ok(Matcher { query, regex })
+    }
+
+    fn text_matches(&self, actual: Option<&str>, expected: &str) -> bool {
+        match actual {
+            Some(actual) if self.query.case_sensitive => actual == expected,
+            Some(actual) => actual.eq_ignore_ascii_case(expected),
+            None => false,
+        }
+    }
+
+    fn matches(&self, message: &ServiceBusMessage) -> bool {
+        if let Some(id) = &self.query.message_id {
+            if !self.text_matches(message.message_id.as_deref(), id) {
+                return false;
+            }
+        }
+        if let Some(id) = &self.query.correlation_id {
+            if !self.text_matches(message.correlation_id.as_deref(), id) {
+                return false;
+            }
+        }
+        for (name, expected) in &self.query.properties {
+            let actual = message.application_properties.as_ref().and_then(|p| p.get(name)).map(|value| match value {
+                serde_json::Value::String(text) => text.clone(),
+                other => other.to_string(),
+            });
+            if !self.text_matches(actual.as_deref(), expected) {
+                return false;
+            }
+        }
+        if self.query.body_contains.is_none() && self.regex.is_none() {
+            return true;
+        }
+
+        let body = body_text(message);
+        if let Some(needle) = &self.query.body_contains {
+            let found = if self.query.case_sensitive {
+                body.contains(needle.as_str())
+            } else {
+                body.to_lowercase().contains(&needle.to_lowercase())
+            };
+            if !found {
+                return false;
+            }
+        }
+        self.regex.as_ref().is_none_or(|regex| regex.is_match(&body))
+    }
+}
+
+// Searched as displayed: text as-is, JSON bodies serialized, binary bodies through
+// their decoded form when a body decoder produced one
+fn body_text(message: &ServiceBusMessage) -> String {
+    if let Some(decoded) = &message.decoded_body {
+        return decoded.to_string();
+    }
+    match &message.body {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Peek from from_sequence_number (default: the head) until the entity is exhausted or
+// max_scan messages were looked at, reporting progress after every page
+#[allow(clippy::too_many_arguments)]
+pub async fn search_messages(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    query: MessageQuery,
+    from_sequence_number: Option<u64>,
+    max_scan: Option<u32>,
+    max_matches: Option<u32>,
+    on_progress: &(dyn Fn(&SearchProgress) + Send + Sync),
+) -> Result<SearchResult, String> {
+    let matcher = Matcher::new(query)?;
+    let max_scan = max_scan.unwrap_or(DEFAULT_MAX_SCAN);
+    let max_matches = max_matches.unwrap_or(DEFAULT_MAX_MATCHES) as usize;
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+    let entity = source.queue_name.as_deref().or(source.topic_name.as_deref());
+
+    let mut result = SearchResult { scanned_count: 0, matches: Vec::new(), next_sequence_number: None };
+    let mut next_sequence_number = from_sequence_number.unwrap_or(0);
+    while result.scanned_count < max_scan {
+        let count = (max_scan - result.scanned_count).min(PAGE_SIZE);
+        let mut page = match &client {
+            Some(client) => client.peek_source(source, count, Some(next_sequence_number), None).await?,
+            None => sandbox::peek_source(source, count, Some(next_sequence_number))?,
+        };
+        let full_page = page.len() as u32 == count;
+        crate::decoders::decode_messages(&mut page, connection, entity).await;
+
+        for message in page {
+            result.scanned_count += 1;
+            if let Some(seq) = message.sequence_number {
+                next_sequence_number = seq + 1;
+            }
+            if matcher.matches(&message) {
+                result.matches.push(message);
+                if result.matches.len() >= max_matches {
+                    result.next_sequence_number = Some(next_sequence_number);
+                    on_progress(&progress(&result));
+                    return Ok(result);
+                }
+            }
+        }
+        on_progress(&progress(&result));
+        if !full_page {
+            return Ok(result);
+        }
+    }
+
+    // Stopped at max_scan with more messages possibly left
+    result.next_sequence_number = Some(next_sequence_number);
+    Ok(result)
+}
+
+fn progress(result: &SearchResult) -> SearchProgress {
+    SearchProgress { scanned_count: result.scanned_count, match_count: result.matches.len() as u32 }
+}
@@ -0,0 +1,187 @@
+// Read-only connection bundles, for handing a teammate access to one entity during an
+// incident without sharing manage keys
+//
+// The bundle holds a connection string for a Listen-only SAS policy on the entity: it
+// can peek and receive, but not send, delete or change anything. The file is encrypted
+// with a passphrase (PBKDF2-HMAC-SHA256, then AES-256-GCM), which is shared out of band.
+// Revoking access means deleting or rotating that policy.
+
+use crate::azure::servicebus::ServiceBusClient;
+use crate::azure::types::{AuthorizationRule, ServiceBusConnection};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const DEFAULT_POLICY_NAME: &str = "explorer-readonly";
+const BUNDLE_FORMAT: &str = "servicebusexplorer-readonly-bundle";
+const BUNDLE_VERSION: u32 = 1;
+const MIN_PASSPHRASE_CHARS: usize = 12;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+// A bundle's own iteration count is only trusted within this range: too few makes the
+// passphrase cheap to guess, too many stalls the app on a crafted file
+const MIN_PBKDF2_ITERATIONS: u32 = 100_000;
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+// What the encrypted file contains
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadOnlyBundle {
+    pub name: String,
+    pub entity_path: String,
+    pub key_name: String,
+    pub connection_string: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadOnlyBundleResult {
+    pub path: String,
+    pub entity_path: String,
+    pub key_name: String,
+    // The policy did not exist yet and was created for the bundle
+    pub created_policy: bool,
+    pub bytes_written: u64,
+    // Only planned (dry run): no file was written
+    pub dry_run: bool,
+}
+
+// The file on disk: everything needed to decrypt it, except the passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptedBundle {
+    format: String,
+    version: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+// Find key_name on entity_path and check it only grants Listen, or create it
+pub async fn ensure_listen_only_policy(
+    client: &ServiceBusClient,
+    entity_path: &str,
+    key_name: &str,
+) -> Result<(AuthorizationRule, bool), String> {
+    let rules = client.list_authorization_rules(entity_path).await?;
+    match rules.into_iter().find(|r| r.key_name == key_name) {
+        Some(rule) => {
+            if rule.rights.iter().any(|right| right != "Listen") {
+                return Err(format!(
+                    "Policy '{}' on '{}' grants {}; a read-only bundle needs a Listen-only policy",
+                    key_name,
+                    entity_path,
+                    rule.rights.join(", ")
+                ));
+            }
+            Ok((rule, false))
+        }
+        None => {
+            let rule = client.create_authorization_rule(entity_path, key_name, &["Listen".to_string()]).await?;
+            Ok((rule, true))
+        }
+    }
+}
+
+// Encrypt bundle with passphrase and write it to path
+pub fn write_bundle(path: &Path, bundle: &ReadOnlyBundle, passphrase: &str) -> Result<u64, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+
+    if !path.is_absolute() {
+        return Err(format!("Bundle path must be absolute: {}", path.display()));
+    }
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!("The passphrase must be at least {} characters", MIN_PASSPHRASE_CHARS));
+    }
+
+    let salt: [u8; 16] = rand::random();
+    let nonce: [u8; 12] = rand::random();
+    let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS);
+    let plaintext = serde_json::to_vec(bundle).map_err(|e| format!("Failed to serialize bundle: {}", e))?;
+    let cipher = aes_gcm::Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to set up encryption: {}", e))?;
+    let ciphertext = cipher
+        .encrypt(aes_gcm::Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|e| format!("Failed to encrypt bundle: {}", e))?;
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let file = EncryptedBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        iterations: PBKDF2_ITERATIONS,
+        salt: base64.encode(salt),
+        nonce: base64.encode(nonce),
+        ciphertext: base64.encode(ciphertext),
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to serialize bundle: {}", e))?;
+    std::fs::write(path, &json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(json.len() as u64)
+}
+
+// Decrypt a bundle written by write_bundle
+pub fn read_bundle(path: &Path, passphrase: &str) -> Result<ReadOnlyBundle, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+
+    let json = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file: EncryptedBundle =
+        serde_json::from_str(&json).map_err(|_| format!("{} is not a read-only connection bundle", path.display()))?;
+    if file.format != BUNDLE_FORMAT {
+        return Err(format!("{} is not a read-only connection bundle", path.display()));
+    }
+    if file.version > BUNDLE_VERSION {
+        return Err(format!("{} was written by a newer version of the app", path.display()));
+    }
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let decode = |value: &str| base64.decode(value).map_err(|_| format!("{} is damaged", path.display()));
+    let salt = decode(&file.salt)?;
+    let nonce = decode(&file.nonce)?;
+    let ciphertext = decode(&file.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err(format!("{} is damaged", path.display()));
+    }
+    if !(MIN_PBKDF2_ITERATIONS..=MAX_PBKDF2_ITERATIONS).contains(&file.iterations) {
+        return Err(format!(
+            "{} uses {} key derivation iterations; expected {} to {}",
+            path.display(),
+            file.iterations,
+            MIN_PBKDF2_ITERATIONS,
+            MAX_PBKDF2_ITERATIONS
+        ));
+    }
+
+    let key = derive_key(passphrase, &salt, file.iterations);
+    let cipher = aes_gcm::Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to set up decryption: {}", e))?;
+    let plaintext = cipher
+        .decrypt(aes_gcm::Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "Wrong passphrase, or the bundle is damaged".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Invalid bundle contents: {}", e))
+}
+
+// A new, unsaved connection for a decrypted bundle
+pub fn bundle_connection(bundle: &ReadOnlyBundle) -> Result<ServiceBusConnection, String> {
+    // Reject anything that would not connect before it is saved
+    crate::azure::auth::parse_connection_string(&bundle.connection_string)?;
+    let now = chrono::Utc::now().timestamp_millis();
+    Ok(ServiceBusConnection {
+        id: crate::templates::random_uuid(),
+        name: format!("{} (read-only)", bundle.name),
+        connection_string: Some(bundle.connection_string.clone()),
+        namespace: None,
+        use_azure_ad: None,
+        tenant_id: None,
+        client_id: None,
+        created_at: now,
+        updated_at: now,
+        uses_root_manage_key: None,
+        training_mode: None,
+        transport: None,
+        color: None,
+    })
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
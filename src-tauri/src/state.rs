@@ -0,0 +1,101 @@
+// Backend state shared by every command, managed by Tauri and taken as
+// tauri::State<'_, AppState>
+//
+// Clients are cached per connection, so commands share one HTTP connection pool and
+// parsed connection string instead of building a client on every call. A cached
+// client is replaced as soon as the connection's settings change (rotated key,
+// rebind, new transport) and dropped when the connection is deleted.
+//
+// The license status lives here too, so check_license_status never waits on the receipt
+// check (see licensing.rs).
+//
+// Training connections never get a client: everything they do is answered by the
+// in-memory sandbox (see sandbox.rs), and a command that forgot its sandbox branch
+// fails here instead of reaching a real namespace.
+//
+// Monitors, operations, repeated sends and peek streams keep their registries in their
+// own modules, since their background tasks run without an AppHandle; shutdown()
+// stops all of them when the app exits. reconnect.rs clears the client cache after
+// the machine resumes from sleep.
+
+use crate::azure::servicebus::ServiceBusClient;
+use crate::azure::types::ServiceBusConnection;
+use crate::licensing::LicenseManager;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+#[derive(Default)]
+pub struct AppState {
+    pub clients: ClientCache,
+    pub license: LicenseManager,
+}
+
+impl AppState {
+    pub async fn client(&self, connection: &ServiceBusConnection) -> Result<Arc<ServiceBusClient>, String> {
+        self.clients.get(connection).await
+    }
+
+    // Stop everything still running in the background
+    pub fn shutdown(&self) {
+        crate::monitor::stop_all();
+        crate::operations::cancel_all();
+        crate::repeat_send::cancel_all();
+        crate::peek_stream::cancel_all();
+        self.clients.clear();
+    }
+}
+
+struct CachedClient {
+    // Hash of the connection's settings the client was built from
+    fingerprint: u64,
+    client: Arc<ServiceBusClient>,
+}
+
+// Cheap to clone, for tasks that outlive the command that started them
+#[derive(Clone, Default)]
+pub struct ClientCache {
+    clients: Arc<RwLock<HashMap<String, CachedClient>>>,
+}
+
+impl ClientCache {
+    pub async fn get(&self, connection: &ServiceBusConnection) -> Result<Arc<ServiceBusClient>, String> {
+        if crate::azure::sandbox::is_training(connection) {
+            return Err("[training] This operation is not available in the training sandbox".to_string());
+        }
+        let fingerprint = fingerprint(connection);
+        let cached = {
+            let clients = self.clients.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+            clients
+                .get(&connection.id)
+                .filter(|cached| cached.fingerprint == fingerprint)
+                .map(|cached| cached.client.clone())
+        };
+        if let Some(client) = cached {
+            return Ok(client);
+        }
+
+        let client = Arc::new(ServiceBusClient::create(connection).await?);
+        let mut clients = self.clients.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        clients.insert(connection.id.clone(), CachedClient { fingerprint, client: client.clone() });
+        Ok(client)
+    }
+
+    pub fn forget(&self, connection_id: &str) {
+        let mut clients = self.clients.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        clients.remove(connection_id);
+    }
+
+    pub fn clear(&self) {
+        let mut clients = self.clients.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        clients.clear();
+    }
+}
+
+// Everything a client is built from; name and timestamps included, which only costs
+// a rebuild after an edit
+fn fingerprint(connection: &ServiceBusConnection) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serde_json::to_string(connection).unwrap_or_default().hash(&mut hasher);
+    hasher.finish()
+}
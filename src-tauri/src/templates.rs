@@ -0,0 +1,243 @@
+// Reusable send-message templates with placeholders expanded at send time
+//
+// Templates live in templates.json next to settings.json. Placeholders may appear in
+// the body (text, or any string inside a JSON body), the message/correlation/session
+// ids, the subject and string application properties:
+//   {{uuid}}            a new random UUID per message
+//   {{now}}             the send time, RFC 3339 UTC
+//   {{counter}}         the template's own counter, +1 per message sent
+//   {{counter:<name>}}  a named counter shared by all templates
+// Counters are saved after every send, so numbering continues across restarts.
+
+use crate::azure::timestamps;
+use crate::azure::types::ServiceBusMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const TEMPLATES_FILE_NAME: &str = "templates.json";
+const MAX_MESSAGES_PER_SEND: u32 = 1000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TemplateStore {
+    pub templates: Vec<MessageTemplate>,
+    // Last value handed out per counter; "template:<id>" for a template's own counter
+    pub counters: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageTemplate {
+    // Assigned on first save
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub message: ServiceBusMessage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+fn templates_path() -> Result<PathBuf, String> {
+    Ok(crate::config::config_dir()?.join(TEMPLATES_FILE_NAME))
+}
+
+fn load_store() -> Result<TemplateStore, String> {
+    let path = templates_path()?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(TemplateStore::default()),
+        Err(e) => return Err(format!("Failed to read templates from {}: {}", path.display(), e)),
+    };
+    serde_json::from_str(&contents).map_err(|e| format!("Failed to parse templates in {}: {}", path.display(), e))
+}
+
+// Written to a temp file and renamed, like settings.json
+fn save_store(store: &TemplateStore) -> Result<(), String> {
+    let path = templates_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory {}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(store).map_err(|e| format!("Failed to serialize templates: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)
+        .map_err(|e| format!("Failed to write templates to {}: {}", tmp_path.display(), e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to save templates to {}: {}", path.display(), e))
+}
+
+pub fn list_templates() -> Result<Vec<MessageTemplate>, String> {
+    Ok(load_store()?.templates)
+}
+
+fn find_template(store: &TemplateStore, id: &str) -> Result<MessageTemplate, String> {
+    store
+        .templates
+        .iter()
+        .find(|t| t.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Template '{}' not found", id))
+}
+
+// Create (empty id) or replace a template. Names must be unique, ignoring case.
+pub fn save_template(mut template: MessageTemplate) -> Result<MessageTemplate, String> {
+    let name = template.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Template name cannot be empty".to_string());
+    }
+    template.name = name;
+    check_placeholders(&template.message)?;
+
+    let mut store = load_store()?;
+    if let Some(other) = store
+        .templates
+        .iter()
+        .find(|t| t.id != template.id && t.name.eq_ignore_ascii_case(&template.name))
+    {
+        return Err(format!("A template named '{}' already exists", other.name));
+    }
+
+    template.updated_at = Some(timestamps::format(chrono::Utc::now()));
+    if template.id.is_empty() {
+        template.id = format!("{:016x}", rand::random::<u64>());
+        store.templates.push(template.clone());
+    } else {
+        let existing = store
+            .templates
+            .iter_mut()
+            .find(|t| t.id == template.id)
+            .ok_or_else(|| format!("Template '{}' not found", template.id))?;
+        *existing = template.clone();
+    }
+    save_store(&store)?;
+    Ok(template)
+}
+
+pub fn delete_template(id: &str) -> Result<(), String> {
+    let mut store = load_store()?;
+    let before = store.templates.len();
+    store.templates.retain(|t| t.id != id);
+    if store.templates.len() == before {
+        return Err(format!("Template '{}' not found", id));
+    }
+    store.counters.remove(&template_counter(id));
+    save_store(&store)
+}
+
+// Expand count messages from a template. With advance_counters the counters are
+// saved, so the next expansion continues after these messages; without it this is a
+// preview and the same values come out again next time.
+pub fn expand_template(id: &str, count: u32, advance_counters: bool) -> Result<Vec<ServiceBusMessage>, String> {
+    if count == 0 || count > MAX_MESSAGES_PER_SEND {
+        return Err(format!("Count must be between 1 and {}", MAX_MESSAGES_PER_SEND));
+    }
+    let mut store = load_store()?;
+    let template = find_template(&store, id)?;
+
+    let mut messages = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut expander = Expander { template_id: id, counters: &mut store.counters, used: HashMap::new() };
+        messages.push(expander.message(&template.message)?);
+    }
+    if advance_counters {
+        save_store(&store)?;
+    }
+    Ok(messages)
+}
+
+fn template_counter(id: &str) -> String {
+    format!("template:{}", id)
+}
+
+// Expands one message. A counter used twice in the same message gets the same value.
+struct Expander<'a> {
+    template_id: &'a str,
+    counters: &'a mut HashMap<String, u64>,
+    used: HashMap<String, u64>,
+}
+
+impl Expander<'_> {
+    fn message(&mut self, template: &ServiceBusMessage) -> Result<ServiceBusMessage, String> {
+        let mut message = template.clone();
+        message.body = self.json(&template.body)?;
+        for field in [
+            &mut message.message_id,
+            &mut message.correlation_id,
+            &mut message.session_id,
+            &mut message.subject,
+        ] {
+            if let Some(value) = field.as_deref() {
+                *field = Some(self.text(value)?);
+            }
+        }
+        message.application_properties =
+            template.application_properties.as_ref().map(|p| self.json(p)).transpose()?;
+        Ok(message)
+    }
+
+    fn json(&mut self, value: &serde_json::Value) -> Result<serde_json::Value, String> {
+        use serde_json::Value;
+        Ok(match value {
+            Value::String(text) => Value::String(self.text(text)?),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.json(v)).collect::<Result<_, _>>()?),
+            Value::Object(fields) => {
+                let mut expanded = serde_json::Map::new();
+                for (key, v) in fields {
+                    expanded.insert(key.clone(), self.json(v)?);
+                }
+                Value::Object(expanded)
+            }
+            other => other.clone(),
+        })
+    }
+
+    fn text(&mut self, text: &str) -> Result<String, String> {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after.find("}}").ok_or_else(|| format!("Unclosed placeholder in '{}'", text))?;
+            out.push_str(&self.placeholder(after[..end].trim())?);
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn placeholder(&mut self, name: &str) -> Result<String, String> {
+        let counter = match name {
+            "uuid" => return Ok(random_uuid()),
+            "now" => return Ok(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+            "counter" => template_counter(self.template_id),
+            other => match other.strip_prefix("counter:").map(str::trim) {
+                Some(counter) if !counter.is_empty() => counter.to_string(),
+                _ => return Err(format!("Unknown placeholder '{{{{{}}}}}'", other)),
+            },
+        };
+        if let Some(value) = self.used.get(&counter) {
+            return Ok(value.to_string());
+        }
+        let value = self.counters.get(&counter).copied().unwrap_or(0) + 1;
+        self.counters.insert(counter.clone(), value);
+        self.used.insert(counter, value);
+        Ok(value.to_string())
+    }
+}
+
+// Reject unknown placeholders when saving instead of at send time
+fn check_placeholders(message: &ServiceBusMessage) -> Result<(), String> {
+    let mut counters = HashMap::new();
+    Expander { template_id: "", counters: &mut counters, used: HashMap::new() }
+        .message(message)
+        .map(|_| ())
+}
+
+// Version 4 (random) UUID
+pub fn random_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
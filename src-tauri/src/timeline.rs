@@ -0,0 +1,222 @@
+// Redelivery timeline: how a (usually dead-lettered) message got where it is
+//
+// Service Bus keeps no per-delivery history. What a peek returns is the enqueue (and
+// scheduled) time, the delivery count and the expiry of the last lock, which a
+// dead-lettered message carries over from the entity it was dead-lettered on. With
+// the entity's LockDuration that places the last delivery; earlier deliveries are
+// known only by number, and anything derived from the last lock is marked estimated
+// since a consumer may have renewed or released it early.
+
+use crate::azure::sandbox;
+use crate::azure::timestamps;
+use crate::azure::types::{MessageSource, ServiceBusConnection, ServiceBusMessage};
+use crate::state::ClientCache;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimelineEventKind {
+    Enqueued,
+    // A scheduled message becoming visible
+    Scheduled,
+    Delivery,
+    DeadLettered,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineEvent {
+    pub kind: TimelineEventKind,
+    // Absent when the service keeps no trace of when it happened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_utc: Option<String>,
+    // End of the lock, for deliveries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until_utc: Option<String>,
+    // Delivery number, from 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    pub estimated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedeliveryTimeline {
+    pub entity_path: String,
+    pub sequence_number: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    pub delivery_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_delivery_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_duration_in_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_letter_reason: Option<String>,
+    // From the message becoming visible to the start of its last delivery: how long
+    // consumers kept retrying
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_span_seconds: Option<i64>,
+    // Oldest first; deliveries without a time sit in attempt order
+    pub events: Vec<TimelineEvent>,
+}
+
+// Reconstruct the timeline of message sequence_number on source (its dead-letter
+// queue when source.dead_letter is set)
+pub async fn redelivery_timeline(
+    clients: &ClientCache,
+    connection: &ServiceBusConnection,
+    source: &MessageSource,
+    sequence_number: u64,
+) -> Result<RedeliveryTimeline, String> {
+    let client = if sandbox::is_training(connection) {
+        None
+    } else {
+        Some(clients.get(connection).await?)
+    };
+
+    let (entity_path, lock_duration, max_delivery_count) =
+        match (&client, &source.queue_name, &source.topic_name, &source.subscription_name) {
+            (Some(client), Some(queue), _, _) => {
+                let queue = client.get_queue(queue).await?;
+                (queue.name.clone(), queue.lock_duration_in_seconds, queue.max_delivery_count)
+            }
+            (Some(client), None, Some(topic), Some(subscription)) => {
+                let properties = client.get_subscription(topic, subscription).await?;
+                let path = format!("{}/Subscriptions/{}", topic, subscription);
+                (path, properties.lock_duration_in_seconds, properties.max_delivery_count)
+            }
+            (None, Some(queue), _, _) => {
+                let queue = sandbox::get_queue(queue)?;
+                (queue.name.clone(), queue.lock_duration_in_seconds, queue.max_delivery_count)
+            }
+            (None, _, _, _) => {
+                return Err("[training] Topics and subscriptions are not available in the training sandbox".to_string())
+            }
+            _ => return Err("A queue or a topic subscription is required".to_string()),
+        };
+
+    let page = match &client {
+        Some(client) => client.peek_source(source, 1, Some(sequence_number), Some(0)).await?,
+        None => sandbox::peek_source(source, 1, Some(sequence_number))?,
+    };
+    let message = page
+        .into_iter()
+        .find(|m| m.sequence_number == Some(sequence_number))
+        .ok_or_else(|| format!("Message {} not found (already received or expired?)", sequence_number))?;
+
+    let (events, retry_span_seconds) = build_events(&message, lock_duration, source.dead_letter);
+    Ok(RedeliveryTimeline {
+        entity_path: if source.dead_letter { format!("{}/$deadletterqueue", entity_path) } else { entity_path },
+        sequence_number,
+        message_id: message.message_id.clone(),
+        delivery_count: message.delivery_count.unwrap_or(0),
+        max_delivery_count,
+        lock_duration_in_seconds: lock_duration,
+        dead_letter_reason: message.dead_letter_reason.clone(),
+        retry_span_seconds,
+        events,
+    })
+}
+
+fn build_events(
+    message: &ServiceBusMessage,
+    lock_duration: Option<u64>,
+    dead_letter: bool,
+) -> (Vec<TimelineEvent>, Option<i64>) {
+    let event = |kind, at: Option<DateTime<Utc>>, estimated, note: Option<&str>| TimelineEvent {
+        kind,
+        at_utc: at.map(timestamps::format),
+        until_utc: None,
+        attempt: None,
+        estimated,
+        note: note.map(|n| n.to_string()),
+    };
+    let mut events = Vec::new();
+
+    let enqueued = message.enqueued_time_utc.as_deref().and_then(timestamps::parse);
+    events.push(event(TimelineEventKind::Enqueued, enqueued, false, None));
+    let scheduled = message
+        .scheduled_enqueue_time_utc
+        .as_deref()
+        .and_then(timestamps::parse)
+        .filter(|scheduled| enqueued.is_none_or(|enqueued| *scheduled > enqueued));
+    if scheduled.is_some() {
+        events.push(event(TimelineEventKind::Scheduled, scheduled, false, None));
+    }
+    let visible_from = scheduled.or(enqueued);
+
+    // A message that was never locked reports a lock expiry far in the past
+    let last_lock_end = message
+        .locked_until_utc
+        .as_deref()
+        .and_then(timestamps::parse)
+        .filter(|until| visible_from.is_none_or(|from| *until > from));
+    let last_lock_start = match (last_lock_end, lock_duration) {
+        (Some(until), Some(seconds)) => {
+            let start = until - chrono::Duration::seconds(seconds as i64);
+            Some(visible_from.map_or(start, |from| start.max(from)))
+        }
+        _ => None,
+    };
+
+    let deliveries = message.delivery_count.unwrap_or(0);
+    for attempt in 1..=deliveries {
+        let last = attempt == deliveries;
+        let mut delivery = if last {
+            let note = "Placed from the last lock's expiry and the lock duration";
+            event(TimelineEventKind::Delivery, last_lock_start, true, Some(note))
+        } else {
+            event(TimelineEventKind::Delivery, None, false, None)
+        };
+        delivery.attempt = Some(attempt);
+        if last {
+            delivery.until_utc = last_lock_end.map(timestamps::format);
+        }
+        events.push(delivery);
+    }
+    if deliveries > 1 {
+        if let Some(first) = events.iter_mut().find(|e| e.attempt == Some(1)) {
+            first.note = Some("Earlier deliveries are recorded only in the delivery count".to_string());
+        }
+    }
+
+    if dead_letter {
+        let dead_lettered = match message.dead_letter_reason.as_deref() {
+            Some("TTLExpiredException") => {
+                let expired = match (visible_from, message.time_to_live) {
+                    (Some(from), Some(ttl)) => from.checked_add_signed(chrono::Duration::seconds(ttl as i64)),
+                    _ => None,
+                };
+                event(
+                    TimelineEventKind::DeadLettered,
+                    expired,
+                    true,
+                    Some("Expired messages are moved lazily; this is the earliest it could have happened"),
+                )
+            }
+            Some("MaxDeliveryCountExceeded") => event(
+                TimelineEventKind::DeadLettered,
+                last_lock_end,
+                true,
+                Some("No later than the end of the last delivery's lock"),
+            ),
+            _ => event(
+                TimelineEventKind::DeadLettered,
+                last_lock_end,
+                true,
+                Some("Dead-lettered by a consumer, no later than the end of its lock"),
+            ),
+        };
+        events.push(dead_lettered);
+    }
+
+    let retry_span_seconds = match (visible_from, last_lock_start) {
+        (Some(from), Some(last)) => Some((last - from).num_seconds()),
+        _ => None,
+    };
+    (events, retry_span_seconds)
+}
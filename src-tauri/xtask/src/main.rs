@@ -0,0 +1,134 @@
+// Explicit build steps, run with `cargo xtask <command>` from src-tauri
+//
+// bundle-standalone copies the Next.js standalone output (plus .next/static, .next/server,
+// public and the bundled Node.js binary) to target/resources/standalone for the App Store
+// bundle. This used to happen in build.rs on every release compile.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let command = std::env::args().nth(1);
+    let result = match command.as_deref() {
+        Some("bundle-standalone") => bundle_standalone(),
+        _ => {
+            eprintln!("Usage: cargo xtask <command>");
+            eprintln!();
+            eprintln!("Commands:");
+            eprintln!("  bundle-standalone   Copy the Next.js standalone build to target/resources/standalone");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// src-tauri, regardless of where cargo was invoked from
+fn tauri_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask lives inside src-tauri")
+        .to_path_buf()
+}
+
+fn bundle_standalone() -> Result<(), String> {
+    let tauri_dir = tauri_dir();
+    let project_dir = tauri_dir.parent().ok_or("src-tauri has no parent directory")?;
+    let next_dir = project_dir.join(".next");
+    let standalone_dir = next_dir.join("standalone");
+    let target_resources = tauri_dir.join("target/resources/standalone");
+
+    if !standalone_dir.exists() {
+        return Err(format!(
+            "Standalone output not found at {}. Run `npm run build` first (next.config.ts sets output: \"standalone\").",
+            standalone_dir.display()
+        ));
+    }
+
+    if target_resources.exists() {
+        fs::remove_dir_all(&target_resources)
+            .map_err(|e| format!("Failed to remove {}: {}", target_resources.display(), e))?;
+    }
+    copy_dir_all(&standalone_dir, &target_resources)
+        .map_err(|e| format!("Failed to copy standalone directory: {}", e))?;
+    println!("✅ Copied standalone directory to {}", target_resources.display());
+
+    // Next.js standalone does not include .next/static; server.js expects it in standalone/.next/
+    let standalone_next = target_resources.join(".next");
+    let next_static = next_dir.join("static");
+    if !next_static.exists() {
+        return Err(format!(".next/static directory not found at {}", next_static.display()));
+    }
+    copy_dir_all(&next_static, &standalone_next.join("static"))
+        .map_err(|e| format!("Failed to copy static directory: {}", e))?;
+    println!("✅ Copied static files to standalone/.next/static");
+
+    // Needed for SSR; usually already part of the standalone output
+    let next_server = next_dir.join("server");
+    let standalone_server = standalone_next.join("server");
+    if !standalone_server.exists() {
+        if !next_server.exists() {
+            return Err(format!(".next/server directory not found at {}", next_server.display()));
+        }
+        copy_dir_all(&next_server, &standalone_server)
+            .map_err(|e| format!("Failed to copy server directory: {}", e))?;
+        println!("✅ Copied server files to standalone/.next/server");
+    }
+
+    let public_dir = project_dir.join("public");
+    if public_dir.exists() {
+        copy_dir_all(&public_dir, &target_resources.join("public"))
+            .map_err(|e| format!("Failed to copy public directory: {}", e))?;
+        println!("✅ Copied public files to standalone/public");
+    }
+
+    let node_binary = tauri_dir.join("target/resources/nodejs/node");
+    if node_binary.exists() {
+        let standalone_node = target_resources.join("node");
+        fs::copy(&node_binary, &standalone_node)
+            .map_err(|e| format!("Failed to copy Node.js binary: {}", e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&standalone_node, fs::Permissions::from_mode(0o755))
+                .map_err(|e| format!("Failed to make {} executable: {}", standalone_node.display(), e))?;
+        }
+        println!("✅ Copied Node.js binary to standalone/node");
+    } else {
+        eprintln!("⚠️  Warning: Node.js binary not found at {}", node_binary.display());
+        eprintln!("   This is required for TestFlight builds.");
+        eprintln!("   Run: npm run bundle:nodejs to bundle Node.js");
+    }
+
+    Ok(())
+}
+
+// Copies files and directories, following symlinks (standalone node_modules uses them)
+fn copy_dir_all(src: &Path, dst: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+
+        // fs::metadata follows symlinks; dangling links are skipped
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        if metadata.is_dir() {
+            copy_dir_all(&path, &dst_path)?;
+        } else {
+            fs::copy(&path, &dst_path)?;
+        }
+    }
+    Ok(())
+}
@@ -4,7 +4,11 @@ import type {
   QueueProperties,
   TopicProperties,
   SubscriptionProperties,
+  SubscriptionRule,
   ServiceBusMessage,
+  OperationInfo,
+  PeekOptions,
+  ConnectionAccess,
 } from "@/types/azure"
 import {
   MOCK_CONNECTION,
@@ -161,7 +165,8 @@ class ApiClient {
     await invoke("update_queue", { connection: tauriConnection, queueName, properties: propertiesWithName })
   }
 
-  async deleteQueue(connection: ServiceBusConnection | null, queueName: string): Promise<void> {
+  // Fails with SBX-1070 while other entities forward to the queue, unless force is set
+  async deleteQueue(connection: ServiceBusConnection | null, queueName: string, force = false): Promise<void> {
     if (this.isDemoMode(connection)) {
       await new Promise(resolve => setTimeout(resolve, 300))
       return
@@ -174,7 +179,7 @@ class ApiClient {
       throw new Error("No connection available")
     }
     const tauriConnection = this.transformConnectionForTauri(connWithString)
-    await invoke("delete_queue", { connection: tauriConnection, queueName })
+    await invoke("delete_queue", { connection: tauriConnection, queueName, force })
   }
 
   async listTopics(connection: ServiceBusConnection | null): Promise<TopicProperties[]> {
@@ -253,7 +258,8 @@ class ApiClient {
     await invoke("update_topic", { connection: tauriConnection, topicName, properties })
   }
 
-  async deleteTopic(connection: ServiceBusConnection | null, topicName: string): Promise<void> {
+  // Fails with SBX-1070 while other entities forward to the topic, unless force is set
+  async deleteTopic(connection: ServiceBusConnection | null, topicName: string, force = false): Promise<void> {
     if (this.isDemoMode(connection)) {
       await new Promise(resolve => setTimeout(resolve, 300))
       return
@@ -266,7 +272,7 @@ class ApiClient {
       throw new Error("No connection available")
     }
     const tauriConnection = this.transformConnectionForTauri(connWithString)
-    await invoke("delete_topic", { connection: tauriConnection, topicName })
+    await invoke("delete_topic", { connection: tauriConnection, topicName, force })
   }
 
   async listSubscriptions(
@@ -292,7 +298,9 @@ class ApiClient {
     connection: ServiceBusConnection | null,
     topicName: string,
     subscriptionName: string,
-    properties?: Partial<SubscriptionProperties>
+    properties?: Partial<SubscriptionProperties>,
+    // Replaces the match-all $Default rule as part of the create call
+    initialRule?: SubscriptionRule
   ): Promise<void> {
     if (this.isDemoMode(connection)) {
       await new Promise(resolve => setTimeout(resolve, 500))
@@ -306,7 +314,7 @@ class ApiClient {
       throw new Error("No connection available")
     }
     const tauriConnection = this.transformConnectionForTauri(connWithString)
-    await invoke("create_subscription", { connection: tauriConnection, topicName, subscriptionName, properties })
+    await invoke("create_subscription", { connection: tauriConnection, topicName, subscriptionName, properties, initialRule })
   }
 
   async peekMessages(
@@ -314,7 +322,13 @@ class ApiClient {
     queueName?: string,
     topicName?: string,
     subscriptionName?: string,
-    maxCount: number = 10
+    maxCount: number = 10,
+    // Only properties needed: 0 drops bodies, n keeps an n-byte preview
+    maxBodyBytes?: number,
+    // Start at this sequence number instead of the head of the entity
+    fromSequenceNumber?: number,
+    // Server wait per request and messages per request (at most 32 over HTTP)
+    options?: Pick<PeekOptions, "timeoutSeconds" | "pageSize">
   ): Promise<ServiceBusMessage[]> {
     if (this.isDemoMode(connection)) {
       await new Promise(resolve => setTimeout(resolve, 400))
@@ -334,6 +348,10 @@ class ApiClient {
       topicName,
       subscriptionName,
       maxCount,
+      fromSequenceNumber,
+      maxBodyBytes,
+      timeoutSeconds: options?.timeoutSeconds,
+      pageSize: options?.pageSize,
     })
   }
 
@@ -342,7 +360,9 @@ class ApiClient {
     queueName?: string,
     topicName?: string,
     subscriptionName?: string,
-    maxCount: number = 10
+    maxCount: number = 10,
+    // Only properties needed: 0 drops bodies, n keeps an n-byte preview
+    maxBodyBytes?: number
   ): Promise<ServiceBusMessage[]> {
     if (this.isDemoMode(connection)) {
       await new Promise(resolve => setTimeout(resolve, 400))
@@ -362,6 +382,7 @@ class ApiClient {
       topicName,
       subscriptionName,
       maxCount,
+      maxBodyBytes,
     })
   }
 
@@ -431,6 +452,37 @@ class ApiClient {
     return await invoke<number>("purge_queue", { connection: tauriConnection, queueName, purgeDeadLetter })
   }
 
+  async listOperations(): Promise<OperationInfo[]> {
+    if (this.isDemoMode()) {
+      return []
+    }
+    return await invoke<OperationInfo[]>("list_operations")
+  }
+
+  // The id comes from the "operation-started" event or listOperations
+  async cancelOperation(operationId: string): Promise<void> {
+    if (this.isDemoMode()) {
+      return
+    }
+    await invoke("cancel_operation", { operationId })
+  }
+
+  // Listen-only credentials can browse their entity but not manage anything
+  async probeConnectionAccess(connection: ServiceBusConnection | null): Promise<ConnectionAccess> {
+    if (this.isDemoMode(connection)) {
+      return { canManage: true }
+    }
+    if (!connection) {
+      throw new Error("No connection available")
+    }
+    const connWithString = await this.getConnectionWithString(connection)
+    if (!connWithString) {
+      throw new Error("No connection available")
+    }
+    const tauriConnection = this.transformConnectionForTauri(connWithString)
+    return await invoke<ConnectionAccess>("probe_connection_access", { connection: tauriConnection })
+  }
+
   async testConnection(connection: Omit<ServiceBusConnection, "id" | "createdAt" | "updatedAt">): Promise<boolean> {
     // For testConnection, we can't check the connection ID since it's a partial connection
     // So we just check localStorage
@@ -8,6 +8,19 @@ export interface ServiceBusConnection {
   clientId?: string
   createdAt: number
   updatedAt: number
+  usesRootManageKey?: boolean // Set by the backend when listing connections
+  trainingMode?: boolean // In-memory training sandbox, never touches Azure
+  // Data-plane transport; unset uses AMQP for peek/send and HTTP for receive
+  transport?: "amqp" | "http"
+  // "#rrggbb"
+  color?: string
+}
+
+// Which connection a listing or monitor result came from
+export interface ConnectionLabel {
+  connectionId: string
+  name: string
+  color?: string
 }
 
 export interface QueueProperties {
@@ -19,9 +32,20 @@ export interface QueueProperties {
   deadLetteringOnMessageExpiration?: boolean
   duplicateDetectionHistoryTimeWindowInSeconds?: number
   enableBatchedOperations?: boolean
+  autoDeleteOnIdleInSeconds?: number
+  status?: "Active" | "Disabled" | "SendDisabled" | "ReceiveDisabled"
   enablePartitioning?: boolean
   requiresSession?: boolean
   requiresDuplicateDetection?: boolean
+  maxMessageSizeInKilobytes?: number
+  // On update an empty string removes the forward or the metadata
+  forwardTo?: string
+  forwardDeadLetteredMessagesTo?: string
+  userMetadata?: string
+  // Read only
+  createdAt?: string
+  updatedAt?: string
+  accessedAt?: string
   messageCount?: number
   activeMessageCount?: number
   deadLetterMessageCount?: number
@@ -29,6 +53,7 @@ export interface QueueProperties {
   transferMessageCount?: number
   transferDeadLetterMessageCount?: number
   sizeInBytes?: number
+  connection?: ConnectionLabel
 }
 
 export interface TopicProperties {
@@ -39,8 +64,19 @@ export interface TopicProperties {
   enableBatchedOperations?: boolean
   enablePartitioning?: boolean
   requiresDuplicateDetection?: boolean
+  autoDeleteOnIdleInSeconds?: number
+  status?: "Active" | "Disabled" | "SendDisabled" | "ReceiveDisabled"
+  supportOrdering?: boolean
+  maxMessageSizeInKilobytes?: number
+  // On update an empty string removes the metadata
+  userMetadata?: string
+  // Read only
+  createdAt?: string
+  updatedAt?: string
+  accessedAt?: string
   sizeInBytes?: number
   subscriptionCount?: number
+  connection?: ConnectionLabel
 }
 
 export interface SubscriptionProperties {
@@ -55,8 +91,126 @@ export interface SubscriptionProperties {
   messageCount?: number
   activeMessageCount?: number
   deadLetterMessageCount?: number
+  scheduledMessageCount?: number
   transferMessageCount?: number
   transferDeadLetterMessageCount?: number
+  // Only set by list_subscriptions_page with includeRuleCounts
+  ruleCount?: number
+  connection?: ConnectionLabel
+}
+
+// An entity that auto-forwards to another one
+export interface ForwardReference {
+  // "queue" or "topic/Subscriptions/subscription"
+  entityPath: string
+  property: "ForwardTo" | "ForwardDeadLetteredMessagesTo"
+  forwardTo: string
+}
+
+// One page of a topic's subscriptions; request the next one with skip + items.length
+export interface SubscriptionPage {
+  topicName: string
+  skip: number
+  items: SubscriptionProperties[]
+  hasMore: boolean
+}
+
+// Unset fields are left as they are on each subscription
+export interface SubscriptionSettings {
+  lockDurationInSeconds?: number
+  maxDeliveryCount?: number
+  defaultMessageTimeToLiveInSeconds?: number
+  deadLetteringOnMessageExpiration?: boolean
+}
+
+export interface SubscriptionSettingsResult {
+  subscriptionName: string
+  success: boolean
+  error?: string
+}
+
+export type EntityKind = "queue" | "topic" | "subscription"
+
+// Durations are in seconds, sizes in megabytes
+export type EntityProperty =
+  | "lockDuration"
+  | "maxDeliveryCount"
+  | "defaultMessageTimeToLive"
+  | "deadLetteringOnMessageExpiration"
+  | "duplicateDetectionHistoryTimeWindow"
+  | "maxSizeInMegabytes"
+  | "autoDeleteOnIdle"
+  | "enableBatchedOperations"
+  | "status"
+
+export interface PropertyCondition {
+  property: EntityProperty
+  op: "eq" | "ne" | "lt" | "le" | "gt" | "ge"
+  value: number | boolean | string
+}
+
+export interface EntityFilter {
+  kinds?: EntityKind[]
+  // Regular expression over the entity path
+  namePattern?: string
+  conditions?: PropertyCondition[]
+}
+
+export interface PropertyAssignment {
+  property: EntityProperty
+  value: number | boolean | string
+}
+
+export interface PropertyDiff {
+  property: EntityProperty
+  before?: number | boolean | string
+  after: number | boolean | string
+}
+
+export interface EntityEditResult {
+  kind: EntityKind
+  entityPath: string
+  changes: PropertyDiff[]
+  applied: boolean
+  error?: string
+}
+
+// The bulk_edit_entities result; applied is false for a preview
+export interface BulkEditReport {
+  applied: boolean
+  scannedCount: number
+  matchedCount: number
+  unchangedCount: number
+  updatedCount: number
+  failedCount: number
+  entities: EntityEditResult[]
+}
+
+// Payload of the "bulk-edit-progress" event
+export interface BulkEditProgress {
+  entityPath: string
+  index: number
+  total: number
+}
+
+export interface RuleFilter {
+  filterType: "sql" | "correlation"
+  sqlExpression?: string
+  correlationId?: string
+  messageId?: string
+  to?: string
+  replyTo?: string
+  subject?: string
+  sessionId?: string
+  replyToSessionId?: string
+  contentType?: string
+  properties?: Record<string, string | number | boolean>
+}
+
+export interface SubscriptionRule {
+  name?: string
+  filter: RuleFilter
+  sqlAction?: string
 }
 
 export interface ServiceBusMessage {
@@ -77,9 +231,868 @@ export interface ServiceBusMessage {
   sequenceNumber?: number
   deadLetterReason?: string
   deadLetterErrorDescription?: string
+  deadLetterSource?: string
+  scheduledEnqueueTimeUtc?: string
+  partitionKey?: string
+  // Must match partitionKey when both are set
+  viaPartitionKey?: string
+  state?: "Active" | "Deferred" | "Scheduled"
+  bodySize?: number
+  bodyTruncated?: boolean
+  // "base64" when body holds raw bytes
+  bodyEncoding?: "base64"
+  bodyHexPreview?: string
+  // Set when the body was unwrapped from a legacy .NET DataContract string
+  bodyEnvelope?: "dataContractXml" | "dataContractBinary"
+  // Set when the body was decompressed; rawBody is the received bytes, base64-encoded
+  bodyCompression?: "gzip" | "deflate"
+  rawBody?: string
+  // JSON rendering from a matching body decoder rule; display only
+  decodedBody?: any
+  decodeError?: string
+  // Set when the body or its properties could not be read as sent; body is then the raw text
+  parseWarning?: string
+  // Set on peek; absent when the message never expires
+  expiresAtUtc?: string
+}
+
+export interface LockedMessage extends ServiceBusMessage {
+  lockToken: string
+}
+
+// Result of drain_messages, and payload of each "drained-messages" event
+export interface DrainResult {
+  entityPath: string
+  messages: ServiceBusMessage[]
+  // Why the drain stopped early; the messages were removed all the same
+  error?: string
 }
 
 export type QueueSortOption = "name" | "messageCount" | "deadLetterCount"
 export type TopicSortOption = "name" | "subscriptionCount"
 export type SubscriptionSortOption = "name" | "messageCount" | "deadLetterCount"
 
+export interface AppSettings {
+  theme: "system" | "light" | "dark"
+  defaultPageSize: number
+  defaultListPageSize: number
+  confirmationLevel: "all" | "destructive" | "none"
+  telemetryOptIn: boolean
+  // Record mutating requests instead of sending them
+  dryRun: boolean
+  // "local", "utc" or a fixed offset like "+05:30", applied by format_timestamps
+  displayTimeZone: string
+}
+
+// A request skipped in dry-run mode (never includes the Authorization header)
+export interface PlannedRequest {
+  timestamp: string
+  operation: string
+  method: string
+  url: string
+  body?: string
+}
+
+// Backend errors arrive as "SBX-xxxx: message"; describe_error returns the parts
+export interface CatalogError {
+  code: string
+  key: string
+  message: string
+  params: Record<string, string>
+  hint?: string
+}
+
+export interface ErrorCatalogEntry {
+  code: string
+  key: string
+  template: string
+  hint?: string | null
+}
+
+// Tuning for a peek; unset fields keep the defaults
+export interface PeekOptions {
+  // 0 drops bodies, n keeps an n-byte preview (AMQP only)
+  maxBodyBytes?: number
+  // 1-300; server wait over HTTP (default 60), time limit per page over AMQP
+  timeoutSeconds?: number
+  // At most 32 over HTTP (the default), 5000 over AMQP
+  pageSize?: number
+}
+
+export interface MessageSource {
+  queueName?: string
+  topicName?: string
+  subscriptionName?: string
+  deadLetter?: boolean
+}
+
+// Payload of the "move-messages-progress" event and the move_messages result
+export interface MoveMessagesProgress {
+  source: string
+  destination: string
+  requested: number
+  moved: number
+  lastSequenceNumber?: number
+  done: boolean
+}
+
+export interface MigrationOptions {
+  // Queue or topic the remaining messages are moved to
+  destination: string
+  // How long the active count may stay flat before consumers count as paused (default 120)
+  stallSeconds?: number
+  // Default one hour
+  maxWaitSeconds?: number
+  includeDeadLetters?: boolean
+}
+
+export type MigrationPhase =
+  | "disablingSends"
+  | "waitingForConsumers"
+  | "transferring"
+  | "transferringDeadLetters"
+  | "done"
+
+// Payload of the "migrate-queue-progress" event and the drain_and_migrate_queue result
+export interface MigrationProgress {
+  queueName: string
+  destination: string
+  phase: MigrationPhase
+  // What to restore if the migration is abandoned; the queue is left SendDisabled
+  previousStatus: string
+  initialActiveCount: number
+  activeCount: number
+  deadLetterCount: number
+  waitedSeconds: number
+  // Waiting ended before the active count reached zero
+  consumersStalled: boolean
+  moved: number
+  movedDeadLetters: number
+  // Only planned (dry run): nothing was disabled, waited for or moved
+  dryRun: boolean
+}
+
+// Payload of the "copy-messages-progress" event and the copy_messages result
+export interface CopyMessagesProgress {
+  source: string
+  destination: string
+  requested: number
+  copied: number
+  // Where a later copy would carry on; unset once the source ran out
+  nextSequenceNumber?: number
+  done: boolean
+}
+
+// Payload of the "dead-letter-all-progress" event and the dead_letter_all_messages result
+export interface DeadLetterAllProgress {
+  source: string
+  reason: string
+  // Active count at the start (or maxCount); later arrivals are left alone
+  requested: number
+  deadLettered: number
+  done: boolean
+}
+
+// Payload of the "delete-messages-progress" event and the delete_messages_by_sequence result
+export interface DeleteMessagesProgress {
+  source: string
+  requested: number
+  deleted: number[]
+  // Messages locked while looking for the targets
+  scanned: number
+  // Targets not seen before the scan ended; only set once done
+  notFound: number[]
+  done: boolean
+}
+
+export interface ReceiverCheckpoint {
+  receiverId: number
+  moved: number
+  lastSequenceNumber?: number
+  finished: boolean
+  error?: string
+}
+
+// Payload of the "drain-dead-letters-progress" event
+export interface DeadLetterDrainProgress {
+  source: string
+  destination: string
+  moved: number
+  checkpoints: ReceiverCheckpoint[]
+}
+
+export interface DeadLetterDrainReport {
+  source: string
+  destination: string
+  receivers: number
+  requested: number
+  moved: number
+  deadLetterCountBefore: number
+  deadLetterCountAfter: number
+  verified: boolean
+  checkpoints: ReceiverCheckpoint[]
+  notes: string[]
+}
+
+// Payload of the "message-count-verification" event, emitted after a peek from the
+// head or a purge; also returned by verify_message_counts
+export interface MessageCountVerification {
+  entityPath: string
+  operation: "peek" | "purge"
+  deadLetter: boolean
+  // Messages the operation returned (peek) or removed (purge)
+  observedCount: number
+  // Active or dead-letter count reported by the service
+  runtimeCount?: number
+  scheduledCount?: number
+  matches: boolean
+  // Likely explanations when the numbers don't line up
+  reasons: string[]
+}
+
+// Payload of the "entity-list-progress" event (list commands with streamProgress: true)
+export interface ListingProgress<T> {
+  entityType: "queues" | "topics" | "subscriptions"
+  scope?: string
+  page: number
+  loaded: number
+  hasMore: boolean
+  items: T[]
+}
+
+// Result of refresh_queue / refresh_topic / refresh_subscription; pass etag back on the next call
+export interface RescheduledMessage {
+  oldSequenceNumber: number
+  newSequenceNumber: number
+  scheduledEnqueueTimeUtc: string
+}
+
+export interface SessionInfo {
+  sessionId: string
+  messageCount: number
+  moreMessages: boolean
+}
+
+export interface SessionState {
+  sessionId: string
+  size: number
+  text?: string
+  base64: string
+}
+
+export interface RequestReplyResult {
+  request: ServiceBusMessage
+  reply?: ServiceBusMessage
+  replyQueue: string
+  elapsedMs: number
+  timedOut: boolean
+  unmatchedReplies: number
+}
+
+export interface EntityRefresh<T> {
+  modified: boolean
+  etag?: string
+  entity?: T
+}
+
+// A queue, a topic or a topic subscription; etag comes from the previous estimate
+export interface BacklogEntity {
+  queueName?: string
+  topicName?: string
+  subscriptionName?: string
+  etag?: string
+}
+
+// When modified is false the previous counts are still current and are omitted
+export interface BacklogEstimate {
+  entityPath: string
+  modified: boolean
+  etag?: string
+  messageCount?: number
+  activeMessageCount?: number
+  deadLetterMessageCount?: number
+  scheduledMessageCount?: number
+  transferMessageCount?: number
+  transferDeadLetterMessageCount?: number
+  error?: string
+}
+
+export interface SasTokenInspection {
+  resourceUri: string
+  namespace?: string
+  entityPath?: string
+  policyName: string
+  expiry: number
+  expiresAt: string
+  expiresInSeconds: number
+  expired: boolean
+  signatureValid?: boolean
+  notes: string[]
+}
+
+export interface AadTokenInspection {
+  audience?: string
+  issuer?: string
+  tenantId?: string
+  objectId?: string
+  appId?: string
+  userName?: string
+  roles: string[]
+  scopes: string[]
+  issuedAt?: string
+  notBefore?: string
+  expiresAt?: string
+  expired: boolean
+  notes: string[]
+}
+
+// Entry of the backend audit log (get_audit_log); never contains secrets
+export interface AuditEvent {
+  timestamp: string
+  action: string
+  connectionId?: string
+  detail: string
+}
+
+export interface MonitorInfo {
+  monitorId: string
+  connectionId: string
+  connection: ConnectionLabel
+  source: MessageSource
+  pollIntervalMs: number
+  startedAt: string
+  nextSequenceNumber?: number
+  messagesSeen: number
+}
+
+// Payload of the "monitor-messages" event
+export interface MonitorMessages {
+  monitorId: string
+  connection: ConnectionLabel
+  messages: ServiceBusMessage[]
+}
+
+// Payload of the "monitor-stopped" event
+export interface MonitorStopped {
+  monitorId: string
+  error?: string
+}
+
+// Payload of the "reconnected" event, sent after the machine resumes from sleep or a
+// burst of network errors; monitors carry on by themselves
+export interface Reconnected {
+  reason: "resume" | "networkErrors"
+  asleepSeconds?: number
+  monitors: number
+  at: string
+}
+
+export type ExportFormat = "json" | "ndjson" | "csv" | "xlsx"
+
+export type CsvColumn =
+  | "sequenceNumber"
+  | "enqueuedTime"
+  | "messageId"
+  | "correlationId"
+  | "sessionId"
+  | "subject"
+  | "contentType"
+  | "deliveryCount"
+  | "deadLetterReason"
+  | "bodyPreview"
+  | "to"
+  | "replyTo"
+  | "partitionKey"
+  | "timeToLive"
+  | "scheduledEnqueueTime"
+  | "lockedUntil"
+  | "deadLetterErrorDescription"
+  | "deadLetterSource"
+  | "state"
+  | { property: string }
+  // Dotted path ("order.lines.0.sku") or JSON pointer ("/order/lines/0/sku")
+  | { bodyField: string }
+
+// A CSV/XLSX column, optionally under a custom header
+export type ExportColumn = CsvColumn | { column: CsvColumn; header: string }
+
+export interface ExportResult {
+  path: string
+  format: ExportFormat
+  messageCount: number
+  bytesWritten: number
+  // Messages whose body was cut down to bodyPreviewBytes
+  truncatedCount: number
+}
+
+// Result of hydrate_export
+export interface HydrateResult {
+  path: string
+  format: ExportFormat
+  hydrated: number
+  // No longer on the entity (received or expired since the export)
+  missing: number[]
+  notInFile: number[]
+  bytesWritten: number
+}
+
+export interface ImportResult {
+  path: string
+  format: ExportFormat
+  messageCount: number
+}
+
+// One dead-letter queue in an export_dead_letters run
+export interface DeadLetterExportEntry {
+  entityPath: string
+  // File name inside the export directory; unset when this entity failed
+  file?: string
+  // Dead-letter count from the listing, before peeking
+  deadLetterCount: number
+  messageCount: number
+  bytesWritten: number
+  // More dead letters than maxPerEntity were waiting
+  truncated: boolean
+  error?: string
+}
+
+// Also written to manifest.json in the export directory
+export interface DeadLetterExportManifest {
+  namespace: string
+  exportedAt: string
+  directory: string
+  entities: DeadLetterExportEntry[]
+  totalMessages: number
+  failedEntities: number
+}
+
+// Payload of the "export-dead-letters-progress" event
+export interface DeadLetterExportProgress {
+  entityPath: string
+  index: number
+  total: number
+  totalMessages: number
+}
+
+// Where export_messages / export_dead_letters upload to instead of the local disk; the
+// path or directory given to them is then relative to prefix
+export type ExportDestination =
+  | {
+      kind: "azureBlob"
+      account: string
+      container: string
+      prefix?: string
+      // Keychain entry with a SAS token (store_export_credential); Azure AD without one
+      credentialId?: string
+      // Defaults to https://<account>.blob.core.windows.net
+      endpoint?: string
+    }
+  | {
+      kind: "s3"
+      bucket: string
+      region: string
+      prefix?: string
+      credentialId: string
+      // S3-compatible services, addressed path-style
+      endpoint?: string
+    }
+
+// What store_export_credential keeps in the keychain
+export type ExportCredential =
+  | { sasToken: string }
+  | { s3: { accessKeyId: string; secretAccessKey: string; sessionToken?: string } }
+
+export interface RepeatSendOptions {
+  count: number
+  // Unset sends as fast as the batches allow
+  ratePerSecond?: number
+  // A new random MessageId per copy
+  newMessageId?: boolean
+  // Application property set to the copy's number, counting from 1
+  counterProperty?: string
+}
+
+// Payload of the "send-repeated-progress" event and the send_message_repeated result;
+// the first event carries the runId for cancel_send_repeated
+export interface RepeatSendProgress {
+  runId: string
+  entity: string
+  requested: number
+  sent: number
+  elapsedMs: number
+  cancelled: boolean
+  done: boolean
+  error?: string
+}
+
+export interface PeekStreamOptions {
+  // Unset peeks until the entity is exhausted
+  maxCount?: number
+  fromSequenceNumber?: number
+  chunkSize?: number
+  // Chunks that may be unacknowledged before the stream waits
+  window?: number
+  maxBodyBytes?: number
+}
+
+// "peek-messages-chunk" event; acknowledge with ack_peek_chunk. The last one has
+// done set and needs no ack.
+export interface PeekChunk {
+  streamId: string
+  chunkIndex: number
+  messages: ServiceBusMessage[]
+  delivered: number
+  done: boolean
+}
+
+export interface PeekStreamSummary {
+  streamId: string
+  delivered: number
+  chunks: number
+  cancelled: boolean
+  nextSequenceNumber?: number
+}
+
+// Placeholders in the body, ids, subject and string properties are expanded per
+// message at send time: {{uuid}}, {{now}}, {{counter}}, {{counter:<name>}}
+export interface MessageTemplate {
+  // Empty for a new template; assigned on save
+  id: string
+  name: string
+  message: ServiceBusMessage
+  updatedAt?: string
+}
+
+// A message matches when every criterion that is set matches
+export interface MessageQuery {
+  bodyContains?: string
+  bodyRegex?: string
+  caseSensitive?: boolean
+  messageId?: string
+  correlationId?: string
+  // Application property name -> value, compared as text
+  properties?: Record<string, string>
+}
+
+export interface SearchResult {
+  scannedCount: number
+  matches: ServiceBusMessage[]
+  // Where to continue when the scan stopped at maxScan or maxMatches
+  nextSequenceNumber?: number
+}
+
+// Payload of the "search-messages-progress" event
+export interface SearchProgress {
+  scannedCount: number
+  matchCount: number
+}
+
+export interface CountBucket {
+  key: string
+  count: number
+}
+
+// Missing values are counted under "(none)"; past 50 buckets the rest are summed into "(other)"
+export interface DeadLetterAnalysis {
+  entityPath: string
+  scannedCount: number
+  byReason: CountBucket[]
+  byContentType: CountBucket[]
+  bySubject: CountBucket[]
+  // Entity the message was dead-lettered on, for DLQs fed by auto-forwarding
+  bySource: CountBucket[]
+  // Keyed by the start of the hour (UTC), oldest first
+  enqueuedByHour: CountBucket[]
+  oldestEnqueuedUtc?: string
+  newestEnqueuedUtc?: string
+  // Where to continue when the scan stopped at maxScan
+  nextSequenceNumber?: number
+}
+
+// Payload of the "analyze-dead-letters-progress" event
+export interface DeadLetterAnalysisProgress {
+  entityPath: string
+  scannedCount: number
+}
+
+// A grouping dimension for group_messages_by_property
+export type GroupBy =
+  | "subject"
+  | "contentType"
+  | "correlationId"
+  | "sessionId"
+  | "to"
+  | "replyTo"
+  | "partitionKey"
+  | "deadLetterReason"
+  | "deliveryCount"
+  | { property: string }
+
+// Missing values are counted under "(none)"; past 50 groups the rest are summed into "(other)"
+export interface MessageGroups {
+  entityPath: string
+  groupBy: GroupBy
+  scannedCount: number
+  // Distinct values seen, including those summed into "(other)"
+  distinctCount: number
+  groups: CountBucket[]
+  // Where to continue when the scan stopped at maxScan
+  nextSequenceNumber?: number
+}
+
+// Payload of the "group-messages-progress" event
+export interface MessageGroupsProgress {
+  entityPath: string
+  scannedCount: number
+}
+
+// Messages sharing one MessageId or body hash
+export interface DuplicateGroup {
+  key: string
+  count: number
+  // Oldest first, at most 100
+  sequenceNumbers: number[]
+  firstEnqueuedUtc?: string
+  lastEnqueuedUtc?: string
+}
+
+export interface DuplicateReport {
+  entityPath: string
+  scannedCount: number
+  // Messages beyond the first of their MessageId
+  duplicateCount: number
+  duplicateGroupCount: number
+  // Largest first, at most 200 groups
+  byMessageId: DuplicateGroup[]
+  // Keyed by a SHA-256 of the body; only when byBodyHash was requested
+  byBodyHash?: DuplicateGroup[]
+  nextSequenceNumber?: number
+}
+
+// Payload of the "find-duplicates-progress" event
+export interface DuplicateReportProgress {
+  entityPath: string
+  scannedCount: number
+}
+
+// Where the producer's sequence number is: an application property, or a body field
+// (a JSON pointer or a dotted path)
+export type SequenceField = { property: string } | { bodyField: string }
+
+export interface SequenceGap {
+  after: number
+  before: number
+  missing: number
+}
+
+export interface SequenceDuplicate {
+  value: number
+  sequenceNumbers: number[]
+}
+
+// A value delivered after a higher one
+export interface OutOfOrderDelivery {
+  value: number
+  sequenceNumber: number
+  afterValue: number
+}
+
+export interface OrderingStream {
+  key: string
+  messageCount: number
+  lowestValue: number
+  highestValue: number
+  // Totals; the lists below hold at most 100 entries each
+  missingCount: number
+  duplicateCount: number
+  outOfOrderCount: number
+  gaps: SequenceGap[]
+  duplicates: SequenceDuplicate[]
+  outOfOrder: OutOfOrderDelivery[]
+}
+
+export interface OrderingReport {
+  entityPath: string
+  sequenceField: SequenceField
+  streamBy?: GroupBy
+  scannedCount: number
+  // Messages without the field, or whose value isn't an integer
+  withoutSequenceCount: number
+  streamCount: number
+  missingCount: number
+  duplicateCount: number
+  outOfOrderCount: number
+  // Most issues first, at most 200 streams
+  streams: OrderingStream[]
+  nextSequenceNumber?: number
+}
+
+// Payload of the "analyze-ordering-progress" event
+export interface OrderingReportProgress {
+  entityPath: string
+  scannedCount: number
+}
+
+// Result of infer_message_schema
+export interface InferredSchema {
+  entityPath: string
+  sampledCount: number
+  // Bodies merged into the schema; the rest weren't JSON
+  jsonCount: number
+  nonJsonCount: number
+  // A draft 2020-12 JSON Schema
+  schema: Record<string, unknown>
+  // Set when the schema was also written to a file
+  path?: string
+}
+
+// Payload of the "operation-started" event; the id is what cancel_operation takes
+export interface OperationInfo {
+  operationId: string
+  kind: "purge" | "export" | "hydrate" | "import" | "send" | "search" | "move" | "copy" | "drain" | "dead-letter" | "migrate" | "bulk-edit"
+  description: string
+  startedAt: string
+}
+
+// Payload of the "operation-progress" event (purge, export and move), at most
+// every 250 ms
+export interface OperationProgress {
+  operationId: string
+  kind: OperationInfo["kind"]
+  processed: number
+  // Unset when the total isn't known up front
+  totalEstimate?: number
+  // Messages per second since the start
+  rate: number
+}
+
+// Payload of the "operation-finished" event
+export interface OperationFinished {
+  operationId: string
+  kind: OperationInfo["kind"]
+  cancelled: boolean
+  error?: string
+}
+
+export type TimelineEventKind = "enqueued" | "scheduled" | "delivery" | "deadLettered"
+
+export interface TimelineEvent {
+  kind: TimelineEventKind
+  // Unset when the service keeps no trace of when it happened
+  atUtc?: string
+  // End of the lock, for deliveries
+  untilUtc?: string
+  attempt?: number
+  estimated: boolean
+  note?: string
+}
+
+export interface RedeliveryTimeline {
+  entityPath: string
+  sequenceNumber: number
+  messageId?: string
+  deliveryCount: number
+  maxDeliveryCount?: number
+  lockDurationInSeconds?: number
+  deadLetterReason?: string
+  // From the message becoming visible to the start of its last delivery
+  retrySpanSeconds?: number
+  events: TimelineEvent[]
+}
+
+export interface MessageExpiry {
+  message: ServiceBusMessage
+  // Effective TTL (the smaller of the message's and the entity's); unset = never expires
+  timeToLiveInSeconds?: number
+  expiresAtUtc?: string
+  // Negative once expired
+  expiresInSeconds?: number
+  expired: boolean
+}
+
+export interface ExpiryPreview {
+  defaultMessageTimeToLiveInSeconds?: number
+  // Whether expired messages go to the dead-letter queue instead of being dropped
+  deadLetteringOnMessageExpiration: boolean
+  scannedCount: number
+  messages: MessageExpiry[]
+  // Where to continue when the scan stopped at maxScan
+  nextSequenceNumber?: number
+}
+
+export interface DescriptorSetInfo {
+  id: string
+  name: string
+  messageTypes: string[]
+}
+
+export interface AvroSchemaInfo {
+  id: string
+  name: string
+}
+
+export type BodyDecoder =
+  | {
+      kind: "protobuf"
+      descriptorSetId: string
+      messageType: string
+    }
+  | {
+      kind: "avro"
+      schemaId: string
+    }
+  | {
+      // Writer schema fetched by the id each message carries
+      kind: "avroRegistry"
+      endpoint: string
+    }
+  | {
+      // AES-GCM; without then the plaintext is shown as JSON or text
+      kind: "decrypt"
+      key: EncryptionKey
+      then?: BodyDecoder
+    }
+
+// A key kept in the keychain under keyId, or a Key Vault secret holding a base64 key
+export type EncryptionKey =
+  | { kind: "keychain"; keyId: string }
+  | { kind: "keyVault"; vaultUrl: string; secretName: string }
+
+// Applies when every match field that is set equals the message's value
+export interface DecoderRule {
+  name: string
+  // Queue or topic name; a topic rule covers all of its subscriptions
+  entity?: string
+  subject?: string
+  contentType?: string
+  decoder: BodyDecoder
+}
+
+export interface DecoderConfig {
+  descriptorSets: DescriptorSetInfo[]
+  avroSchemas: AvroSchemaInfo[]
+  rules: DecoderRule[]
+}
+
+// Result of export_read_only_bundle; open_read_only_bundle returns a ServiceBusConnection
+export interface ReadOnlyBundleResult {
+  path: string
+  entityPath: string
+  keyName: string
+  // The Listen-only policy was created for this bundle
+  createdPolicy: boolean
+  bytesWritten: number
+  // Only planned (dry run): no file was written
+  dryRun: boolean
+}
+
+// Result of probe_connection_access
+export interface ConnectionAccess {
+  // Listing, creating, editing and deleting entities
+  canManage: boolean
+  managementError?: string
+  entityPath?: string
+  entityKind?: "queue" | "topic" | "subscription"
+  // Unknown for a topic: read it through a subscription
+  canListen?: boolean
+}